@@ -0,0 +1,38 @@
+//! Dump a recording made by `Client::record_to` as decoded messages.
+//!
+//! Usage: `cargo run --example ibkr-replay -- <recording>`
+//!
+//! Every frame is printed with its direction, its timestamp, and its raw fields. Each inbound
+//! frame is then decoded, and the default wrapper methods print the callbacks that it produces.
+
+use ibapi::record::ReplayClient;
+use ibapi::wrapper::LocalWrapper;
+
+struct Dump;
+
+impl LocalWrapper for Dump {}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let Some(path) = std::env::args().nth(1) else {
+        eprintln!("Usage: ibkr-replay <recording>");
+        std::process::exit(2);
+    };
+
+    let mut replay = ReplayClient::from_file(path)?;
+    let mut contracts = 0;
+    while let Some(frame) = replay.next_frame() {
+        eprintln!(
+            "{} {} {:?}",
+            frame.direction,
+            frame.timestamp.format("%F %T%.6f"),
+            frame.fields()
+        );
+        replay.step(&mut Dump).await;
+        for contract in &replay.contracts()[contracts..] {
+            eprintln!("[Contract] {contract}");
+        }
+        contracts = replay.contracts().len();
+    }
+    Ok(())
+}
@@ -2,28 +2,46 @@ use proc_macro2::{Ident, TokenStream};
 use quote::{quote, ToTokens};
 use syn::{ItemStruct, parse_quote, Type};
 
+#[derive(Clone, Copy)]
+enum Access {
+    Move,
+    Ref,
+    // Non-NaN floats are unwrapped, so that the getter returns a plain `f64`
+    NotNan,
+    OptionNotNan,
+}
+
 fn impl_method(
     struct_name: &Ident,
     meth_name: &Ident,
     return_type: &Type,
     vis: &syn::Visibility,
-    can_move: bool,
+    access: Access,
 ) -> TokenStream {
     let d = format!("Get the {struct_name}'s {meth_name}.\n\n # Returns\n The {meth_name}");
     let doc: syn::Attribute = parse_quote!(#[doc = #d]);
 
-    let body = if can_move {
-        quote! {
+    let body = match access {
+        Access::Move => quote! {
             #vis fn #meth_name(&self) -> #return_type {
                 self.#meth_name
             }
-        }
-    } else {
-        quote! {
+        },
+        Access::Ref => quote! {
             #vis fn #meth_name(&self) -> &#return_type {
                 &self.#meth_name
             }
-        }
+        },
+        Access::NotNan => quote! {
+            #vis fn #meth_name(&self) -> f64 {
+                self.#meth_name.get()
+            }
+        },
+        Access::OptionNotNan => quote! {
+            #vis fn #meth_name(&self) -> Option<f64> {
+                self.#meth_name.map(NotNan::get)
+            }
+        },
     };
 
     quote! {
@@ -49,13 +67,15 @@ pub fn impl_make_getters(ast: &mut ItemStruct) -> TokenStream {
         .filter_map(|f| {
             if let Some(ref meth_name) = f.ident {
                 let r_type_str = f.ty.to_token_stream().to_string();
-                let (r_type, can_move) = match r_type_str.as_str() {
-                    "String" => (parse_quote! { str }, false),
-                    s if s.starts_with("Vec < ") => (f.ty.clone(), false),
-                    _ => (f.ty.clone(), true),
+                let (r_type, access) = match r_type_str.as_str() {
+                    "String" => (parse_quote! { str }, Access::Ref),
+                    s if s.starts_with("Vec < ") || s == "ContractDetails" || s == "Schedule" => (f.ty.clone(), Access::Ref),
+                    "NotNan" => (f.ty.clone(), Access::NotNan),
+                    "Option < NotNan >" => (f.ty.clone(), Access::OptionNotNan),
+                    _ => (f.ty.clone(), Access::Move),
                 };
 
-                Some(impl_method(s_name, meth_name, &r_type, vis, can_move))
+                Some(impl_method(s_name, meth_name, &r_type, vis, access))
             } else {
                 None
             }
@@ -15,6 +15,14 @@ enum SecType {
     SecFuture,
     SecOption,
     Commodity,
+    Bond,
+    Cfd,
+    MutualFund,
+    SecFutureOption,
+    Warrant,
+    ContinuousFuture,
+    StructuredProduct,
+    Combo,
 }
 
 impl SecType {
@@ -28,8 +36,64 @@ impl SecType {
             SecFuture => "SecFuture",
             SecOption => "SecOption",
             Commodity => "Commodity",
+            Bond => "Bond",
+            Cfd => "Cfd",
+            MutualFund => "MutualFund",
+            SecFutureOption => "SecFutureOption",
+            Warrant => "Warrant",
+            ContinuousFuture => "ContinuousFuture",
+            StructuredProduct => "StructuredProduct",
+            Combo => "Combo",
         }
     }
+
+    #[inline]
+    const fn security_type(self) -> &'static str {
+        match self {
+            Forex => "CASH",
+            Crypto => "Crypto",
+            Stock => "STK",
+            Index => "IND",
+            SecFuture => "FUT",
+            SecOption => "OPT",
+            Commodity => "CMDTY",
+            Bond => "BOND",
+            Cfd => "CFD",
+            MutualFund => "FUND",
+            SecFutureOption => "FOP",
+            Warrant => "WAR",
+            ContinuousFuture => "CONTFUT",
+            StructuredProduct => "IOPT",
+            Combo => "BAG",
+        }
+    }
+
+    #[inline]
+    // Option-like securities are enums with a `Call` and a `Put` variant wrapping the same inner struct.
+    const fn is_option_like(self) -> bool {
+        matches!(self, SecOption | SecFutureOption | Warrant)
+    }
+
+    #[inline]
+    const fn has_expiration(self) -> bool {
+        matches!(self, SecFuture | SecOption | SecFutureOption | Warrant | StructuredProduct)
+    }
+
+    #[inline]
+    /// A future or an option may only know its contract month, so its expiration date is optional.
+    const fn has_contract_month(self) -> bool {
+        matches!(self, SecFuture | SecOption)
+    }
+
+    #[inline]
+    const fn has_multiplier(self) -> bool {
+        matches!(self, SecFuture | SecOption | SecFutureOption | Warrant | StructuredProduct)
+    }
+
+    #[inline]
+    const fn has_trading_class(self) -> bool {
+        !matches!(self, Index | Combo)
+    }
 }
 
 impl From<&str> for SecType {
@@ -42,6 +106,14 @@ impl From<&str> for SecType {
             "SecFuture" => SecFuture,
             "SecOption" => SecOption,
             "Commodity" => Commodity,
+            "Bond" => Bond,
+            "Cfd" => Cfd,
+            "MutualFund" => MutualFund,
+            "SecFutureOption" => SecFutureOption,
+            "Warrant" => Warrant,
+            "ContinuousFuture" => ContinuousFuture,
+            "StructuredProduct" => StructuredProduct,
+            "Combo" => Combo,
             _ => panic!("Invalid Security name {s}."),
         }
     }
@@ -60,7 +132,23 @@ impl From<&Ident> for SecType {
     }
 }
 
-const CONTRACTS: [SecType; 7] = [Forex, Crypto, Stock, Index, SecFuture, SecOption, Commodity];
+const CONTRACTS: [SecType; 15] = [
+    Forex,
+    Crypto,
+    Stock,
+    Index,
+    SecFuture,
+    SecOption,
+    Commodity,
+    Bond,
+    Cfd,
+    MutualFund,
+    SecFutureOption,
+    Warrant,
+    ContinuousFuture,
+    StructuredProduct,
+    Combo,
+];
 
 fn impl_try_from_other_contracts(name: &Ident) -> TokenStream {
     let idents = CONTRACTS
@@ -127,152 +215,134 @@ fn impl_into_contract(name: &Ident) -> TokenStream {
     }
 }
 
+/// Generate an expression that accesses `field` on `self`, looking through the `Call` and `Put`
+/// variants of option-like securities.
+fn access(name: &Ident, s_name: SecType, field: &TokenStream) -> TokenStream {
+    if s_name.is_option_like() {
+        quote! {
+            match self {
+                #name::Call(inner) | #name::Put(inner) => inner.#field
+            }
+        }
+    } else {
+        quote! { self.#field }
+    }
+}
+
+/// Like [`access`], but generates a reference to `field`.
+fn access_ref(name: &Ident, s_name: SecType, field: &TokenStream) -> TokenStream {
+    if s_name.is_option_like() {
+        quote! {
+            match self {
+                #name::Call(inner) | #name::Put(inner) => &inner.#field
+            }
+        }
+    } else {
+        quote! { &self.#field }
+    }
+}
+
 #[allow(clippy::module_name_repetitions, clippy::too_many_lines)]
 pub fn impl_security(ast: &syn::DeriveInput) -> TokenStream {
     let name = &ast.ident;
     let s_name: SecType = name.into();
 
-    let contract_id = match s_name {
-        Forex | Crypto | Stock | Index | SecFuture | Commodity => quote! { self.contract_id },
-        SecOption => quote! {
-            match self {
-                SecOption::Call(inner) | SecOption::Put(inner) => inner.contract_id
-            }
-        },
+    let contract_id = access(name, s_name, &quote! { contract_id });
+    let symbol = access(name, s_name, &quote! { symbol.as_str() });
+    let security_type = s_name.security_type();
+    let expiration_date = if s_name.has_contract_month() {
+        access(name, s_name, &quote! { expiration_date })
+    } else if s_name.has_expiration() {
+        let exp = access(name, s_name, &quote! { expiration_date });
+        quote! { Some(#exp) }
+    } else {
+        quote! { None::<NaiveDate> }
     };
-    let symbol = match s_name {
-        Forex | Crypto | Stock | Index | SecFuture | Commodity => quote! { self.symbol.as_str() },
-        SecOption => quote! {
-            match self {
-                SecOption::Call(inner) | SecOption::Put(inner) => inner.symbol.as_str()
-            }
-        },
+    let contract_month = if s_name.has_contract_month() {
+        let month = access(name, s_name, &quote! { contract_month });
+        quote! { Some(#month) }
+    } else {
+        quote! { None::<(i32, u8)> }
     };
-    let security_type = match s_name {
-        Forex => "CASH",
-        Crypto => "Crypto",
-        Stock => "STK",
-        Index => "IND",
-        SecFuture => "FUT",
-        SecOption => "OPT",
-        Commodity => "CMDTY",
+    // A bond's maturity date is its expiration date
+    let ser_expiration_date = match s_name {
+        Bond => quote! { Some(self.maturity_date) },
+        _ => expiration_date.clone(),
     };
-    let expiration_date = match s_name {
-        Forex | Crypto | Stock | Index | Commodity => {
-            quote! { None::<NaiveDate> }
-        }
-        SecFuture => quote! { Some(self.expiration_date) },
-        SecOption => quote! {
-            match self {
-                SecOption::Call(inner) | SecOption::Put(inner) => Some(inner.expiration_date)
-            }
-        },
+    let strike = if s_name.is_option_like() {
+        let strike = access(name, s_name, &quote! { strike.get() });
+        quote! { Some(#strike) }
+    } else {
+        quote! { None::<f64> }
     };
-    let strike = match s_name {
-        Forex | Crypto | Stock | Index | SecFuture | Commodity => quote! { None::<f64> },
-        SecOption => quote! {
+    let right = if s_name.is_option_like() {
+        quote! {
             match self {
-                SecOption::Call(inner) | SecOption::Put(inner) => Some(inner.strike)
+                #name::Call(_) => Some("C"),
+                #name::Put(_) => Some("P"),
             }
-        },
+        }
+    } else {
+        quote! { None::<&str> }
     };
-    let right = match s_name {
-        Forex | Crypto | Stock | Index | SecFuture | Commodity => quote! { None::<&str> },
-        SecOption => quote! {
-            match self {
-                SecOption::Call(_) => Some("C"),
-                SecOption::Put(_) => Some("P"),
-            }
-        },
+    let multiplier = if s_name.has_multiplier() {
+        let mult = access(name, s_name, &quote! { multiplier.get() });
+        quote! { Some(#mult) }
+    } else {
+        quote! { None::<f64> }
     };
-    let multiplier = match s_name {
-        Forex | Crypto | Stock | Index | Commodity => {
-            quote! { None::<u32> }
-        }
-        SecFuture => quote! { Some(self.multiplier) },
-        SecOption => quote! {
-            match self {
-                SecOption::Call(inner) | SecOption::Put(inner) => Some(inner.multiplier)
+    let multiplier_impl = if s_name.has_multiplier() {
+        quote! {
+            #[inline]
+            fn multiplier(&self) -> f64 {
+                #multiplier.unwrap_or(1.0)
             }
-        },
-    };
-    let exchange = match s_name {
-        Forex | Stock | Index | SecFuture | Commodity => {
-            quote! { self.exchange }
         }
-        Crypto => quote! { Routing::Primary(Primary::PaxosCryptoExchange) },
-        SecOption => quote! {
-            match self {
-                SecOption::Call(inner) | SecOption::Put(inner) => inner.exchange
-            }
-        },
+    } else {
+        quote! {}
     };
+    let exchange = access(name, s_name, &quote! { exchange });
     let primary_exchange = match s_name {
-        Forex | Crypto | Index | SecFuture | SecOption | Commodity => quote! { None::<Primary> },
         Stock => quote! { Some(self.primary_exchange) },
+        _ => quote! { None::<Primary> },
     };
-    let currency = match s_name {
-        Forex | Crypto | Stock | Index | SecFuture | Commodity => quote! { self.currency },
-        SecOption => quote! {
-            match self {
-                SecOption::Call(inner) | SecOption::Put(inner) => inner.currency
-            }
-        },
-    };
-    let local_symbol = match s_name {
-        Forex | Crypto | Stock | Index | SecFuture | Commodity => {
-            quote! { self.local_symbol.as_str() }
-        }
-        SecOption => quote! {
-            match self {
-                SecOption::Call(inner) | SecOption::Put(inner) => inner.local_symbol.as_str()
-            }
-        },
-    };
-    let min_tick = match s_name {
-        Forex | Crypto | Stock | Index | SecFuture | Commodity => quote! { self.min_tick },
-        SecOption => quote! {
-            match self {
-                SecOption::Call(inner) | SecOption::Put(inner) => inner.min_tick
-            }
-        },
-    };
-    let trading_class = match s_name {
-        Forex | Crypto | Stock | SecFuture | Commodity => {
-            quote! { Some(self.trading_class.as_str()) }
-        }
-        Index => quote! { None::<&str> },
-        SecOption => quote! {
-            match self {
-                SecOption::Call(inner) | SecOption::Put(inner) => Some(inner.trading_class.as_str())
-            }
-        },
+    let currency = access(name, s_name, &quote! { currency });
+    let local_symbol = access(name, s_name, &quote! { local_symbol.as_str() });
+    let min_tick = access(name, s_name, &quote! { min_tick.get() });
+    let trading_class = if s_name.has_trading_class() {
+        let class = access(name, s_name, &quote! { trading_class.as_str() });
+        quote! { Some(#class) }
+    } else {
+        quote! { None::<&str> }
     };
-    let long_name = match s_name {
-        Forex | Crypto | Stock | Index | SecFuture | Commodity => {
-            quote! { self.long_name.as_str() }
-        }
-        SecOption => quote! {
-            match self {
-                SecOption::Call(inner) | SecOption::Put(inner) => inner.long_name.as_str()
-            }
-        },
+    let long_name = access(name, s_name, &quote! { long_name.as_str() });
+    let order_types = access_ref(name, s_name, &quote! { order_types });
+    let valid_exchanges = access_ref(name, s_name, &quote! { valid_exchanges });
+    let details = access_ref(name, s_name, &quote! { details });
+    let security_ids = match s_name {
+        Stock | Bond => quote! { self.security_ids.as_slice() },
+        _ => quote! { &[] },
     };
-    let order_types = match s_name {
-        Forex | Crypto | Stock | Index | SecFuture | Commodity => quote! { &self.order_types },
-        SecOption => quote! {
-            match self {
-                SecOption::Call(inner) | SecOption::Put(inner) => &inner.order_types
-            }
-        },
+    let (combo_legs, delta_neutral_contract) = match s_name {
+        Combo => (quote! { Some(self.legs.as_slice()) }, quote! { self.delta_neutral_contract }),
+        _ => (quote! { None }, quote! { None }),
     };
-    let valid_exchanges = match s_name {
-        Forex | Crypto | Stock | Index | SecFuture | Commodity => quote! { &self.valid_exchanges },
-        SecOption => quote! {
-            match self {
-                SecOption::Call(inner) | SecOption::Put(inner) => &inner.valid_exchanges
-            }
-        },
+    // The legs of a combo can't be recovered from the other summary fields
+    let (ser_len, ser_legs) = match s_name {
+        Combo => (
+            16_usize,
+            quote! {
+                state.serialize_field("legs", &self.legs)?;
+                state.serialize_field("delta_neutral_contract", &self.delta_neutral_contract)?;
+            },
+        ),
+        Warrant => (
+            15_usize,
+            quote! {
+                state.serialize_field("issuer", &self.issuer())?;
+            },
+        ),
+        _ => (14_usize, quote! {}),
     };
 
     let try_from_impl = impl_try_from_other_contracts(name);
@@ -280,15 +350,20 @@ pub fn impl_security(ast: &syn::DeriveInput) -> TokenStream {
 
     let gen_tokens = quote! {
         impl crate::contract::indicators::Valid for #name {
+            const CONTRACT_TYPE: Option<ContractType> = Some(ContractType::#name);
+
             fn as_out_msg(&self) -> crate::contract::indicators::SecurityOutMsg<'_> {
                 crate::contract::indicators::SecurityOutMsg {
                     contract_id: #contract_id,
                     symbol: #symbol,
                     security_type: #security_type,
                     expiration_date: #expiration_date,
+                    contract_month: #contract_month,
                     strike: #strike,
                     right: #right,
-                    multiplier: #multiplier,
+                    // A partially populated security has no multiplier, which is sent as an empty
+                    // field
+                    multiplier: #multiplier.filter(|m| *m != 0.0),
                     exchange: #exchange,
                     primary_exchange: #primary_exchange,
                     currency: #currency,
@@ -296,11 +371,26 @@ pub fn impl_security(ast: &syn::DeriveInput) -> TokenStream {
                     trading_class: #trading_class,
                 }
             }
+
+            fn combo_legs(&self) -> Option<&[crate::contract::ComboLeg]> {
+                #combo_legs
+            }
+
+            fn delta_neutral_contract(&self) -> Option<crate::contract::DeltaNeutralContract> {
+                #delta_neutral_contract
+            }
+
+            fn security_ids(&self) -> &[crate::contract::SecurityId] {
+                #security_ids
+            }
         }
 
+        #[cfg(not(feature = "serde-full"))]
         impl serde::Serialize for #name {
             fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: Serializer {
-                let mut state = serializer.serialize_struct("Contract", 14)?;
+                use serde::ser::SerializeStruct;
+
+                let mut state = serializer.serialize_struct("Contract", #ser_len)?;
                 state.serialize_field("contract_id", &#contract_id)?;
                 state.serialize_field("security_type", &#security_type)?;
                 state.serialize_field("symbol", &#symbol)?;
@@ -311,14 +401,47 @@ pub fn impl_security(ast: &syn::DeriveInput) -> TokenStream {
                 state.serialize_field("currency", &#currency)?;
                 state.serialize_field("local_symbol", &#local_symbol)?;
                 state.serialize_field("trading_class", &#trading_class)?;
-                state.serialize_field("expiration_date", &#expiration_date.map(|d| d.format("%Y%m%d").to_string()))?;
+                state.serialize_field("expiration_date", &crate::contract::format_expiration(#ser_expiration_date, #contract_month))?;
                 state.serialize_field("strike", &#strike)?;
                 state.serialize_field("option_class", &#right)?;
                 state.serialize_field("multiplier", &#multiplier)?;
+                #ser_legs
                 state.end()
             }
         }
 
+        #[cfg(not(feature = "serde-full"))]
+        impl<'de> serde::Deserialize<'de> for #name {
+            fn deserialize<D>(deserializer: D) -> Result<Self, D::Error> where D: serde::Deserializer<'de> {
+                Contract::deserialize(deserializer)?
+                    .try_into()
+                    .map_err(serde::de::Error::custom)
+            }
+        }
+
+        impl std::fmt::Display for #name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                write!(f, "{} {}", #symbol, ContractType::#name)?;
+                if let Some(expiration) = crate::contract::format_expiration(#expiration_date, #contract_month) {
+                    write!(f, " {expiration}")?;
+                }
+                if let Some(right) = #right {
+                    write!(f, " {right}")?;
+                }
+                if let Some(strike) = #strike {
+                    write!(f, " {strike}")?;
+                }
+                write!(f, " {}", #exchange)?;
+                if let Some(primary_exchange) = #primary_exchange {
+                    write!(f, "/{primary_exchange}")?;
+                }
+                if let Some(multiplier) = #multiplier {
+                    write!(f, " {multiplier}x")?;
+                }
+                write!(f, " {} (conid {})", #currency, #contract_id.0)
+            }
+        }
+
         impl Security for #name {
             #[inline]
             fn contract_id(&self) -> ContractId {
@@ -328,6 +451,7 @@ pub fn impl_security(ast: &syn::DeriveInput) -> TokenStream {
             fn min_tick(&self) -> f64 {
                 #min_tick
             }
+            #multiplier_impl
             #[inline]
             fn symbol(&self) -> &str {
                 #symbol
@@ -345,7 +469,7 @@ pub fn impl_security(ast: &syn::DeriveInput) -> TokenStream {
                 #long_name
             }
             #[inline]
-            fn order_types(&self) -> &Vec<String> {
+            fn order_types(&self) -> &Vec<OrderTypeCode> {
                 #order_types
             }
             #[inline]
@@ -356,6 +480,10 @@ pub fn impl_security(ast: &syn::DeriveInput) -> TokenStream {
             fn contract_type(&self) -> ContractType {
                 ContractType::#name
             }
+            #[inline]
+            fn details(&self) -> &ContractDetails {
+                #details
+            }
         }
 
         #try_from_impl
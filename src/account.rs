@@ -495,3 +495,85 @@ impl FromStr for Tag {
         })
     }
 }
+
+#[derive(Debug, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+/// The code of an individual account, like "U1234567" for a live account or "DU1234567" for a
+/// paper trading account.
+pub struct AccountCode(String);
+
+impl AccountCode {
+    #[must_use]
+    #[inline]
+    /// Return the account code as a string slice.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    #[must_use]
+    #[inline]
+    /// Return `true` if the account is a paper trading account, whose code starts with "DU".
+    pub fn is_paper(&self) -> bool {
+        self.0.starts_with("DU")
+    }
+}
+
+impl std::fmt::Display for AccountCode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl AsRef<str> for AccountCode {
+    #[inline]
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<AccountCode> for String {
+    #[inline]
+    fn from(value: AccountCode) -> Self {
+        value.0
+    }
+}
+
+#[derive(Debug, Default, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Error)]
+#[error("Invalid account code {0}. Expected \"U\" or \"DU\" followed by digits.")]
+/// An error returned when attempting to parse an [`AccountCode`]
+pub struct ParseAccountCodeError(String);
+
+impl FromStr for AccountCode {
+    type Err = ParseAccountCodeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let digits = s
+            .strip_prefix("DU")
+            .or_else(|| s.strip_prefix('U'))
+            .ok_or_else(|| ParseAccountCodeError(s.to_owned()))?;
+        if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(ParseAccountCodeError(s.to_owned()));
+        }
+        Ok(Self(s.to_owned()))
+    }
+}
+
+impl TryFrom<String> for AccountCode {
+    type Error = ParseAccountCodeError;
+
+    #[inline]
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+#[test]
+fn test_account_code() {
+    let live: AccountCode = "U1234567".parse().unwrap();
+    assert!(!live.is_paper());
+    assert_eq!(live.as_str(), "U1234567");
+    assert!("DU1234567".parse::<AccountCode>().unwrap().is_paper());
+    for invalid in ["", "U", "DU", "X1234567", "DDU1234567", "U12A4567", "All"] {
+        assert!(invalid.parse::<AccountCode>().is_err(), "{invalid}");
+    }
+}
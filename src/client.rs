@@ -3,28 +3,35 @@ use std::fmt::Formatter;
 use chrono_tz::Tz;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
-use tokio::{io::AsyncReadExt, net::TcpStream, sync::mpsc};
+use tokio::{io::AsyncReadExt, net::TcpStream, sync::{broadcast, mpsc}};
 use tokio::net::tcp::OwnedReadHalf;
 use tokio::task::JoinHandle;
-use tracing::{error, info};
+use tracing::{error, info, warn};
 
 use crate::{
     account::Tag,
     comm::Writer,
-    constants, decode,
+    constants,
+    currency::Currency,
+    decode,
+    exchange::Routing,
     execution::Filter,
     order::{Executable, Order},
-    payload::ExchangeId,
+    payload::{ExchangeId, HistogramEntry},
     reader::Reader,
+    record::Recorder,
+};
+use crate::contract::{
+    Contract, ContractId, ContractSpec, ContractType, MarketRule, MarketRuleError, MarketRuleId, NewSecurityError, OptionChain,
+    OptionChainError, Query, QueryOptions, QueryOutMsg, RequestKind, Schedule, SearchSymbolsError, SecFuture, Security, SymbolMatch,
 };
-use crate::contract::{ContractId, Query, Security};
 use crate::decode::DecodeError;
-use crate::exchange::Routing;
+use crate::error_code::{Notification, TwsError};
 use crate::market_data::{
     histogram, historical_bar, historical_ticks, live_bar, live_data, live_ticks,
     updating_historical_bar,
 };
-use crate::message::{In, Out, ToClient, ToWrapper};
+use crate::message::{In, Out, Subscriber, ToClient, ToWrapper};
 use crate::wrapper::{
     CancelToken, Initializer, LocalInitializer, LocalWrapper, Recurring, Wrapper,
 };
@@ -157,6 +164,20 @@ pub enum ConnectionError {
     )]
     /// Occurs if required buffer size exceeds `usize::MAX`
     InvalidBufferSize,
+    #[error("Failed to initiate connection to IBKR API: TCP connection timed out after {0:?}.")]
+    /// The TCP connection was not established within the timeout set by
+    /// [`ClientBuilder::connect_timeout`]
+    ConnectTimeout(std::time::Duration),
+    #[error("Failed to initiate connection to IBKR API: Handshake timed out after {0:?}.")]
+    /// The IBKR API did not complete the handshake within the timeout set by
+    /// [`ClientBuilder::handshake_timeout`]
+    HandshakeTimeout(std::time::Duration),
+    #[error(
+        "Failed to initiate connection to IBKR API: Server version {0} is below the minimum supported version {}.",
+        constants::MIN_SERVER_VERSION
+    )]
+    /// The negotiated server version is older than the minimum that this crate supports
+    UnsupportedServerVersion(u32),
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -220,6 +241,8 @@ impl Builder {
     ///    [`Builder::from_config_file`].
     /// 2) An error occurs while reading or writing the handshake message that initiates a
     ///    connection with IBKR's trading systems.
+    /// 3) The negotiated server version is older than 180, the minimum that this crate supports,
+    ///    in which case it returns [`ConnectionError::UnsupportedServerVersion`].
     ///
     /// # Returns
     /// An inactive [`Client`] that will become active upon calling [`Client::local`] or
@@ -228,7 +251,14 @@ impl Builder {
         &self,
         client_id: i64,
     ) -> Result<Client<indicators::Inactive>, ConnectionError> {
-        let (mode, host, port, address) = match self.0 {
+        self.connect_with(client_id, &ConnectOptions::default())
+            .await
+    }
+
+    #[inline]
+    // The mode, host, port, and address with which to connect
+    fn target(&self) -> (Option<Mode>, Option<Host>, u16, std::net::Ipv4Addr) {
+        match self.0 {
             Inner::ConfigFile { mode, host, config } => (
                 Some(mode),
                 Some(host),
@@ -241,9 +271,41 @@ impl Builder {
                 config.address,
             ),
             Inner::Manual { port, address } => (None, None, port, address),
+        }
+    }
+
+    async fn connect_with(
+        &self,
+        client_id: i64,
+        options: &ConnectOptions,
+    ) -> Result<Client<indicators::Inactive>, ConnectionError> {
+        let (_, _, port, address) = self.target();
+        let connect = TcpStream::connect((address, port));
+        let stream = match options.connect_timeout {
+            Some(t) => tokio::time::timeout(t, connect)
+                .await
+                .map_err(|_| ConnectionError::ConnectTimeout(t))??,
+            None => connect.await?,
         };
+        stream.set_nodelay(options.tcp_nodelay)?;
+
+        let handshake = self.handshake(stream, client_id, options.optional_capabilities.as_deref());
+        match options.handshake_timeout {
+            Some(t) => tokio::time::timeout(t, handshake)
+                .await
+                .map_err(|_| ConnectionError::HandshakeTimeout(t))?,
+            None => handshake.await,
+        }
+    }
 
-        let (mut reader, writer) = TcpStream::connect((address, port)).await?.into_split();
+    async fn handshake(
+        &self,
+        stream: TcpStream,
+        client_id: i64,
+        optional_capabilities: Option<&str>,
+    ) -> Result<Client<indicators::Inactive>, ConnectionError> {
+        let (mode, host, port, address) = self.target();
+        let (mut reader, writer) = stream.into_split();
 
         let mut writer = Writer::new(writer);
         writer.add_prefix("API\0")?;
@@ -267,6 +329,9 @@ impl Builder {
             .ok_or(ConnectionError::ServerVersion)?
             .parse()
             .map_err(|_| ConnectionError::ServerVersion)?;
+        if server_version < constants::MIN_SERVER_VERSION {
+            return Err(ConnectionError::UnsupportedServerVersion(server_version));
+        }
         let (conn_time, tz) = chrono::NaiveDateTime::parse_and_remainder(
             params.next().ok_or(ConnectionError::DateTime)?,
             "%Y%m%d %T",
@@ -292,12 +357,139 @@ impl Builder {
             writer,
             status: indicators::Inactive { reader },
         };
-        client.start_api().await?;
+        client.start_api(optional_capabilities).await?;
 
         Ok(client)
     }
 }
 
+#[derive(Debug, Clone, Default)]
+// The settings of a connection that only a `ClientBuilder` can change
+struct ConnectOptions {
+    connect_timeout: Option<std::time::Duration>,
+    handshake_timeout: Option<std::time::Duration>,
+    tcp_nodelay: bool,
+    optional_capabilities: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+/// Facilitates the creation of a new connection to IBKR's trading systems, like [`Builder`], with
+/// more control over how the connection is made.
+///
+/// By default, a [`ClientBuilder`] connects to a paper trading TWS on the local machine with a
+/// client ID of 0, with no timeouts, so that it behaves just like [`Builder::connect`]. Setting
+/// a timeout bounds how long [`ClientBuilder::connect`] may wait on an unresponsive TWS or
+/// Gateway, rather than waiting forever.
+pub struct ClientBuilder {
+    builder: Builder,
+    client_id: i64,
+    options: ConnectOptions,
+}
+
+impl Default for ClientBuilder {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl From<Builder> for ClientBuilder {
+    #[inline]
+    fn from(builder: Builder) -> Self {
+        Self {
+            builder,
+            client_id: 0,
+            options: ConnectOptions::default(),
+        }
+    }
+}
+
+impl ClientBuilder {
+    #[must_use]
+    #[inline]
+    /// Creates a new [`ClientBuilder`] that connects to 127.0.0.1 on port 7497, the default port of
+    /// a paper trading TWS.
+    pub fn new() -> Self {
+        Builder::manual(7497, None).into()
+    }
+
+    #[must_use]
+    #[inline]
+    /// Set the IP address with which to connect to IBKR's trading systems.
+    pub fn host(mut self, address: std::net::Ipv4Addr) -> Self {
+        self.builder = Builder::manual(self.builder.target().2, Some(address));
+        self
+    }
+
+    #[must_use]
+    #[inline]
+    /// Set the TCP port with which to connect to IBKR's trading systems.
+    pub fn port(mut self, port: u16) -> Self {
+        self.builder = Builder::manual(port, Some(self.builder.target().3));
+        self
+    }
+
+    #[must_use]
+    #[inline]
+    /// Set the unique ID for IBKR's systems to distinguish between clients.
+    pub const fn client_id(mut self, client_id: i64) -> Self {
+        self.client_id = client_id;
+        self
+    }
+
+    #[must_use]
+    #[inline]
+    /// Set the maximum time to wait for the TCP connection to be established.
+    pub const fn connect_timeout(mut self, timeout: std::time::Duration) -> Self {
+        self.options.connect_timeout = Some(timeout);
+        self
+    }
+
+    #[must_use]
+    #[inline]
+    /// Set the maximum time to wait for the IBKR API to complete its handshake once the TCP
+    /// connection is established.
+    pub const fn handshake_timeout(mut self, timeout: std::time::Duration) -> Self {
+        self.options.handshake_timeout = Some(timeout);
+        self
+    }
+
+    #[must_use]
+    #[inline]
+    /// Set whether Nagle's algorithm is disabled on the connection, which sends small messages
+    /// like requests without delay. By default, it is not disabled.
+    pub const fn tcp_nodelay(mut self, nodelay: bool) -> Self {
+        self.options.tcp_nodelay = nodelay;
+        self
+    }
+
+    #[must_use]
+    #[inline]
+    /// Set the optional capabilities that are sent to the IBKR API when the connection starts.
+    pub fn optional_capabilities(mut self, capabilities: impl Into<String>) -> Self {
+        self.options.optional_capabilities = Some(capabilities.into());
+        self
+    }
+
+    /// Initiates a connection to IBKR's trading systems and returns a [`Client`].
+    ///
+    /// # Errors
+    /// Returns the same errors as [`Builder::connect`], as well as:
+    /// 1) [`ConnectionError::ConnectTimeout`] or [`ConnectionError::HandshakeTimeout`] if a
+    ///    timeout elapses.
+    /// 2) [`ConnectionError::UnsupportedServerVersion`] if the negotiated server version is older
+    ///    than 180, the minimum that this crate supports.
+    ///
+    /// # Returns
+    /// An inactive [`Client`] that will become active upon calling [`Client::local`] or
+    /// [`Client::remote`].
+    pub async fn connect(&self) -> Result<Client<indicators::Inactive>, ConnectionError> {
+        self.builder
+            .connect_with(self.client_id, &self.options)
+            .await
+    }
+}
+
 // ===============================
 // === Status Trait Definition ===
 // ===============================
@@ -309,7 +501,7 @@ pub type ActiveClient = Client<indicators::Active>;
 type IntoActive = (
     Client<indicators::Active>,
     mpsc::Sender<ToClient>,
-    mpsc::Receiver<ToWrapper>,
+    decode::PendingQueries,
     mpsc::Receiver<Vec<String>>,
     std::collections::VecDeque<Vec<String>>,
 );
@@ -317,7 +509,7 @@ type IntoActive = (
 type LoopParams = (
     mpsc::Receiver<Vec<String>>,
     mpsc::Sender<ToClient>,
-    mpsc::Receiver<ToWrapper>,
+    decode::PendingQueries,
     std::collections::VecDeque<Vec<String>>,
 );
 
@@ -328,10 +520,19 @@ async fn decode_msg_remote<W>(
     fields: Vec<String>,
     remote: &mut W,
     tx: &mut mpsc::Sender<ToClient>,
-    rx: &mut mpsc::Receiver<ToWrapper>,
+    rx: &mut decode::PendingQueries,
+    notifications: &broadcast::Sender<Notification>,
 ) where
     W: Wrapper,
 {
+    let fields = match decode::decode_subscription_msg(fields, rx).await {
+        Ok(Some(fields)) => fields,
+        Ok(None) => return,
+        Err(e) => {
+            error!("Error in decoding incoming message from API. Error message: {}", e.with_context("subscription msg"));
+            return;
+        }
+    };
     let status = match fields.first() {
         None => Err(DecodeError::MissingData {
             field_name: "In-message identifier",
@@ -349,9 +550,13 @@ async fn decode_msg_remote<W>(
                     .await
                     .map_err(|e| e.with_context("order status msg"))
             }
-            Ok(In::ErrMsg) => decode::Remote::err_msg_msg(&mut fields.into_iter(), remote)
-                .await
-                .map_err(|e| e.with_context("err msg msg")),
+            Ok(In::ErrMsg) => {
+                match decode::decode_err_msg_no_wrapper(&mut fields.clone().into_iter(), tx, rx, notifications).await {
+                    Ok(()) => decode::Remote::err_msg_msg(&mut fields.into_iter(), remote).await,
+                    Err(e) => Err(e),
+                }
+                .map_err(|e| e.with_context("err msg msg"))
+            }
             Ok(In::OpenOrder) => decode::Remote::open_order_msg(&mut fields.into_iter(), remote)
                 .await
                 .map_err(|e| e.with_context("open order msg")),
@@ -412,7 +617,7 @@ async fn decode_msg_remote<W>(
                     .map_err(|e| e.with_context("historical data msg"))
             }
             Ok(In::BondContractData) => {
-                decode::Remote::bond_contract_data_msg(&mut fields.into_iter(), remote)
+                decode::Remote::bond_contract_data_msg(&mut fields.into_iter(), remote, tx, rx)
                     .await
                     .map_err(|e| e.with_context("bond contract data msg"))
             }
@@ -443,7 +648,7 @@ async fn decode_msg_remote<W>(
                 .await
                 .map_err(|e| e.with_context("tick efp msg")),
             Ok(In::CurrentTime) => {
-                decode::Remote::current_time_msg(&mut fields.into_iter(), remote)
+                decode::Remote::current_time_msg(&mut fields.into_iter(), remote, tx, rx)
                     .await
                     .map_err(|e| e.with_context("current time msg"))
             }
@@ -458,7 +663,7 @@ async fn decode_msg_remote<W>(
                     .map_err(|e| e.with_context("fundamental data msg"))
             }
             Ok(In::ContractDataEnd) => {
-                decode::Remote::contract_data_end_msg(&mut fields.into_iter(), remote)
+                decode::Remote::contract_data_end_msg(&mut fields.into_iter(), remote, tx, rx)
                     .await
                     .map_err(|e| e.with_context("contract data end msg"))
             }
@@ -571,6 +776,8 @@ async fn decode_msg_remote<W>(
                 decode::Remote::security_definition_option_parameter_msg(
                     &mut fields.into_iter(),
                     remote,
+                    tx,
+                    rx,
                 )
                 .await
                 .map_err(|e| e.with_context("security definition option parameter msg"))
@@ -579,6 +786,8 @@ async fn decode_msg_remote<W>(
                 decode::Remote::security_definition_option_parameter_end_msg(
                     &mut fields.into_iter(),
                     remote,
+                    tx,
+                    rx,
                 )
                 .await
                 .map_err(|e| e.with_context("security definition option parameter end msg"))
@@ -594,7 +803,7 @@ async fn decode_msg_remote<W>(
                     .map_err(|e| e.with_context("family codes msg"))
             }
             Ok(In::SymbolSamples) => {
-                decode::Remote::symbol_samples_msg(&mut fields.into_iter(), remote)
+                decode::Remote::symbol_samples_msg(&mut fields.into_iter(), remote, tx, rx)
                     .await
                     .map_err(|e| e.with_context("symbol samples msg"))
             }
@@ -637,12 +846,12 @@ async fn decode_msg_remote<W>(
                     .map_err(|e| e.with_context("historical news end msg"))
             }
             Ok(In::HeadTimestamp) => {
-                decode::Remote::head_timestamp_msg(&mut fields.into_iter(), remote)
+                decode::Remote::head_timestamp_msg(&mut fields.into_iter(), remote, tx, rx)
                     .await
                     .map_err(|e| e.with_context("head timestamp msg"))
             }
             Ok(In::HistogramData) => {
-                decode::Remote::histogram_data_msg(&mut fields.into_iter(), remote)
+                decode::Remote::histogram_data_msg(&mut fields.into_iter(), remote, tx, rx)
                     .await
                     .map_err(|e| e.with_context("histogram data msg"))
             }
@@ -661,9 +870,11 @@ async fn decode_msg_remote<W>(
                     .await
                     .map_err(|e| e.with_context("reroute mkt depth req msg"))
             }
-            Ok(In::MarketRule) => decode::Remote::market_rule_msg(&mut fields.into_iter(), remote)
-                .await
-                .map_err(|e| e.with_context("market rule msg")),
+            Ok(In::MarketRule) => {
+                decode::Remote::market_rule_msg(&mut fields.into_iter(), remote, tx, rx)
+                    .await
+                    .map_err(|e| e.with_context("market rule msg"))
+            }
             Ok(In::Pnl) => decode::Remote::pnl_msg(&mut fields.into_iter(), remote)
                 .await
                 .map_err(|e| e.with_context("pnl msg")),
@@ -739,14 +950,23 @@ async fn decode_msg_remote<W>(
 #[inline]
 #[allow(clippy::too_many_lines)]
 #[tracing::instrument(skip(local), level = tracing::Level::DEBUG)]
-async fn decode_msg_local<W>(
+pub(crate) async fn decode_msg_local<W>(
     fields: Vec<String>,
     local: &mut W,
     tx: &mut mpsc::Sender<ToClient>,
-    rx: &mut mpsc::Receiver<ToWrapper>,
+    rx: &mut decode::PendingQueries,
+    notifications: &broadcast::Sender<Notification>,
 ) where
     W: LocalWrapper,
 {
+    let fields = match decode::decode_subscription_msg(fields, rx).await {
+        Ok(Some(fields)) => fields,
+        Ok(None) => return,
+        Err(e) => {
+            error!("Error in decoding incoming message from API. Error message: {}", e.with_context("subscription msg"));
+            return;
+        }
+    };
     let status = match fields.first() {
         None => Err(DecodeError::MissingData {
             field_name: "In-message identifier",
@@ -762,9 +982,13 @@ async fn decode_msg_local<W>(
             Ok(In::OrderStatus) => decode::Local::order_status_msg(&mut fields.into_iter(), local)
                 .await
                 .map_err(|e| e.with_context("order status msg")),
-            Ok(In::ErrMsg) => decode::Local::err_msg_msg(&mut fields.into_iter(), local)
-                .await
-                .map_err(|e| e.with_context("err msg msg")),
+            Ok(In::ErrMsg) => {
+                match decode::decode_err_msg_no_wrapper(&mut fields.clone().into_iter(), tx, rx, notifications).await {
+                    Ok(()) => decode::Local::err_msg_msg(&mut fields.into_iter(), local).await,
+                    Err(e) => Err(e),
+                }
+                .map_err(|e| e.with_context("err msg msg"))
+            }
             Ok(In::OpenOrder) => decode::Local::open_order_msg(&mut fields.into_iter(), local)
                 .await
                 .map_err(|e| e.with_context("open order msg")),
@@ -823,7 +1047,7 @@ async fn decode_msg_local<W>(
                     .map_err(|e| e.with_context("historical data msg"))
             }
             Ok(In::BondContractData) => {
-                decode::Local::bond_contract_data_msg(&mut fields.into_iter(), local)
+                decode::Local::bond_contract_data_msg(&mut fields.into_iter(), local, tx, rx)
                     .await
                     .map_err(|e| e.with_context("bond contract data msg"))
             }
@@ -849,7 +1073,7 @@ async fn decode_msg_local<W>(
             Ok(In::TickEfp) => decode::Local::tick_efp_msg(&mut fields.into_iter(), local)
                 .await
                 .map_err(|e| e.with_context("tick efp msg")),
-            Ok(In::CurrentTime) => decode::Local::current_time_msg(&mut fields.into_iter(), local)
+            Ok(In::CurrentTime) => decode::Local::current_time_msg(&mut fields.into_iter(), local, tx, rx)
                 .await
                 .map_err(|e| e.with_context("current time msg")),
             Ok(In::RealTimeBars) => {
@@ -863,7 +1087,7 @@ async fn decode_msg_local<W>(
                     .map_err(|e| e.with_context("fundamental data msg"))
             }
             Ok(In::ContractDataEnd) => {
-                decode::Local::contract_data_end_msg(&mut fields.into_iter(), local)
+                decode::Local::contract_data_end_msg(&mut fields.into_iter(), local, tx, rx)
                     .await
                     .map_err(|e| e.with_context("contract data end msg"))
             }
@@ -974,6 +1198,8 @@ async fn decode_msg_local<W>(
                 decode::Local::security_definition_option_parameter_msg(
                     &mut fields.into_iter(),
                     local,
+                    tx,
+                    rx,
                 )
                 .await
                 .map_err(|e| e.with_context("security definition option parameter msg"))
@@ -982,6 +1208,8 @@ async fn decode_msg_local<W>(
                 decode::Local::security_definition_option_parameter_end_msg(
                     &mut fields.into_iter(),
                     local,
+                    tx,
+                    rx,
                 )
                 .await
                 .map_err(|e| e.with_context("security definition option parameter end msg"))
@@ -995,7 +1223,7 @@ async fn decode_msg_local<W>(
                 .await
                 .map_err(|e| e.with_context("family codes msg")),
             Ok(In::SymbolSamples) => {
-                decode::Local::symbol_samples_msg(&mut fields.into_iter(), local)
+                decode::Local::symbol_samples_msg(&mut fields.into_iter(), local, tx, rx)
                     .await
                     .map_err(|e| e.with_context("symbol samples msg"))
             }
@@ -1036,12 +1264,12 @@ async fn decode_msg_local<W>(
                     .map_err(|e| e.with_context("historical news end msg"))
             }
             Ok(In::HeadTimestamp) => {
-                decode::Local::head_timestamp_msg(&mut fields.into_iter(), local)
+                decode::Local::head_timestamp_msg(&mut fields.into_iter(), local, tx, rx)
                     .await
                     .map_err(|e| e.with_context("head timestamp msg"))
             }
             Ok(In::HistogramData) => {
-                decode::Local::histogram_data_msg(&mut fields.into_iter(), local)
+                decode::Local::histogram_data_msg(&mut fields.into_iter(), local, tx, rx)
                     .await
                     .map_err(|e| e.with_context("histogram data msg"))
             }
@@ -1060,9 +1288,11 @@ async fn decode_msg_local<W>(
                     .await
                     .map_err(|e| e.with_context("reroute mkt depth req msg"))
             }
-            Ok(In::MarketRule) => decode::Local::market_rule_msg(&mut fields.into_iter(), local)
-                .await
-                .map_err(|e| e.with_context("market rule msg")),
+            Ok(In::MarketRule) => {
+                decode::Local::market_rule_msg(&mut fields.into_iter(), local, tx, rx)
+                    .await
+                    .map_err(|e| e.with_context("market rule msg"))
+            }
             Ok(In::Pnl) => decode::Local::pnl_msg(&mut fields.into_iter(), local)
                 .await
                 .map_err(|e| e.with_context("pnl msg")),
@@ -1155,11 +1385,16 @@ pub(crate) mod indicators {
     pub struct Active {
         pub(crate) r_thread: JoinHandle<Reader>,
         pub(crate) disconnect: super::CancelToken,
-        pub(crate) tx: mpsc::Sender<ToWrapper>,
+        pub(crate) tx: mpsc::UnboundedSender<ToWrapper>,
         pub(crate) rx: mpsc::Receiver<ToClient>,
         pub(crate) managed_accounts: HashSet<String>,
+        pub(crate) account_codes: Vec<crate::account::AccountCode>,
         pub(crate) order_id: core::ops::RangeFrom<i64>,
         pub(crate) req_id: core::ops::RangeFrom<i64>,
+        pub(crate) last_symbol_search: Option<tokio::time::Instant>,
+        pub(crate) request_timeout: Option<std::time::Duration>,
+        pub(crate) notifications: tokio::sync::broadcast::Sender<crate::error_code::Notification>,
+        pub(crate) request_errors: std::collections::HashMap<i64, crate::error_code::TwsError>,
     }
 
     impl Status for Active {}
@@ -1248,18 +1483,50 @@ impl<S: indicators::Status> Client<S> {
     pub const fn get_server_version(&self) -> u32 {
         self.server_version
     }
+
+    // ==========================
+    // === Recording Messages ===
+    // ==========================
+
+    /// Record every message that the client sends or receives from now on to a new file at
+    /// `path`, replacing any recording in progress. See [`crate::record::read`] for the format of
+    /// the file, which can be replayed with a [`crate::record::ReplayClient`].
+    ///
+    /// # Errors
+    /// Returns any error encountered while creating the file or while finishing the previous
+    /// recording.
+    pub fn record_to(&mut self, path: impl AsRef<std::path::Path>) -> Result<(), std::io::Error> {
+        self.writer.recorder.start(path.as_ref())
+    }
+
+    /// Stop the recording in progress, if any.
+    ///
+    /// # Errors
+    /// Returns any error encountered while finishing the recording.
+    pub fn stop_recording(&mut self) -> Result<(), std::io::Error> {
+        self.writer.recorder.stop()
+    }
+}
+
+#[inline]
+fn connection_lost() -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::ConnectionAborted,
+        "The connection to the IBKR trading systems was lost",
+    )
 }
 
 #[inline]
 fn spawn_reader_thread(
     rdr: OwnedReadHalf,
+    recorder: Recorder,
 ) -> (CancelToken, mpsc::Receiver<Vec<String>>, JoinHandle<Reader>) {
     let disconnect = CancelToken::new();
     let (tx, rx) = mpsc::channel(constants::FROM_READER_CHANNEL_SIZE);
 
     let r_disconnect = disconnect.clone();
     let r_thread = tokio::spawn(async move {
-        let reader = Reader::new(rdr, tx, r_disconnect);
+        let reader = Reader::new(rdr, tx, r_disconnect, recorder);
         reader.run().await
     });
     (disconnect, rx, r_thread)
@@ -1271,15 +1538,32 @@ fn spawn_temp_contract_thread(
     mut rx_reader: mpsc::Receiver<Vec<String>>,
     mut backlog: std::collections::VecDeque<Vec<String>>,
     mut tx: mpsc::Sender<ToClient>,
-    mut rx: mpsc::Receiver<ToWrapper>,
+    mut rx: decode::PendingQueries,
 ) -> JoinHandle<LoopParams> {
     tokio::spawn(async move {
+        // Notifications are broadcast once the backlog is decoded, so that none is sent twice
+        let (notifications, _) = broadcast::channel(1);
         loop {
             tokio::select! {
                 biased;
                 Some(fields) = rx_reader.recv() => {
                      let _ = match fields.first().and_then(|t| t.parse().ok()) {
                         Some(In::ContractData) => decode::decode_contract_no_wrapper(&mut fields.into_iter(), &mut tx, &mut rx).await.map_err(|e| e.with_context("contract data msg")),
+                        Some(In::BondContractData) => decode::decode_bond_contract_no_wrapper(&mut fields.into_iter(), &mut tx, &mut rx).await.map_err(|e| e.with_context("bond contract data msg")),
+                        Some(In::ContractDataEnd) => decode::decode_contract_data_end_no_wrapper(&mut fields.into_iter(), &mut tx, &mut rx).await.map_err(|e| e.with_context("contract data end msg")),
+                        Some(In::SecurityDefinitionOptionParameter) => decode::decode_option_chain_no_wrapper(&mut fields.into_iter(), &mut tx).await.map_err(|e| e.with_context("security definition option parameter msg")),
+                        Some(In::SecurityDefinitionOptionParameterEnd) => decode::decode_option_chain_end_no_wrapper(&mut fields.into_iter(), &mut tx).await.map_err(|e| e.with_context("security definition option parameter end msg")),
+                        Some(In::SymbolSamples) => decode::decode_symbol_samples_no_wrapper(&mut fields.into_iter(), &mut tx).await.map_err(|e| e.with_context("symbol samples msg")),
+                        Some(In::MarketRule) => decode::decode_market_rule_no_wrapper(&mut fields.into_iter(), &mut tx).await.map_err(|e| e.with_context("market rule msg")),
+                        Some(In::CurrentTime) if rx.take_current_time() => decode::decode_current_time_no_wrapper(&mut fields.into_iter(), &mut tx).await.map_err(|e| e.with_context("current time msg")),
+                        Some(In::HeadTimestamp) => decode::decode_head_timestamp_no_wrapper(&mut fields.into_iter(), &mut tx).await.map_err(|e| e.with_context("head timestamp msg")),
+                        Some(In::HistogramData) => decode::decode_histogram_no_wrapper(&mut fields.into_iter(), &mut tx).await.map_err(|e| e.with_context("histogram data msg")),
+                        // The wrapper still receives the error once it is built
+                        Some(In::ErrMsg) => {
+                            let routed = decode::decode_err_msg_no_wrapper(&mut fields.clone().into_iter(), &mut tx, &mut rx, &notifications).await.map_err(|e| e.with_context("err msg msg"));
+                            backlog.push_back(fields);
+                            routed
+                        },
                         Some(_) => { backlog.push_back(fields); Ok(()) },
                         None => Ok(()),
                     };
@@ -1297,17 +1581,17 @@ impl Client<indicators::Inactive> {
     // === Methods That Initiate the API Loop ===
     // ==========================================
 
-    async fn start_api(&mut self) -> Result<(), std::io::Error> {
+    async fn start_api(&mut self, optional_capabilities: Option<&str>) -> Result<(), std::io::Error> {
         const VERSION: u8 = 2;
 
         self.writer
-            .add_body((Out::StartApi, VERSION, self.client_id, None::<()>))?;
+            .add_body((Out::StartApi, VERSION, self.client_id, optional_capabilities))?;
         self.writer.send().await?;
         Ok(())
     }
 
     async fn into_active(self) -> IntoActive {
-        let (disconnect, mut rx_reader, r_thread) = spawn_reader_thread(self.status.reader);
+        let (disconnect, mut rx_reader, r_thread) = spawn_reader_thread(self.status.reader, self.writer.recorder.clone());
 
         let mut backlog = std::collections::VecDeque::new();
         let (mut managed_accounts, mut valid_id) = (None, None);
@@ -1315,12 +1599,15 @@ impl Client<indicators::Inactive> {
             if let Some(fields) = rx_reader.recv().await {
                 match fields.first().and_then(|t| t.parse().ok()) {
                     Some(In::ManagedAccts) => {
+                        // The accounts are sent as a single comma-separated field
                         managed_accounts = Some(
                             fields
-                                .into_iter()
+                                .iter()
                                 .skip(2)
-                                .filter(|v| v.as_str() != "")
-                                .collect::<std::collections::HashSet<String>>(),
+                                .flat_map(|v| v.split(','))
+                                .filter(|v| !v.is_empty())
+                                .map(ToOwned::to_owned)
+                                .collect::<Vec<String>>(),
                         );
                     }
                     Some(In::NextValidId) => {
@@ -1339,8 +1626,18 @@ impl Client<indicators::Inactive> {
                 "The loop should only exit if a valid set of accounts and id are received."
             )
         };
-        let (client_tx, wrapper_rx) =
-            mpsc::channel::<ToWrapper>(constants::TO_WRAPPER_CHANNEL_SIZE);
+        let account_codes = managed_accounts
+            .iter()
+            .filter_map(|account| match account.parse() {
+                Ok(code) => Some(code),
+                Err(e) => {
+                    warn!("Managed account is not an individual account: {e}");
+                    None
+                }
+            })
+            .collect();
+        // Requests are registered with the decoder without waiting, so that none is ever dropped
+        let (client_tx, wrapper_rx) = mpsc::unbounded_channel::<ToWrapper>();
         let (wrapper_tx, client_rx) = mpsc::channel::<ToClient>(constants::TO_CLIENT_CHANNEL_SIZE);
 
         let client = Client {
@@ -1357,12 +1654,23 @@ impl Client<indicators::Inactive> {
                 disconnect,
                 tx: client_tx,
                 rx: client_rx,
-                managed_accounts,
+                managed_accounts: managed_accounts.into_iter().collect(),
+                account_codes,
                 order_id: valid_id..,
                 req_id: 0_i64..,
+                last_symbol_search: None,
+                request_timeout: Some(constants::DEFAULT_REQUEST_TIMEOUT),
+                notifications: broadcast::channel(constants::NOTIFICATION_CHANNEL_SIZE).0,
+                request_errors: std::collections::HashMap::new(),
             },
         };
-        (client, wrapper_tx, wrapper_rx, rx_reader, backlog)
+        (
+            client,
+            wrapper_tx,
+            decode::PendingQueries::new(wrapper_rx),
+            rx_reader,
+            backlog,
+        )
     }
 
     /// Initiates the main message loop and spawns all helper threads to manage the application.
@@ -1379,6 +1687,8 @@ impl Client<indicators::Inactive> {
     ///
     /// # Errors
     /// Returns any error that occurs in the loop initialization or in the disconnection process.
+    /// Also returns an error of kind [`std::io::ErrorKind::ConnectionAborted`] if the connection
+    /// is lost, like when TWS restarts. See [`ResilientClient`] to reconnect automatically.
     #[tracing::instrument(skip(init), level = tracing::Level::DEBUG)]
     pub async fn local<I: LocalInitializer>(
         self,
@@ -1386,6 +1696,7 @@ impl Client<indicators::Inactive> {
         disconnect_token: Option<CancelToken>,
     ) -> Result<Builder, std::io::Error> {
         let (mut client, tx, rx, rx_reader, backlog) = self.into_active().await;
+        let notifications = client.status.notifications.clone();
         let temp = CancelToken::new();
         let con_fut = spawn_temp_contract_thread(temp.clone(), rx_reader, backlog, tx, rx);
 
@@ -1396,14 +1707,20 @@ impl Client<indicators::Inactive> {
         drop(temp);
         let (mut rx_reader, mut tx, mut rx, mut backlog) = con_fut.await?;
         while let Some(fields) = backlog.pop_front() {
-            decode_msg_local(fields, &mut wrapper, &mut tx, &mut rx).await;
+            decode_msg_local(fields, &mut wrapper, &mut tx, &mut rx, &notifications).await;
         }
         drop(backlog);
+        let mut lost = false;
         loop {
             tokio::select! {
                 biased;
-                Some(fields) = rx_reader.recv() => {
-                    decode_msg_local(fields, &mut wrapper, &mut tx, &mut rx).await;
+                fields = rx_reader.recv() => match fields {
+                    Some(fields) => decode_msg_local(fields, &mut wrapper, &mut tx, &mut rx, &notifications).await,
+                    None => {
+                        warn!("Client loop: connection lost");
+                        lost = true;
+                        break
+                    },
                 },
                 () = tokio::task::yield_now() => (),
                 () = disconnect_token.cancelled() => {
@@ -1414,6 +1731,11 @@ impl Client<indicators::Inactive> {
             crate::wrapper::LocalRecurring::cycle(&mut wrapper).await;
         }
         drop(wrapper);
+        if lost {
+            // The connection is already gone, so failing to shut it down cleanly is expected
+            let _ = client.disconnect().await;
+            return Err(connection_lost());
+        }
         client.disconnect().await
     }
 
@@ -1428,6 +1750,7 @@ impl Client<indicators::Inactive> {
     #[tracing::instrument(skip(init), level = tracing::Level::DEBUG)]
     pub async fn remote<I: Initializer + 'static>(self, init: I) -> CancelToken {
         let (mut client, tx, rx, rx_reader, backlog) = self.into_active().await;
+        let notifications = client.status.notifications.clone();
 
         let temp = CancelToken::new();
         let con_fut = spawn_temp_contract_thread(temp.clone(), rx_reader, backlog, tx, rx);
@@ -1440,14 +1763,20 @@ impl Client<indicators::Inactive> {
             drop(temp);
             let (mut rx_reader, mut tx, mut rx, mut backlog) = con_fut.await?;
             while let Some(fields) = backlog.pop_front() {
-                decode_msg_remote(fields, &mut wrapper, &mut tx, &mut rx).await;
+                decode_msg_remote(fields, &mut wrapper, &mut tx, &mut rx, &notifications).await;
             }
             drop(backlog);
+            let mut lost = false;
             loop {
                 tokio::select! {
                     biased;
-                    Some(fields) = rx_reader.recv() => {
-                        decode_msg_remote(fields, &mut wrapper, &mut tx, &mut rx).await;
+                    fields = rx_reader.recv() => match fields {
+                        Some(fields) => decode_msg_remote(fields, &mut wrapper, &mut tx, &mut rx, &notifications).await,
+                        None => {
+                            warn!("Client loop: connection lost");
+                            lost = true;
+                            break
+                        },
                     },
                     () = tokio::task::yield_now() => (),
                     () = break_loop_inner.cancelled() => {
@@ -1458,6 +1787,12 @@ impl Client<indicators::Inactive> {
                 Recurring::cycle(&mut wrapper).await;
             }
             drop(wrapper);
+            if lost {
+                // Cancel the returned token so that its holder learns that the loop is over
+                break_loop_inner.cancel();
+                let _ = client.disconnect().await;
+                return Err(connection_lost());
+            }
             client.disconnect().await
         });
 
@@ -1478,9 +1813,10 @@ impl Client<indicators::Inactive> {
     ) -> Client<indicators::Active> {
         let (client, mut tx, mut rx, mut rx_reader, mut backlog) = self.into_active().await;
         let c_loop_disconnect = client.status.disconnect.clone();
+        let notifications = client.status.notifications.clone();
 
         while let Some(fields) = backlog.pop_front() {
-            decode_msg_remote(fields, &mut wrapper, &mut tx, &mut rx).await;
+            decode_msg_remote(fields, &mut wrapper, &mut tx, &mut rx, &notifications).await;
         }
         drop(backlog);
         tokio::spawn(async move {
@@ -1488,8 +1824,15 @@ impl Client<indicators::Inactive> {
             loop {
                 tokio::select! {
                     biased;
-                    Some(fields) = rx_reader.recv() => {
-                        decode_msg_remote(fields, &mut wrapper, &mut tx, &mut rx).await;
+                    fields = rx_reader.recv() => match fields {
+                        Some(fields) => decode_msg_remote(fields, &mut wrapper, &mut tx, &mut rx, &notifications).await,
+                        None => {
+                            // Ending the loop drops the channel to the client, so its pending
+                            // requests fail with a disconnection error instead of hanging
+                            warn!("Client loop: connection lost");
+                            c_loop_disconnect.cancel();
+                            break
+                        },
                     },
                     () = tokio::task::yield_now() => (),
                     () = c_loop_disconnect.cancelled() => {info!("Client loop: disconnecting"); break},
@@ -1531,6 +1874,36 @@ impl Client<indicators::Active> {
         self.status.req_id.next().unwrap()
     }
 
+    /// Register a subscription with the decoder, so that its data is sent to the returned stream
+    /// rather than the wrapper. Call this before sending the request of the subscription, so that
+    /// none of its data is missed.
+    ///
+    /// # Arguments
+    /// * `req_id` - The ID of the subscription's request.
+    /// * `subscriber` - The kind of the subscription.
+    /// * `cancel` - The message that cancels the subscription, which is sent once the stream is
+    ///   dropped.
+    fn subscription<T, C: Serialize>(
+        &mut self,
+        req_id: i64,
+        subscriber: fn(mpsc::UnboundedSender<Result<T, crate::error_code::Error>>) -> Subscriber,
+        cancel: C,
+    ) -> Result<crate::market_data::Subscription<T>, std::io::Error> {
+        let cancel = self.writer.send_on_drop(cancel)?;
+        let (tx, rx) = mpsc::unbounded_channel();
+        // The decoder is gone if the connection is lost, in which case the stream ends at once
+        let _ = self
+            .status
+            .tx
+            .send(ToWrapper::Subscribe((req_id, subscriber(tx))));
+        Ok(crate::market_data::Subscription::new(
+            req_id,
+            rx,
+            self.status.tx.clone(),
+            cancel,
+        ))
+    }
+
     #[inline]
     #[must_use]
     /// Get the set of accounts managed by the client
@@ -1541,6 +1914,55 @@ impl Client<indicators::Active> {
         &self.status.managed_accounts
     }
 
+    #[inline]
+    #[must_use]
+    /// Get the codes of the individual accounts managed by the client, in the order in which they
+    /// were received when the client connected.
+    ///
+    /// Accounts whose codes are not valid [`crate::account::AccountCode`]s, like the master account
+    /// of a financial advisor, are left out, but are still included in
+    /// [`Client::get_managed_accounts`].
+    ///
+    /// # Returns
+    /// The codes of the client's managed accounts, which can be used to scope account and position
+    /// requests.
+    pub fn managed_accounts(&self) -> &[crate::account::AccountCode] {
+        &self.status.account_codes
+    }
+
+    #[inline]
+    #[must_use]
+    /// Subscribe to the messages that IBKR sends without a request ID, like the loss of TWS's
+    /// connectivity (1100) or the status of its data farms (2104 and 2106).
+    ///
+    /// Only the notifications that arrive after subscribing are received, and a receiver that
+    /// falls behind by more than 64 notifications skips the oldest ones. The wrapper's `error`
+    /// method still receives every notification as well.
+    ///
+    /// The receiver is not a `Stream`, since this crate does not depend on a stream library, but
+    /// `tokio_stream::wrappers::BroadcastStream` turns it into one.
+    ///
+    /// # Returns
+    /// A receiver of every subsequent [`Notification`].
+    pub fn notifications(&self) -> broadcast::Receiver<Notification> {
+        self.status.notifications.subscribe()
+    }
+
+    #[inline]
+    /// Set the maximum time to wait for the response to a contract query or a symbol search, after
+    /// which functions like [`crate::contract::new`] fail with a
+    /// [`crate::contract::RequestTimeout`]. The default is 10 seconds.
+    ///
+    /// A request whose future is dropped before its response arrives is cancelled in the same
+    /// way: the late response is discarded, and since request IDs are never reused, it cannot be
+    /// mistaken for the response to a later request.
+    ///
+    /// # Arguments
+    /// * `timeout` - The timeout, or [`None`] to wait for as long as it takes.
+    pub fn set_request_timeout(&mut self, timeout: Option<std::time::Duration>) {
+        self.status.request_timeout = timeout;
+    }
+
     // ===================================
     // === Methods That Make API Calls ===
     // ===================================
@@ -1558,6 +1980,70 @@ impl Client<indicators::Active> {
         self.writer.send().await
     }
 
+    /// Request the current time from the server and wait for the response, rather than receiving
+    /// it in [`crate::wrapper::LocalWrapper::current_time`] like [`Client::req_current_time`].
+    ///
+    /// This is TWS's current time, which TWS synchronizes with the IBKR servers (not the local
+    /// computer) using NTP, to the second.
+    ///
+    /// # Errors
+    /// Returns any error encountered while writing the outgoing message, an error of kind
+    /// [`std::io::ErrorKind::ConnectionAborted`] if the connection closes before the time is
+    /// received, or an error of kind [`std::io::ErrorKind::TimedOut`] if the time is not received
+    /// within the client's request timeout (see [`Client::set_request_timeout`]).
+    pub async fn current_time(&mut self) -> Result<chrono::DateTime<chrono::Utc>, std::io::Error> {
+        let timeout = self.status.request_timeout;
+        let response = self.send_current_time();
+        let received = match timeout {
+            Some(timeout) => tokio::time::timeout(timeout, response).await.ok(),
+            None => Some(response.await),
+        };
+        if let Some(datetime) = received {
+            return datetime?.ok_or_else(connection_lost);
+        }
+        // A late response goes to the wrapper, like that of `Client::req_current_time`
+        let _ = self.status.tx.send(ToWrapper::CancelCurrentTime);
+        Err(std::io::Error::new(
+            std::io::ErrorKind::TimedOut,
+            "The IBKR API did not answer the current time request within the request timeout.",
+        ))
+    }
+
+    #[inline]
+    async fn send_current_time(
+        &mut self,
+    ) -> Result<Option<chrono::DateTime<chrono::Utc>>, std::io::Error> {
+        const VERSION: u8 = 1;
+
+        self.status
+            .tx
+            .send(ToWrapper::CurrentTime)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::BrokenPipe, e.to_string()))?;
+        self.writer.add_body((Out::ReqCurrentTime, VERSION))?;
+        self.writer.send().await?;
+        Ok(self.recv_current_time().await)
+    }
+
+    #[inline]
+    async fn recv_current_time(&mut self) -> Option<chrono::DateTime<chrono::Utc>> {
+        loop {
+            match self.status.rx.recv().await? {
+                ToClient::CurrentTime(datetime) => return Some(datetime),
+                ToClient::NewContract(..)
+                | ToClient::ContractQueryEnd(_)
+                | ToClient::OptionChain(_)
+                | ToClient::OptionChainEnd(_)
+                | ToClient::SymbolMatches(_)
+                | ToClient::MarketRule(_)
+                | ToClient::HeadTimestamp(..)
+                | ToClient::Histogram(..)
+                | ToClient::RequestError(_) => {
+                    warn!("Unexpected contract data received during a current time request");
+                }
+            }
+        }
+    }
+
     /// Requests the accounts to which the logged user has access to.
     ///
     /// # Errors
@@ -1790,7 +2276,9 @@ impl Client<indicators::Active> {
     /// * `regular_trading_hours_only` - When [`true`], only return bars from regular trading hours.
     ///
     /// # Errors
-    /// Returns any error encountered while writing the outgoing message.
+    /// Returns an [`std::io::ErrorKind::InvalidInput`] error if `bar_size` is not valid for
+    /// `duration` (see [`historical_bar::Duration::validate`]), or any error encountered while
+    /// writing the outgoing message.
     ///
     /// # Returns
     /// The unique ID associated with the request.
@@ -1807,6 +2295,9 @@ impl Client<indicators::Active> {
         S: Security,
         D: historical_bar::DataType<S>,
     {
+        duration
+            .validate(bar_size)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
         let id = self.get_next_req_id();
 
         self.writer.add_body((
@@ -1839,7 +2330,9 @@ impl Client<indicators::Active> {
     /// * `regular_trading_hours_only` - When [`true`], only return bars from regular trading hours.
     ///
     /// # Errors
-    /// Returns any error encountered while writing the outgoing message.
+    /// Returns an [`std::io::ErrorKind::InvalidInput`] error if `bar_size` is not valid for
+    /// `duration` (see [`historical_bar::Duration::validate`]), or any error encountered while
+    /// writing the outgoing message.
     ///
     /// # Returns
     /// The unique ID associated with the request.
@@ -1855,11 +2348,38 @@ impl Client<indicators::Active> {
         S: Security,
         D: updating_historical_bar::DataType<S>,
     {
+        duration
+            .validate(bar_size)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
         let id = self.get_next_req_id();
+        self.send_updating_historical_bar(
+            id,
+            security,
+            duration,
+            bar_size,
+            data,
+            regular_trading_hours_only,
+        )
+        .await?;
+        Ok(id)
+    }
 
+    async fn send_updating_historical_bar<S, D>(
+        &mut self,
+        req_id: i64,
+        security: &S,
+        duration: updating_historical_bar::Duration,
+        bar_size: updating_historical_bar::Size,
+        data: D,
+        regular_trading_hours_only: bool,
+    ) -> ReqResult
+    where
+        S: Security,
+        D: updating_historical_bar::DataType<S>,
+    {
         self.writer.add_body((
             Out::ReqHistoricalData,
-            id,
+            req_id,
             security.as_out_msg(),
             false,
             None::<()>,
@@ -1871,8 +2391,40 @@ impl Client<indicators::Active> {
             true,
             None::<()>,
         ))?;
-        self.writer.send().await?;
-        Ok(id)
+        self.writer.send().await
+    }
+
+    /// See [`crate::market_data::bars_updating`].
+    pub(crate) async fn updating_historical_bar_stream<S, D>(
+        &mut self,
+        security: &S,
+        duration: updating_historical_bar::Duration,
+        bar_size: updating_historical_bar::Size,
+        data: D,
+        regular_trading_hours_only: bool,
+    ) -> Result<crate::market_data::Subscription<crate::message::UpdatingBar>, std::io::Error>
+    where
+        S: Security,
+        D: updating_historical_bar::DataType<S>,
+    {
+        const CANCEL_VERSION: u8 = 1;
+
+        duration
+            .validate(bar_size)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+        let id = self.get_next_req_id();
+        let cancel = (Out::CancelHistoricalData, CANCEL_VERSION, id);
+        let stream = self.subscription(id, Subscriber::UpdatingBars, cancel)?;
+        self.send_updating_historical_bar(
+            id,
+            security,
+            duration,
+            bar_size,
+            data,
+            regular_trading_hours_only,
+        )
+        .await?;
+        Ok(stream)
     }
 
     /// Cancel an existing [`historical_bar`] data request.
@@ -1890,62 +2442,237 @@ impl Client<indicators::Active> {
         self.writer.send().await
     }
 
-    /// Request the earliest available data point for a given security and data type.
+    /// Request the earliest available data point for a given security and data type and wait
+    /// for the response, like to know how far back a backfill can go.
+    ///
+    /// If the response does not arrive within the client's request timeout (see
+    /// [`Client::set_request_timeout`]), the request is cancelled so that IBKR frees its ID. An
+    /// error with which IBKR rejects the request, like error 162 when it has no data, is
+    /// delivered to the wrapper, so the request then times out.
     ///
     /// # Arguments
-    /// `security` - The security for which to make the request.
-    /// `data` - The data for which to make the request.
-    /// * `regular_trading_hours_only` - When [`true`], only return ticks from regular trading
+    /// * `security` - The security for which to make the request.
+    /// * `data` - The data for which to make the request.
+    /// * `regular_trading_hours_only` - When [`true`], only consider data from regular trading
     ///   hours.
     ///
     /// # Errors
-    /// Returns any error encountered while writing the outgoing message.
+    /// Returns any error encountered while writing the outgoing messages. Also returns
+    /// [`historical_ticks::HeadTimestampError::Timeout`] if the request timed out, and
+    /// [`historical_ticks::HeadTimestampError::Disconnected`] if the connection closes before the
+    /// timestamp is received.
     ///
     /// # Returns
-    /// The unique ID associated with the request.
-    pub async fn req_head_timestamp<S, D>(
+    /// The datetime of the earliest available data point.
+    pub async fn head_timestamp<S, D>(
         &mut self,
         security: &S,
         data: D,
         regular_trading_hours_only: bool,
-    ) -> IdResult
+    ) -> Result<chrono::DateTime<chrono::Utc>, historical_ticks::HeadTimestampError>
     where
         S: Security,
         D: historical_ticks::DataType<S>,
     {
-        let id = self.get_next_req_id();
+        let req_id = self.get_next_req_id();
+        self.send_head_timestamp(req_id, security, data, regular_trading_hours_only)
+            .await?;
+        match crate::contract::with_timeout(
+            self.status.request_timeout,
+            req_id,
+            RequestKind::HeadTimestamp,
+            self.recv_head_timestamp(req_id),
+        )
+        .await
+        {
+            Ok(timestamp) => timestamp.ok_or(historical_ticks::HeadTimestampError::Disconnected),
+            Err(timeout) => {
+                self.cancel_head_timestamp(req_id).await?;
+                Err(timeout.into())
+            }
+        }
+    }
 
-        self.writer.add_body((
-            Out::ReqHeadTimestamp,
-            id,
-            security.as_out_msg(),
-            None::<()>,
-            regular_trading_hours_only,
-            data,
-            2,
-        ))?;
-        self.writer.send().await?;
-        Ok(id)
+    #[inline]
+    async fn recv_head_timestamp(
+        &mut self,
+        req_id: i64,
+    ) -> Option<chrono::DateTime<chrono::Utc>> {
+        loop {
+            match self.status.rx.recv().await? {
+                ToClient::HeadTimestamp(id, timestamp) if id == req_id => return Some(timestamp),
+                ToClient::HeadTimestamp(id, _) => {
+                    warn!("Unexpected head timestamp received for request {id}");
+                }
+                ToClient::NewContract(..)
+                | ToClient::ContractQueryEnd(_)
+                | ToClient::OptionChain(_)
+                | ToClient::OptionChainEnd(_)
+                | ToClient::SymbolMatches(_)
+                | ToClient::MarketRule(_)
+                | ToClient::CurrentTime(_)
+                | ToClient::Histogram(..)
+                | ToClient::RequestError(_) => {
+                    warn!("Unexpected contract data received during a head timestamp request");
+                }
+            }
+        }
     }
 
-    /// Cancel an existing [`Client::req_head_timestamp`] data request.
+    #[deprecated(since = "0.2.0", note = "use `Client::head_timestamp`, which returns the timestamp")]
+    /// Request the earliest available data point for a given security and data type. The
+    /// response is delivered to the deprecated [`crate::wrapper::LocalWrapper::head_timestamp`].
     ///
     /// # Arguments
-    /// * `req_id` - The ID of the [`Client::req_head_timestamp`] request to cancel.
+    /// * `security` - The security for which to make the request.
+    /// * `data` - The data for which to make the request.
+    /// * `regular_trading_hours_only` - When [`true`], only consider data from regular trading
+    ///   hours.
     ///
     /// # Errors
     /// Returns any error encountered while writing the outgoing message.
-    pub async fn cancel_head_timestamp(&mut self, req_id: i64) -> ReqResult {
-        self.writer.add_body((Out::CancelHeadTimestamp, req_id))?;
-        self.writer.send().await
-    }
-
-    /// Request a histogram of historical data.
     ///
-    /// # Arguments
-    /// * `security` - The security for which to request histogram data.
-    /// * `regular_trading_hours_only` - When [`true`], only return ticks from regular trading hours.
-    /// * `duration` - The duration of data to return.
+    /// # Returns
+    /// The unique ID associated with the request.
+    pub async fn req_head_timestamp<S, D>(
+        &mut self,
+        security: &S,
+        data: D,
+        regular_trading_hours_only: bool,
+    ) -> IdResult
+    where
+        S: Security,
+        D: historical_ticks::DataType<S>,
+    {
+        let req_id = self.get_next_req_id();
+        self.status
+            .tx
+            .send(ToWrapper::WrapperRequest(req_id))
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::BrokenPipe, e.to_string()))?;
+        self.send_head_timestamp(req_id, security, data, regular_trading_hours_only)
+            .await?;
+        Ok(req_id)
+    }
+
+    #[inline]
+    async fn send_head_timestamp<S, D>(
+        &mut self,
+        req_id: i64,
+        security: &S,
+        data: D,
+        regular_trading_hours_only: bool,
+    ) -> ReqResult
+    where
+        S: Security,
+        D: historical_ticks::DataType<S>,
+    {
+        self.writer.add_body((
+            Out::ReqHeadTimestamp,
+            req_id,
+            security.as_out_msg(),
+            None::<()>,
+            regular_trading_hours_only,
+            data,
+            2,
+        ))?;
+        self.writer.send().await
+    }
+
+    /// Cancel an existing [`Client::head_timestamp`] or [`Client::req_head_timestamp`] data
+    /// request.
+    ///
+    /// # Arguments
+    /// * `req_id` - The ID of the request to cancel.
+    ///
+    /// # Errors
+    /// Returns any error encountered while writing the outgoing message.
+    pub async fn cancel_head_timestamp(&mut self, req_id: i64) -> ReqResult {
+        self.writer.add_body((Out::CancelHeadTimestamp, req_id))?;
+        self.writer.send().await
+    }
+
+    /// Request a histogram of the sizes traded at each price over a period, like for a volume
+    /// profile, and wait for the response.
+    ///
+    /// If the response does not arrive within the client's request timeout (see
+    /// [`Client::set_request_timeout`]), the request is cancelled so that IBKR frees its ID. An
+    /// error with which IBKR rejects the request is delivered to the wrapper, so the request then
+    /// times out.
+    ///
+    /// # Arguments
+    /// * `security` - The security for which to request histogram data.
+    /// * `regular_trading_hours_only` - When [`true`], only count trades from regular trading
+    ///   hours.
+    /// * `duration` - The period, ending now, over which to count trades.
+    ///
+    /// # Errors
+    /// Returns any error encountered while writing the outgoing messages. Also returns
+    /// [`histogram::HistogramError::Timeout`] if the request timed out, and
+    /// [`histogram::HistogramError::Disconnected`] if the connection closes before the histogram
+    /// is received.
+    ///
+    /// # Returns
+    /// The entries of the histogram, in the order in which IBKR sent them.
+    pub async fn histogram<S>(
+        &mut self,
+        security: &S,
+        regular_trading_hours_only: bool,
+        duration: histogram::Duration,
+    ) -> Result<Vec<HistogramEntry>, histogram::HistogramError>
+    where
+        S: Security,
+    {
+        let req_id = self.get_next_req_id();
+        self.send_histogram_data(req_id, security, regular_trading_hours_only, duration)
+            .await?;
+        match crate::contract::with_timeout(
+            self.status.request_timeout,
+            req_id,
+            RequestKind::Histogram,
+            self.recv_histogram(req_id),
+        )
+        .await
+        {
+            Ok(entries) => entries.ok_or(histogram::HistogramError::Disconnected),
+            Err(timeout) => {
+                self.cancel_histogram_data(req_id).await?;
+                Err(timeout.into())
+            }
+        }
+    }
+
+    #[inline]
+    async fn recv_histogram(&mut self, req_id: i64) -> Option<Vec<HistogramEntry>> {
+        loop {
+            match self.status.rx.recv().await? {
+                ToClient::Histogram(id, entries) if id == req_id => return Some(entries),
+                ToClient::Histogram(id, _) => {
+                    warn!("Unexpected histogram received for request {id}");
+                }
+                ToClient::NewContract(..)
+                | ToClient::ContractQueryEnd(_)
+                | ToClient::OptionChain(_)
+                | ToClient::OptionChainEnd(_)
+                | ToClient::SymbolMatches(_)
+                | ToClient::MarketRule(_)
+                | ToClient::CurrentTime(_)
+                | ToClient::HeadTimestamp(..)
+                | ToClient::RequestError(_) => {
+                    warn!("Unexpected contract data received during a histogram request");
+                }
+            }
+        }
+    }
+
+    #[deprecated(since = "0.2.0", note = "use `Client::histogram`, which returns the entries in order")]
+    /// Request a histogram of historical data. The response is delivered to the deprecated
+    /// [`crate::wrapper::LocalWrapper::histogram`].
+    ///
+    /// # Arguments
+    /// * `security` - The security for which to request histogram data.
+    /// * `regular_trading_hours_only` - When [`true`], only count trades from regular trading
+    ///   hours.
+    /// * `duration` - The period, ending now, over which to count trades.
     ///
     /// # Errors
     /// Returns any error encountered while writing the outgoing message.
@@ -1961,24 +2688,42 @@ impl Client<indicators::Active> {
     where
         S: Security,
     {
-        let id = self.get_next_req_id();
+        let req_id = self.get_next_req_id();
+        self.status
+            .tx
+            .send(ToWrapper::WrapperRequest(req_id))
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::BrokenPipe, e.to_string()))?;
+        self.send_histogram_data(req_id, security, regular_trading_hours_only, duration)
+            .await?;
+        Ok(req_id)
+    }
 
+    #[inline]
+    async fn send_histogram_data<S>(
+        &mut self,
+        req_id: i64,
+        security: &S,
+        regular_trading_hours_only: bool,
+        duration: histogram::Duration,
+    ) -> ReqResult
+    where
+        S: Security,
+    {
         self.writer.add_body((
             Out::ReqHistogramData,
-            id,
+            req_id,
             security.as_out_msg(),
             None::<()>,
             regular_trading_hours_only,
             duration,
         ))?;
-        self.writer.send().await?;
-        Ok(id)
+        self.writer.send().await
     }
 
-    /// Cancel an existing [`histogram`] data request.
+    /// Cancel an existing [`Client::histogram`] or [`Client::req_histogram_data`] request.
     ///
     /// # Arguments
-    /// * `req_id` - The ID of the [`histogram`] data request to cancel.
+    /// * `req_id` - The ID of the request to cancel.
     ///
     /// # Errors
     /// Returns any error encountered while writing the outgoing message.
@@ -2061,13 +2806,30 @@ impl Client<indicators::Active> {
         S: Security,
         D: live_data::DataType<S>,
     {
-        const VERSION: u8 = 11;
         let id = self.get_next_req_id();
+        self.send_market_data(id, security, additional_data, refresh_type, use_regulatory_snapshot)
+            .await?;
+        Ok(id)
+    }
+
+    async fn send_market_data<S, D>(
+        &mut self,
+        req_id: i64,
+        security: &S,
+        additional_data: Vec<D>,
+        refresh_type: live_data::RefreshType,
+        use_regulatory_snapshot: bool,
+    ) -> ReqResult
+    where
+        S: Security,
+        D: live_data::DataType<S>,
+    {
+        const VERSION: u8 = 11;
 
         self.writer.add_body((
             Out::ReqMktData,
             VERSION,
-            id,
+            req_id,
             security.as_out_msg(),
             false,
             additional_data,
@@ -2075,8 +2837,28 @@ impl Client<indicators::Active> {
             use_regulatory_snapshot,
             None::<()>,
         ))?;
-        self.writer.send().await?;
-        Ok(id)
+        self.writer.send().await
+    }
+
+    /// See [`crate::market_data::subscribe`].
+    pub(crate) async fn market_data_stream<S, D>(
+        &mut self,
+        security: &S,
+        additional_data: Vec<D>,
+        refresh_type: live_data::RefreshType,
+    ) -> Result<crate::market_data::TickStream, std::io::Error>
+    where
+        S: Security,
+        D: live_data::DataType<S>,
+    {
+        const CANCEL_VERSION: u8 = 2;
+
+        let id = self.get_next_req_id();
+        let stream =
+            self.subscription(id, Subscriber::Ticks, (Out::CancelMktData, CANCEL_VERSION, id))?;
+        self.send_market_data(id, security, additional_data, refresh_type, false)
+            .await?;
+        Ok(stream)
     }
 
     /// Cancel an open streaming data connection with a given `req_id`.
@@ -2132,21 +2914,57 @@ impl Client<indicators::Active> {
         S: Security,
         D: live_bar::DataType<S>,
     {
-        const VERSION: u8 = 3;
         let id = self.get_next_req_id();
+        self.send_real_time_bars(id, security, data, regular_trading_hours_only)
+            .await?;
+        Ok(id)
+    }
+
+    async fn send_real_time_bars<S, D>(
+        &mut self,
+        req_id: i64,
+        security: &S,
+        data: D,
+        regular_trading_hours_only: bool,
+    ) -> ReqResult
+    where
+        S: Security,
+        D: live_bar::DataType<S>,
+    {
+        const VERSION: u8 = 3;
 
         self.writer.add_body((
             Out::ReqRealTimeBars,
             VERSION,
-            id,
+            req_id,
             security.as_out_msg(),
             5_u32,
             data,
             regular_trading_hours_only,
             None::<()>,
         ))?;
-        self.writer.send().await?;
-        Ok(id)
+        self.writer.send().await
+    }
+
+    /// See [`crate::market_data::realtime_bars`].
+    pub(crate) async fn real_time_bars_stream<S, D>(
+        &mut self,
+        security: &S,
+        data: D,
+        regular_trading_hours_only: bool,
+    ) -> Result<crate::market_data::BarStream, std::io::Error>
+    where
+        S: Security,
+        D: live_bar::DataType<S>,
+    {
+        const CANCEL_VERSION: u8 = 1;
+
+        let id = self.get_next_req_id();
+        let cancel = (Out::CancelRealTimeBars, CANCEL_VERSION, id);
+        let stream = self.subscription(id, Subscriber::RealTimeBars, cancel)?;
+        self.send_real_time_bars(id, security, data, regular_trading_hours_only)
+            .await?;
+        Ok(stream)
     }
 
     /// Cancel an existing real-time bars subscription.
@@ -2225,7 +3043,7 @@ impl Client<indicators::Active> {
     /// * `security` - The security for which to return the market depth data.
     /// * `number_of_rows` - The maximum number of rows in the returned limit order book.
     /// * `smart_depth` - When `true`, return the [`crate::exchange::Primary`] exchange holding the
-    ///      order, otherwise return the [`crate::payload::market_depth::Mpid`] associated with each entry.
+    ///   order, otherwise return the [`crate::payload::market_depth::Mpid`] associated with each entry.
     ///
     /// # Errors
     /// Returns any error encountered while writing the outgoing message.
@@ -2241,20 +3059,54 @@ impl Client<indicators::Active> {
     where
         S: Security,
     {
-        const VERSION: u8 = 5;
         let id = self.get_next_req_id();
+        self.send_market_depth(id, security, number_of_rows, smart_depth)
+            .await?;
+        Ok(id)
+    }
+
+    async fn send_market_depth<S>(
+        &mut self,
+        req_id: i64,
+        security: &S,
+        number_of_rows: u32,
+        smart_depth: bool,
+    ) -> ReqResult
+    where
+        S: Security,
+    {
+        const VERSION: u8 = 5;
 
         self.writer.add_body((
             Out::ReqMktDepth,
             VERSION,
-            id,
+            req_id,
             security.as_out_msg(),
             number_of_rows,
             smart_depth,
             None::<()>,
         ))?;
-        self.writer.send().await?;
-        Ok(id)
+        self.writer.send().await
+    }
+
+    /// See [`crate::market_data::depth`].
+    pub(crate) async fn market_depth_stream<S>(
+        &mut self,
+        security: &S,
+        number_of_rows: u32,
+        smart_depth: bool,
+    ) -> Result<crate::market_data::DepthStream, std::io::Error>
+    where
+        S: Security,
+    {
+        const CANCEL_VERSION: u8 = 1;
+
+        let id = self.get_next_req_id();
+        let stream =
+            self.subscription(id, Subscriber::Depth, (Out::CancelMktDepth, CANCEL_VERSION, id))?;
+        self.send_market_depth(id, security, number_of_rows, smart_depth)
+            .await?;
+        Ok(stream)
     }
 
     /// Request exchanges available for market depth.
@@ -2325,8 +3177,7 @@ impl Client<indicators::Active> {
             Out::PlaceOrder,
             id,
             order.get_security().as_out_msg(),
-            None::<()>,
-            None::<()>,
+            order.get_security().security_id_out_msg(),
             order,
         ))?;
         self.writer.send().await?;
@@ -2354,8 +3205,7 @@ impl Client<indicators::Active> {
             Out::PlaceOrder,
             id,
             order.get_security().as_out_msg(),
-            None::<()>,
-            None::<()>,
+            order.get_security().security_id_out_msg(),
             order,
         ))?;
         self.writer.send().await?;
@@ -2456,77 +3306,1078 @@ impl Client<indicators::Active> {
     // === Contract Creation ===
 
     #[inline]
-    pub(crate) async fn send_contract_query(&mut self, query: Query) -> Result<(), std::io::Error> {
+    pub(crate) async fn send_contract_query(
+        &mut self,
+        query: Query,
+        options: QueryOptions,
+    ) -> Result<i64, std::io::Error> {
         const VERSION: u8 = 8;
         let req_id = self.get_next_req_id();
+        // The decoder only drains the channel when a contract or an error arrives, so it is
+        // unbounded to register every query however many are sent before the first response
         self.status
             .tx
-            .send(ToWrapper::ContractQuery((query, req_id)))
-            .await
-            .map_err(|e| std::io::Error::new(std::io::ErrorKind::BrokenPipe, e))?;
-
-        match query {
-            Query::IbContractId(contract_id, routing) => {
-                self.writer.add_body((
-                    Out::ReqContractData,
-                    VERSION,
-                    req_id,
-                    contract_id,
-                    [None::<()>; 6],
-                    routing,
-                    [None::<()>; 8],
-                ))?;
-            }
-            Query::Figi(figi) => {
-                self.writer.add_body((
-                    Out::ReqContractData,
-                    VERSION,
-                    req_id,
-                    [None::<()>; 7],
-                    Routing::Smart,
-                    [None::<()>; 5],
-                    "FIGI",
-                    figi,
-                    None::<()>,
-                ))?;
+            .send(ToWrapper::ContractQuery((query.clone(), req_id)))
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::BrokenPipe, e.to_string()))?;
+
+        self.writer.add_body((
+            Out::ReqContractData,
+            VERSION,
+            req_id,
+            QueryOutMsg {
+                query: &query,
+                options,
+            },
+        ))?;
+        self.writer.send().await?;
+        Ok(req_id)
+    }
+
+    /// Receive contracts for the pending queries in `pending` until one of the queries ends, then
+    /// remove that query and return its ID and contracts. Contracts for the other pending queries
+    /// are collected in `pending` in the meantime.
+    pub(crate) async fn recv_contract_queries(
+        &mut self,
+        pending: &mut std::collections::HashMap<i64, Vec<crate::contract::Contract>>,
+    ) -> Option<(i64, Vec<crate::contract::Contract>)> {
+        loop {
+            match self.status.rx.recv().await? {
+                ToClient::NewContract(req_id, c) => match pending.get_mut(&req_id) {
+                    Some(contracts) => contracts.push(c),
+                    None => warn!("Unexpected contract received for request {req_id}"),
+                },
+                ToClient::ContractQueryEnd(req_id) => {
+                    if let Some(contracts) = pending.remove(&req_id) {
+                        return Some((req_id, contracts));
+                    }
+                    warn!("Unexpected contract query end received for request {req_id}");
+                }
+                ToClient::OptionChain(_) | ToClient::OptionChainEnd(_) => {
+                    warn!("Unexpected option chain received during a contract query");
+                }
+                ToClient::SymbolMatches(_) => {
+                    warn!("Unexpected symbol matches received during a contract query");
+                }
+                ToClient::MarketRule(_) => {
+                    warn!("Unexpected market rule received during a contract query");
+                }
+                ToClient::CurrentTime(_) => {
+                    warn!("Unexpected current time received during a contract query");
+                }
+                ToClient::HeadTimestamp(..) => {
+                    warn!("Unexpected head timestamp received during a contract query");
+                }
+                ToClient::Histogram(..) => {
+                    warn!("Unexpected histogram received during a contract query");
+                }
+                ToClient::RequestError(e) => {
+                    if let Some(contracts) = pending.remove(&e.req_id) {
+                        let req_id = e.req_id;
+                        self.status.request_errors.insert(req_id, e);
+                        return Some((req_id, contracts));
+                    }
+                    warn!("Unexpected error received for request {}: {e}", e.req_id);
+                }
+            }
+        }
+    }
+
+    #[inline]
+    pub(crate) async fn send_matching_symbols(
+        &mut self,
+        pattern: &str,
+    ) -> Result<i64, SearchSymbolsError> {
+        const PACING: std::time::Duration = std::time::Duration::from_secs(1);
+
+        let now = tokio::time::Instant::now();
+        if let Some(last) = self.status.last_symbol_search {
+            let elapsed = now.duration_since(last);
+            if elapsed < PACING {
+                return Err(SearchSymbolsError::Pacing(PACING - elapsed));
             }
         }
+        self.status.last_symbol_search = Some(now);
 
+        let req_id = self.get_next_req_id();
+        self.writer
+            .add_body((Out::ReqMatchingSymbols, req_id, pattern))?;
         self.writer.send().await?;
-        Ok(())
+        Ok(req_id)
     }
 
     #[inline]
-    pub(crate) async fn recv_contract_query(&mut self) -> Option<crate::contract::Contract> {
-        if let Some(ToClient::NewContract(c)) = self.status.rx.recv().await {
-            Some(c)
-        } else {
-            None
+    pub(crate) async fn recv_matching_symbols(&mut self) -> Option<Vec<SymbolMatch>> {
+        loop {
+            match self.status.rx.recv().await? {
+                ToClient::SymbolMatches(matches) => return Some(matches),
+                ToClient::NewContract(..)
+                | ToClient::ContractQueryEnd(_)
+                | ToClient::OptionChain(_)
+                | ToClient::OptionChainEnd(_)
+                | ToClient::MarketRule(_)
+                | ToClient::CurrentTime(_)
+                | ToClient::HeadTimestamp(..)
+                | ToClient::Histogram(..)
+                | ToClient::RequestError(_) => {
+                    warn!("Unexpected contract data received during a symbol search");
+                }
+            }
         }
     }
 
-    // === Disconnect ==
+    // === Option Chains ===
 
-    #[inline]
-    /// Terminate the connection with the IBKR trading systems and return a [`Builder`] that can
-    /// be used to reconnect if necessary.
+    /// Request the expirations and strikes of the options on `underlying`, merged across every
+    /// exchange on which they trade. See [`OptionChain`] for how differing exchanges are merged,
+    /// and use [`Client::option_chains`] to get each exchange's chain instead.
+    ///
+    /// # Arguments
+    /// * `underlying` - The security whose options to look up, like a [`crate::contract::Stock`],
+    ///   an [`crate::contract::Index`] or a [`crate::contract::SecFuture`].
     ///
     /// # Errors
-    /// Returns any error encountered while flushing and shutting down the outgoing buffer.
+    /// Returns the same errors as [`Client::option_chains`]. Also returns
+    /// [`OptionChainError::NoOptions`] if there are no options on `underlying`.
     ///
     /// # Returns
-    /// A [`Builder`] with the same port and address as the existing client.
-    pub async fn disconnect(mut self) -> Result<Builder, std::io::Error> {
-        self.writer.flush().await?;
-        self.writer.shutdown().await?;
-        self.status.disconnect.cancel();
-        self.status.r_thread.await?;
-        Ok(Builder(Inner::Manual {
-            port: self.port,
-            address: self.address,
-        }))
+    /// The merged option chain.
+    pub async fn option_chain<S: Security>(
+        &mut self,
+        underlying: &S,
+    ) -> Result<OptionChain, OptionChainError> {
+        OptionChain::merge(self.option_chains(underlying).await?).ok_or(OptionChainError::NoOptions)
     }
-}
+
+    /// Request the expirations and strikes of the options on `underlying`, with one
+    /// [`OptionChain`] for each exchange and trading class on which they trade.
+    ///
+    /// # Arguments
+    /// * `underlying` - The security whose options to look up, like a [`crate::contract::Stock`],
+    ///   an [`crate::contract::Index`] or a [`crate::contract::SecFuture`]. The futures options
+    ///   on a future are only looked up on the future's own exchange.
+    ///
+    /// # Errors
+    /// Returns any error encountered while writing the outgoing message. Also returns
+    /// [`OptionChainError::Disconnected`] if the connection closes before all the chains are received.
+    ///
+    /// # Returns
+    /// The option chain on each exchange, which may be empty if there are no options on
+    /// `underlying`.
+    pub async fn option_chains<S: Security>(
+        &mut self,
+        underlying: &S,
+    ) -> Result<Vec<OptionChain>, OptionChainError> {
+        let req_id = self.get_next_req_id();
+        let out_msg = underlying.as_out_msg();
+        // IBKR only reports the futures options on a future when given the future's exchange
+        let fut_fop_exchange = (out_msg.security_type == "FUT").then_some(out_msg.exchange);
+        self.writer.add_body((
+            Out::ReqSecDefOptParams,
+            req_id,
+            out_msg.symbol,
+            fut_fop_exchange,
+            out_msg.security_type,
+            out_msg.contract_id,
+        ))?;
+        self.writer.send().await?;
+
+        let mut chains = Vec::new();
+        loop {
+            match self.status.rx.recv().await.ok_or(OptionChainError::Disconnected)? {
+                ToClient::OptionChain(c) => chains.push(c),
+                ToClient::OptionChainEnd(_) => return Ok(chains),
+                ToClient::NewContract(..)
+                | ToClient::ContractQueryEnd(_)
+                | ToClient::SymbolMatches(_)
+                | ToClient::MarketRule(_)
+                | ToClient::CurrentTime(_)
+                | ToClient::HeadTimestamp(..)
+                | ToClient::Histogram(..)
+                | ToClient::RequestError(_) => {
+                    warn!("Unexpected contract data received during an option chain request");
+                }
+            }
+        }
+    }
+
+    // === Market Rules ===
+
+    /// Request the table of price-dependent tick sizes identified by `id`, which can be found
+    /// with [`crate::contract::Security::market_rule_ids`].
+    ///
+    /// # Arguments
+    /// * `id` - The ID of the market rule to request.
+    ///
+    /// # Errors
+    /// Returns any error encountered while writing the outgoing message. Also returns
+    /// [`MarketRuleError::Disconnected`] if the connection closes before the rule is received.
+    ///
+    /// # Returns
+    /// The requested market rule.
+    pub async fn request_market_rule(
+        &mut self,
+        id: MarketRuleId,
+    ) -> Result<MarketRule, MarketRuleError> {
+        self.writer.add_body((Out::ReqMarketRule, id.0))?;
+        self.writer.send().await?;
+
+        loop {
+            match self.status.rx.recv().await.ok_or(MarketRuleError::Disconnected)? {
+                ToClient::MarketRule(rule) if rule.id == id => return Ok(rule),
+                ToClient::MarketRule(rule) => {
+                    warn!("Unexpected market rule {} received during a request for {id}", rule.id);
+                }
+                ToClient::NewContract(..)
+                | ToClient::ContractQueryEnd(_)
+                | ToClient::OptionChain(_)
+                | ToClient::OptionChainEnd(_)
+                | ToClient::SymbolMatches(_)
+                | ToClient::CurrentTime(_)
+                | ToClient::HeadTimestamp(..)
+                | ToClient::Histogram(..)
+                | ToClient::RequestError(_) => {
+                    warn!("Unexpected contract data received during a market rule request");
+                }
+            }
+        }
+    }
+
+    // === Trading Hours ===
+
+    /// Request the current trading hours of `security`, including those outside regular trading
+    /// hours, with a fresh contract details request.
+    ///
+    /// The schedule that a resolved security holds, from [`Security::trading_hours`], only covers
+    /// the days after it was resolved, so this is useful for a long-lived security or a partially
+    /// populated one. The regular trading hours are available from [`Security::liquid_hours`] of a
+    /// contract resolved with [`crate::contract::new`].
+    ///
+    /// # Arguments
+    /// * `security` - The security whose trading hours to request. A partially populated security,
+    ///   which has no contract ID, is looked up by its symbol, type, exchange, currency, and local
+    ///   symbol instead.
+    ///
+    /// # Errors
+    /// Returns the same errors as [`crate::contract::new`].
+    ///
+    /// # Returns
+    /// The trading sessions of `security` over the coming days, in its exchange's time zone.
+    pub async fn trading_hours<S: Security>(
+        &mut self,
+        security: &S,
+    ) -> Result<Schedule, NewSecurityError> {
+        let exchange = security.as_out_msg().exchange;
+        let query = if security.contract_id() == ContractId(0) {
+            Query::Pattern(
+                ContractSpec::new(
+                    security.symbol(),
+                    security.contract_type(),
+                    exchange,
+                    security.currency(),
+                )
+                .local_symbol(security.local_symbol()),
+            )
+        } else {
+            Query::IbContractId(security.contract_id(), exchange)
+        };
+        let contract: Contract = crate::contract::new(self, query).await?;
+        Ok(contract.trading_hours().clone())
+    }
+
+    // === Front Month Futures ===
+
+    /// Request the front month future of `symbol`, like the ES contract that expires soonest,
+    /// without enumerating its expirations by hand.
+    ///
+    /// To roll ahead of expiry, a future that expires within `roll_days` days is skipped in favor
+    /// of the next one. Days are counted from the current UTC date.
+    ///
+    /// # Arguments
+    /// * `symbol` - The symbol of the futures, like "ES".
+    /// * `exchange` - The exchange on which the futures trade, like CME.
+    /// * `currency` - The currency of the futures.
+    /// * `roll_days` - How many days before its expiration date a future stops being the front
+    ///   month. Use 0 to only skip those that have already expired.
+    ///
+    /// # Errors
+    /// Returns the same errors as [`crate::contract::search`]. Also returns
+    /// [`NewSecurityError::BadResponse`] if no future of `symbol` expires late enough.
+    ///
+    /// # Returns
+    /// The front month [`SecFuture`].
+    pub async fn front_month_future(
+        &mut self,
+        symbol: &str,
+        exchange: Routing,
+        currency: Currency,
+        roll_days: u16,
+    ) -> Result<SecFuture, NewSecurityError> {
+        let query = Query::Pattern(ContractSpec::new(
+            symbol,
+            ContractType::SecFuture,
+            exchange,
+            currency,
+        ));
+        let req_id = self
+            .send_contract_query(query, QueryOptions::default())
+            .await?;
+        let contracts = crate::contract::await_contract_query(self, req_id).await?;
+        crate::contract::front_month_future(contracts, chrono::Utc::now().date_naive(), roll_days)
+            .ok_or(NewSecurityError::BadResponse)
+    }
+
+    /// Request every future of `symbol`, like each ES contract from the front month to the last
+    /// listed expiration, for term structures and calendar spreads.
+    ///
+    /// # Arguments
+    /// * `symbol` - The symbol of the futures, like "ES".
+    /// * `exchange` - The exchange on which the futures trade, like CME.
+    /// * `currency` - The currency of the futures.
+    /// * `options` - The options with which to resolve the query. Set
+    ///   [`QueryOptions::include_expired`] to also return the futures that have expired.
+    ///
+    /// # Errors
+    /// Returns the same errors as [`crate::contract::search`].
+    ///
+    /// # Returns
+    /// The futures of `symbol`, sorted by their expiration dates.
+    pub async fn futures_curve(
+        &mut self,
+        symbol: &str,
+        exchange: Routing,
+        currency: Currency,
+        options: QueryOptions,
+    ) -> Result<Vec<SecFuture>, NewSecurityError> {
+        let query = Query::Pattern(ContractSpec::new(
+            symbol,
+            ContractType::SecFuture,
+            exchange,
+            currency,
+        ));
+        let req_id = self.send_contract_query(query, options).await?;
+        let contracts = crate::contract::await_contract_query(self, req_id).await?;
+        Ok(crate::contract::futures_curve(contracts))
+    }
+
+    // === Disconnect ==
+
+    #[inline]
+    /// Terminate the connection with the IBKR trading systems and return a [`Builder`] that can
+    /// be used to reconnect if necessary.
+    ///
+    /// # Errors
+    /// Returns any error encountered while flushing and shutting down the outgoing buffer.
+    ///
+    /// # Returns
+    /// A [`Builder`] with the same port and address as the existing client.
+    pub async fn disconnect(mut self) -> Result<Builder, std::io::Error> {
+        self.writer.flush().await?;
+        self.writer.shutdown().await?;
+        self.status.disconnect.cancel();
+        self.status.r_thread.await?;
+        Ok(Builder(Inner::Manual {
+            port: self.port,
+            address: self.address,
+        }))
+    }
+}
+
+// ==========================
+// === Resilient Client ===
+// ==========================
+
+/// The future returned by a callback registered with [`ResilientClient::subscribe`] or
+/// [`ResilientClient::on_reconnect`].
+pub type ResubscribeFuture<'c> =
+    std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), std::io::Error>> + Send + 'c>>;
+
+type Resubscribe = Box<dyn for<'c> FnMut(&'c mut ActiveClient) -> ResubscribeFuture<'c> + Send>;
+
+/// A client that reconnects to the IBKR trading systems whenever the connection is lost, like
+/// when TWS restarts overnight, and then resumes its subscriptions.
+///
+/// Each connection runs [`Client::local`] with a clone of the same [`LocalInitializer`], so the
+/// wrapper is built again for every connection. Before it is built, the callbacks registered with
+/// [`ResilientClient::subscribe`] are replayed, in the order in which they were registered, and
+/// after a reconnection, the [`ResilientClient::on_reconnect`] hook is called too, so that state
+/// like open orders can be synced again. Since the request IDs of a new connection start from 0
+/// again, callbacks that are always registered in the same order get the same request IDs.
+///
+/// Failed connection attempts are retried with an exponential backoff with jitter, so that many
+/// clients that lose the same TWS don't all reconnect at the same time.
+pub struct ResilientClient {
+    builder: Builder,
+    client_id: i64,
+    initial_backoff: std::time::Duration,
+    max_backoff: std::time::Duration,
+    max_attempts: Option<u32>,
+    subscriptions: Vec<Resubscribe>,
+    on_reconnect: Option<Resubscribe>,
+}
+
+impl std::fmt::Debug for ResilientClient {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ResilientClient")
+            .field("builder", &self.builder)
+            .field("client_id", &self.client_id)
+            .field("initial_backoff", &self.initial_backoff)
+            .field("max_backoff", &self.max_backoff)
+            .field("max_attempts", &self.max_attempts)
+            .field("subscriptions", &self.subscriptions.len())
+            .field("on_reconnect", &self.on_reconnect.is_some())
+            .finish()
+    }
+}
+
+impl ResilientClient {
+    #[must_use]
+    #[inline]
+    /// Creates a new [`ResilientClient`] that connects with `builder`, retrying forever with a
+    /// backoff that starts at one second and doubles up to a minute.
+    ///
+    /// # Arguments
+    /// * `builder` - The builder with which to connect, and to reconnect.
+    /// * `client_id` - A unique ID for IBKR's systems to distinguish between clients.
+    pub fn new(builder: Builder, client_id: i64) -> Self {
+        Self {
+            builder,
+            client_id,
+            initial_backoff: std::time::Duration::from_secs(1),
+            max_backoff: std::time::Duration::from_secs(60),
+            max_attempts: None,
+            subscriptions: Vec::new(),
+            on_reconnect: None,
+        }
+    }
+
+    #[must_use]
+    #[inline]
+    /// Set the delay before the first retry of a failed connection attempt, which doubles with
+    /// each further retry up to `max`.
+    pub fn backoff(mut self, initial: std::time::Duration, max: std::time::Duration) -> Self {
+        self.initial_backoff = initial;
+        self.max_backoff = max;
+        self
+    }
+
+    #[must_use]
+    #[inline]
+    /// Give up after `attempts` connection attempts in a row have failed, rather than retrying
+    /// forever.
+    pub fn max_attempts(mut self, attempts: u32) -> Self {
+        self.max_attempts = Some(attempts);
+        self
+    }
+
+    #[must_use]
+    /// Register a subscription, like a market data line or the account updates, that is sent on
+    /// every connection. A subscription that fails to send is logged rather than ending the loop.
+    ///
+    /// # Arguments
+    /// * `resubscribe` - A callback that sends the subscription's requests with the new client,
+    ///   like `|client| Box::pin(async move { client.req_account_updates(None).await })`.
+    pub fn subscribe<F>(mut self, resubscribe: F) -> Self
+    where
+        F: for<'c> FnMut(&'c mut ActiveClient) -> ResubscribeFuture<'c> + Send + 'static,
+    {
+        self.subscriptions.push(Box::new(resubscribe));
+        self
+    }
+
+    #[must_use]
+    /// Register a hook that is called with the new client after every reconnection, once the
+    /// subscriptions have been replayed, like to request the open orders again.
+    pub fn on_reconnect<F>(mut self, hook: F) -> Self
+    where
+        F: for<'c> FnMut(&'c mut ActiveClient) -> ResubscribeFuture<'c> + Send + 'static,
+    {
+        self.on_reconnect = Some(Box::new(hook));
+        self
+    }
+
+    /// Connect and run the main message loop like [`Client::local`], reconnecting whenever the
+    /// connection is lost.
+    ///
+    /// # Arguments
+    /// * `init` - A [`LocalInitializer`], which is cloned to build the wrapper of each connection.
+    /// * `disconnect_token` - The client will disconnect, and stop reconnecting, when this token
+    ///   is cancelled.
+    ///
+    /// # Errors
+    /// Returns the last connection error if the maximum number of attempts is reached, or any
+    /// other error that [`Client::local`] returns.
+    ///
+    /// # Returns
+    /// Does not return until `disconnect_token` is cancelled, like [`Client::local`].
+    pub async fn local<I: LocalInitializer + Clone>(
+        mut self,
+        init: I,
+        disconnect_token: CancelToken,
+    ) -> Result<Builder, ConnectionError> {
+        let mut reconnection = false;
+        loop {
+            let Some(client) = self.connect(&disconnect_token).await? else {
+                return Ok(self.builder);
+            };
+            let init = Resubscribing {
+                init: init.clone(),
+                subscriptions: &mut self.subscriptions,
+                on_reconnect: if reconnection {
+                    self.on_reconnect.as_mut()
+                } else {
+                    None
+                },
+            };
+            match client.local(init, Some(disconnect_token.clone())).await {
+                Err(e)
+                    if e.kind() == std::io::ErrorKind::ConnectionAborted
+                        && !disconnect_token.is_cancelled() =>
+                {
+                    warn!("Connection lost. Reconnecting");
+                    reconnection = true;
+                }
+                res => return res.map_err(ConnectionError::from),
+            }
+        }
+    }
+
+    // Connect with backoff. Returns `None` if the token is cancelled while waiting to retry
+    async fn connect(
+        &self,
+        disconnect_token: &CancelToken,
+    ) -> Result<Option<Client<indicators::Inactive>>, ConnectionError> {
+        let mut attempt = 0;
+        loop {
+            match self.builder.connect(self.client_id).await {
+                Ok(client) => return Ok(Some(client)),
+                Err(e) => {
+                    attempt += 1;
+                    if self.max_attempts.is_some_and(|max| attempt >= max) {
+                        return Err(e);
+                    }
+                    let seed = std::time::SystemTime::now()
+                        .duration_since(std::time::UNIX_EPOCH)
+                        .map_or(0, |t| u64::from(t.subsec_nanos()));
+                    let delay =
+                        backoff_delay(self.initial_backoff, self.max_backoff, attempt, seed);
+                    warn!(error=%e, "Connection attempt {attempt} failed. Retrying in {delay:?}");
+                    tokio::select! {
+                        () = tokio::time::sleep(delay) => (),
+                        () = disconnect_token.cancelled() => return Ok(None),
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// The delay before retry number `attempt`, which doubles from `initial` up to `max`, and of
+/// which a random part of the upper half, chosen by `seed`, is jitter.
+fn backoff_delay(
+    initial: std::time::Duration,
+    max: std::time::Duration,
+    attempt: u32,
+    seed: u64,
+) -> std::time::Duration {
+    let delay = initial
+        .saturating_mul(2_u32.saturating_pow(attempt.saturating_sub(1)))
+        .min(max);
+    let half = delay / 2;
+    let jitter = u64::try_from(half.as_nanos()).unwrap_or(u64::MAX);
+    half + std::time::Duration::from_nanos(if jitter == 0 { 0 } else { seed % jitter })
+}
+
+// Replays the subscriptions and the reconnection hook before building the wrapper
+struct Resubscribing<'r, I> {
+    init: I,
+    subscriptions: &'r mut [Resubscribe],
+    on_reconnect: Option<&'r mut Resubscribe>,
+}
+
+impl<I: LocalInitializer> LocalInitializer for Resubscribing<'_, I> {
+    type Wrap<'c> = I::Wrap<'c>;
+
+    async fn build(self, client: &mut ActiveClient, cancel_loop: CancelToken) -> Self::Wrap<'_> {
+        for resubscribe in self.subscriptions.iter_mut() {
+            if let Err(e) = resubscribe(client).await {
+                warn!(error=%e, "Failed to resume a subscription");
+            }
+        }
+        if let Some(hook) = self.on_reconnect {
+            if let Err(e) = hook(client).await {
+                warn!(error=%e, "Reconnection hook failed");
+            }
+        }
+        self.init.build(client, cancel_loop).await
+    }
+}
+
+// ========================
+// === Client Port Trait ===
+// ========================
+
+/// The requests and responses with which the functions in [`crate::contract`], like
+/// [`crate::contract::new`] and [`crate::contract::search`], resolve contracts.
+///
+/// This trait is implemented by [`ActiveClient`], which sends the requests to the IBKR trading
+/// systems, and by [`crate::mock::MockClient`], which answers them with recorded messages so that
+/// code that resolves contracts can be tested without a connection.
+pub trait ClientPort: Send {
+    /// Send a contract details request for `query`.
+    ///
+    /// # Errors
+    /// Returns any error encountered while sending the request.
+    ///
+    /// # Returns
+    /// The request ID with which the contracts that match `query` are received.
+    fn send_contract_query(
+        &mut self,
+        query: Query,
+        options: QueryOptions,
+    ) -> impl std::future::Future<Output = Result<i64, std::io::Error>> + Send;
+
+    /// Receive contracts for the pending queries in `pending` until one of the queries ends, then
+    /// remove that query and return its ID and contracts. Contracts for the other pending queries
+    /// are collected in `pending` in the meantime.
+    ///
+    /// # Returns
+    /// The ID and contracts of the query that ended, or [`None`] if no more responses will be
+    /// received.
+    fn recv_contract_queries(
+        &mut self,
+        pending: &mut std::collections::HashMap<i64, Vec<Contract>>,
+    ) -> impl std::future::Future<Output = Option<(i64, Vec<Contract>)>> + Send;
+
+    /// Receive the contracts that match the query sent with `req_id`.
+    ///
+    /// # Returns
+    /// The matching contracts, or [`None`] if no more responses will be received.
+    fn recv_contract_query(
+        &mut self,
+        req_id: i64,
+    ) -> impl std::future::Future<Output = Option<Vec<Contract>>> + Send {
+        async move {
+            let mut pending = std::collections::HashMap::from([(req_id, Vec::new())]);
+            self.recv_contract_queries(&mut pending)
+                .await
+                .map(|(_, contracts)| contracts)
+        }
+    }
+
+    /// Send a free-text symbol search for `pattern`.
+    ///
+    /// # Errors
+    /// Returns any error encountered while sending the search, or an error if the search was
+    /// paced.
+    ///
+    /// # Returns
+    /// The request ID of the search.
+    fn send_matching_symbols(
+        &mut self,
+        pattern: &str,
+    ) -> impl std::future::Future<Output = Result<i64, SearchSymbolsError>> + Send;
+
+    /// Receive the matches of the last symbol search.
+    ///
+    /// # Returns
+    /// The matches, or [`None`] if no more responses will be received.
+    fn recv_matching_symbols(
+        &mut self,
+    ) -> impl std::future::Future<Output = Option<Vec<SymbolMatch>>> + Send;
+
+    /// Request every contract that matches `query`, like all the expirations of a future, with
+    /// the metadata that IBKR reports about it. Unlike [`crate::contract::new`], this does not
+    /// error when several contracts match.
+    ///
+    /// The metadata of each contract, like its industry, its trading hours, and its market rules,
+    /// is returned by [`Security::details`].
+    ///
+    /// # Errors
+    /// Returns the same errors as [`crate::contract::search`].
+    ///
+    /// # Returns
+    /// Every contract that matches `query`, which may be empty.
+    fn contract_details(
+        &mut self,
+        query: Query,
+    ) -> impl std::future::Future<Output = Result<Vec<Contract>, NewSecurityError>> + Send {
+        async move {
+            let options = QueryOptions::for_query::<Contract>(&query);
+            let req_id = self.send_contract_query(query, options).await?;
+            let contracts = crate::contract::await_contract_query(self, req_id).await?;
+            crate::contract::check_rejected_search(self, req_id, contracts)
+        }
+    }
+
+    /// Search for the contracts whose symbol or name matches a free-text `pattern`, like "apple",
+    /// which is useful for autocompleting symbols. Each match can then be created by
+    /// [`crate::contract::new`] with its [`SymbolMatch::contract_id`].
+    ///
+    /// # Errors
+    /// Returns the same errors as [`crate::contract::search_symbols`].
+    ///
+    /// # Returns
+    /// The matching contracts, which may be empty.
+    fn search_symbols(
+        &mut self,
+        pattern: &str,
+    ) -> impl std::future::Future<Output = Result<Vec<SymbolMatch>, SearchSymbolsError>> + Send
+    where
+        Self: Sized,
+    {
+        crate::contract::search_symbols(self, pattern)
+    }
+
+    /// The maximum time to wait for the response to a contract query or a symbol search, after
+    /// which the functions that send them fail with a [`crate::contract::RequestTimeout`].
+    ///
+    /// # Returns
+    /// The timeout, or [`None`] to wait for as long as it takes. The default is [`None`].
+    fn request_timeout(&self) -> Option<std::time::Duration> {
+        None
+    }
+
+    /// Forget the contract query sent with `req_id`, whose response is no longer awaited, like
+    /// after it timed out, so that the port stops keeping it pending. TWS has no message that
+    /// cancels a contract details request, so a late response is ignored instead. The default does
+    /// nothing.
+    fn forget_contract_query(&mut self, _req_id: i64) {}
+
+    /// Take the error with which IBKR rejected the query sent with `req_id`, if any. A rejected
+    /// query ends without any contracts, so this tells it apart from a query that matched none.
+    ///
+    /// # Returns
+    /// The error, or [`None`] if the query was not rejected. The default is [`None`].
+    fn take_request_error(&mut self, _req_id: i64) -> Option<TwsError> {
+        None
+    }
+}
+
+impl ClientPort for ActiveClient {
+    #[inline]
+    async fn send_contract_query(
+        &mut self,
+        query: Query,
+        options: QueryOptions,
+    ) -> Result<i64, std::io::Error> {
+        Client::send_contract_query(self, query, options).await
+    }
+
+    #[inline]
+    async fn recv_contract_queries(
+        &mut self,
+        pending: &mut std::collections::HashMap<i64, Vec<Contract>>,
+    ) -> Option<(i64, Vec<Contract>)> {
+        Client::recv_contract_queries(self, pending).await
+    }
+
+    #[inline]
+    async fn send_matching_symbols(&mut self, pattern: &str) -> Result<i64, SearchSymbolsError> {
+        Client::send_matching_symbols(self, pattern).await
+    }
+
+    #[inline]
+    async fn recv_matching_symbols(&mut self) -> Option<Vec<SymbolMatch>> {
+        Client::recv_matching_symbols(self).await
+    }
+
+    #[inline]
+    fn request_timeout(&self) -> Option<std::time::Duration> {
+        self.status.request_timeout
+    }
+
+    #[inline]
+    fn forget_contract_query(&mut self, req_id: i64) {
+        // The decoder is gone once the connection is lost, and with it every pending query
+        let _ = self.status.tx.send(ToWrapper::ForgetContractQuery(req_id));
+    }
+
+    #[inline]
+    fn take_request_error(&mut self, req_id: i64) -> Option<TwsError> {
+        self.status.request_errors.remove(&req_id)
+    }
+}
+
+// =======================
+// === Contract Client ===
+// =======================
+
+type ContractReply = (i64, Result<Vec<Contract>, TwsError>);
+
+// A request from a `ContractClient` to the task that owns the port
+enum PortRequest {
+    Query {
+        query: Query,
+        options: QueryOptions,
+        req_id: tokio::sync::oneshot::Sender<Result<i64, std::io::Error>>,
+        reply: mpsc::UnboundedSender<ContractReply>,
+    },
+    Symbols {
+        pattern: String,
+        req_id: tokio::sync::oneshot::Sender<Result<i64, SearchSymbolsError>>,
+        reply: tokio::sync::oneshot::Sender<Option<Vec<SymbolMatch>>>,
+    },
+    Forget(i64),
+}
+
+// Where to send the contracts of a pending query
+struct Route {
+    reply: mpsc::UnboundedSender<ContractReply>,
+    sent: tokio::time::Instant,
+}
+
+#[derive(Debug)]
+/// A cloneable handle with which many tasks can resolve contracts concurrently over a single
+/// [`ClientPort`], like an [`ActiveClient`].
+///
+/// [`ContractClient::spawn`] moves the port into a task that sends the queries of every handle
+/// and routes each response back to the handle that sent it by its request ID, so responses can
+/// arrive in any order. Each handle is a [`ClientPort`] itself, so functions like
+/// [`crate::contract::new`] can be called with a clone of the handle from as many tasks as
+/// needed, without waiting for each other's responses.
+///
+/// Symbol searches, whose responses carry no request ID, are sent one at a time once no contract
+/// query is pending, and queries that are sent in the meantime wait for the search to finish.
+pub struct ContractClient {
+    requests: mpsc::UnboundedSender<PortRequest>,
+    tx: mpsc::UnboundedSender<ContractReply>,
+    rx: mpsc::UnboundedReceiver<ContractReply>,
+    symbols: Option<tokio::sync::oneshot::Receiver<Option<Vec<SymbolMatch>>>>,
+    request_timeout: Option<std::time::Duration>,
+    request_errors: std::collections::HashMap<i64, TwsError>,
+}
+
+impl Clone for ContractClient {
+    fn clone(&self) -> Self {
+        // Each handle receives the responses to its own queries only
+        let (tx, rx) = mpsc::unbounded_channel();
+        Self {
+            requests: self.requests.clone(),
+            tx,
+            rx,
+            symbols: None,
+            request_timeout: self.request_timeout,
+            request_errors: std::collections::HashMap::new(),
+        }
+    }
+}
+
+impl ContractClient {
+    /// Move `port` into a new task that serves the queries of every [`ContractClient`] handle.
+    ///
+    /// # Arguments
+    /// * `port` - The port with which to send the queries, like an [`ActiveClient`]. Its
+    ///   [`ClientPort::request_timeout`] applies to every handle.
+    ///
+    /// # Returns
+    /// The first handle, and the task, which returns the port once every handle is dropped, or
+    /// once the port stops receiving responses.
+    pub fn spawn<P: ClientPort + 'static>(port: P) -> (Self, JoinHandle<P>) {
+        // Unbounded, so that a handle can forget a query without waiting, like when it is dropped
+        let (requests, rx_requests) = mpsc::unbounded_channel();
+        let (tx, rx) = mpsc::unbounded_channel();
+        let client = Self {
+            requests,
+            tx,
+            rx,
+            symbols: None,
+            request_timeout: port.request_timeout(),
+            request_errors: std::collections::HashMap::new(),
+        };
+        (client, tokio::spawn(serve_port(port, rx_requests)))
+    }
+}
+
+async fn serve_port<P: ClientPort>(
+    mut port: P,
+    mut requests: mpsc::UnboundedReceiver<PortRequest>,
+) -> P {
+    let timeout = port.request_timeout();
+    let mut routes = std::collections::HashMap::<i64, Route>::new();
+    let mut pending = std::collections::HashMap::new();
+    let mut searches = std::collections::VecDeque::<(String, _, _)>::new();
+    loop {
+        // Forget the queries that no handle is waiting for anymore
+        let now = tokio::time::Instant::now();
+        routes.retain(|req_id, route| {
+            let awaited = !route.reply.is_closed()
+                && timeout.is_none_or(|t| now.duration_since(route.sent) < t);
+            if !awaited {
+                port.forget_contract_query(*req_id);
+            }
+            awaited
+        });
+        pending.retain(|req_id, _| routes.contains_key(req_id));
+
+        if pending.is_empty() {
+            if let Some((pattern, req_id, reply)) = searches.pop_front() {
+                search_symbols(&mut port, timeout, &pattern, req_id, reply).await;
+                continue;
+            }
+        }
+        let expiry = routes
+            .values()
+            .map(|route| route.sent)
+            .min()
+            .zip(timeout)
+            .map(|(sent, t)| sent + t);
+        let request = tokio::select! {
+            biased;
+            request = requests.recv() => request,
+            received = port.recv_contract_queries(&mut pending), if !pending.is_empty() => {
+                let Some((req_id, contracts)) = received else {
+                    break port;
+                };
+                let contracts = port.take_request_error(req_id).map_or(Ok(contracts), Err);
+                if let Some(route) = routes.remove(&req_id) {
+                    let _ = route.reply.send((req_id, contracts));
+                }
+                continue;
+            }
+            () = async { tokio::time::sleep_until(expiry.unwrap_or(now)).await }, if expiry.is_some() => continue,
+        };
+        match request {
+            Some(PortRequest::Query {
+                query,
+                options,
+                req_id,
+                reply,
+            }) => match port.send_contract_query(query, options).await {
+                Ok(id) => {
+                    pending.insert(id, Vec::new());
+                    routes.insert(
+                        id,
+                        Route {
+                            reply,
+                            sent: tokio::time::Instant::now(),
+                        },
+                    );
+                    let _ = req_id.send(Ok(id));
+                }
+                Err(e) => {
+                    let _ = req_id.send(Err(e));
+                }
+            },
+            Some(PortRequest::Symbols {
+                pattern,
+                req_id,
+                reply,
+            }) => searches.push_back((pattern, req_id, reply)),
+            Some(PortRequest::Forget(req_id)) => {
+                if routes.remove(&req_id).is_some() {
+                    pending.remove(&req_id);
+                    port.forget_contract_query(req_id);
+                }
+            }
+            None => break port,
+        }
+    }
+}
+
+// Send a symbol search and wait for its matches, which carry no request ID with which to route
+// them alongside contract queries
+async fn search_symbols<P: ClientPort>(
+    port: &mut P,
+    timeout: Option<std::time::Duration>,
+    pattern: &str,
+    req_id: tokio::sync::oneshot::Sender<Result<i64, SearchSymbolsError>>,
+    reply: tokio::sync::oneshot::Sender<Option<Vec<SymbolMatch>>>,
+) {
+    match port.send_matching_symbols(pattern).await {
+        Ok(id) => {
+            let _ = req_id.send(Ok(id));
+            let recv = port.recv_matching_symbols();
+            let matches = crate::contract::with_timeout(timeout, id, RequestKind::MatchingSymbols, recv).await;
+            let _ = reply.send(matches.ok().flatten());
+        }
+        Err(e) => {
+            let _ = req_id.send(Err(e));
+        }
+    }
+}
+
+#[inline]
+fn port_closed() -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::BrokenPipe,
+        "The task that serves the contract client has stopped",
+    )
+}
+
+impl ClientPort for ContractClient {
+    async fn send_contract_query(
+        &mut self,
+        query: Query,
+        options: QueryOptions,
+    ) -> Result<i64, std::io::Error> {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        self.requests
+            .send(PortRequest::Query {
+                query,
+                options,
+                req_id: tx,
+                reply: self.tx.clone(),
+            })
+            .map_err(|_| port_closed())?;
+        rx.await.map_err(|_| port_closed())?
+    }
+
+    async fn recv_contract_queries(
+        &mut self,
+        pending: &mut std::collections::HashMap<i64, Vec<Contract>>,
+    ) -> Option<(i64, Vec<Contract>)> {
+        loop {
+            tokio::select! {
+                biased;
+                Some((req_id, contracts)) = self.rx.recv() => {
+                    // Responses to queries that were given up on, like after a timeout, are stale
+                    if pending.remove(&req_id).is_some() {
+                        let contracts = contracts.unwrap_or_else(|e| {
+                            self.request_errors.insert(req_id, e);
+                            Vec::new()
+                        });
+                        return Some((req_id, contracts));
+                    }
+                }
+                () = self.requests.closed() => return None,
+            }
+        }
+    }
+
+    async fn send_matching_symbols(&mut self, pattern: &str) -> Result<i64, SearchSymbolsError> {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        let (reply, matches) = tokio::sync::oneshot::channel();
+        self.requests
+            .send(PortRequest::Symbols {
+                pattern: pattern.to_owned(),
+                req_id: tx,
+                reply,
+            })
+            .map_err(|_| port_closed())?;
+        let req_id = rx.await.map_err(|_| port_closed())??;
+        self.symbols = Some(matches);
+        Ok(req_id)
+    }
+
+    async fn recv_matching_symbols(&mut self) -> Option<Vec<SymbolMatch>> {
+        self.symbols.take()?.await.ok().flatten()
+    }
+
+    #[inline]
+    fn request_timeout(&self) -> Option<std::time::Duration> {
+        self.request_timeout
+    }
+
+    #[inline]
+    fn forget_contract_query(&mut self, req_id: i64) {
+        let _ = self.requests.send(PortRequest::Forget(req_id));
+    }
+
+    #[inline]
+    fn take_request_error(&mut self, req_id: i64) -> Option<TwsError> {
+        self.request_errors.remove(&req_id)
+    }
+}
 
 #[inline]
 fn check_valid_account(
@@ -2542,3 +4393,165 @@ fn check_valid_account(
         ))
     }
 }
+
+#[test]
+fn test_backoff_delay() {
+    use std::time::Duration;
+
+    let (initial, max) = (Duration::from_secs(1), Duration::from_secs(60));
+    for seed in [0, 1, 123_456_789, u64::MAX] {
+        for (attempt, full) in [(1, 1), (2, 2), (3, 4), (7, 60), (40, 60)] {
+            let delay = backoff_delay(initial, max, attempt, seed);
+            let full = Duration::from_secs(full);
+            assert!(delay >= full / 2 && delay <= full, "{delay:?} for attempt {attempt}");
+        }
+    }
+    assert_eq!(backoff_delay(Duration::ZERO, max, 3, 42), Duration::ZERO);
+}
+
+#[tokio::test]
+async fn test_client_builder() -> Result<(), ConnectionError> {
+    use tokio::io::AsyncWriteExt;
+
+    // Accepts a connection, reads the handshake, and replies with a server version
+    async fn serve(
+        listener: &tokio::net::TcpListener,
+        server_version: Option<u32>,
+    ) -> Result<Vec<u8>, std::io::Error> {
+        let (mut stream, _) = listener.accept().await?;
+        let mut prefix = [0; 4];
+        stream.read_exact(&mut prefix).await?;
+        let len = stream.read_u32().await?;
+        stream.read_exact(&mut vec![0; len as usize]).await?;
+        let Some(server_version) = server_version else {
+            // Never finish the handshake
+            return std::future::pending().await;
+        };
+        let resp = format!("{server_version}\020240102 09:30:00 US/Eastern\0");
+        stream.write_u32(u32::try_from(resp.len()).unwrap_or_default()).await?;
+        stream.write_all(resp.as_bytes()).await?;
+        let len = stream.read_u32().await?;
+        let mut start_api = vec![0; len as usize];
+        stream.read_exact(&mut start_api).await?;
+        Ok(start_api)
+    }
+
+    let listener = tokio::net::TcpListener::bind((std::net::Ipv4Addr::LOCALHOST, 0)).await?;
+    let builder = ClientBuilder::new()
+        .host(std::net::Ipv4Addr::LOCALHOST)
+        .port(listener.local_addr()?.port())
+        .client_id(7)
+        .tcp_nodelay(true)
+        .handshake_timeout(std::time::Duration::from_millis(50))
+        .optional_capabilities("+PACEAPI");
+
+    let (client, start_api) = tokio::join!(builder.connect(), serve(&listener, Some(180)));
+    let client = client?;
+    assert_eq!(client.get_client_id(), 7);
+    assert_eq!(client.get_server_version(), 180);
+    assert_eq!(start_api?, b"71\x002\x007\x00+PACEAPI\x00");
+
+    let (client, _) = tokio::join!(builder.connect(), serve(&listener, Some(100)));
+    let Err(error @ ConnectionError::UnsupportedServerVersion(100)) = client else {
+        panic!("A server version below the minimum was accepted");
+    };
+    assert!(error.to_string().ends_with("minimum supported version 180."));
+
+    let res = tokio::select! {
+        res = builder.connect() => res,
+        _ = serve(&listener, None) => unreachable!("The server never finishes the handshake"),
+    };
+    assert!(matches!(res, Err(ConnectionError::HandshakeTimeout(_))));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_contract_client_out_of_order() -> Result<(), std::io::Error> {
+    use crate::mock::{fixtures, MockClient};
+
+    const QUERIES: i64 = 50;
+
+    // The contract details of the fixture, answering each request with a contract ID of its own,
+    // in a scrambled order
+    let (prefix, rest) = fixtures::STOCK_CONTRACT_DETAILS.split_at(4);
+    let len = u32::from_be_bytes(prefix.try_into().unwrap_or_default()) as usize;
+    let template = crate::reader::split_fields(&rest[..len]);
+    let mut messages = Vec::new();
+    for req_id in (0..QUERIES).map(|i| (i * 17 + 5) % QUERIES) {
+        let mut fields = template.clone();
+        fields[1] = req_id.to_string();
+        fields[12] = (1000 + req_id).to_string();
+        messages.push(fields.join("\0").into_bytes());
+        messages.push(format!("52\x001\x00{req_id}\x00").into_bytes());
+    }
+
+    let (client, task) = ContractClient::spawn(MockClient::new(messages));
+    let mut tasks = Vec::new();
+    for i in 0..QUERIES {
+        let mut handle = client.clone();
+        let query = Query::IbContractId(ContractId(1000 + i), Routing::Smart);
+        let req_id = handle
+            .send_contract_query(query, QueryOptions::default())
+            .await?;
+        assert_eq!(req_id, i);
+        tasks.push(tokio::spawn(async move {
+            handle.recv_contract_query(req_id).await
+        }));
+    }
+    for (i, task) in (0..).zip(tasks) {
+        let contracts = task.await?.ok_or_else(port_closed)?;
+        let [contract] = contracts.as_slice() else {
+            panic!("Each query has a single contract");
+        };
+        assert_eq!(contract.contract_id(), ContractId(1000 + i));
+    }
+
+    // The task returns the port once every handle is dropped
+    drop(client);
+    assert_eq!(task.await?.queries().len(), QUERIES as usize);
+
+    // Concurrent calls from many tasks, answered in reverse
+    let messages = (0..QUERIES)
+        .rev()
+        .flat_map(|req_id| {
+            let mut fields = template.clone();
+            fields[1] = req_id.to_string();
+            [
+                fields.join("\0").into_bytes(),
+                format!("52\x001\x00{req_id}\x00").into_bytes(),
+            ]
+        })
+        .collect();
+    let (client, task) = ContractClient::spawn(MockClient::new(messages));
+    let tasks = (0..QUERIES)
+        .map(|_| {
+            let mut handle = client.clone();
+            tokio::spawn(async move {
+                crate::contract::new::<crate::contract::Stock>(
+                    &mut handle,
+                    Query::IbContractId(ContractId(265_598), Routing::Smart),
+                )
+                .await
+            })
+        })
+        .collect::<Vec<_>>();
+    for task in tasks {
+        let stock = task.await?.map_err(std::io::Error::other)?;
+        assert_eq!(stock.contract_id(), ContractId(265_598));
+    }
+
+    // Once the port stops receiving responses, so do the handles
+    let mut handle = client.clone();
+    drop(client);
+    assert!(matches!(
+        crate::contract::new::<crate::contract::Stock>(
+            &mut handle,
+            Query::IbContractId(ContractId(265_598), Routing::Smart),
+        )
+        .await,
+        Err(NewSecurityError::Disconnected)
+    ));
+    drop(handle);
+    assert_eq!(task.await?.remaining(), 0);
+    Ok(())
+}
@@ -1,13 +1,17 @@
 use std::fmt::{Display, Formatter};
 use std::io::{Error, Write};
+use std::sync::Arc;
 
 use serde::Serialize;
 
+use crate::record::{Direction, Recorder};
+
 #[derive(Debug)]
 pub(crate) struct Writer {
-    buf: Vec<u8>,
+    buf: Fields,
     offset: Option<usize>,
-    inner: tokio::net::tcp::OwnedWriteHalf,
+    inner: Arc<tokio::sync::Mutex<tokio::net::tcp::OwnedWriteHalf>>,
+    pub(crate) recorder: Recorder,
 }
 
 impl Writer {
@@ -23,15 +27,16 @@ impl Writer {
         let buf = Vec::with_capacity(cap);
 
         Self {
-            buf,
+            buf: Fields(buf),
             offset: None,
-            inner: writer,
+            inner: Arc::new(tokio::sync::Mutex::new(writer)),
+            recorder: Recorder::default(),
         }
     }
 
     #[inline]
     pub(crate) fn add_prefix(&mut self, prefix: &str) -> Result<(), Error> {
-        self.buf.write_all(prefix.as_bytes())?;
+        self.buf.0.write_all(prefix.as_bytes())?;
         self.offset = Some(prefix.len());
 
         Ok(())
@@ -43,15 +48,15 @@ impl Writer {
     /// This function will panic if the length of the message overflows by exceeding a length of 2^32 - 1 bytes.
     pub(crate) fn add_body<T: Serialize>(&mut self, body: T) -> Result<(), Error> {
         const LENGTH_PREFIX: &[u8] = b"\0\0\0\0";
-        self.buf.write_all(LENGTH_PREFIX)?;
+        self.buf.0.write_all(LENGTH_PREFIX)?;
 
-        body.serialize(&mut *self)?;
+        body.serialize(&mut self.buf)?;
         let (len, offset) = match self.offset {
-            Some(o) => (self.buf.len() - o - LENGTH_PREFIX.len(), o),
-            None => (self.buf.len() - LENGTH_PREFIX.len(), 0),
+            Some(o) => (self.buf.0.len() - o - LENGTH_PREFIX.len(), o),
+            None => (self.buf.0.len() - LENGTH_PREFIX.len(), 0),
         };
 
-        self.buf.splice(
+        self.buf.0.splice(
             offset..LENGTH_PREFIX.len() + offset,
             u32::try_from(len)
                 .expect("Overflow: Message length exceeds the max of 2³² - 1 bytes.")
@@ -63,8 +68,11 @@ impl Writer {
 
     #[inline]
     pub(crate) async fn send(&mut self) -> Result<(), Error> {
-        tokio::io::AsyncWriteExt::write_all(&mut self.inner, &self.buf).await?;
-        self.buf.clear();
+        tokio::io::AsyncWriteExt::write_all(&mut *self.inner.lock().await, &self.buf.0).await?;
+        // The handshake prefix is not a length-prefixed message, so it is not recorded
+        self.recorder
+            .record_frames(Direction::Outbound, &self.buf.0[self.offset.unwrap_or(0)..]);
+        self.buf.0.clear();
         self.offset = None;
 
         Ok(())
@@ -72,12 +80,90 @@ impl Writer {
 
     #[inline]
     pub(crate) async fn flush(&mut self) -> Result<(), Error> {
-        tokio::io::AsyncWriteExt::flush(&mut self.inner).await
+        tokio::io::AsyncWriteExt::flush(&mut *self.inner.lock().await).await
     }
 
     #[inline]
     pub(crate) async fn shutdown(&mut self) -> Result<(), Error> {
-        tokio::io::AsyncWriteExt::shutdown(&mut self.inner).await
+        tokio::io::AsyncWriteExt::shutdown(&mut *self.inner.lock().await).await
+    }
+
+    /// Prepare a message that is sent when the returned [`SendOnDrop`] is dropped, like the
+    /// cancellation of a subscription whose stream is dropped.
+    pub(crate) fn send_on_drop<T: Serialize>(&self, body: T) -> Result<SendOnDrop, Error> {
+        let mut frame = Fields(vec![0; 4]);
+        body.serialize(&mut frame)?;
+        let len = u32::try_from(frame.0.len() - 4)
+            .map_err(|_| Error::new(std::io::ErrorKind::InvalidInput, "Message is too long"))?;
+        frame.0[..4].copy_from_slice(&len.to_be_bytes());
+        Ok(SendOnDrop {
+            frame: frame.0,
+            inner: Arc::clone(&self.inner),
+            recorder: self.recorder.clone(),
+        })
+    }
+}
+
+#[derive(Debug)]
+/// A message that is sent, once it is dropped, on the connection of the [`Writer`] that prepared
+/// it. Since a destructor cannot wait, the message is written by a new task, which requires a
+/// running Tokio runtime. Without one, the message is not sent.
+pub(crate) struct SendOnDrop {
+    frame: Vec<u8>,
+    inner: Arc<tokio::sync::Mutex<tokio::net::tcp::OwnedWriteHalf>>,
+    recorder: Recorder,
+}
+
+impl SendOnDrop {
+    #[inline]
+    /// Drop the message without sending it.
+    pub(crate) fn disarm(mut self) {
+        self.frame.clear();
+    }
+}
+
+impl Drop for SendOnDrop {
+    fn drop(&mut self) {
+        if self.frame.is_empty() {
+            return;
+        }
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            return;
+        };
+        let (frame, inner, recorder) = (
+            std::mem::take(&mut self.frame),
+            Arc::clone(&self.inner),
+            self.recorder.clone(),
+        );
+        runtime.spawn(async move {
+            // The client may already be disconnected, in which case there is nothing to cancel
+            if tokio::io::AsyncWriteExt::write_all(&mut *inner.lock().await, &frame)
+                .await
+                .is_ok()
+            {
+                recorder.record_frames(Direction::Outbound, &frame);
+            }
+        });
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+/// The null-terminated fields of an outgoing message, as written by the serializer in [`ser`].
+pub(crate) struct Fields(Vec<u8>);
+
+impl Fields {
+    /// Serialize `body` into the fields that would be sent for it, without sending them.
+    pub(crate) fn to_strings<T: Serialize>(body: &T) -> Result<Vec<String>, SerializeMessageError> {
+        let mut fields = Self::default();
+        body.serialize(&mut fields)?;
+        let mut strings = fields
+            .0
+            .split(|&b| b == b'\0')
+            .map(|field| String::from_utf8_lossy(field).into_owned())
+            .collect::<Vec<_>>();
+        // Every field is terminated, which leaves an empty split after the last one
+        strings.pop();
+        Ok(strings)
     }
 }
 
@@ -126,7 +212,7 @@ pub(crate) mod ser {
         Serialize, Serializer,
     };
 
-    use super::{SerializeMessageError, Writer};
+    use super::{Fields, SerializeMessageError};
 
     #[inline]
     fn serialize_int<I: itoa::Integer>(buf: &mut Vec<u8>, int: I) -> Result<(), std::io::Error> {
@@ -146,7 +232,7 @@ pub(crate) mod ser {
         Ok(())
     }
 
-    impl Serializer for &mut Writer {
+    impl Serializer for &mut Fields {
         type Ok = ();
         type Error = SerializeMessageError;
         type SerializeSeq = Self;
@@ -159,68 +245,68 @@ pub(crate) mod ser {
 
         #[inline]
         fn serialize_bool(self, v: bool) -> Result<Self::Ok, Self::Error> {
-            self.buf.write_all(if v { b"1\0" } else { b"0\0" })?;
+            self.0.write_all(if v { b"1\0" } else { b"0\0" })?;
 
             Ok(())
         }
 
         #[inline]
         fn serialize_i8(self, v: i8) -> Result<Self::Ok, Self::Error> {
-            serialize_int(&mut self.buf, v)?;
+            serialize_int(&mut self.0, v)?;
             Ok(())
         }
 
         #[inline]
         fn serialize_i16(self, v: i16) -> Result<Self::Ok, Self::Error> {
-            serialize_int(&mut self.buf, v)?;
+            serialize_int(&mut self.0, v)?;
             Ok(())
         }
 
         #[inline]
         fn serialize_i32(self, v: i32) -> Result<Self::Ok, Self::Error> {
-            serialize_int(&mut self.buf, v)?;
+            serialize_int(&mut self.0, v)?;
             Ok(())
         }
 
         #[inline]
         fn serialize_i64(self, v: i64) -> Result<Self::Ok, Self::Error> {
-            serialize_int(&mut self.buf, v)?;
+            serialize_int(&mut self.0, v)?;
             Ok(())
         }
 
         #[inline]
         fn serialize_u8(self, v: u8) -> Result<Self::Ok, Self::Error> {
-            serialize_int(&mut self.buf, v)?;
+            serialize_int(&mut self.0, v)?;
             Ok(())
         }
 
         #[inline]
         fn serialize_u16(self, v: u16) -> Result<Self::Ok, Self::Error> {
-            serialize_int(&mut self.buf, v)?;
+            serialize_int(&mut self.0, v)?;
             Ok(())
         }
 
         #[inline]
         fn serialize_u32(self, v: u32) -> Result<Self::Ok, Self::Error> {
-            serialize_int(&mut self.buf, v)?;
+            serialize_int(&mut self.0, v)?;
             Ok(())
         }
 
         #[inline]
         fn serialize_u64(self, v: u64) -> Result<Self::Ok, Self::Error> {
-            serialize_int(&mut self.buf, v)?;
+            serialize_int(&mut self.0, v)?;
             Ok(())
         }
 
         #[inline]
         fn serialize_f32(self, v: f32) -> Result<Self::Ok, Self::Error> {
-            serialize_float(&mut self.buf, v)?;
+            serialize_float(&mut self.0, v)?;
             Ok(())
         }
 
         #[inline]
         fn serialize_f64(self, v: f64) -> Result<Self::Ok, Self::Error> {
-            serialize_float(&mut self.buf, v)?;
+            serialize_float(&mut self.0, v)?;
             Ok(())
         }
 
@@ -228,7 +314,7 @@ pub(crate) mod ser {
         fn serialize_char(self, v: char) -> Result<Self::Ok, Self::Error> {
             let mut temp = [0; 5];
             v.encode_utf8(&mut temp);
-            self.buf.write_all(&temp[..=v.len_utf8()])?;
+            self.0.write_all(&temp[..=v.len_utf8()])?;
 
             Ok(())
         }
@@ -240,15 +326,15 @@ pub(crate) mod ser {
 
         #[inline]
         fn serialize_bytes(self, v: &[u8]) -> Result<Self::Ok, Self::Error> {
-            self.buf.write_all(v)?;
-            self.buf.write_all(b"\0")?;
+            self.0.write_all(v)?;
+            self.0.write_all(b"\0")?;
 
             Ok(())
         }
 
         #[inline]
         fn serialize_none(self) -> Result<Self::Ok, Self::Error> {
-            self.buf.write_all(b"\0")?;
+            self.0.write_all(b"\0")?;
 
             Ok(())
         }
@@ -363,7 +449,7 @@ pub(crate) mod ser {
         }
     }
 
-    impl SerializeSeq for &mut Writer {
+    impl SerializeSeq for &mut Fields {
         type Ok = <Self as Serializer>::Ok;
         type Error = <Self as Serializer>::Error;
 
@@ -373,18 +459,18 @@ pub(crate) mod ser {
             T: ?Sized + Serialize,
         {
             value.serialize(&mut **self)?;
-            self.buf.splice(self.buf.len() - 1..self.buf.len(), *b",");
+            self.0.splice(self.0.len() - 1..self.0.len(), *b",");
             Ok(())
         }
 
         #[inline]
         fn end(self) -> Result<Self::Ok, Self::Error> {
-            self.buf.splice(self.buf.len() - 1..self.buf.len(), *b"\0");
+            self.0.splice(self.0.len() - 1..self.0.len(), *b"\0");
             Ok(())
         }
     }
 
-    impl SerializeTuple for &mut Writer {
+    impl SerializeTuple for &mut Fields {
         type Ok = <Self as Serializer>::Ok;
         type Error = <Self as Serializer>::Error;
 
@@ -402,7 +488,7 @@ pub(crate) mod ser {
         }
     }
 
-    impl SerializeTupleStruct for &mut Writer {
+    impl SerializeTupleStruct for &mut Fields {
         type Ok = <Self as Serializer>::Ok;
         type Error = <Self as Serializer>::Error;
 
@@ -420,7 +506,7 @@ pub(crate) mod ser {
         }
     }
 
-    impl SerializeTupleVariant for &mut Writer {
+    impl SerializeTupleVariant for &mut Fields {
         type Ok = <Self as Serializer>::Ok;
         type Error = <Self as Serializer>::Error;
 
@@ -438,7 +524,7 @@ pub(crate) mod ser {
         }
     }
 
-    impl SerializeMap for &mut Writer {
+    impl SerializeMap for &mut Fields {
         type Ok = <Self as Serializer>::Ok;
         type Error = <Self as Serializer>::Error;
 
@@ -464,7 +550,7 @@ pub(crate) mod ser {
         }
     }
 
-    impl SerializeStruct for &mut Writer {
+    impl SerializeStruct for &mut Fields {
         type Ok = <Self as Serializer>::Ok;
         type Error = <Self as Serializer>::Error;
 
@@ -482,7 +568,7 @@ pub(crate) mod ser {
         }
     }
 
-    impl SerializeStructVariant for &mut Writer {
+    impl SerializeStructVariant for &mut Fields {
         type Ok = <Self as Serializer>::Ok;
         type Error = <Self as Serializer>::Error;
 
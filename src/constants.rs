@@ -1,7 +1,9 @@
 pub const MIN_CLIENT_VERSION: u8 = 180;
 pub const MAX_CLIENT_VERSION: u8 = 180;
 pub const TO_CLIENT_CHANNEL_SIZE: usize = 10;
-pub const TO_WRAPPER_CHANNEL_SIZE: usize = 10;
 pub const FROM_READER_CHANNEL_SIZE: usize = 20;
+pub const NOTIFICATION_CHANNEL_SIZE: usize = 64;
 pub const OUT_MESSAGE_SIZE: usize = 512;
 pub const ORDER_TUPLE_SIZE: usize = 98;
+pub const MIN_SERVER_VERSION: u32 = MIN_CLIENT_VERSION as u32;
+pub const DEFAULT_REQUEST_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
@@ -4,25 +4,40 @@ use std::hash::Hash;
 
 use chrono::NaiveDate;
 use ibapi_macros::{make_getters, Security};
-use serde::{Deserialize, Deserializer, ser::SerializeStruct, Serialize, Serializer};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use thiserror::Error;
 
 use crate::{
+    client::ClientPort,
     currency::Currency,
-    exchange::{Primary, Routing},
+    exchange::{ParseExchangeError, Primary, Routing},
     match_poly,
 };
 use crate::contract::proxy_indicators::{HasExchange, NoExchange};
+use crate::error_code::{ErrorCode, TwsError};
 use crate::figi::{Figi, InvalidFigi};
+use crate::order::{Action, OrderTypeCode};
 
 // =========================================================
 // === Utility Types and Functions for Contract Creation ===
 // =========================================================
 
-// todo!("Ensure that includeExpired is always set to true");
-
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(
+    feature = "serde-full",
+    derive(Serialize, Deserialize),
+    serde(tag = "contract_type", content = "contract")
+)]
 /// Wrapper enum for all possible contracts available in the API
+///
+/// By default, a [`Contract`] is (de)serialized as the same flat summary as each [`Security`],
+/// which drops fields like `order_types` and `valid_exchanges`. With the `serde-full` feature
+/// enabled, every field is (de)serialized instead and the variant is recorded in a
+/// `contract_type` tag.
+///
+/// Two contracts are only equal, and only hash the same, when every field matches, including
+/// metadata like the trading hours that IBKR updates from day to day. To key a map or set on the
+/// identity of a contract instead, use a [`ContractKey`], which compares only the contract ID.
 pub enum Contract {
     /// A [`Forex`] contract.
     Forex(Forex),
@@ -32,18 +47,28 @@ pub enum Contract {
     Stock(Stock),
     /// An [`Index`] contract.
     Index(Index),
-    //Cfd(Cfd),
+    /// A [`Cfd`] contract.
+    Cfd(Cfd),
     /// A [`SecFuture`] contract.
     SecFuture(SecFuture),
     /// A [`SecOption`] contract.
     SecOption(SecOption),
-    //FutureSecOption(SecFutureOption),
-    //Bond(Bond),
-    //MutualFund(MutualFund),
+    /// A [`SecFutureOption`] contract.
+    SecFutureOption(SecFutureOption),
+    /// A [`Bond`] contract.
+    Bond(Bond),
+    /// A [`MutualFund`] contract.
+    MutualFund(MutualFund),
     /// A [`Commodity`] contract.
     Commodity(Commodity),
-    //Warrant(Warrant),
-    //StructuredProduct(StructuredProduct),
+    /// A [`Warrant`] contract.
+    Warrant(Warrant),
+    /// A [`ContinuousFuture`] contract.
+    ContinuousFuture(ContinuousFuture),
+    /// A [`StructuredProduct`] contract.
+    StructuredProduct(StructuredProduct),
+    /// A [`Combo`] contract.
+    Combo(Combo),
 }
 
 macro_rules! contract_impl {
@@ -83,18 +108,28 @@ impl Contract {
     contract_impl!(SecFuture, Self::SecFuture(t) => Some(t), secfuture_ref, secfuture);
     contract_impl!(SecOption, Self::SecOption(t) => Some(t), secoption_ref, secoption);
     contract_impl!(Commodity, Self::Commodity(t) => Some(t), commodity_ref, commodity);
+    contract_impl!(Bond, Self::Bond(t) => Some(t), bond_ref, bond);
+    contract_impl!(Cfd, Self::Cfd(t) => Some(t), cfd_ref, cfd);
+    contract_impl!(MutualFund, Self::MutualFund(t) => Some(t), mutual_fund_ref, mutual_fund);
+    contract_impl!(SecFutureOption, Self::SecFutureOption(t) => Some(t), secfutureoption_ref, secfutureoption);
+    contract_impl!(Warrant, Self::Warrant(t) => Some(t), warrant_ref, warrant);
+    contract_impl!(ContinuousFuture, Self::ContinuousFuture(t) => Some(t), continuous_future_ref, continuous_future);
+    contract_impl!(StructuredProduct, Self::StructuredProduct(t) => Some(t), structured_product_ref, structured_product);
+    contract_impl!(Combo, Self::Combo(t) => Some(t), combo_ref, combo);
 
     #[inline]
     #[must_use]
-    /// Attempt to get the inner security's exchange.
+    /// Get the inner security's exchange.
     ///
     /// # Returns
-    /// The inner security's exchange, `None` if the field doesn't exist (for a [`Crypto`] contract)
-    pub fn exchange(&self) -> Option<Routing> {
+    /// The inner security's exchange.
+    pub fn exchange(&self) -> Routing {
         match_poly!(self;
-            Contract::SecOption(s) | Contract::Forex(s) | Contract::Index(s) |
-            Contract::SecFuture(s) | Contract::Commodity(s) | Contract::Stock(s) => Some(s.exchange()),
-            Contract::Crypto(_) => None,
+            Contract::SecOption(s) | Contract::SecFutureOption(s) | Contract::Forex(s) |
+            Contract::Crypto(s) | Contract::Index(s) | Contract::SecFuture(s) |
+            Contract::Commodity(s) | Contract::Stock(s) | Contract::Bond(s) | Contract::Cfd(s) |
+            Contract::MutualFund(s) | Contract::Warrant(s) | Contract::ContinuousFuture(s) |
+            Contract::StructuredProduct(s) | Contract::Combo(s) => s.exchange()
         )
     }
 
@@ -103,12 +138,16 @@ impl Contract {
     /// Attempt to get the inner security's trading class.
     ///
     /// # Returns
-    /// The inner security's exchange, `None` if the field doesn't exist (for an [`Index`] contract)
+    /// The inner security's exchange, `None` if the field doesn't exist (for an [`Index`] or
+    /// [`Combo`] contract)
     pub fn trading_class(&self) -> Option<&str> {
         match_poly!(self;
-            Contract::SecOption(s) | Contract::Forex(s) | Contract::Crypto(s) |
-            Contract::SecFuture(s) | Contract::Commodity(s) | Contract::Stock(s) => Some(s.trading_class()),
-            Contract::Index(_) => None,
+            Contract::SecOption(s) | Contract::SecFutureOption(s) | Contract::Forex(s) | Contract::Crypto(s) |
+            Contract::SecFuture(s) | Contract::Commodity(s) | Contract::Stock(s) |
+            Contract::Bond(s) | Contract::Cfd(s) | Contract::MutualFund(s) |
+            Contract::Warrant(s) | Contract::ContinuousFuture(s) |
+            Contract::StructuredProduct(s) => Some(s.trading_class()),
+            Contract::Index(_) | Contract::Combo(_) => None,
         )
     }
 
@@ -117,10 +156,13 @@ impl Contract {
     /// Attempt to get the inner security's multiplier.
     ///
     /// # Returns
-    /// The inner security's multiplier if the inner contract is a [`SecOption`] or [`SecFuture`], `None` otherwise
-    pub fn multiplier(&self) -> Option<u32> {
+    /// The inner security's multiplier, which may be fractional, if the inner contract is a
+    /// [`SecOption`], [`SecFutureOption`], [`SecFuture`], [`Warrant`], or [`StructuredProduct`],
+    /// `None` otherwise.
+    pub fn multiplier(&self) -> Option<f64> {
         match_poly!(self;
-            Contract::SecOption(s) | Contract::SecFuture(s) => Some(s.multiplier()),
+            Contract::SecOption(s) | Contract::SecFutureOption(s) | Contract::SecFuture(s) | Contract::Warrant(s) |
+            Contract::StructuredProduct(s) => Some(s.multiplier()),
             _ => None
         )
     }
@@ -130,10 +172,11 @@ impl Contract {
     /// Attempt to get the inner security's expiration date.
     ///
     /// # Returns
-    /// The inner security's expiration date if the inner contract is a [`SecOption`] or [`SecFuture`], `None` otherwise
+    /// The inner security's expiration date if the inner contract is a [`SecOption`], [`SecFutureOption`], [`Warrant`], [`SecFuture`], or [`StructuredProduct`] with a known expiration date, `None` otherwise
     pub fn expiration_date(&self) -> Option<NaiveDate> {
         match_poly!(self;
-            Contract::SecOption(s) | Contract::SecFuture(s) => Some(s.expiration_date()),
+            Contract::SecOption(s) | Contract::SecFutureOption(s) | Contract::Warrant(s) |
+            Contract::SecFuture(s) | Contract::StructuredProduct(s) => Expiring::expiration_date(s),
             _ => None
         )
     }
@@ -143,15 +186,118 @@ impl Contract {
     /// Attempt to get the underlying security's expiration date.
     ///
     /// # Returns
-    /// The inner security's underlying contract ID if the inner contract is a [`SecOption`] or [`SecFuture`], `None` otherwise
+    /// The inner security's underlying contract ID if the inner contract is a [`SecOption`], [`SecFutureOption`], [`Warrant`], [`SecFuture`], [`ContinuousFuture`], or [`Cfd`], `None` otherwise
     pub fn underlying_contract_id(&self) -> Option<ContractId> {
         match_poly!(self;
-            Contract::SecOption(s) | Contract::SecFuture(s) => Some(s.underlying_contract_id()),
+            Contract::SecOption(s) | Contract::SecFutureOption(s) | Contract::SecFuture(s) |
+            Contract::Cfd(s) | Contract::Warrant(s) | Contract::ContinuousFuture(s) => {
+                Some(HasUnderlying::underlying_contract_id(s))
+            },
             _ => None
         )
     }
+
+    #[inline]
+    #[must_use]
+    /// Get the type of the inner security without matching on every variant.
+    ///
+    /// # Returns
+    /// The [`ContractType`] corresponding to the inner security.
+    pub const fn security_type(&self) -> ContractType {
+        match self {
+            Self::Forex(_) => ContractType::Forex,
+            Self::Crypto(_) => ContractType::Crypto,
+            Self::Stock(_) => ContractType::Stock,
+            Self::Index(_) => ContractType::Index,
+            Self::SecFuture(_) => ContractType::SecFuture,
+            Self::SecOption(_) => ContractType::SecOption,
+            Self::Commodity(_) => ContractType::Commodity,
+            Self::Bond(_) => ContractType::Bond,
+            Self::Cfd(_) => ContractType::Cfd,
+            Self::MutualFund(_) => ContractType::MutualFund,
+            Self::SecFutureOption(_) => ContractType::SecFutureOption,
+            Self::Warrant(_) => ContractType::Warrant,
+            Self::ContinuousFuture(_) => ContractType::ContinuousFuture,
+            Self::StructuredProduct(_) => ContractType::StructuredProduct,
+            Self::Combo(_) => ContractType::Combo,
+        }
+    }
+
+    #[must_use]
+    /// Get a key by which to sort contracts for display, like in a watchlist: by symbol, then by
+    /// currency code, then by security type.
+    ///
+    /// Unlike the derived [`Ord`] implementation, which compares every field in declaration order,
+    /// the key only depends on what a user sees, so it orders contracts the same way however they
+    /// were resolved. Contracts with the same key, like the options on the same underlying, keep
+    /// their relative order under a stable sort.
+    ///
+    /// # Returns
+    /// The contract's symbol, its currency code, and its [`ContractType`].
+    pub fn display_key(&self) -> (String, String, ContractType) {
+        (
+            self.symbol().to_owned(),
+            self.currency().to_string(),
+            self.security_type(),
+        )
+    }
+
+    /// Put the contract into a canonical form, so that two resolutions of the same contract
+    /// compare equal with `==` and serialize identically, like in a [`ContractCache`] that is
+    /// saved to disk.
+    ///
+    /// IBKR reports the order types and valid exchanges of a contract in no fixed order, so they
+    /// are sorted and deduplicated, along with the market rule IDs of its [`ContractDetails`].
+    /// The whitespace around its symbol, local symbol, and long name is trimmed.
+    pub fn canonicalize(&mut self) {
+        match_poly!(self;
+            Self::Forex(s)
+            | Self::Crypto(s)
+            | Self::Stock(s)
+            | Self::Index(s)
+            | Self::SecFuture(s)
+            | Self::SecOption(SecOption::Call(s) | SecOption::Put(s))
+            | Self::Commodity(s)
+            | Self::Bond(s)
+            | Self::Cfd(s)
+            | Self::MutualFund(s)
+            | Self::SecFutureOption(SecFutureOption::Call(s) | SecFutureOption::Put(s))
+            | Self::Warrant(Warrant::Call(s) | Warrant::Put(s))
+            | Self::ContinuousFuture(s)
+            | Self::StructuredProduct(s)
+            | Self::Combo(s) => canonicalize_fields(
+                [&mut s.symbol, &mut s.local_symbol, &mut s.long_name],
+                &mut s.order_types,
+                &mut s.valid_exchanges,
+                &mut s.details,
+            )
+        );
+    }
+}
+
+/// Sort and deduplicate the lists, and trim the names, that every contract struct holds for
+/// [`Contract::canonicalize`].
+fn canonicalize_fields(
+    names: [&mut String; 3],
+    order_types: &mut Vec<OrderTypeCode>,
+    valid_exchanges: &mut Vec<Routing>,
+    details: &mut ContractDetails,
+) {
+    for name in names {
+        let trimmed = name.trim();
+        if trimmed.len() != name.len() {
+            *name = trimmed.to_owned();
+        }
+    }
+    order_types.sort_unstable();
+    order_types.dedup();
+    valid_exchanges.sort_unstable();
+    valid_exchanges.dedup();
+    details.market_rule_ids.sort_unstable();
+    details.market_rule_ids.dedup();
 }
 
+#[cfg(not(feature = "serde-full"))]
 impl Serialize for Contract {
     #[inline]
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
@@ -165,7 +311,160 @@ impl Serialize for Contract {
             | Self::Index(t)
             | Self::SecFuture(t)
             | Self::SecOption(t)
-            | Self::Commodity(t) => t.serialize(serializer)
+            | Self::Commodity(t)
+            | Self::Bond(t)
+            | Self::Cfd(t)
+            | Self::MutualFund(t)
+            | Self::SecFutureOption(t)
+            | Self::Warrant(t)
+            | Self::ContinuousFuture(t)
+            | Self::StructuredProduct(t)
+            | Self::Combo(t) => t.serialize(serializer)
+        )
+    }
+}
+
+#[cfg(not(feature = "serde-full"))]
+impl<'de> Deserialize<'de> for Contract {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        use serde::de::Error;
+
+        let SerContractHelp {
+            contract_id,
+            security_type,
+            symbol,
+            long_name,
+            min_tick,
+            exchange,
+            primary_exchange,
+            currency,
+            local_symbol,
+            trading_class,
+            expiration_date,
+            strike,
+            option_class,
+            multiplier,
+            legs,
+            delta_neutral_contract,
+            issuer,
+        } = SerContractHelp::deserialize(deserializer)?;
+
+        // A `Proxy` can't hold the legs of a combo
+        if security_type.eq_ignore_ascii_case("BAG") {
+            return Ok(Self::Combo(Combo {
+                contract_id,
+                min_tick,
+                symbol,
+                exchange,
+                legs,
+                delta_neutral_contract,
+                currency,
+                local_symbol,
+                long_name,
+                order_types: Vec::default(),
+                valid_exchanges: Vec::default(),
+                details: ContractDetails::default(),
+            }));
+        }
+
+        // The same fields as a `Proxy` are held, so reuse its deserialization for the variants
+        let proxy: Proxy<Self, HasExchange> = SerProxyHelp {
+            contract_type: security_type.to_uppercase().parse().map_err(Error::custom)?,
+            contract_id,
+            symbol,
+            currency,
+            local_symbol,
+            exchange: Some(exchange),
+            trading_class,
+            primary_exchange,
+            contract_month: match expiration_date.as_deref() {
+                Some(month) if month.len() == 6 => Some(
+                    NaiveDate::parse_from_str(&format!("{month}01"), "%Y%m%d")
+                        .map(contract_month_of)
+                        .map_err(Error::custom)?,
+                ),
+                _ => None,
+            },
+            expiration_date: expiration_date
+                .filter(|d| d.len() != 6)
+                .map(|d| NaiveDate::parse_from_str(&d, "%Y%m%d"))
+                .transpose()
+                .map_err(Error::custom)?,
+            multiplier,
+            option_type: match option_class.as_deref() {
+                Some("C") => Some(SecOptionClass::Call),
+                Some("P") => Some(SecOptionClass::Put),
+                Some(c) => return Err(Error::custom(format!("Invalid option class {c}"))),
+                None => None,
+            },
+            strike,
+        }
+        .try_into()
+        .map_err(Error::custom)?;
+        let mut contract = proxy.inner;
+
+        // A `Proxy` lacks these fields
+        match &mut contract {
+            Self::Forex(Forex { min_tick: tick, long_name: name, .. })
+            | Self::Crypto(Crypto { min_tick: tick, long_name: name, .. })
+            | Self::Stock(Stock { min_tick: tick, long_name: name, .. })
+            | Self::Index(Index { min_tick: tick, long_name: name, .. })
+            | Self::SecFuture(SecFuture { min_tick: tick, long_name: name, .. })
+            | Self::SecOption(
+                SecOption::Call(SecOptionInner { min_tick: tick, long_name: name, .. })
+                | SecOption::Put(SecOptionInner { min_tick: tick, long_name: name, .. }),
+            )
+            | Self::Commodity(Commodity { min_tick: tick, long_name: name, .. })
+            | Self::Bond(Bond { min_tick: tick, long_name: name, .. })
+            | Self::Cfd(Cfd { min_tick: tick, long_name: name, .. })
+            | Self::MutualFund(MutualFund { min_tick: tick, long_name: name, .. })
+            | Self::SecFutureOption(
+                SecFutureOption::Call(SecFutureOptionInner { min_tick: tick, long_name: name, .. })
+                | SecFutureOption::Put(SecFutureOptionInner { min_tick: tick, long_name: name, .. }),
+            )
+            | Self::Warrant(
+                Warrant::Call(WarrantInner { min_tick: tick, long_name: name, .. })
+                | Warrant::Put(WarrantInner { min_tick: tick, long_name: name, .. }),
+            )
+            | Self::ContinuousFuture(ContinuousFuture { min_tick: tick, long_name: name, .. })
+            | Self::StructuredProduct(StructuredProduct { min_tick: tick, long_name: name, .. })
+            | Self::Combo(Combo { min_tick: tick, long_name: name, .. }) => {
+                *tick = min_tick;
+                *name = long_name;
+            }
+        }
+        if let (Self::Stock(stk), Some(primary_exchange)) = (&mut contract, primary_exchange) {
+            stk.primary_exchange = primary_exchange;
+        }
+        if let Self::Warrant(Warrant::Call(war) | Warrant::Put(war)) = &mut contract {
+            war.issuer = issuer;
+        }
+        Ok(contract)
+    }
+}
+
+impl std::fmt::Display for Contract {
+    #[inline]
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match_poly!(self;
+            Self::Forex(t)
+            | Self::Crypto(t)
+            | Self::Stock(t)
+            | Self::Index(t)
+            | Self::SecFuture(t)
+            | Self::SecOption(t)
+            | Self::Commodity(t)
+            | Self::Bond(t)
+            | Self::Cfd(t)
+            | Self::MutualFund(t)
+            | Self::SecFutureOption(t)
+            | Self::Warrant(t)
+            | Self::ContinuousFuture(t)
+            | Self::StructuredProduct(t)
+            | Self::Combo(t) => std::fmt::Display::fmt(t, f)
         )
     }
 }
@@ -180,7 +479,15 @@ impl Security for Contract {
             | Self::Index(t)
             | Self::SecFuture(t)
             | Self::SecOption(t)
-            | Self::Commodity(t) => t.contract_id()
+            | Self::Commodity(t)
+            | Self::Bond(t)
+            | Self::Cfd(t)
+            | Self::MutualFund(t)
+            | Self::SecFutureOption(t)
+            | Self::Warrant(t)
+            | Self::ContinuousFuture(t)
+            | Self::StructuredProduct(t)
+            | Self::Combo(t) => t.contract_id()
         )
     }
 
@@ -193,7 +500,36 @@ impl Security for Contract {
             | Self::Index(t)
             | Self::SecFuture(t)
             | Self::SecOption(t)
-            | Self::Commodity(t) => t.min_tick()
+            | Self::Commodity(t)
+            | Self::Bond(t)
+            | Self::Cfd(t)
+            | Self::MutualFund(t)
+            | Self::SecFutureOption(t)
+            | Self::Warrant(t)
+            | Self::ContinuousFuture(t)
+            | Self::StructuredProduct(t)
+            | Self::Combo(t) => t.min_tick()
+        )
+    }
+
+    #[inline]
+    fn multiplier(&self) -> f64 {
+        match_poly!(self;
+            Self::Forex(t)
+            | Self::Crypto(t)
+            | Self::Stock(t)
+            | Self::Index(t)
+            | Self::SecFuture(t)
+            | Self::SecOption(t)
+            | Self::Commodity(t)
+            | Self::Bond(t)
+            | Self::Cfd(t)
+            | Self::MutualFund(t)
+            | Self::SecFutureOption(t)
+            | Self::Warrant(t)
+            | Self::ContinuousFuture(t)
+            | Self::StructuredProduct(t)
+            | Self::Combo(t) => Security::multiplier(t)
         )
     }
 
@@ -206,7 +542,15 @@ impl Security for Contract {
             | Self::Index(t)
             | Self::SecFuture(t)
             | Self::SecOption(t)
-            | Self::Commodity(t) => t.symbol()
+            | Self::Commodity(t)
+            | Self::Bond(t)
+            | Self::Cfd(t)
+            | Self::MutualFund(t)
+            | Self::SecFutureOption(t)
+            | Self::Warrant(t)
+            | Self::ContinuousFuture(t)
+            | Self::StructuredProduct(t)
+            | Self::Combo(t) => t.symbol()
         )
     }
 
@@ -219,7 +563,15 @@ impl Security for Contract {
             | Self::Index(t)
             | Self::SecFuture(t)
             | Self::SecOption(t)
-            | Self::Commodity(t) => t.currency()
+            | Self::Commodity(t)
+            | Self::Bond(t)
+            | Self::Cfd(t)
+            | Self::MutualFund(t)
+            | Self::SecFutureOption(t)
+            | Self::Warrant(t)
+            | Self::ContinuousFuture(t)
+            | Self::StructuredProduct(t)
+            | Self::Combo(t) => t.currency()
         )
     }
 
@@ -232,7 +584,15 @@ impl Security for Contract {
             | Self::Index(t)
             | Self::SecFuture(t)
             | Self::SecOption(t)
-            | Self::Commodity(t) => t.local_symbol()
+            | Self::Commodity(t)
+            | Self::Bond(t)
+            | Self::Cfd(t)
+            | Self::MutualFund(t)
+            | Self::SecFutureOption(t)
+            | Self::Warrant(t)
+            | Self::ContinuousFuture(t)
+            | Self::StructuredProduct(t)
+            | Self::Combo(t) => t.local_symbol()
         )
     }
 
@@ -245,12 +605,20 @@ impl Security for Contract {
             | Self::Index(t)
             | Self::SecFuture(t)
             | Self::SecOption(t)
-            | Self::Commodity(t) => t.long_name()
+            | Self::Commodity(t)
+            | Self::Bond(t)
+            | Self::Cfd(t)
+            | Self::MutualFund(t)
+            | Self::SecFutureOption(t)
+            | Self::Warrant(t)
+            | Self::ContinuousFuture(t)
+            | Self::StructuredProduct(t)
+            | Self::Combo(t) => t.long_name()
         )
     }
 
     #[inline]
-    fn order_types(&self) -> &Vec<String> {
+    fn order_types(&self) -> &Vec<OrderTypeCode> {
         match_poly!(self;
             Self::Forex(t)
             | Self::Crypto(t)
@@ -258,7 +626,15 @@ impl Security for Contract {
             | Self::Index(t)
             | Self::SecFuture(t)
             | Self::SecOption(t)
-            | Self::Commodity(t) => t.order_types()
+            | Self::Commodity(t)
+            | Self::Bond(t)
+            | Self::Cfd(t)
+            | Self::MutualFund(t)
+            | Self::SecFutureOption(t)
+            | Self::Warrant(t)
+            | Self::ContinuousFuture(t)
+            | Self::StructuredProduct(t)
+            | Self::Combo(t) => t.order_types()
         )
     }
 
@@ -271,12 +647,25 @@ impl Security for Contract {
             | Self::Index(t)
             | Self::SecFuture(t)
             | Self::SecOption(t)
-            | Self::Commodity(t) => t.valid_exchanges()
+            | Self::Commodity(t)
+            | Self::Bond(t)
+            | Self::Cfd(t)
+            | Self::MutualFund(t)
+            | Self::SecFutureOption(t)
+            | Self::Warrant(t)
+            | Self::ContinuousFuture(t)
+            | Self::StructuredProduct(t)
+            | Self::Combo(t) => t.valid_exchanges()
         )
     }
 
     #[inline]
     fn contract_type(&self) -> ContractType {
+        self.security_type()
+    }
+
+    #[inline]
+    fn details(&self) -> &ContractDetails {
         match_poly!(self;
             Self::Forex(t)
             | Self::Crypto(t)
@@ -284,7 +673,15 @@ impl Security for Contract {
             | Self::Index(t)
             | Self::SecFuture(t)
             | Self::SecOption(t)
-            | Self::Commodity(t) => t.contract_type()
+            | Self::Commodity(t)
+            | Self::Bond(t)
+            | Self::Cfd(t)
+            | Self::MutualFund(t)
+            | Self::SecFutureOption(t)
+            | Self::Warrant(t)
+            | Self::ContinuousFuture(t)
+            | Self::StructuredProduct(t)
+            | Self::Combo(t) => t.details()
         )
     }
 }
@@ -301,520 +698,3974 @@ impl Security for Contract {
 /// Returns any error encountered while writing the query string to the outgoing buffer, while
 /// sending the creation signal to the client loop thread, or while receiving the complete contract
 /// from the client loop thread. Additionally, this function will error if the contract does not
-/// match the generic type specified in the function call.
+/// match the generic type specified in the function call, if several contracts match the query, or
+/// if the IBKR API does not answer within the client's request timeout.
 ///
 /// # Returns
 /// A fully-defined contract that can be used for market data, placing orders, etc.
+///
+/// Expired futures, options, and futures options are resolved as well. Use [`new_with_options`]
+/// to leave them out.
+///
+/// A call borrows its client mutably until the contract arrives. To resolve contracts from many
+/// tasks concurrently, call this with clones of a [`crate::client::ContractClient`], which routes
+/// each response back to the handle that sent its query. To resolve several contracts at once
+/// from a single task, use [`new_many`].
 pub async fn new<S: Security>(
-    client: &mut crate::client::ActiveClient,
+    client: &mut impl ClientPort,
     query: Query,
 ) -> Result<S, NewSecurityError> {
-    client.send_contract_query(query).await?;
-    client
-        .recv_contract_query()
-        .await
-        .ok_or(NewSecurityError::BadResponse)?
-        .try_into()
-        .map_err(|e: <S as TryFrom<Contract>>::Error| {
-            NewSecurityError::UnexpectedSecurityType(e.into())
-        })
+    let options = QueryOptions::for_query::<S>(&query);
+    new_with_options(client, query, options).await
 }
 
-#[derive(Debug, Error)]
-/// An error type that is returned if creating a [`new`] [`Security`] fails
-pub enum NewSecurityError {
-    /// Failed to send the contract query to the IBKR API
-    #[error("Failed to send contract query to IBKR API. Cause {0}")]
-    Io(#[from] std::io::Error),
-    /// Failed to receive valid response form the IBKR API
-    #[error("No valid contract received from the IBKR API.")]
-    BadResponse,
-    /// Unexpected security type returned from the IBKR API
-    #[error("Invalid contract received from the IBKR API. {0}")]
-    UnexpectedSecurityType(#[from] UnexpectedSecurityType),
+/// Create a new contract like [`new`], but with additional [`QueryOptions`] that control how the
+/// query is resolved, like whether expired contracts can be returned.
+///
+/// # Arguments
+/// * `client` - The client with which to send the contract query.
+/// * `query` - The query that uniquely identifies the contract in the IBKR system.
+/// * `options` - The options with which to make the query.
+///
+/// # Errors
+/// Returns the same errors as [`new`].
+///
+/// # Returns
+/// A fully-defined contract that can be used for market data, placing orders, etc.
+pub async fn new_with_options<S: Security>(
+    client: &mut impl ClientPort,
+    query: Query,
+    options: QueryOptions,
+) -> Result<S, NewSecurityError> {
+    let req_id = client.send_contract_query(query, options).await?;
+    let contracts = await_contract_query(client, req_id).await?;
+    resolve_contracts(check_rejected(client, req_id, contracts)?)
 }
 
-#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Error)]
-#[error("Unexpected security type. Expected {expected:?}. Found {found:?}")]
-/// An error type that's returned when a [`Security`] of type `S` is requested, but a security of
-/// another type is received from the API
-pub struct UnexpectedSecurityType {
-    /// The expected contract type
-    expected: ContractType,
-    /// The contract type that was actually found
-    found: ContractType,
-}
+/// Create several new contracts like [`new`], keeping a few of the queries in flight at once so
+/// that the round trips to the IBKR API overlap rather than happen one after another.
+///
+/// Each query succeeds or fails on its own, so one failed query does not abort the others. If
+/// none of the queries in flight is answered within the client's request timeout, all of them
+/// fail with [`NewSecurityError::Timeout`].
+///
+/// # Arguments
+/// * `client` - The client with which to send the contract queries.
+/// * `queries` - The queries that each uniquely identify a contract in the IBKR system.
+///
+/// # Returns
+/// The result of each query, in the same order as `queries`. Each result has the same errors as
+/// [`new`].
+pub async fn new_many<S: Security>(
+    client: &mut impl ClientPort,
+    queries: Vec<Query>,
+) -> Vec<Result<S, NewSecurityError>> {
+    // Bound the queries in flight so that neither channel to the client loop thread fills up while
+    // the queries are being sent
+    const MAX_IN_FLIGHT: usize = 5;
+
+    let mut results = queries.iter().map(|_| None).collect::<Vec<_>>();
+    let mut queries = queries.into_iter().enumerate();
+    let mut indices = std::collections::HashMap::new();
+    let mut pending = std::collections::HashMap::new();
+    // Every query is forgotten once this returns, or once the returned future is dropped
+    let mut guard = QueryGuard {
+        client,
+        req_ids: Vec::new(),
+    };
+    loop {
+        while pending.len() < MAX_IN_FLIGHT {
+            let Some((i, query)) = queries.next() else {
+                break;
+            };
+            let options = QueryOptions::for_query::<S>(&query);
+            match guard.client.send_contract_query(query, options).await {
+                Ok(req_id) => {
+                    indices.insert(req_id, i);
+                    pending.insert(req_id, Vec::new());
+                    guard.req_ids.push(req_id);
+                }
+                Err(e) => results[i] = Some(Err(e.into())),
+            }
+        }
+        if pending.is_empty() {
+            break;
+        }
+        let timeout = guard.client.request_timeout();
+        // Any pending query stands in for the others in the error
+        let first = pending.keys().copied().min().unwrap_or_default();
+        let recv = guard.client.recv_contract_queries(&mut pending);
+        let Ok(received) = with_timeout(timeout, first, RequestKind::ContractDetails, recv).await
+        else {
+            for (req_id, _) in pending.drain() {
+                guard.client.forget_contract_query(req_id);
+                if let Some(i) = indices.remove(&req_id) {
+                    let kind = RequestKind::ContractDetails;
+                    results[i] = Some(Err(RequestTimeout { req_id, kind }.into()));
+                }
+            }
+            continue;
+        };
+        let Some((req_id, contracts)) = received else {
+            break;
+        };
+        let contracts = check_rejected(guard.client, req_id, contracts);
+        if let Some(i) = indices.remove(&req_id) {
+            results[i] = Some(contracts.and_then(resolve_contracts));
+        }
+    }
+    drop(guard);
 
-#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
-/// A type used to represent a query for a new contract, which can be made by providing either an
-/// IBKR contract ID, or a FIGI.
-pub enum Query {
-    /// An IBKR contract ID with which to make a query. When parsing from a string, the routing field
-    /// defaults to [`Routing::Smart`].
-    IbContractId(ContractId, Routing),
-    /// A FIGI.
-    Figi(Figi),
+    // Any query without a result was never answered before the client loop thread stopped
+    results
+        .into_iter()
+        .map(|r| r.unwrap_or(Err(NewSecurityError::Disconnected)))
+        .collect()
 }
 
-impl From<ContractId> for Query {
-    fn from(value: ContractId) -> Self {
-        Self::IbContractId(value, Routing::Smart)
+#[inline]
+/// Snap `price` to a multiple of `tick` with `rounding`, treating a price within floating-point
+/// error of a multiple, like 100.03 with a tick of 0.01, as that multiple.
+fn snap_to_tick(price: f64, tick: f64, rounding: fn(f64) -> f64) -> f64 {
+    if tick <= 0.0 || !price.is_finite() {
+        return price;
+    }
+    let ticks = price / tick;
+    let nearest = ticks.round();
+    if (ticks - nearest).abs() <= 1e-9 * nearest.abs().max(1.0) {
+        nearest * tick
+    } else {
+        rounding(ticks) * tick
     }
 }
 
-impl From<Figi> for Query {
-    fn from(value: Figi) -> Self {
-        Self::Figi(value)
+#[inline]
+/// Fail with the error with which IBKR rejected the query sent with `req_id`, if any, since a
+/// rejected query ends without any contracts.
+fn check_rejected(
+    client: &mut (impl ClientPort + ?Sized),
+    req_id: i64,
+    contracts: Vec<Contract>,
+) -> Result<Vec<Contract>, NewSecurityError> {
+    match client.take_request_error(req_id) {
+        Some(e) => Err(e.into()),
+        None => Ok(contracts),
     }
 }
 
-#[derive(Debug, Clone, Error)]
-/// An error type representing the potential ways that a [`Query`] can be invalid.
-pub enum ParseQueryError {
-    #[error("Invalid value when parsing IBKR contract ID. Cause: {0}")]
-    /// An invalid [`Query::IbContractId`]
-    IbContractId(ParseContractIdError),
-    #[error("Invalid value when parsing FIGI. Cause: {0}")]
-    /// AN invalid [`Query::Figi`]
-    Figi(InvalidFigi),
-    #[error("Cannot construct query from empty string.")]
-    /// Invalid in a way such that it's impossible to tell whether it was intended to be an [`Query::IbContractId`] or a [`Query::Figi`].
-    Empty,
+#[inline]
+/// Like [`check_rejected`], but for a search, which has no matches rather than failing when IBKR
+/// reports error 200 because no contract matches it.
+pub(crate) fn check_rejected_search(
+    client: &mut (impl ClientPort + ?Sized),
+    req_id: i64,
+    contracts: Vec<Contract>,
+) -> Result<Vec<Contract>, NewSecurityError> {
+    match check_rejected(client, req_id, contracts) {
+        Err(NewSecurityError::Rejected(e)) if e.code == ErrorCode::NoSecurityDefinition => {
+            Ok(Vec::new())
+        }
+        res => res,
+    }
 }
 
-impl FromStr for Query {
-    type Err = ParseQueryError;
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        // A FIGI always begin with a letter
-        if s.chars().nth(0).ok_or(ParseQueryError::Empty)?.is_numeric() {
-            Ok(Self::IbContractId(
-                s.parse().map_err(ParseQueryError::IbContractId)?,
-                Routing::Smart,
-            ))
-        } else {
-            Ok(Self::Figi(s.parse().map_err(ParseQueryError::Figi)?))
-        }
+#[inline]
+/// Coerce the contracts that match a query into the single [`Security`] that a [`new`] call
+/// expects.
+fn resolve_contracts<S: Security>(mut contracts: Vec<Contract>) -> Result<S, NewSecurityError> {
+    if contracts.len() > 1 {
+        return Err(NewSecurityError::Ambiguous(contracts));
     }
+    contracts
+        .pop()
+        .ok_or(NewSecurityError::BadResponse)?
+        .try_into()
+        .map_err(|e: <S as TryFrom<Contract>>::Error| {
+            NewSecurityError::UnexpectedSecurityType(e.into())
+        })
 }
 
-#[allow(clippy::module_name_repetitions)]
-#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Serialize, Deserialize)]
-/// A unique identifier used by both IBKR's trading systems and the API to define a specific
-/// contract.
-pub struct ContractId(pub i64);
+/// Search for all the contracts that match a [`ContractSpec`]. Unlike [`new`], this does not
+/// error when several contracts match, so it can be used to disambiguate a query.
+///
+/// # Arguments
+/// * `client` - The client with which to send the contract query.
+/// * `spec` - The partially-specified contract for which to search.
+///
+/// # Errors
+/// Returns any error encountered while writing the query string to the outgoing buffer, while
+/// sending the creation signal to the client loop thread, or while receiving the contracts from
+/// the client loop thread.
+///
+/// # Returns
+/// Every contract that matches `spec`, which may be empty.
+pub async fn search(
+    client: &mut impl ClientPort,
+    spec: ContractSpec,
+) -> Result<Vec<Contract>, NewSecurityError> {
+    let query = Query::Pattern(spec);
+    let options = QueryOptions::for_query::<Contract>(&query);
+    let req_id = client.send_contract_query(query, options).await?;
+    let contracts = await_contract_query(client, req_id).await?;
+    check_rejected_search(client, req_id, contracts)
+}
 
-#[derive(Debug, Clone, Error)]
-#[error("Invalid value encountered when attempting to parse contract ID. Cause: {0}")]
-/// An error returned when parsing a [`ContractId`] fails.
-pub struct ParseContractIdError(pub ParseIntError);
+/// Search for the contracts whose symbol or name matches a free-text `pattern`, like "apple".
+///
+/// IBKR only allows one such search per second, so a search that follows another too closely
+/// fails with [`SearchSymbolsError::Pacing`] instead of waiting on a response that never comes.
+///
+/// # Arguments
+/// * `client` - The client with which to send the search.
+/// * `pattern` - The start of a symbol, like "AAP", or of a company name, like "apple".
+///
+/// # Errors
+/// Returns any error encountered while writing the search to the outgoing buffer or while
+/// receiving the matches from the client loop thread. Also returns an error if the previous search
+/// was less than a second ago, or if the IBKR API does not answer within the client's request
+/// timeout.
+///
+/// # Returns
+/// The matching contracts, which may be empty.
+pub async fn search_symbols(
+    client: &mut impl ClientPort,
+    pattern: &str,
+) -> Result<Vec<SymbolMatch>, SearchSymbolsError> {
+    let req_id = client.send_matching_symbols(pattern).await?;
+    let timeout = client.request_timeout();
+    with_timeout(
+        timeout,
+        req_id,
+        RequestKind::MatchingSymbols,
+        client.recv_matching_symbols(),
+    )
+    .await?
+    .ok_or(SearchSymbolsError::Disconnected)
+}
 
-impl FromStr for ContractId {
-    type Err = ParseContractIdError;
+#[derive(Debug, Clone, Default, PartialEq)]
+/// A cache of the contracts resolved by [`new`], so that repeated queries for the same contract
+/// don't each send a contract details request, which IBKR paces aggressively.
+///
+/// Contracts are keyed by the [`Query`] that resolved them, including its routing, so a contract
+/// resolved on SMART is cached separately from one resolved on a direct exchange. Entries can
+/// expire after a time-to-live, and with the `serde-full` feature the cache can be saved with
+/// [`ContractCache::serialize_to`] and reloaded with [`ContractCache::load_from`] to warm start a
+/// later process.
+///
+/// The cache does not deduplicate queries across tasks: two tasks that each fetch the same
+/// missing contract with their own cache send two queries. To share one cache between tasks, put
+/// it behind a [`tokio::sync::Mutex`] that is held across [`ContractCache::get_or_fetch`], so that
+/// a task that asks for a contract that is being fetched waits for it and then finds it cached.
+/// Within a single call to [`ContractCache::get_or_fetch_many`], identical queries are only sent
+/// once.
+pub struct ContractCache {
+    ttl: Option<chrono::Duration>,
+    entries: std::collections::HashMap<Query, CacheEntry>,
+}
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        s.parse().map(Self).map_err(ParseContractIdError)
-    }
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Serialize, Deserialize))]
+struct CacheEntry {
+    contract: Contract,
+    fetched_at: chrono::DateTime<chrono::Utc>,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
-/// Identifiers used by the broader industry / regulators to define a specific contract / asset.
-pub enum SecurityId {
-    /// For details, see:
-    /// [CUSIP Description](https://www.cusip.com/identifiers.html?section=CUSIP).
-    Cusip(String),
-    /// For details, see:
-    /// [SEDOL Description](https://www.lseg.com/en/data-indices-analytics/data/sedol).
-    Sedol(String),
-    /// For details, see:
-    /// [ISIN Description](https://www.cusip.com/identifiers.html?section=ISIN#/ISIN).
-    Isin(String),
-    /// For details, see:
-    /// [RIC Description](https://en.wikipedia.org/wiki/Refinitiv_Identification_Code).
-    Ric(String),
+#[cfg(feature = "serde-full")]
+#[derive(Serialize, Deserialize)]
+// TOML documents must be tables, and their keys must be strings, so the entries are stored as an
+// array of tables instead of as a map
+struct SerCacheHelp {
+    entries: Vec<(Query, CacheEntry)>,
 }
 
-// =================================
-// === Valid Trait Definition ===
-// =================================
+impl ContractCache {
+    #[must_use]
+    #[inline]
+    /// Create an empty cache whose entries never expire.
+    pub fn new() -> Self {
+        Self::default()
+    }
 
-mod indicators {
-    use std::convert::Infallible;
+    #[must_use]
+    #[inline]
+    /// Create an empty cache whose entries expire `ttl` after they are fetched.
+    pub fn with_ttl(ttl: chrono::Duration) -> Self {
+        Self {
+            ttl: Some(ttl),
+            entries: std::collections::HashMap::new(),
+        }
+    }
 
-    use chrono::NaiveDate;
-    use serde::{Serialize, Serializer};
+    #[inline]
+    fn is_fresh(&self, entry: &CacheEntry, now: chrono::DateTime<chrono::Utc>) -> bool {
+        self.ttl.is_none_or(|ttl| now - entry.fetched_at < ttl)
+    }
 
-    use crate::currency::Currency;
+    #[must_use]
+    /// Get the cached contract for `query` without querying IBKR.
+    ///
+    /// # Returns
+    /// The contract, or `None` if it is not cached or its entry has expired.
+    pub fn get(&self, query: &Query) -> Option<&Contract> {
+        let now = chrono::Utc::now();
+        self.entries
+            .get(query)
+            .filter(|entry| self.is_fresh(entry, now))
+            .map(|entry| &entry.contract)
+    }
+
+    /// Cache `contract` as the result of `query`, replacing any previous entry.
+    pub fn insert(&mut self, query: Query, contract: Contract) {
+        self.entries.insert(
+            query,
+            CacheEntry {
+                contract,
+                fetched_at: chrono::Utc::now(),
+            },
+        );
+    }
+
+    /// Get the cached contract for `query`, or resolve it with [`new`] and cache it if it is not
+    /// cached or has expired.
+    ///
+    /// # Arguments
+    /// * `client` - The client with which to send the contract query on a cache miss.
+    /// * `query` - The query that uniquely identifies the contract in the IBKR system.
+    ///
+    /// # Errors
+    /// Returns the same errors as [`new`] on a cache miss. Failed queries are not cached.
+    ///
+    /// # Returns
+    /// The resolved contract.
+    pub async fn get_or_fetch(
+        &mut self,
+        client: &mut impl ClientPort,
+        query: Query,
+    ) -> Result<Contract, NewSecurityError> {
+        if let Some(contract) = self.get(&query) {
+            return Ok(contract.clone());
+        }
+        let contract: Contract = new(client, query.clone()).await?;
+        self.insert(query, contract.clone());
+        Ok(contract)
+    }
+
+    /// Get the cached contract for each query like [`ContractCache::get_or_fetch`], resolving the
+    /// misses together with [`new_many`].
+    ///
+    /// A query that appears several times in `queries` is only sent to IBKR once, and each of its
+    /// appearances gets the same result. A contract that is cached when the call starts is
+    /// returned even if its entry expires before the misses are resolved.
+    ///
+    /// # Arguments
+    /// * `client` - The client with which to send the contract queries on a cache miss.
+    /// * `queries` - The queries that each uniquely identify a contract in the IBKR system.
+    ///
+    /// # Returns
+    /// The result of each query, in the same order as `queries`. Each result has the same errors
+    /// as [`new`].
+    pub async fn get_or_fetch_many(
+        &mut self,
+        client: &mut impl ClientPort,
+        queries: Vec<Query>,
+    ) -> Vec<Result<Contract, NewSecurityError>> {
+        let mut hits = std::collections::HashMap::new();
+        let mut misses = Vec::new();
+        let mut in_flight = std::collections::HashMap::new();
+        for query in &queries {
+            if let Some(contract) = self.get(query) {
+                hits.insert(query.clone(), contract.clone());
+            } else if !in_flight.contains_key(query) {
+                in_flight.insert(query.clone(), misses.len());
+                misses.push(query.clone());
+            }
+        }
+
+        let fetched = new_many::<Contract>(client, misses.clone()).await;
+        for (query, result) in misses.into_iter().zip(&fetched) {
+            if let Ok(contract) = result {
+                self.insert(query, contract.clone());
+            }
+        }
+
+        queries
+            .into_iter()
+            .map(|query| match in_flight.get(&query) {
+                Some(&i) => match &fetched[i] {
+                    Ok(contract) => Ok(contract.clone()),
+                    Err(e) => Err(e.duplicate()),
+                },
+                None => hits.get(&query).cloned().ok_or(NewSecurityError::BadResponse),
+            })
+            .collect()
+    }
+
+    /// Remove every entry that has expired.
+    pub fn evict_expired(&mut self) {
+        let now = chrono::Utc::now();
+        let ttl = self.ttl;
+        self.entries
+            .retain(|_, entry| ttl.is_none_or(|ttl| now - entry.fetched_at < ttl));
+    }
+
+    /// Remove every entry.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    #[must_use]
+    #[inline]
+    /// Get the number of entries in the cache, including any that have expired but have not been
+    /// evicted.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    #[must_use]
+    #[inline]
+    /// Check whether the cache has no entries.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    #[cfg(feature = "serde-full")]
+    /// Write every entry that has not expired to `writer` as TOML, so that a later process can
+    /// warm start from it with [`ContractCache::load_from`].
+    ///
+    /// # Errors
+    /// Returns any error encountered while serializing the entries or while writing them.
+    pub fn serialize_to(&self, mut writer: impl std::io::Write) -> Result<(), ContractCacheError> {
+        let now = chrono::Utc::now();
+        let entries = self
+            .entries
+            .iter()
+            .filter(|(_, entry)| self.is_fresh(entry, now))
+            .map(|(query, entry)| (query.clone(), entry.clone()))
+            .collect();
+        writer.write_all(toml::to_string(&SerCacheHelp { entries })?.as_bytes())?;
+        Ok(())
+    }
+
+    #[cfg(feature = "serde-full")]
+    /// Read the entries written by [`ContractCache::serialize_to`] from `reader` into the cache.
+    /// Each entry keeps the time at which it was originally fetched, so entries that have expired
+    /// since are dropped.
+    ///
+    /// # Errors
+    /// Returns any error encountered while reading the entries or while deserializing them.
+    pub fn load_from(&mut self, mut reader: impl std::io::Read) -> Result<(), ContractCacheError> {
+        let mut s = String::new();
+        reader.read_to_string(&mut s)?;
+        let SerCacheHelp { entries } = toml::from_str(&s)?;
+        self.entries.extend(entries);
+        self.evict_expired();
+        Ok(())
+    }
+}
+
+#[cfg(feature = "serde-full")]
+#[derive(Debug, Error)]
+/// An error returned when saving or loading a [`ContractCache`] fails.
+pub enum ContractCacheError {
+    #[error("Failed to read or write the contract cache. Cause: {0}")]
+    /// An error reading or writing the cache
+    Io(#[from] std::io::Error),
+    #[error("Failed to serialize the contract cache. Cause: {0}")]
+    /// An error serializing the cache
+    Serialize(#[from] toml::ser::Error),
+    #[error("Failed to deserialize the contract cache. Cause: {0}")]
+    /// An error deserializing the cache
+    Deserialize(#[from] toml::de::Error),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// A contract that matches the pattern of a [`search_symbols`] call.
+pub struct SymbolMatch {
+    /// The matching contract, which is only partially populated.
+    pub contract: Proxy<Contract, NoExchange>,
+    /// The types of derivatives, like options and futures, that have the contract as their
+    /// underlying. Derivative types that this crate does not support are left out.
+    pub derivative_types: Vec<ContractType>,
+    /// A description of the contract, like its company name.
+    pub description: String,
+    /// The primary exchange on which the contract is listed, if it is one that this crate
+    /// supports. Unlike [`Proxy::primary_exchange`], this is available for every type of contract.
+    pub primary_exchange: Option<Primary>,
+}
+
+impl SymbolMatch {
+    #[inline]
+    #[must_use]
+    /// Get the matching contract's ID, with which the full contract can be created by [`new`].
+    pub fn contract_id(&self) -> ContractId {
+        self.contract.contract_id()
+    }
+
+    #[inline]
+    #[must_use]
+    /// Get the matching contract's symbol.
+    pub fn symbol(&self) -> &str {
+        self.contract.symbol()
+    }
+
+    #[inline]
+    #[must_use]
+    /// Get the matching contract's security type.
+    pub fn security_type(&self) -> ContractType {
+        self.contract.contract_type()
+    }
+
+    #[inline]
+    #[must_use]
+    /// Get the matching contract's currency.
+    pub fn currency(&self) -> Currency {
+        self.contract.currency()
+    }
+}
+
+#[derive(Debug, Error)]
+/// An error type that is returned if a [`search_symbols`] call fails
+pub enum SearchSymbolsError {
+    /// Failed to send the search to the IBKR API
+    #[error("Failed to send symbol search to IBKR API. Cause {0}")]
+    Io(#[from] std::io::Error),
+    /// Failed to receive a valid response from the IBKR API
+    #[error("No valid symbol matches received from the IBKR API.")]
+    BadResponse,
+    /// The connection to the IBKR API was lost before a response was received
+    #[error("The connection to the IBKR API was lost before a response was received.")]
+    Disconnected,
+    /// The previous search was too recent. Retry after the given duration
+    #[error("Symbol searches are limited to one per second. Retry in {0:?}.")]
+    Pacing(std::time::Duration),
+    /// The IBKR API did not answer the search within the client's request timeout
+    #[error(transparent)]
+    Timeout(#[from] RequestTimeout),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// The kinds of requests that can time out while waiting for a response.
+pub enum RequestKind {
+    /// A contract details request, like the one sent by [`new`] or [`search`].
+    ContractDetails,
+    /// A free-text symbol search, like the one sent by [`search_symbols`].
+    MatchingSymbols,
+    /// A request for the earliest available data, like the one sent by
+    /// [`crate::client::Client::head_timestamp`].
+    HeadTimestamp,
+    /// A request for a histogram, like the one sent by [`crate::client::Client::histogram`].
+    Histogram,
+}
+
+impl std::fmt::Display for RequestKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Self::ContractDetails => "contract details",
+            Self::MatchingSymbols => "matching symbols",
+            Self::HeadTimestamp => "head timestamp",
+            Self::Histogram => "histogram",
+        };
+        write!(f, "{s}")
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Error)]
+#[error("The IBKR API did not answer {kind} request {req_id} within the request timeout.")]
+/// An error returned when the IBKR API does not answer a request within the timeout of the client
+/// that sent it, like [`crate::client::Client::set_request_timeout`].
+///
+/// A response that arrives after the timeout is discarded, so the request does not need to be
+/// cancelled.
+pub struct RequestTimeout {
+    /// The ID of the request that timed out.
+    pub req_id: i64,
+    /// The kind of request that timed out.
+    pub kind: RequestKind,
+}
+
+#[inline]
+/// Wait for the response to a request for at most `timeout`, if any.
+pub(crate) async fn with_timeout<T>(
+    timeout: Option<std::time::Duration>,
+    req_id: i64,
+    kind: RequestKind,
+    response: impl std::future::Future<Output = T>,
+) -> Result<T, RequestTimeout> {
+    match timeout {
+        Some(timeout) => tokio::time::timeout(timeout, response)
+            .await
+            .map_err(|_| RequestTimeout { req_id, kind }),
+        None => Ok(response.await),
+    }
+}
+
+/// Forgets the contract queries sent with a port once dropped, whether they ended, timed out, or
+/// were abandoned because the future awaiting them was dropped, so that the port does not keep
+/// them pending. Forgetting a query that already ended does nothing.
+struct QueryGuard<'c, P: ClientPort + ?Sized> {
+    client: &'c mut P,
+    req_ids: Vec<i64>,
+}
+
+impl<P: ClientPort + ?Sized> Drop for QueryGuard<'_, P> {
+    fn drop(&mut self) {
+        for req_id in self.req_ids.drain(..) {
+            self.client.forget_contract_query(req_id);
+        }
+    }
+}
+
+/// Receive the contracts that match the query sent with `req_id` for at most the client's request
+/// timeout, then forget the query, even if the returned future is dropped before it completes.
+pub(crate) async fn await_contract_query<P: ClientPort + ?Sized>(
+    client: &mut P,
+    req_id: i64,
+) -> Result<Vec<Contract>, NewSecurityError> {
+    let guard = QueryGuard {
+        client,
+        req_ids: vec![req_id],
+    };
+    let timeout = guard.client.request_timeout();
+    let recv = guard.client.recv_contract_query(req_id);
+    with_timeout(timeout, req_id, RequestKind::ContractDetails, recv)
+        .await?
+        .ok_or(NewSecurityError::Disconnected)
+}
+
+#[derive(Debug, Error)]
+/// An error type that is returned if creating a [`new`] [`Security`] fails
+pub enum NewSecurityError {
+    /// Failed to send the contract query to the IBKR API
+    #[error("Failed to send contract query to IBKR API. Cause {0}")]
+    Io(#[from] std::io::Error),
+    /// Failed to receive valid response form the IBKR API
+    #[error("No valid contract received from the IBKR API.")]
+    BadResponse,
+    /// The connection to the IBKR API was lost before a response was received
+    #[error("The connection to the IBKR API was lost before a response was received.")]
+    Disconnected,
+    /// Unexpected security type returned from the IBKR API
+    #[error("Invalid contract received from the IBKR API. {0}")]
+    UnexpectedSecurityType(#[from] UnexpectedSecurityType),
+    /// More than one contract matched the query. Use [`search`] to choose between the candidates.
+    #[error("Query matched {} contracts: {}", .0.len(), list_contracts(.0))]
+    Ambiguous(Vec<Contract>),
+    /// The IBKR API did not answer the query within the client's request timeout
+    #[error(transparent)]
+    Timeout(#[from] RequestTimeout),
+    /// The IBKR API rejected the query, like with error 200 when no contract matches it
+    #[error(transparent)]
+    Rejected(#[from] TwsError),
+}
+
+impl NewSecurityError {
+    /// Copy the error for each of several identical queries that share a response, like in
+    /// [`ContractCache::get_or_fetch_many`]. An I/O error keeps its kind and message, but not its
+    /// source.
+    fn duplicate(&self) -> Self {
+        match self {
+            Self::Io(e) => Self::Io(std::io::Error::new(e.kind(), e.to_string())),
+            Self::BadResponse => Self::BadResponse,
+            Self::Disconnected => Self::Disconnected,
+            Self::UnexpectedSecurityType(e) => Self::UnexpectedSecurityType(*e),
+            Self::Ambiguous(contracts) => Self::Ambiguous(contracts.clone()),
+            Self::Timeout(e) => Self::Timeout(*e),
+            Self::Rejected(e) => Self::Rejected(e.clone()),
+        }
+    }
+}
+
+fn list_contracts(contracts: &[Contract]) -> String {
+    contracts
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+/// The expirations and strikes of the options on an underlying [`Security`], as returned by
+/// [`crate::client::Client::option_chain`] and [`crate::client::Client::option_chains`].
+///
+/// Each expiration / strike pair is not necessarily a valid contract, but any [`SecOption`] that
+/// does exist can be created with [`new`] by way of a [`ContractSpec`].
+pub struct OptionChain {
+    /// The exchange on which the options trade.
+    pub exchange: Routing,
+    /// The contract ID of the underlying security.
+    pub underlying_contract_id: ContractId,
+    /// The trading classes of the options, like "SPX" and "SPXW".
+    pub trading_classes: Vec<String>,
+    /// The options' multiplier, which can be fractional.
+    pub multiplier: f64,
+    /// The available expiration dates, in ascending order.
+    pub expirations: Vec<NaiveDate>,
+    /// The available strikes, in ascending order.
+    pub strikes: Vec<f64>,
+}
+
+impl OptionChain {
+    #[must_use]
+    /// Return the strike that is nearest to `spot`, which is the at-the-money strike when `spot` is
+    /// the price of the underlying security. If two strikes are equally near, the lower one is
+    /// returned.
+    ///
+    /// # Returns
+    /// The nearest strike, or [`None`] if the chain has no strikes or `spot` is not a finite
+    /// number.
+    pub fn nearest_strike(&self, spot: f64) -> Option<f64> {
+        if !spot.is_finite() {
+            return None;
+        }
+        self.strikes.iter().copied().min_by(|a, b| {
+            (a - spot)
+                .abs()
+                .total_cmp(&(b - spot).abs())
+                .then(a.total_cmp(b))
+        })
+    }
+
+    #[must_use]
+    /// Return the expiration date that is nearest to `target`. If two expirations are equally near,
+    /// the earlier one is returned.
+    ///
+    /// # Returns
+    /// The nearest expiration, or [`None`] if the chain has no expirations.
+    pub fn nearest_expiration(&self, target: NaiveDate) -> Option<NaiveDate> {
+        self.expirations
+            .iter()
+            .copied()
+            .min_by_key(|&d| ((d - target).num_days().abs(), d))
+    }
+
+    /// Merge the per-exchange chains returned by IBKR into a single chain.
+    ///
+    /// If any chain is routed through [`Routing::Smart`], only the SMART chains are used, since
+    /// those already cover every exchange. Otherwise, the expirations and strikes of every exchange
+    /// are combined. Chains with a different multiplier than the first one (like mini options) are
+    /// left out.
+    pub(crate) fn merge(mut chains: Vec<Self>) -> Option<Self> {
+        if chains.iter().any(|c| c.exchange == Routing::Smart) {
+            chains.retain(|c| c.exchange == Routing::Smart);
+        }
+        let mut chains = chains.into_iter();
+        let mut merged = chains.next()?;
+        for chain in chains.filter(|c| c.multiplier == merged.multiplier) {
+            if chain.exchange != merged.exchange {
+                merged.exchange = Routing::Smart;
+            }
+            merged.trading_classes.extend(chain.trading_classes);
+            merged.expirations.extend(chain.expirations);
+            merged.strikes.extend(chain.strikes);
+        }
+        merged.trading_classes.sort();
+        merged.trading_classes.dedup();
+        merged.expirations.sort_unstable();
+        merged.expirations.dedup();
+        merged.strikes.sort_by(f64::total_cmp);
+        merged.strikes.dedup();
+        Some(merged)
+    }
+}
+
+#[derive(Debug, Error)]
+/// An error type that is returned if looking up an [`OptionChain`] fails
+pub enum OptionChainError {
+    /// Failed to send the option chain request to the IBKR API
+    #[error("Failed to send option chain request to IBKR API. Cause {0}")]
+    Io(#[from] std::io::Error),
+    /// Failed to receive a valid response from the IBKR API
+    #[error("No valid option chain received from the IBKR API.")]
+    BadResponse,
+    /// The connection to the IBKR API was lost before a response was received
+    #[error("The connection to the IBKR API was lost before a response was received.")]
+    Disconnected,
+    /// The IBKR API did not return any options for the underlying security
+    #[error("No options were found for the underlying security.")]
+    NoOptions,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+/// An identifier of a [`MarketRule`], which IBKR reports for each exchange on which a contract
+/// trades.
+pub struct MarketRuleId(pub i64);
+
+impl FromStr for MarketRuleId {
+    type Err = ParseIntError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self(s.parse()?))
+    }
+}
+
+impl std::fmt::Display for MarketRuleId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+/// The tick size that applies to prices at or above a given level.
+pub struct PriceIncrement {
+    /// The lowest price to which the increment applies.
+    pub low_edge: f64,
+    /// The minimum price increment.
+    pub increment: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+/// A table of price-dependent tick sizes, as returned by
+/// [`crate::client::Client::request_market_rule`].
+///
+/// Many contracts, like stocks priced under $1 and bonds, have a tick size that depends on their
+/// price, which a single [`Security::min_tick`] cannot describe.
+pub struct MarketRule {
+    /// The rule's identifier.
+    pub id: MarketRuleId,
+    /// The price increments, in ascending order of their low edges.
+    pub increments: Vec<PriceIncrement>,
+}
+
+impl MarketRule {
+    #[must_use]
+    /// Get the tick size that applies at `price`.
+    ///
+    /// # Arguments
+    /// * `price` - The price whose tick size to look up.
+    ///
+    /// # Returns
+    /// The increment of the highest low edge at or below `price`, the first increment if `price`
+    /// is below every low edge, or 0.0 if the rule has no increments.
+    pub fn tick_for_price(&self, price: f64) -> f64 {
+        self.increments
+            .iter()
+            .take_while(|i| i.low_edge <= price)
+            .last()
+            .or_else(|| self.increments.first())
+            .map_or(0.0, |i| i.increment)
+    }
+
+    #[must_use]
+    /// Round `price` to the nearest multiple of the tick size that applies at it.
+    ///
+    /// # Arguments
+    /// * `price` - The price to round, like the limit price of an order.
+    ///
+    /// # Returns
+    /// The rounded price, or `price` itself if the rule has no positive tick size at it.
+    pub fn round_to_tick(&self, price: f64) -> f64 {
+        snap_to_tick(price, self.tick_for_price(price), f64::round)
+    }
+
+    #[must_use]
+    /// Round `price` down to a multiple of the tick size that applies at it, like
+    /// [`Security::round_price_down`] does without a market rule.
+    ///
+    /// # Arguments
+    /// * `price` - The price to round, like the limit price of a buy order.
+    ///
+    /// # Returns
+    /// The rounded price, or `price` itself if the rule has no positive tick size at it.
+    pub fn round_to_tick_down(&self, price: f64) -> f64 {
+        snap_to_tick(price, self.tick_for_price(price), f64::floor)
+    }
+
+    #[must_use]
+    /// Round `price` up to a multiple of the tick size that applies at it, like
+    /// [`Security::round_price_up`] does without a market rule.
+    ///
+    /// # Arguments
+    /// * `price` - The price to round, like the limit price of a sell order.
+    ///
+    /// # Returns
+    /// The rounded price, or `price` itself if the rule has no positive tick size at it.
+    pub fn round_to_tick_up(&self, price: f64) -> f64 {
+        snap_to_tick(price, self.tick_for_price(price), f64::ceil)
+    }
+}
+
+#[derive(Debug, Error)]
+/// An error type that is returned if requesting a [`MarketRule`] fails
+pub enum MarketRuleError {
+    /// Failed to send the market rule request to the IBKR API
+    #[error("Failed to send market rule request to IBKR API. Cause {0}")]
+    Io(#[from] std::io::Error),
+    /// Failed to receive a valid response from the IBKR API
+    #[error("No valid market rule received from the IBKR API.")]
+    BadResponse,
+    /// The connection to the IBKR API was lost before a response was received
+    #[error("The connection to the IBKR API was lost before a response was received.")]
+    Disconnected,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Error)]
+#[error("Unexpected security type. Expected {expected:?}; found {found:?}.")]
+/// An error type that's returned when a [`Security`] of type `S` is requested, but a security of
+/// another type is received from the API
+pub struct UnexpectedSecurityType {
+    /// The expected contract type
+    expected: ContractType,
+    /// The contract type that was actually found
+    found: ContractType,
+}
+
+impl UnexpectedSecurityType {
+    #[must_use]
+    #[inline]
+    /// Get the contract type that was expected.
+    pub const fn expected(&self) -> ContractType {
+        self.expected
+    }
+
+    #[must_use]
+    #[inline]
+    /// Get the contract type that was actually found.
+    pub const fn found(&self) -> ContractType {
+        self.found
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+/// A type used to represent a query for a new contract, which can be made by providing an IBKR
+/// contract ID, a FIGI, or an industry [`SecurityId`].
+///
+/// When parsing from a string, the routing can be given with an `@` suffix, like
+/// `"265598@ISLAND"` or `"BBG000B9XRY4@NASDAQ"`.
+///
+/// A query is (de)serialized with its variant as a tag, like `{ Figi = ["BBG000B9XRY4", "SMART"] }`
+/// in TOML, so that a list of queries can be saved and loaded again unambiguously.
+pub enum Query {
+    /// An IBKR contract ID with which to make a query. When parsing from a string, the routing field
+    /// defaults to [`Routing::Smart`].
+    IbContractId(ContractId, Routing),
+    /// A FIGI with which to make a query. When parsing from a string, the routing field defaults
+    /// to [`Routing::Smart`].
+    Figi(Figi, Routing),
+    /// A CUSIP, SEDOL, ISIN, or RIC with which to make a query. When parsing from a string, only
+    /// ISINs and CUSIPs are recognized and the routing field defaults to [`Routing::Smart`].
+    SecurityId(SecurityId, Routing),
+    /// A [`ContinuousFuture`], identified by its root symbol and the exchange on which it trades.
+    /// This variant is never produced when parsing from a string.
+    ContinuousFuture(String, Routing),
+    /// A partially-specified contract, which may match several contracts. This variant is never
+    /// produced when parsing from a string.
+    Pattern(ContractSpec),
+}
+
+impl Query {
+    #[must_use]
+    /// Create a query for the [`Forex`] contract that trades `base` against `quote` on IDEALPRO.
+    ///
+    /// IBKR only lists each currency pair once, following the market convention for which
+    /// currency is quoted in terms of the other, like EUR.USD and USD.JPY. If `base` and `quote`
+    /// are inverted relative to that convention, like USD and EUR, the query is for the listed
+    /// pair instead, so the resulting [`Forex::pair`] may be `(quote, base)`.
+    ///
+    /// # Arguments
+    /// * `base` - The currency being bought or sold.
+    /// * `quote` - The currency in which the price of `base` is given.
+    ///
+    /// # Returns
+    /// A [`Query::Pattern`] for the currency pair on IDEALPRO.
+    pub fn forex_pair(base: Currency, quote: Currency) -> Self {
+        let (base, quote) = if forex_rank(quote) < forex_rank(base) {
+            (quote, base)
+        } else {
+            (base, quote)
+        };
+        Self::Pattern(ContractSpec::new(
+            base.to_string(),
+            ContractType::Forex,
+            Routing::Primary(Primary::IbForexPro),
+            quote,
+        ))
+    }
+
+    /// Create a query for the US equity option identified by an OSI symbol, like
+    /// "AAPL  250117C00190000". See [`SecOption::parse_osi`] for the accepted formats.
+    ///
+    /// IBKR uses the padded OSI symbol as the local symbol of a US equity option, so the query
+    /// matches on it.
+    ///
+    /// # Arguments
+    /// * `osi` - The OSI symbol of the option.
+    /// * `routing` - The exchange to which the query is routed.
+    ///
+    /// # Errors
+    /// Returns an error if `osi` is not a well-formed OSI symbol.
+    ///
+    /// # Returns
+    /// A [`Query::Pattern`] for the option.
+    pub fn from_osi(osi: &str, routing: Routing) -> Result<Self, InvalidOsi> {
+        let parts = SecOption::parse_osi(osi)?;
+        Ok(Self::Pattern(
+            ContractSpec::new(
+                parts.symbol.as_str(),
+                ContractType::SecOption,
+                routing,
+                Currency::UsDollar,
+            )
+            .expiration_date(parts.expiration_date)
+            .local_symbol(parts.to_string()),
+        ))
+    }
+}
+
+// The priority of each currency as the base of a pair in IBKR's listings. When two currencies
+// are paired, the one with the lower rank is the base, so EUR.USD and USD.JPY are listed but
+// USD.EUR and JPY.USD are not.
+const fn forex_rank(currency: Currency) -> u8 {
+    match currency {
+        Currency::Euro => 0,
+        Currency::BritishPound => 1,
+        Currency::AustralianDollar => 2,
+        Currency::NewZealandDollar => 3,
+        Currency::UsDollar => 4,
+        Currency::CanadianDollar => 5,
+        Currency::SwissFranc => 6,
+        Currency::DanishKrone => 7,
+        Currency::NorwegianKrone => 8,
+        Currency::SwedishKrona => 9,
+        Currency::ChineseYuan => 10,
+        Currency::HongKongDollar => 11,
+        Currency::MexicanPeso => 12,
+        Currency::IsraeliNewShekel => 13,
+        Currency::IndianRupee => 14,
+        Currency::KoreanWon => 15,
+        Currency::JapaneseYen => 16,
+    }
+}
+
+impl From<ContractId> for Query {
+    fn from(value: ContractId) -> Self {
+        Self::IbContractId(value, Routing::Smart)
+    }
+}
+
+impl From<Figi> for Query {
+    fn from(value: Figi) -> Self {
+        Self::Figi(value, Routing::Smart)
+    }
+}
+
+impl From<ContractSpec> for Query {
+    fn from(value: ContractSpec) -> Self {
+        Self::Pattern(value)
+    }
+}
+
+impl From<SecurityId> for Query {
+    fn from(value: SecurityId) -> Self {
+        Self::SecurityId(value, Routing::Smart)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+/// Options that control how a [`Query`] is resolved by the IBKR API. The default options match the
+/// IBKR defaults.
+pub struct QueryOptions {
+    /// Whether expired contracts can be returned, which is useful for backtests. IBKR only
+    /// supports this for contracts that expire, like futures and options.
+    pub include_expired: bool,
+}
+
+impl QueryOptions {
+    /// The options with which [`new`] and [`search`] resolve a query for an `S`.
+    ///
+    /// Expired contracts are included for futures, options, and futures options, so that a
+    /// contract that expired recently can still be resolved. The contract type is taken from `S`
+    /// or, if `S` is a [`Contract`], from a [`Query::Pattern`]. A [`Query::IbContractId`] for a
+    /// [`Contract`] includes them as well, since its contract may be any of these.
+    pub(crate) fn for_query<S: Security>(query: &Query) -> Self {
+        let contract_type = S::CONTRACT_TYPE.or(match query {
+            Query::Pattern(spec) => Some(spec.contract_type),
+            _ => None,
+        });
+        Self {
+            include_expired: match contract_type {
+                Some(contract_type) => matches!(
+                    contract_type,
+                    ContractType::SecFuture | ContractType::SecOption | ContractType::SecFutureOption
+                ),
+                None => matches!(query, Query::IbContractId(..)),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+/// A partially-specified contract, like "AAPL stock on NASDAQ in USD", with which to make a
+/// [`Query::Pattern`].
+pub struct ContractSpec {
+    symbol: String,
+    contract_type: ContractType,
+    exchange: Routing,
+    currency: Currency,
+    trading_class: Option<String>,
+    expiration_date: Option<NaiveDate>,
+    local_symbol: Option<String>,
+}
+
+impl ContractSpec {
+    #[must_use]
+    #[inline]
+    /// Creates a new [`ContractSpec`].
+    ///
+    /// # Arguments
+    /// * `symbol` - The contract's symbol, like "AAPL".
+    /// * `contract_type` - The type of the contract.
+    /// * `exchange` - The exchange on which the contract trades.
+    /// * `currency` - The contract's currency.
+    pub fn new(
+        symbol: impl Into<String>,
+        contract_type: ContractType,
+        exchange: Routing,
+        currency: Currency,
+    ) -> Self {
+        Self {
+            symbol: symbol.into(),
+            contract_type,
+            exchange,
+            currency,
+            trading_class: None,
+            expiration_date: None,
+            local_symbol: None,
+        }
+    }
+
+    #[must_use]
+    #[inline]
+    /// Only match contracts with the provided trading class.
+    pub fn trading_class(mut self, trading_class: impl Into<String>) -> Self {
+        self.trading_class = Some(trading_class.into());
+        self
+    }
+
+    #[must_use]
+    #[inline]
+    /// Only match contracts that expire on the provided date.
+    pub fn expiration_date(mut self, expiration_date: NaiveDate) -> Self {
+        self.expiration_date = Some(expiration_date);
+        self
+    }
+
+    #[must_use]
+    #[inline]
+    /// Only match contracts with the provided local symbol, like "AAPL  250117C00190000".
+    pub fn local_symbol(mut self, local_symbol: impl Into<String>) -> Self {
+        self.local_symbol = Some(local_symbol.into());
+        self
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// The contract fields of a contract details request, in the order expected by the IBKR API.
+pub(crate) struct QueryOutMsg<'q> {
+    pub(crate) query: &'q Query,
+    pub(crate) options: QueryOptions,
+}
+
+impl Serialize for QueryOutMsg<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let include_expired = self.options.include_expired;
+        match self.query {
+            Query::IbContractId(contract_id, routing) => (
+                contract_id,
+                [None::<()>; 6],
+                routing,
+                [None::<()>; 4],
+                include_expired,
+                [None::<()>; 3],
+            )
+                .serialize(serializer),
+            Query::Figi(figi, routing) => (
+                [None::<()>; 7],
+                routing,
+                [None::<()>; 4],
+                include_expired,
+                "FIGI",
+                figi,
+                None::<()>,
+            )
+                .serialize(serializer),
+            Query::SecurityId(security_id, routing) => (
+                [None::<()>; 7],
+                routing,
+                [None::<()>; 4],
+                include_expired,
+                security_id.type_code(),
+                security_id.value(),
+                None::<()>,
+            )
+                .serialize(serializer),
+            Query::ContinuousFuture(symbol, routing) => (
+                None::<()>,
+                symbol,
+                "CONTFUT",
+                [None::<()>; 4],
+                routing,
+                [None::<()>; 4],
+                include_expired,
+                [None::<()>; 3],
+            )
+                .serialize(serializer),
+            Query::Pattern(spec) => (
+                None::<()>,
+                spec.symbol.as_str(),
+                spec.contract_type,
+                spec.expiration_date.map(|d| d.format("%Y%m%d").to_string()),
+                [None::<()>; 3],
+                spec.exchange,
+                None::<()>,
+                spec.currency,
+                spec.local_symbol.as_deref(),
+                spec.trading_class.as_deref(),
+                include_expired,
+                [None::<()>; 3],
+            )
+                .serialize(serializer),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Error)]
+/// An error type representing the potential ways that a [`Query`] can be invalid.
+pub enum ParseQueryError {
+    #[error("Invalid value when parsing IBKR contract ID. Cause: {0}")]
+    /// An invalid [`Query::IbContractId`]
+    IbContractId(ParseContractIdError),
+    #[error("Invalid value when parsing FIGI. Cause: {0}")]
+    /// AN invalid [`Query::Figi`]
+    Figi(InvalidFigi),
+    #[error("Invalid value when parsing ISIN. Cause: {0}")]
+    /// An invalid ISIN for a [`Query::SecurityId`]
+    Isin(InvalidIsin),
+    #[error("Invalid value when parsing CUSIP. Cause: {0}")]
+    /// An invalid CUSIP for a [`Query::SecurityId`]
+    Cusip(InvalidCusip),
+    #[error("Invalid value when parsing the routing of a query. Cause: {0}")]
+    /// An invalid routing after the `@` of a query
+    Routing(ParseExchangeError),
+    #[error("Cannot construct query from empty string.")]
+    /// Invalid in a way such that it's impossible to tell whether it was intended to be an [`Query::IbContractId`] or a [`Query::Figi`].
+    Empty,
+}
+
+impl FromStr for Query {
+    type Err = ParseQueryError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (s, routing) = match s.split_once('@') {
+            Some((s, routing)) => (s, routing.parse().map_err(ParseQueryError::Routing)?),
+            None => (s, Routing::Smart),
+        };
+        let first = s.chars().nth(0).ok_or(ParseQueryError::Empty)?;
+        // IBKR contract IDs are always numeric, so purely numeric CUSIPs are parsed as contract IDs.
+        // A FIGI and an ISIN always begin with a letter.
+        if s.chars().all(|c| c.is_ascii_digit()) {
+            Ok(Self::IbContractId(
+                s.parse().map_err(ParseQueryError::IbContractId)?,
+                routing,
+            ))
+        } else if s.len() == 9 {
+            Ok(Self::SecurityId(
+                SecurityId::cusip(s).map_err(ParseQueryError::Cusip)?,
+                routing,
+            ))
+        } else if first.is_numeric() {
+            Ok(Self::IbContractId(
+                s.parse().map_err(ParseQueryError::IbContractId)?,
+                routing,
+            ))
+        } else {
+            // FIGIs never start with a vowel and always have G as their third character, so a valid
+            // FIGI can't be mistaken for a typical ISIN. Try the FIGI first to keep its precedence.
+            match s.parse() {
+                Ok(figi) => Ok(Self::Figi(figi, routing)),
+                Err(figi_err) => match SecurityId::isin(s) {
+                    Ok(isin) => Ok(Self::SecurityId(isin, routing)),
+                    Err(_) if s.chars().nth(2) == Some('G') => Err(ParseQueryError::Figi(figi_err)),
+                    Err(isin_err) => Err(ParseQueryError::Isin(isin_err)),
+                },
+            }
+        }
+    }
+}
+
+#[allow(clippy::module_name_repetitions)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, PartialOrd, Ord, Hash, Serialize)]
+/// A unique identifier used by both IBKR's trading systems and the API to define a specific
+/// contract.
+///
+/// Contract IDs are always positive. The only exceptions are a partially populated contract, like
+/// one built by a [`StockBuilder`], whose contract ID is left as 0 until IBKR resolves it, and a
+/// [`Combo`], which IBKR identifies by its legs instead.
+pub struct ContractId(pub(crate) i64);
+
+impl ContractId {
+    #[inline]
+    /// Create a new contract ID.
+    ///
+    /// # Errors
+    /// Returns an error if `contract_id` is not positive, since IBKR would reject it anyway.
+    pub const fn new(contract_id: i64) -> Result<Self, InvalidContractId> {
+        if contract_id > 0 {
+            Ok(Self(contract_id))
+        } else {
+            Err(InvalidContractId(contract_id))
+        }
+    }
+
+    #[must_use]
+    #[inline]
+    /// Get the contract ID's numeric value.
+    pub const fn get(self) -> i64 {
+        self.0
+    }
+}
+
+impl TryFrom<i64> for ContractId {
+    type Error = InvalidContractId;
+
+    #[inline]
+    fn try_from(value: i64) -> Result<Self, Self::Error> {
+        Self::new(value)
+    }
+}
+
+impl<'de> Deserialize<'de> for ContractId {
+    /// Deserialize a positive contract ID, or the 0 of a partially populated contract or a
+    /// [`Combo`], so that those round-trip as well.
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        match i64::deserialize(deserializer)? {
+            0 => Ok(Self(0)),
+            contract_id => Self::new(contract_id).map_err(serde::de::Error::custom),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Error)]
+#[error("Invalid contract ID {0}. Contract IDs must be positive.")]
+/// An error returned when creating a [`ContractId`] from a value that is not positive.
+pub struct InvalidContractId(pub i64);
+
+#[derive(Debug, Clone, Error)]
+/// An error returned when parsing a [`ContractId`] fails.
+pub enum ParseContractIdError {
+    #[error("Invalid value encountered when attempting to parse contract ID. Cause: {0}")]
+    /// The value is not an integer
+    Int(#[from] ParseIntError),
+    #[error("{0}")]
+    /// The value is an integer, but not a valid contract ID
+    Invalid(#[from] InvalidContractId),
+}
+
+impl FromStr for ContractId {
+    type Err = ParseContractIdError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self::new(s.parse()?)?)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, PartialOrd, Ord, Hash)]
+/// A key with which to look up a security in a map or set, like a
+/// [`HashMap`](std::collections::HashMap) of positions, that compares and hashes only the
+/// security's [`ContractId`].
+///
+/// Two copies of the same contract with different metadata, like one resolved today and one
+/// resolved yesterday, have the same key. A partially populated contract or a [`Combo`], whose
+/// contract ID is 0, has no key, since every such security would otherwise share it.
+pub struct ContractKey(ContractId);
+
+impl ContractKey {
+    #[inline]
+    /// Create a key for `security`.
+    ///
+    /// # Returns
+    /// The key, or [`None`] if the contract ID of `security` is 0.
+    pub fn new(security: &impl Security) -> Option<Self> {
+        ContractId::new(security.contract_id().get()).ok().map(Self)
+    }
+
+    #[must_use]
+    #[inline]
+    /// Get the contract ID of the key.
+    pub const fn contract_id(self) -> ContractId {
+        self.0
+    }
+}
+
+impl TryFrom<&Contract> for ContractKey {
+    type Error = InvalidContractId;
+
+    #[inline]
+    fn try_from(value: &Contract) -> Result<Self, Self::Error> {
+        ContractId::new(value.contract_id().get()).map(Self)
+    }
+}
+
+impl From<ContractKey> for ContractId {
+    #[inline]
+    fn from(value: ContractKey) -> Self {
+        value.0
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "f64", into = "f64")]
+/// An `f64` that is never NaN, with which contracts store their prices and sizes so that they can
+/// implement [`Eq`], [`Ord`], and [`Hash`]. The getters of those fields return a plain `f64`.
+pub(crate) struct NotNan(f64);
+
+impl NotNan {
+    #[inline]
+    /// Wrap `value`, normalizing -0.0 to 0.0 so that equal values hash the same.
+    ///
+    /// # Errors
+    /// Returns an error if `value` is NaN.
+    pub(crate) fn new(value: f64) -> Result<Self, NanError> {
+        if value.is_nan() {
+            Err(NanError)
+        } else {
+            Ok(Self(value + 0.0))
+        }
+    }
+
+    #[must_use]
+    #[inline]
+    /// Get the wrapped value.
+    pub(crate) const fn get(self) -> f64 {
+        self.0
+    }
+}
+
+impl Eq for NotNan {}
+
+impl PartialOrd for NotNan {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for NotNan {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+impl Hash for NotNan {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.0.to_bits().hash(state);
+    }
+}
+
+impl TryFrom<f64> for NotNan {
+    type Error = NanError;
+
+    #[inline]
+    fn try_from(value: f64) -> Result<Self, Self::Error> {
+        Self::new(value)
+    }
+}
+
+impl From<NotNan> for f64 {
+    #[inline]
+    fn from(value: NotNan) -> Self {
+        value.0
+    }
+}
+
+impl FromStr for NotNan {
+    type Err = ParseNotNanError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self::new(s.parse()?)?)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Error)]
+#[error("Invalid value NaN. Contract prices and sizes must be numbers.")]
+/// An error returned when a contract price or size is NaN.
+pub struct NanError;
+
+#[derive(Debug, Clone, Error)]
+/// An error returned when parsing a contract price or size fails.
+pub(crate) enum ParseNotNanError {
+    #[error("Invalid value encountered when attempting to parse float. Cause: {0}")]
+    /// The value is not a float
+    Float(#[from] std::num::ParseFloatError),
+    #[error("{0}")]
+    /// The value is a float, but NaN
+    Nan(#[from] NanError),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+/// Identifiers used by the broader industry / regulators to define a specific contract / asset.
+pub enum SecurityId {
+    /// For details, see:
+    /// [CUSIP Description](https://www.cusip.com/identifiers.html?section=CUSIP).
+    Cusip(String),
+    /// For details, see:
+    /// [SEDOL Description](https://www.lseg.com/en/data-indices-analytics/data/sedol).
+    Sedol(String),
+    /// For details, see:
+    /// [ISIN Description](https://www.cusip.com/identifiers.html?section=ISIN#/ISIN).
+    Isin(String),
+    /// For details, see:
+    /// [RIC Description](https://en.wikipedia.org/wiki/Refinitiv_Identification_Code).
+    Ric(String),
+}
+
+impl SecurityId {
+    /// Construct a new [`SecurityId::Cusip`], validating its characters and check digit. The
+    /// CUSIP is stored in uppercase.
+    ///
+    /// # Errors
+    /// Will error if `cusip` is not a well-formed CUSIP.
+    pub fn cusip(cusip: &str) -> Result<Self, InvalidCusip> {
+        let cusip = cusip.to_ascii_uppercase();
+        let cusip = cusip.as_str();
+        if cusip.len() != 9 {
+            return Err(InvalidCusip::Length(cusip.to_owned()));
+        }
+        let bytes = cusip.as_bytes();
+        let mut sum = 0;
+        for (i, &b) in bytes[..8].iter().enumerate() {
+            let value = match b {
+                b'0'..=b'9' => b - b'0',
+                b'A'..=b'Z' => b - b'A' + 10,
+                b'*' => 36,
+                b'@' => 37,
+                b'#' => 38,
+                _ => return Err(InvalidCusip::Character(cusip.to_owned())),
+            };
+            let value = if i % 2 == 1 { value * 2 } else { value };
+            sum += u32::from(value / 10 + value % 10);
+        }
+        if !bytes[8].is_ascii_digit() || u32::from(bytes[8] - b'0') != (10 - sum % 10) % 10 {
+            return Err(InvalidCusip::Checksum(cusip.to_owned()));
+        }
+        Ok(Self::Cusip(cusip.to_owned()))
+    }
+
+    /// Construct a new [`SecurityId::Isin`], validating its country code and check digit. The ISIN
+    /// is stored in uppercase.
+    ///
+    /// # Errors
+    /// Will error if `isin` is not a well-formed ISIN.
+    pub fn isin(isin: &str) -> Result<Self, InvalidIsin> {
+        let isin = isin.to_ascii_uppercase();
+        let isin = isin.as_str();
+        if isin.len() != 12 {
+            return Err(InvalidIsin::Length(isin.to_owned()));
+        }
+        let bytes = isin.as_bytes();
+        if !bytes[..2].iter().all(u8::is_ascii_uppercase) {
+            return Err(InvalidIsin::CountryCode(isin.to_owned()));
+        }
+        if !bytes[2..11]
+            .iter()
+            .all(|b| b.is_ascii_uppercase() || b.is_ascii_digit())
+        {
+            return Err(InvalidIsin::Alphanumeric(isin.to_owned()));
+        }
+        if !bytes[11].is_ascii_digit() {
+            return Err(InvalidIsin::Checksum(isin.to_owned()));
+        }
+
+        // Letters expand to two digits (A = 10, ..., Z = 35), then the Luhn algorithm applies
+        let mut digits = Vec::with_capacity(24);
+        for &b in bytes {
+            let value = if b.is_ascii_digit() { b - b'0' } else { b - b'A' + 10 };
+            if value >= 10 {
+                digits.push(value / 10);
+            }
+            digits.push(value % 10);
+        }
+        let sum = digits
+            .iter()
+            .rev()
+            .enumerate()
+            .map(|(i, &d)| {
+                let d = if i % 2 == 1 { d * 2 } else { d };
+                u32::from(d / 10 + d % 10)
+            })
+            .sum::<u32>();
+        if sum % 10 != 0 {
+            return Err(InvalidIsin::Checksum(isin.to_owned()));
+        }
+        Ok(Self::Isin(isin.to_owned()))
+    }
+
+    /// Construct a new [`SecurityId::Sedol`], validating its characters and check digit. The
+    /// SEDOL is stored in uppercase.
+    ///
+    /// # Errors
+    /// Will error if `sedol` is not a well-formed SEDOL.
+    pub fn sedol(sedol: &str) -> Result<Self, InvalidSedol> {
+        const WEIGHTS: [u32; 6] = [1, 3, 1, 7, 3, 9];
+
+        let sedol = sedol.to_ascii_uppercase();
+        if sedol.len() != 7 {
+            return Err(InvalidSedol::Length(sedol));
+        }
+        let bytes = sedol.as_bytes();
+        let mut sum = 0;
+        for (&b, weight) in bytes[..6].iter().zip(WEIGHTS) {
+            let value = match b {
+                b'0'..=b'9' => b - b'0',
+                // SEDOLs never contain vowels
+                b'A' | b'E' | b'I' | b'O' | b'U' => {
+                    return Err(InvalidSedol::Character(sedol));
+                }
+                b'B'..=b'Z' => b - b'A' + 10,
+                _ => return Err(InvalidSedol::Character(sedol)),
+            };
+            sum += u32::from(value) * weight;
+        }
+        if !bytes[6].is_ascii_digit() || u32::from(bytes[6] - b'0') != (10 - sum % 10) % 10 {
+            return Err(InvalidSedol::Checksum(sedol));
+        }
+        Ok(Self::Sedol(sedol))
+    }
+
+    #[inline]
+    #[must_use]
+    /// Get the code with which the IBKR API identifies the type of the security ID, which is sent
+    /// as the `secIdType` of a contract.
+    ///
+    /// # Returns
+    /// "CUSIP", "SEDOL", "ISIN", or "RIC".
+    pub const fn type_code(&self) -> &'static str {
+        match self {
+            Self::Cusip(_) => "CUSIP",
+            Self::Sedol(_) => "SEDOL",
+            Self::Isin(_) => "ISIN",
+            Self::Ric(_) => "RIC",
+        }
+    }
+
+    #[inline]
+    #[must_use]
+    /// Get the value of the security ID, which is sent as the `secId` of a contract.
+    ///
+    /// # Returns
+    /// The identifier itself, like "US0378331005" for an ISIN.
+    pub fn value(&self) -> &str {
+        match self {
+            Self::Cusip(s) | Self::Sedol(s) | Self::Isin(s) | Self::Ric(s) => s.as_str(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Error)]
+/// Represents all the possible ways a CUSIP could be invalid.
+pub enum InvalidCusip {
+    /// The provided code is not exactly nine characters.
+    #[error("Invalid length. A CUSIP is exactly 9 characters long. {0}")]
+    Length(String),
+    /// One of the first eight characters is not an uppercase letter, a digit, `*`, `@`, or `#`.
+    #[error("Invalid character for {0}.")]
+    Character(String),
+    /// The check digit is invalid.
+    #[error("Invalid check digit for: {0}")]
+    Checksum(String),
+}
+
+#[derive(Debug, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Error)]
+/// Represents all the possible ways a SEDOL could be invalid.
+pub enum InvalidSedol {
+    /// The provided code is not exactly seven characters.
+    #[error("Invalid length. A SEDOL is exactly 7 characters long. {0}")]
+    Length(String),
+    /// One of the first six characters is not a digit or an uppercase consonant.
+    #[error("Invalid character for {0}. A SEDOL contains only digits and consonants.")]
+    Character(String),
+    /// The check digit is invalid.
+    #[error("Invalid check digit for: {0}")]
+    Checksum(String),
+}
+
+#[derive(Debug, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Error)]
+/// Represents all the possible ways a [`SecurityId`] could fail to parse.
+pub enum InvalidSecurityId {
+    #[error("Invalid value when parsing CUSIP. Cause: {0}")]
+    /// A nine character identifier that is not a valid CUSIP
+    Cusip(#[from] InvalidCusip),
+    #[error("Invalid value when parsing ISIN. Cause: {0}")]
+    /// A twelve character identifier that is not a valid ISIN
+    Isin(#[from] InvalidIsin),
+    #[error("Invalid value when parsing SEDOL. Cause: {0}")]
+    /// A seven character identifier that is not a valid SEDOL
+    Sedol(#[from] InvalidSedol),
+    #[error("Unrecognized security ID {0:?}. Expected a CUSIP, an ISIN, a SEDOL, or a RIC.")]
+    /// An identifier whose length and characters don't match any scheme
+    Unrecognized(String),
+}
+
+impl FromStr for SecurityId {
+    type Err = InvalidSecurityId;
+
+    /// Parse a security ID, detecting its scheme by its shape: an identifier with a `.`, like
+    /// "AAPL.OQ", is a RIC, and otherwise an identifier of 7, 9, or 12 characters is a SEDOL, a
+    /// CUSIP, or an ISIN, respectively. Every scheme other than a RIC is validated and stored in
+    /// uppercase.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.contains('.') && s.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'.') {
+            return Ok(Self::Ric(s.to_owned()));
+        }
+        Ok(match s.len() {
+            7 => Self::sedol(s)?,
+            9 => Self::cusip(s)?,
+            12 => Self::isin(s)?,
+            _ => return Err(InvalidSecurityId::Unrecognized(s.to_owned())),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Error)]
+/// Represents all the possible ways an ISIN could be invalid.
+pub enum InvalidIsin {
+    /// The provided code is not exactly twelve characters.
+    #[error("Invalid length. An ISIN is exactly 12 characters long. {0}")]
+    Length(String),
+    /// The first two characters are not an uppercase country code.
+    #[error("Invalid country code for {0}. The first two characters must be uppercase letters.")]
+    CountryCode(String),
+    /// One of the third through eleventh characters is not an uppercase letter or a digit.
+    #[error("Invalid character for {0}. The national security identifier must be alphanumeric.")]
+    Alphanumeric(String),
+    /// The check digit is invalid.
+    #[error("Invalid check digit for: {0}")]
+    Checksum(String),
+}
+
+// =================================
+// === Valid Trait Definition ===
+// =================================
+
+mod indicators {
+    use std::convert::Infallible;
+
+    use chrono::NaiveDate;
+    use serde::{Serialize, Serializer};
+
+    use crate::currency::Currency;
     use crate::exchange::{Primary, Routing};
     use crate::match_poly;
 
-    use super::{
-        Commodity, Contract, ContractId, Crypto, Forex, Index, SecFuture, SecOption, Stock,
-        UnexpectedSecurityType,
+    use super::{
+        Bond, Cfd, Combo, ComboLeg, Commodity, ContinuousFuture, Contract, ContractId, ContractType,
+        Crypto, DeltaNeutralContract, Forex, Index, MutualFund, SecFuture, SecFutureOption,
+        SecOption, SecurityId, Stock, StructuredProduct, UnexpectedSecurityType, Warrant,
+    };
+
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct SecurityOutMsg<'s> {
+        pub contract_id: ContractId,
+        pub symbol: &'s str,
+        pub security_type: &'static str,
+        pub expiration_date: Option<NaiveDate>,
+        pub contract_month: Option<(i32, u8)>,
+        pub strike: Option<f64>,
+        pub right: Option<&'static str>,
+        pub multiplier: Option<f64>,
+        pub exchange: Routing,
+        pub primary_exchange: Option<Primary>,
+        pub currency: Currency,
+        pub local_symbol: &'s str,
+        pub trading_class: Option<&'s str>,
+    }
+
+    impl Serialize for SecurityOutMsg<'_> {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            (
+                self.contract_id,
+                self.symbol,
+                self.security_type,
+                super::format_expiration(self.expiration_date, self.contract_month),
+                self.strike,
+                self.right,
+                // `Display` omits the decimal point for integral multipliers
+                self.multiplier.map(|m| m.to_string()),
+                self.exchange,
+                self.primary_exchange,
+                self.currency,
+                self.local_symbol,
+                self.trading_class,
+            )
+                .serialize(serializer)
+        }
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    /// A [`SecurityId`] as the pair of fields for its type code and value, or two empty fields if
+    /// there is none.
+    pub struct SecurityIdOutMsg<'s>(pub Option<&'s SecurityId>);
+
+    impl Serialize for SecurityIdOutMsg<'_> {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            match self.0 {
+                Some(security_id) => (security_id.type_code(), security_id.value()).serialize(serializer),
+                None => (None::<()>, None::<()>).serialize(serializer),
+            }
+        }
+    }
+
+    pub trait Valid:
+        Serialize
+        + Send
+        + Sync
+        + TryFrom<Forex, Error: Into<UnexpectedSecurityType>>
+        + TryFrom<Crypto, Error: Into<UnexpectedSecurityType>>
+        + TryFrom<Stock, Error: Into<UnexpectedSecurityType>>
+        + TryFrom<Index, Error: Into<UnexpectedSecurityType>>
+        + TryFrom<SecFuture, Error: Into<UnexpectedSecurityType>>
+        + TryFrom<SecOption, Error: Into<UnexpectedSecurityType>>
+        + TryFrom<Commodity, Error: Into<UnexpectedSecurityType>>
+        + TryFrom<Bond, Error: Into<UnexpectedSecurityType>>
+        + TryFrom<Cfd, Error: Into<UnexpectedSecurityType>>
+        + TryFrom<MutualFund, Error: Into<UnexpectedSecurityType>>
+        + TryFrom<SecFutureOption, Error: Into<UnexpectedSecurityType>>
+        + TryFrom<Warrant, Error: Into<UnexpectedSecurityType>>
+        + TryFrom<ContinuousFuture, Error: Into<UnexpectedSecurityType>>
+        + TryFrom<StructuredProduct, Error: Into<UnexpectedSecurityType>>
+        + TryFrom<Combo, Error: Into<UnexpectedSecurityType>>
+        + TryFrom<Contract, Error: Into<UnexpectedSecurityType>>
+        + Into<Contract>
+    {
+        /// The contract type of every value of the type, if there is one.
+        const CONTRACT_TYPE: Option<ContractType>;
+
+        fn as_out_msg(&self) -> SecurityOutMsg<'_>;
+
+        /// The legs that make up the security, if it is a [`Combo`].
+        fn combo_legs(&self) -> Option<&[ComboLeg]>;
+
+        /// The underlying that hedges the security's delta, if it is a [`Combo`] that has one.
+        fn delta_neutral_contract(&self) -> Option<DeltaNeutralContract>;
+
+        /// The industry identifiers of the security, which are only reported for a [`Stock`] or
+        /// a [`Bond`].
+        fn security_ids(&self) -> &[SecurityId];
+
+        /// The `secIdType` and `secId` fields that follow the contract fields of an order.
+        fn security_id_out_msg(&self) -> SecurityIdOutMsg<'_> {
+            SecurityIdOutMsg(self.security_ids().first())
+        }
+    }
+
+    impl Valid for Contract {
+        const CONTRACT_TYPE: Option<ContractType> = None;
+
+        fn as_out_msg(&self) -> SecurityOutMsg<'_> {
+            match_poly!(self;
+                Self::Forex(t)
+                | Self::Crypto(t)
+                | Self::Stock(t)
+                | Self::Index(t)
+                | Self::SecFuture(t)
+                | Self::SecOption(t)
+                | Self::Commodity(t)
+                | Self::Bond(t)
+                | Self::Cfd(t)
+                | Self::MutualFund(t)
+                | Self::SecFutureOption(t)
+                | Self::Warrant(t)
+                | Self::ContinuousFuture(t)
+                | Self::StructuredProduct(t)
+                | Self::Combo(t) => t.as_out_msg()
+            )
+        }
+
+        fn combo_legs(&self) -> Option<&[ComboLeg]> {
+            match self {
+                Self::Combo(t) => t.combo_legs(),
+                _ => None,
+            }
+        }
+
+        fn delta_neutral_contract(&self) -> Option<DeltaNeutralContract> {
+            match self {
+                Self::Combo(t) => t.delta_neutral_contract,
+                _ => None,
+            }
+        }
+
+        fn security_ids(&self) -> &[SecurityId] {
+            match self {
+                Self::Stock(t) => &t.security_ids,
+                Self::Bond(t) => &t.security_ids,
+                _ => &[],
+            }
+        }
+    }
+
+    impl From<Infallible> for UnexpectedSecurityType {
+        fn from(_: Infallible) -> Self {
+            unreachable!()
+        }
+    }
+}
+
+#[doc(alias = "Contract")]
+/// Attributes shared by a tradable contract or asset. All valid contracts implement this trait.
+pub trait Security: indicators::Valid {
+    /// Get the security's contract ID
+    ///
+    /// # Returns
+    /// The security's unique contract ID
+    fn contract_id(&self) -> ContractId;
+    /// Get the security's minimum tick size.
+    ///
+    /// # Returns
+    /// The security's minimum tick size
+    fn min_tick(&self) -> f64;
+    /// Get the security's multiplier, which is the number of units of the underlying that one
+    /// contract represents.
+    ///
+    /// # Returns
+    /// The multiplier of a [`SecFuture`], [`SecOption`], [`SecFutureOption`], [`Warrant`], or
+    /// [`StructuredProduct`], which may be fractional, and 1 for every other security.
+    fn multiplier(&self) -> f64 {
+        1.0
+    }
+    /// Get the security's symbol.
+    ///
+    /// # Returns
+    /// The security's symbol.
+    fn symbol(&self) -> &str;
+    /// Get the security's currency.
+    ///
+    /// # Returns
+    /// The security's currency.
+    fn currency(&self) -> Currency;
+    /// Get the security's local symbol.
+    ///
+    /// # Returns
+    /// The security's local symbol.
+    fn local_symbol(&self) -> &str;
+    /// Get the security's long name.
+    ///
+    /// # Returns
+    /// The security's long name.
+    fn long_name(&self) -> &str;
+    /// Get the security's order types.
+    ///
+    /// # Returns
+    /// The order types and order attributes that IBKR supports for the security.
+    fn order_types(&self) -> &Vec<OrderTypeCode>;
+    /// Get the security's valid exchanges.
+    ///
+    /// # Returns
+    /// The security's valid exchanges.
+    fn valid_exchanges(&self) -> &Vec<Routing>;
+    /// Get the security's contract type.
+    ///
+    /// # Returns
+    /// The security's contract type.
+    fn contract_type(&self) -> ContractType;
+    /// Get the security's additional contract details.
+    ///
+    /// # Returns
+    /// The security's contract details.
+    fn details(&self) -> &ContractDetails;
+    /// Get the security's trading hours, including those outside regular trading hours.
+    ///
+    /// # Returns
+    /// The security's trading hours, which are empty for a partially populated security.
+    fn trading_hours(&self) -> &Schedule {
+        self.details().trading_hours()
+    }
+    /// Get the security's liquid hours, which are its regular trading hours.
+    ///
+    /// # Returns
+    /// The security's liquid hours, which are empty for a partially populated security.
+    fn liquid_hours(&self) -> &Schedule {
+        self.details().liquid_hours()
+    }
+    /// Get the contract fields that are sent to IBKR for the security, like in a market data
+    /// request or an order. This helps to find out why TWS rejects a contract.
+    ///
+    /// # Returns
+    /// The fields in the order in which IBKR expects them: the contract ID, symbol, security
+    /// type, expiration date, strike, right, multiplier, exchange, primary exchange, currency,
+    /// local symbol, and trading class. A field that does not apply to the security is empty.
+    fn to_ib_fields(&self) -> Vec<String> {
+        // Writing fields to memory cannot fail
+        crate::comm::Fields::to_strings(&self.as_out_msg()).unwrap_or_default()
+    }
+    /// Get the security's order types as the TWS codes that IBKR reports, like "LMT" or
+    /// "STP LMT". See [`OrderTypeCode`] for how codes with several spellings are normalized.
+    ///
+    /// # Returns
+    /// The TWS code of each of the security's order types.
+    fn order_types_raw(&self) -> Vec<&str> {
+        self.order_types().iter().map(OrderTypeCode::as_str).collect()
+    }
+    /// Check whether IBKR supports an order type for the security.
+    ///
+    /// # Arguments
+    /// * `code` - The order type to check, like [`OrderTypeCode::StopLimit`].
+    ///
+    /// # Returns
+    /// Whether `code` is among the security's [`Security::order_types`]. This is always `false`
+    /// for a partially populated security, which has no order types.
+    fn supports_order_type(&self, code: OrderTypeCode) -> bool {
+        self.order_types().contains(&code)
+    }
+    /// Get the smallest quantity of the security that can be ordered.
+    ///
+    /// # Returns
+    /// The security's minimum order size, or `None` if IBKR did not report one, like for a
+    /// partially populated security.
+    fn min_size(&self) -> Option<f64> {
+        self.details().min_size()
+    }
+    /// Get the increment in which the quantity of an order for the security must be given.
+    ///
+    /// # Returns
+    /// The security's size increment, or `None` if IBKR did not report one, like for a partially
+    /// populated security.
+    fn size_increment(&self) -> Option<f64> {
+        self.details().size_increment()
+    }
+    /// Snap a desired order quantity to the nearest quantity that IBKR accepts for the security,
+    /// which is a multiple of its [`Security::size_increment`] no smaller than its
+    /// [`Security::min_size`].
+    ///
+    /// A positive quantity that rounds to less than the minimum order size is raised to the
+    /// minimum. A quantity is returned unchanged if IBKR did not report a size increment, like
+    /// for a partially populated security.
+    ///
+    /// # Arguments
+    /// * `qty` - The desired order quantity.
+    ///
+    /// # Returns
+    /// The nearest valid order quantity.
+    fn round_size(&self, qty: f64) -> f64 {
+        let rounded = match self.size_increment() {
+            Some(increment) if increment > 0.0 => (qty / increment).round() * increment,
+            _ => return qty,
+        };
+        match self.min_size() {
+            Some(min_size) if qty > 0.0 && rounded < min_size => min_size,
+            _ => rounded,
+        }
+    }
+    /// Get the tick size that applies to the security at `price`, which is that of the price band
+    /// of `market_rule` if it is known, or else the security's [`Security::min_tick`].
+    ///
+    /// # Arguments
+    /// * `price` - The price whose tick size to look up.
+    /// * `market_rule` - The market rule of the security on the exchange to which the order is
+    ///   routed, as returned by [`crate::client::Client::request_market_rule`] for
+    ///   [`Security::market_rule_id`], if it is known.
+    ///
+    /// # Returns
+    /// The rule's [`MarketRule::tick_for_price`] if it is positive, the minimum tick otherwise.
+    fn tick_for_price(&self, price: f64, market_rule: Option<&MarketRule>) -> f64 {
+        market_rule
+            .map(|rule| rule.tick_for_price(price))
+            .filter(|&tick| tick > 0.0)
+            .unwrap_or_else(|| self.min_tick())
+    }
+    /// Snap a price to the nearest multiple of the security's tick size, which IBKR requires of
+    /// the prices of orders.
+    ///
+    /// The tick size is that of the price band of `market_rule` for securities whose tick size
+    /// depends on the price, or the security's [`Security::min_tick`] if no rule is known. See
+    /// [`Security::tick_for_price`].
+    ///
+    /// # Arguments
+    /// * `price` - The desired price.
+    /// * `market_rule` - The market rule of the security, if it is known.
+    ///
+    /// # Returns
+    /// The nearest valid price, or `price` itself if the tick size is not positive.
+    fn round_price(&self, price: f64, market_rule: Option<&MarketRule>) -> f64 {
+        snap_to_tick(price, self.tick_for_price(price, market_rule), f64::round)
+    }
+    /// Snap a price down to the nearest multiple of the security's tick size, so that a buy
+    /// order never pays more than `price`. See [`Security::round_price`].
+    ///
+    /// A price that is already a multiple of the tick, up to floating-point error, is kept as it
+    /// is.
+    ///
+    /// # Arguments
+    /// * `price` - The desired price.
+    /// * `market_rule` - The market rule of the security, if it is known.
+    ///
+    /// # Returns
+    /// The greatest valid price no greater than `price`, or `price` itself if the tick size is
+    /// not positive.
+    fn round_price_down(&self, price: f64, market_rule: Option<&MarketRule>) -> f64 {
+        snap_to_tick(price, self.tick_for_price(price, market_rule), f64::floor)
+    }
+    /// Snap a price up to the nearest multiple of the security's tick size, so that a sell order
+    /// never receives less than `price`. See [`Security::round_price`].
+    ///
+    /// A price that is already a multiple of the tick, up to floating-point error, is kept as it
+    /// is.
+    ///
+    /// # Arguments
+    /// * `price` - The desired price.
+    /// * `market_rule` - The market rule of the security, if it is known.
+    ///
+    /// # Returns
+    /// The least valid price no less than `price`, or `price` itself if the tick size is not
+    /// positive.
+    fn round_price_up(&self, price: f64, market_rule: Option<&MarketRule>) -> f64 {
+        snap_to_tick(price, self.tick_for_price(price, market_rule), f64::ceil)
+    }
+    /// Get the IDs of the market rules that set the security's tick sizes on each exchange.
+    ///
+    /// # Returns
+    /// The market rule ID for each of the security's valid exchanges, which is empty for a
+    /// partially populated security.
+    fn market_rule_ids(&self) -> &[(Routing, MarketRuleId)] {
+        self.details().market_rule_ids()
+    }
+    /// Get the ID of the market rule that sets the security's tick sizes on an exchange, which
+    /// can be requested with [`crate::client::Client::request_market_rule`] to round prices with
+    /// [`MarketRule::round_to_tick`].
+    ///
+    /// # Arguments
+    /// * `routing` - The exchange on which the security is traded.
+    ///
+    /// # Returns
+    /// The market rule ID, or `None` if the security has none for `routing`.
+    fn market_rule_id(&self, routing: Routing) -> Option<MarketRuleId> {
+        self.market_rule_ids()
+            .iter()
+            .find_map(|&(r, id)| (r == routing).then_some(id))
+    }
+    /// Check whether orders and data requests for the security can be routed to `routing`.
+    ///
+    /// Note that a partially populated security, like one built by a [`StockBuilder`], has no
+    /// valid exchanges, so this is always `false` for it.
+    ///
+    /// # Returns
+    /// Whether `routing` is one of the security's valid exchanges.
+    fn is_valid_exchange(&self, routing: &Routing) -> bool {
+        self.valid_exchanges().contains(routing)
+    }
+    /// Get the best exchange to which orders and data requests for the security can be routed.
+    ///
+    /// # Returns
+    /// [`Routing::Smart`] if it is a valid exchange, otherwise the first valid exchange, or `None`
+    /// if the security has no valid exchanges.
+    fn best_exchange(&self) -> Option<Routing> {
+        let valid_exchanges = self.valid_exchanges();
+        if valid_exchanges.contains(&Routing::Smart) {
+            Some(Routing::Smart)
+        } else {
+            valid_exchanges.first().copied()
+        }
+    }
+    /// Check whether `other` refers to the same contract as the security, by comparing only their
+    /// contract IDs.
+    ///
+    /// This is intentionally weaker than `==`, which also compares fields like the long name and
+    /// order types that differ between a partially populated security and one resolved with
+    /// [`new`]. Note that every [`Combo`] has a contract ID of 0, so combos are never
+    /// distinguished by this method.
+    ///
+    /// # Returns
+    /// Whether the two securities have the same contract ID.
+    fn same_contract(&self, other: &impl Security) -> bool
+    where
+        Self: Sized,
+    {
+        self.contract_id() == other.contract_id()
+    }
+}
+
+/// Attributes shared by securities that expire on a fixed date.
+pub trait Expiring: Security {
+    /// Get the security's expiration date.
+    ///
+    /// # Returns
+    /// The security's expiration date, or `None` if IBKR only reported the contract month of a
+    /// [`SecFuture`] or [`SecOption`].
+    fn expiration_date(&self) -> Option<NaiveDate>;
+}
+
+macro_rules! impl_expiring {
+    ($($name: ident),*; $($dated: ident),*) => {
+        $(
+            impl Expiring for $name {
+                #[inline]
+                fn expiration_date(&self) -> Option<NaiveDate> {
+                    $name::expiration_date(self)
+                }
+            }
+        )*
+        $(
+            impl Expiring for $dated {
+                #[inline]
+                fn expiration_date(&self) -> Option<NaiveDate> {
+                    Some($dated::expiration_date(self))
+                }
+            }
+        )*
+    };
+}
+
+impl_expiring!(SecFuture, SecOption; SecFutureOption, Warrant, StructuredProduct);
+
+#[inline]
+#[must_use]
+/// Format an expiration for IBKR's `lastTradeDateOrContractMonth` field: the date as YYYYMMDD if
+/// it is known, or else the contract month as YYYYMM.
+pub(crate) fn format_expiration(
+    expiration_date: Option<NaiveDate>,
+    contract_month: Option<(i32, u8)>,
+) -> Option<String> {
+    expiration_date
+        .map(|d| d.format("%Y%m%d").to_string())
+        .or_else(|| contract_month.map(|(year, month)| format!("{year:04}{month:02}")))
+}
+
+#[inline]
+#[must_use]
+#[allow(clippy::cast_possible_truncation)]
+/// Get the contract month in which `date` falls, as a year and a month from 1 to 12.
+pub(crate) fn contract_month_of(date: NaiveDate) -> (i32, u8) {
+    use chrono::Datelike;
+
+    (date.year(), date.month() as u8)
+}
+
+/// Attributes shared by derivatives, which reference an underlying contract.
+///
+/// The underlying can be resolved with [`new`] and a [`Query::IbContractId`].
+pub trait HasUnderlying {
+    /// Get the contract ID of the security's underlying.
+    ///
+    /// # Returns
+    /// The underlying's contract ID. For a [`SecFutureOption`], this is the underlying [`SecFuture`].
+    fn underlying_contract_id(&self) -> ContractId;
+}
+
+macro_rules! impl_has_underlying {
+    ($($name: ident),*; $($fop_name: ident),*) => {
+        $(
+            impl HasUnderlying for $name {
+                #[inline]
+                fn underlying_contract_id(&self) -> ContractId {
+                    $name::underlying_contract_id(self)
+                }
+            }
+        )*
+        $(
+            impl HasUnderlying for $fop_name {
+                #[inline]
+                fn underlying_contract_id(&self) -> ContractId {
+                    $fop_name::underlying_future_contract_id(self)
+                }
+            }
+        )*
+    };
+}
+
+impl_has_underlying!(
+    SecFuture, ContinuousFuture, Cfd, SecOption, SecOptionInner, Warrant, WarrantInner;
+    SecFutureOption, SecFutureOptionInner
+);
+
+/// Attributes shared by securities that IBKR groups into a trading class, which is every security
+/// except an [`Index`] and a [`Combo`].
+pub trait TradingClass {
+    /// Get the security's trading class.
+    ///
+    /// # Returns
+    /// The security's trading class, like "ES" or "NMS".
+    fn trading_class(&self) -> &str;
+}
+
+macro_rules! impl_trading_class {
+    ($($name: ident),*) => {
+        $(
+            impl TradingClass for $name {
+                #[inline]
+                fn trading_class(&self) -> &str {
+                    $name::trading_class(self)
+                }
+            }
+        )*
+    };
+}
+
+impl_trading_class!(
+    Forex, Crypto, Stock, Commodity, SecFuture, SecOption, SecOptionInner, Bond, Cfd, MutualFund,
+    SecFutureOption, SecFutureOptionInner, Warrant, WarrantInner, ContinuousFuture,
+    StructuredProduct
+);
+
+macro_rules! impl_notional {
+    ($($name: ident),*) => {
+        $(
+            impl $name {
+                #[must_use]
+                #[inline]
+                /// Compute the notional value of `quantity` contracts at `price`, which is
+                /// `price * multiplier * quantity`.
+                ///
+                /// # Arguments
+                /// * `price` - The price of a single unit of the underlying.
+                /// * `quantity` - The number of contracts.
+                ///
+                /// # Returns
+                /// The notional value, in the contract's [`Security::currency`].
+                pub fn notional(&self, price: f64, quantity: f64) -> f64 {
+                    price * self.multiplier.get() * quantity
+                }
+            }
+        )*
+    };
+}
+
+impl_notional!(SecFuture, SecOptionInner, SecFutureOptionInner, WarrantInner, StructuredProduct);
+
+// =======================================
+// === Definitions of Contract Structs ===
+// =======================================
+
+macro_rules! make_contract {
+    ($( #[doc = $name_doc:expr] )* $name: ident $(,$trt: ident)?; $($field: ident: $f_type: ty),* $(,)?) => {
+        $( #[doc = $name_doc] )*
+        #[make_getters]
+        #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, $($trt)?)]
+        #[cfg_attr(feature = "serde-full", derive(Serialize, Deserialize), serde(deny_unknown_fields))]
+        pub struct $name {
+            pub(crate) contract_id: ContractId,
+            pub(crate) min_tick: NotNan,
+            pub(crate) symbol: String,
+            $(pub(crate) $field: $f_type,)*
+            pub(crate) currency: Currency,
+            pub(crate) local_symbol: String,
+            pub(crate) long_name: String,
+            pub(crate) order_types: Vec<OrderTypeCode>,
+            pub(crate) valid_exchanges: Vec<Routing>,
+            pub(crate) details: ContractDetails,
+        }
+    }
+}
+
+#[make_getters]
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde-full", derive(Serialize, Deserialize), serde(deny_unknown_fields))]
+/// Additional information that IBKR reports about a contract, which is not needed to identify it.
+///
+/// The details of a partially populated contract, like one built by a [`StockBuilder`], are empty.
+pub struct ContractDetails {
+    /// The name of the market on which the contract trades, like "NMS" or "ES".
+    pub(crate) market_name: String,
+    /// The contract month of a derivative, like "202412".
+    pub(crate) contract_month: String,
+    /// The industry of the contract's issuer, like "Technology".
+    pub(crate) industry: String,
+    /// The category of the contract's issuer within its industry, like "Computers".
+    pub(crate) category: String,
+    /// The subcategory of the contract's issuer within its category, like "Computers".
+    pub(crate) subcategory: String,
+    /// The IANA time zone of the contract's trading hours, like "US/Eastern".
+    pub(crate) time_zone_id: String,
+    /// The group of contracts whose positions are aggregated for margin purposes.
+    pub(crate) agg_group: Option<i64>,
+    /// The symbol of a derivative's underlying contract, like "AAPL".
+    pub(crate) under_symbol: String,
+    /// The security type of a derivative's underlying contract, like "STK".
+    pub(crate) under_security_type: String,
+    /// The date on which a derivative actually expires, which may differ from its last trading date.
+    pub(crate) real_expiration_date: Option<NaiveDate>,
+    /// The time on the last trading date at which trading stops, like "16:00:00".
+    pub(crate) last_trade_time: String,
+    /// The hours during which the contract can be traded, including outside regular hours.
+    pub(crate) trading_hours: Schedule,
+    /// The regular trading hours of the contract, during which it is most liquid.
+    pub(crate) liquid_hours: Schedule,
+    /// The smallest quantity of the contract that can be ordered, like 0.0001 for fractional
+    /// crypto orders.
+    pub(crate) min_size: Option<NotNan>,
+    /// The increment in which the quantity of an order must be given.
+    pub(crate) size_increment: Option<NotNan>,
+    /// The increment that IBKR suggests for the quantity of an order, which may be coarser than
+    /// the size increment.
+    pub(crate) suggested_size_increment: Option<NotNan>,
+    /// The [`MarketRule`] that sets the tick sizes of the contract on each of its valid exchanges.
+    pub(crate) market_rule_ids: Vec<(Routing, MarketRuleId)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde-full", derive(Serialize, Deserialize))]
+/// The trading sessions of a contract over the coming days, as reported by IBKR.
+pub struct Schedule {
+    /// The time zone in which the session times are given.
+    pub time_zone: chrono_tz::Tz,
+    /// The sessions, in the order in which IBKR reports them. A date with several sessions, like
+    /// one with a midday break, has one session for each.
+    pub sessions: Vec<Session>,
+}
+
+impl PartialOrd for Schedule {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// Time zones aren't ordered, so schedules are ordered by the names of their time zones instead
+impl Ord for Schedule {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        (self.time_zone.name(), &self.sessions).cmp(&(other.time_zone.name(), &other.sessions))
+    }
+}
+
+impl Default for Schedule {
+    fn default() -> Self {
+        Self {
+            time_zone: chrono_tz::UTC,
+            sessions: Vec::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde-full", derive(Serialize, Deserialize))]
+/// A single trading session of a [`Schedule`].
+pub struct Session {
+    /// The date on which the session opens.
+    pub date: NaiveDate,
+    /// The local time at which the session opens, or `None` if the session is closed.
+    pub open: Option<chrono::NaiveTime>,
+    /// The local time at which the session closes, or `None` if the session is closed. A close at
+    /// or before the open, like that of an overnight futures session, is on the following day.
+    pub close: Option<chrono::NaiveTime>,
+    /// Whether there is no trading on the date.
+    pub closed: bool,
+}
+
+impl Session {
+    /// The local date and time at which the session opens and closes, if it is not closed.
+    fn bounds(&self) -> Option<(chrono::NaiveDateTime, chrono::NaiveDateTime)> {
+        let (open, close) = (self.date.and_time(self.open?), self.date.and_time(self.close?));
+        if close <= open {
+            Some((open, close + chrono::Days::new(1)))
+        } else {
+            Some((open, close))
+        }
+    }
+}
+
+#[derive(Debug, Clone, Error)]
+#[error("Invalid value encountered when attempting to parse trading hours: {0}")]
+/// An error returned when parsing a [`Schedule`] fails.
+pub struct ParseScheduleError(pub String);
+
+impl Schedule {
+    /// Parse the trading or liquid hours that IBKR reports for a contract.
+    ///
+    /// Each session is given either as `20240304:0930-1600` or as `20240304:0930-20240304:1600`,
+    /// sessions are separated by `;`, and a date without trading is given as `20240305:CLOSED`.
+    /// Several sessions on the same date may also be separated by `,`.
+    ///
+    /// # Arguments
+    /// * `hours` - The trading or liquid hours.
+    /// * `time_zone` - The time zone in which the session times are given.
+    ///
+    /// # Errors
+    /// Returns an error if any session is malformed.
+    ///
+    /// # Returns
+    /// The parsed schedule.
+    pub fn parse(hours: &str, time_zone: chrono_tz::Tz) -> Result<Self, ParseScheduleError> {
+        let err = || ParseScheduleError(hours.to_owned());
+        let time = |s: &str| chrono::NaiveTime::parse_from_str(s, "%H%M").map_err(|_| err());
+
+        let mut sessions = Vec::new();
+        for day in hours.split(';').filter(|d| !d.is_empty()) {
+            let (date, times) = day.split_once(':').ok_or_else(err)?;
+            let date = NaiveDate::parse_from_str(date, "%Y%m%d").map_err(|_| err())?;
+            if times == "CLOSED" {
+                sessions.push(Session {
+                    date,
+                    open: None,
+                    close: None,
+                    closed: true,
+                });
+                continue;
+            }
+            for session in times.split(',') {
+                let (open, close) = session.split_once('-').ok_or_else(err)?;
+                // Newer versions of TWS prefix the close with its date
+                let close = close.split_once(':').map_or(close, |(_, t)| t);
+                sessions.push(Session {
+                    date,
+                    open: Some(time(open)?),
+                    close: Some(time(close)?),
+                    closed: false,
+                });
+            }
+        }
+        Ok(Self {
+            time_zone,
+            sessions,
+        })
+    }
+
+    #[must_use]
+    /// Check whether any session of the schedule is open at `time`.
+    ///
+    /// Sessions open at their open time and close just before their close time.
+    ///
+    /// # Arguments
+    /// * `time` - The time to check, in any time zone.
+    ///
+    /// # Returns
+    /// Whether a session is open at `time`.
+    pub fn is_open_at(&self, time: chrono::DateTime<chrono_tz::Tz>) -> bool {
+        self.is_open(time.with_timezone(&self.time_zone).naive_local())
+    }
+
+    #[must_use]
+    /// Check whether any session of the schedule is open at the local time `at`, like
+    /// [`Schedule::is_open_at`].
+    ///
+    /// # Arguments
+    /// * `at` - The time to check, in the schedule's [`Schedule::time_zone`].
+    ///
+    /// # Returns
+    /// Whether a session is open at `at`.
+    pub fn is_open(&self, at: chrono::NaiveDateTime) -> bool {
+        self.sessions
+            .iter()
+            .filter_map(Session::bounds)
+            .any(|(open, close)| open <= at && at < close)
+    }
+}
+
+make_contract!(
+    /// A [forex contract](https://interactivebrokers.github.io/tws-api/basic_contracts.html#cash), like GBPUSD.
+    Forex,
+    Security;
+    exchange: Routing,
+    trading_class: String
+);
+
+impl Forex {
+    /// Create a partially populated [`Forex`] contract for the pair of `base` and `quote`, like
+    /// GBP.USD, the same way that a [`StockBuilder`] creates a partially populated [`Stock`].
+    ///
+    /// The symbol is the base currency, the local symbol and trading class are the pair in IBKR's
+    /// `BASE.QUOTE` format, and the currency is the quote currency. Unlike
+    /// [`Query::forex_pair`], the pair is not reordered to follow the market convention.
+    ///
+    /// # Arguments
+    /// * `base` - The currency being bought or sold.
+    /// * `quote` - The currency in which the price of `base` is given.
+    /// * `exchange` - The exchange on which the pair trades, usually IDEALPRO.
+    ///
+    /// # Errors
+    /// Returns an error if `base` and `quote` are the same currency.
+    ///
+    /// # Returns
+    /// The new forex contract, with a contract ID of 0.
+    pub fn from_pair(
+        base: Currency,
+        quote: Currency,
+        exchange: Routing,
+    ) -> Result<Self, SameCurrencyPairError> {
+        if base == quote {
+            return Err(SameCurrencyPairError(base));
+        }
+        let pair = format!("{base}.{quote}");
+        Ok(Self {
+            contract_id: ContractId(0),
+            min_tick: NotNan::default(),
+            symbol: base.to_string(),
+            exchange,
+            trading_class: pair.clone(),
+            currency: quote,
+            local_symbol: pair,
+            long_name: String::default(),
+            order_types: Vec::default(),
+            valid_exchanges: Vec::default(),
+            details: ContractDetails::default(),
+        })
+    }
+
+    #[must_use]
+    #[inline]
+    /// Get the base currency of the pair, which is the currency being bought or sold. IBKR reports
+    /// it as the contract's symbol.
+    ///
+    /// # Panics
+    /// Panics if the symbol is not a [`Currency`]. This is checked whenever a [`Forex`] is decoded
+    /// or parsed, so it can only happen for one deserialized from invalid data.
+    pub fn base(&self) -> Currency {
+        self.symbol
+            .parse()
+            .expect("The symbol of a forex contract is always its base currency")
+    }
+
+    #[must_use]
+    #[inline]
+    /// Get the quote currency of the pair, in which the price of the base currency is given. IBKR
+    /// reports it as the contract's currency.
+    pub fn quote(&self) -> Currency {
+        self.currency
+    }
+
+    #[must_use]
+    #[inline]
+    /// Get the currency pair, like `(Currency::Euro, Currency::UsDollar)` for EUR.USD.
+    ///
+    /// # Returns
+    /// The base and quote currencies, in that order.
+    pub fn pair(&self) -> (Currency, Currency) {
+        (self.base(), self.quote())
+    }
+}
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Error)]
+#[error("The base and quote currencies of a forex pair must differ, but both are {0}.")]
+/// An error returned by [`Forex::from_pair`] when the base and quote currencies are the same.
+pub struct SameCurrencyPairError(pub Currency);
+
+make_contract!(
+    /// A [crypto contract](https://interactivebrokers.github.io/tws-api/basic_contracts.html#crypto), like BTC.
+    Crypto,
+    Security;
+    exchange: Routing,
+    trading_class: String
+);
+make_contract!(
+    /// An [equity contract](https://interactivebrokers.github.io/tws-api/basic_contracts.html#stk), like AAPL.
+    Stock,
+    Security;
+    exchange: Routing,
+    primary_exchange: Primary,
+    stock_type: String,
+    security_ids: Vec<SecurityId>,
+    sector: String,
+    trading_class: String
+);
+make_contract!(
+    /// An [index](https://interactivebrokers.github.io/tws-api/basic_contracts.html#ind), like SPX.
+    Index,
+    Security;
+    exchange: Routing
+);
+
+impl Index {
+    /// Create a partially populated [`Index`], like SPX on CBOE, the same way that a
+    /// [`StockBuilder`] creates a partially populated [`Stock`]. It can be passed to market data
+    /// requests without first being resolved with [`new`].
+    ///
+    /// The local symbol is the symbol, and fields like `long_name` and the [`ContractDetails`] are
+    /// left empty.
+    ///
+    /// # Arguments
+    /// * `symbol` - The symbol of the index, like "SPX" or "VIX".
+    /// * `exchange` - The exchange that publishes the index, like CBOE.
+    /// * `currency` - The currency in which the index is quoted.
+    ///
+    /// # Errors
+    /// Returns an error if `symbol` is empty.
+    ///
+    /// # Returns
+    /// The new index, with a contract ID of 0.
+    pub fn new(
+        symbol: &str,
+        exchange: Routing,
+        currency: Currency,
+    ) -> Result<Self, EmptyIndexSymbolError> {
+        let symbol = symbol.trim();
+        if symbol.is_empty() {
+            return Err(EmptyIndexSymbolError);
+        }
+        Ok(Self {
+            contract_id: ContractId(0),
+            min_tick: NotNan(DEFAULT_MIN_TICK),
+            symbol: symbol.to_owned(),
+            exchange,
+            currency,
+            local_symbol: symbol.to_owned(),
+            long_name: String::default(),
+            order_types: Vec::default(),
+            valid_exchanges: Vec::default(),
+            details: ContractDetails::default(),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Error)]
+#[error("Cannot create an index with an empty symbol.")]
+/// An error returned by [`Index::new`] when the symbol is empty.
+pub struct EmptyIndexSymbolError;
+
+make_contract!(
+    /// A [commodity](https://interactivebrokers.github.io/tws-api/basic_contracts.html#Commodities), like XAUUSD.
+    Commodity,
+    Security;
+    exchange: Routing,
+    commodity_type: String,
+    trading_class: String
+);
+make_contract!(
+    /// A [futures contract](https://interactivebrokers.github.io/tws-api/basic_contracts.html#fut), like FGBL MAR 23.
+    SecFuture,
+    Security;
+    exchange: Routing,
+    multiplier: NotNan,
+    expiration_date: Option<NaiveDate>,
+    contract_month: (i32, u8),
+    trading_class: String,
+    underlying_contract_id: ContractId
+);
+impl SecFuture {
+    /// Create a partially populated [`SecFuture`], like ES DEC 24 on CME, from its symbol and
+    /// expiration date. It can be passed to market data requests and orders without first being
+    /// resolved with [`new`], unlike [`crate::client::Client::front_month_future`].
+    ///
+    /// The multiplier is unknown, so it is 0 and sent to IBKR as an empty field. The contract
+    /// month is taken from the expiration date, and fields like `trading_class` and the
+    /// [`ContractDetails`] are left empty.
+    ///
+    /// # Arguments
+    /// * `symbol` - The symbol of the future, like "ES".
+    /// * `expiration_date` - The last trading date of the future.
+    /// * `exchange` - The exchange on which the future trades, like CME.
+    /// * `currency` - The currency of the future.
+    ///
+    /// # Errors
+    /// Returns an error if `symbol` is empty or if IBKR cannot be sent `expiration_date`, whose
+    /// year must have four digits.
+    ///
+    /// # Returns
+    /// The new future, with a contract ID of 0.
+    pub fn new(
+        symbol: &str,
+        expiration_date: NaiveDate,
+        exchange: Routing,
+        currency: Currency,
+    ) -> Result<Self, InvalidSecFutureError> {
+        let symbol = symbol.trim();
+        if symbol.is_empty() {
+            return Err(InvalidSecFutureError::EmptySymbol);
+        }
+        let contract_month = contract_month_of(expiration_date);
+        if !(1..=9999).contains(&contract_month.0) {
+            return Err(InvalidSecFutureError::ExpirationDate(expiration_date));
+        }
+        Ok(Self {
+            contract_id: ContractId(0),
+            min_tick: NotNan(DEFAULT_MIN_TICK),
+            symbol: symbol.to_owned(),
+            exchange,
+            currency,
+            local_symbol: String::default(),
+            long_name: String::default(),
+            order_types: Vec::default(),
+            valid_exchanges: Vec::default(),
+            details: ContractDetails::default(),
+            multiplier: NotNan(0.0),
+            expiration_date: Some(expiration_date),
+            contract_month,
+            trading_class: String::default(),
+            underlying_contract_id: ContractId(0),
+        })
+    }
+
+    #[must_use]
+    /// Set the local symbol of a partially populated [`SecFuture`], like "ESZ4", which IBKR also
+    /// accepts to identify a future.
+    ///
+    /// # Arguments
+    /// * `local_symbol` - The local symbol of the future.
+    ///
+    /// # Returns
+    /// The future with its local symbol.
+    pub fn with_local_symbol(mut self, local_symbol: &str) -> Self {
+        local_symbol.trim().clone_into(&mut self.local_symbol);
+        self
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Error)]
+/// An error returned by [`SecFuture::new`] when it is given an invalid future.
+pub enum InvalidSecFutureError {
+    /// The symbol is empty
+    #[error("Cannot create a future with an empty symbol.")]
+    EmptySymbol,
+    /// The expiration date cannot be sent to IBKR
+    #[error("Cannot create a future that expires on {0}, which IBKR cannot represent.")]
+    ExpirationDate(NaiveDate),
+}
+
+make_contract!(
+    /// A [continuous futures contract](https://interactivebrokers.github.io/tws-api/basic_contracts.html#fut), which stitches together consecutive expiries for historical data.
+    ContinuousFuture,
+    Security;
+    exchange: Routing,
+    trading_class: String,
+    underlying_contract_id: ContractId
+);
+
+impl ContinuousFuture {
+    /// Resolve the continuous future into the currently active (front month) [`SecFuture`].
+    ///
+    /// IBKR reports the contract ID of the front month future as the contract ID of a continuous
+    /// future, so this queries the contract details for that ID.
+    ///
+    /// # Arguments
+    /// * `client` - The client with which to send the contract query.
+    ///
+    /// # Errors
+    /// Returns any error encountered while querying the front month contract.
+    ///
+    /// # Returns
+    /// The front month [`SecFuture`].
+    pub async fn resolve_front_month(
+        &self,
+        client: &mut impl ClientPort,
+    ) -> Result<SecFuture, NewSecurityError> {
+        new(client, Query::IbContractId(self.contract_id, self.exchange)).await
+    }
+}
+
+/// Pick the front month from the futures among `contracts`: the one that expires soonest, but no
+/// sooner than `roll_days` days after `today`.
+pub(crate) fn front_month_future(
+    contracts: impl IntoIterator<Item = Contract>,
+    today: NaiveDate,
+    roll_days: u16,
+) -> Option<SecFuture> {
+    let earliest = today + chrono::Days::new(roll_days.into());
+    contracts
+        .into_iter()
+        .filter_map(|c| match c {
+            Contract::SecFuture(fut) if fut.expiration_date.is_some_and(|d| d >= earliest) => {
+                Some(fut)
+            }
+            _ => None,
+        })
+        .min_by_key(|fut| (fut.expiration_date, fut.contract_id))
+}
+
+/// Collect the futures among `contracts` into a curve, from the one that expires soonest to the
+/// one that expires last.
+pub(crate) fn futures_curve(contracts: impl IntoIterator<Item = Contract>) -> Vec<SecFuture> {
+    let mut curve = contracts
+        .into_iter()
+        .filter_map(|c| match c {
+            Contract::SecFuture(fut) => Some(fut),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    // A future whose expiration date is unknown sorts first within its contract month
+    curve.sort_by_key(|fut| (fut.contract_month, fut.expiration_date, fut.contract_id));
+    curve
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Error)]
+#[error("Cannot sum notional values in different currencies: {currencies:?}")]
+/// An error returned by [`notional_sum`] when the positions do not share a single currency.
+pub struct MixedCurrency {
+    /// The distinct currencies of the positions, in sorted order. It is empty if there were no
+    /// positions at all.
+    pub currencies: Vec<Currency>,
+}
+
+/// Sum the notional values of several positions, which is `price * multiplier * quantity` for
+/// each of them, as long as their securities share a [`Security::currency`].
+///
+/// # Arguments
+/// * `positions` - The security, the price of a single unit of the underlying, and the quantity
+///   of each position.
+///
+/// # Errors
+/// Returns a [`MixedCurrency`] with the distinct currencies found if the securities do not all
+/// have the same currency, or if `positions` is empty.
+///
+/// # Returns
+/// The total notional value and the currency in which it is denominated.
+pub fn notional_sum<S: Security>(
+    positions: &[(S, f64, f64)],
+) -> Result<(f64, Currency), MixedCurrency> {
+    let mut currencies = positions
+        .iter()
+        .map(|(security, _, _)| security.currency())
+        .collect::<Vec<_>>();
+    currencies.sort_unstable();
+    currencies.dedup();
+    match currencies.as_slice() {
+        [currency] => Ok((
+            positions
+                .iter()
+                .map(|(security, price, quantity)| price * security.multiplier() * quantity)
+                .sum(),
+            *currency,
+        )),
+        _ => Err(MixedCurrency { currencies }),
+    }
+}
+make_contract!(
+    /// A [bond contract](https://interactivebrokers.github.io/tws-api/basic_contracts.html#bond), like a US Treasury note.
+    Bond,
+    Security;
+    exchange: Routing,
+    cusip: String,
+    coupon: NotNan,
+    maturity_date: NaiveDate,
+    issue_date: String,
+    ratings: String,
+    bond_type: String,
+    coupon_type: String,
+    callable: bool,
+    putable: bool,
+    convertible: bool,
+    security_ids: Vec<SecurityId>,
+    trading_class: String
+);
+make_contract!(
+    /// A [contract for difference](https://interactivebrokers.github.io/tws-api/basic_contracts.html#cfd), like IBUS500.
+    Cfd,
+    Security;
+    exchange: Routing,
+    underlying_contract_id: ContractId,
+    cfd_type: String,
+    trading_class: String
+);
+make_contract!(
+    /// A [mutual fund](https://interactivebrokers.github.io/tws-api/basic_contracts.html#fund), like VINIX.
+    ///
+    /// Funds are bought and sold at their net asset value rather than at a quoted price, so their
+    /// minimum tick size may be zero.
+    MutualFund,
+    Security;
+    exchange: Routing,
+    fund_family: String,
+    fund_type: String,
+    fund_front_load: Option<NotNan>,
+    fund_back_load: Option<NotNan>,
+    fund_back_load_time_interval: String,
+    fund_management_fee: Option<NotNan>,
+    trading_class: String
+);
+
+make_contract!(
+    /// Helper struct to hold the fields of a [`SecOption`].
+    SecOptionInner;
+    exchange: Routing,
+    strike: NotNan,
+    multiplier: NotNan,
+    expiration_date: Option<NaiveDate>,
+    contract_month: (i32, u8),
+    underlying_contract_id: ContractId,
+    sector: String,
+    trading_class: String
+);
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Security)]
+#[cfg_attr(feature = "serde-full", derive(Serialize, Deserialize))]
+/// A [vanilla option contract](https://interactivebrokers.github.io/tws-api/basic_contracts.html#opt), like P BMW  20221216 72 M.
+pub enum SecOption {
+    /// A vanilla call option, defined by the following payoff function: max(S<sub>T</sub> - K, 0)
+    Call(SecOptionInner),
+    /// A vanilla put option, defined by the following payoff function: max(K - S<sub>T</sub>, 0)
+    Put(SecOptionInner),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// A builder for a [`Stock`] whose symbol, exchange, primary exchange, and currency are already
+/// known, which avoids a round-trip to the IBKR API in [`new`].
+///
+/// The resulting [`Stock`] is only partially populated: fields like `long_name` and the
+/// [`ContractDetails`] are left empty, the minimum tick size defaults to 0.01, and the contract ID
+/// defaults to 0 unless it is set, so it is only suitable for order submission and market data
+/// requests that can be resolved by IBKR on the basis of the symbol, and for tests.
+pub struct StockBuilder {
+    symbol: String,
+    exchange: Routing,
+    primary_exchange: Option<Primary>,
+    currency: Option<Currency>,
+    contract_id: ContractId,
+    local_symbol: String,
+}
+
+impl StockBuilder {
+    #[must_use]
+    #[inline]
+    /// Creates a new [`StockBuilder`] that routes to [`Routing::Smart`] by default.
+    ///
+    /// # Arguments
+    /// * `symbol` - The stock's ticker symbol, like "AAPL".
+    pub fn new(symbol: impl Into<String>) -> Self {
+        Self {
+            symbol: symbol.into(),
+            exchange: Routing::Smart,
+            primary_exchange: None,
+            currency: None,
+            contract_id: ContractId(0),
+            local_symbol: String::default(),
+        }
+    }
+
+    #[must_use]
+    #[inline]
+    /// Set the exchange to which orders and data requests are routed.
+    pub fn exchange(mut self, exchange: Routing) -> Self {
+        self.exchange = exchange;
+        self
+    }
+
+    #[must_use]
+    #[inline]
+    /// Set the stock's primary listing exchange.
+    pub fn primary_exchange(mut self, primary_exchange: Primary) -> Self {
+        self.primary_exchange = Some(primary_exchange);
+        self
+    }
+
+    #[must_use]
+    #[inline]
+    /// Set the stock's currency.
+    pub fn currency(mut self, currency: Currency) -> Self {
+        self.currency = Some(currency);
+        self
+    }
+
+    #[must_use]
+    #[inline]
+    /// Set the stock's contract ID, if it is already known.
+    pub fn contract_id(mut self, contract_id: ContractId) -> Self {
+        self.contract_id = contract_id;
+        self
+    }
+
+    #[must_use]
+    #[inline]
+    /// Set the stock's local symbol, like "BRK B".
+    pub fn local_symbol(mut self, local_symbol: impl Into<String>) -> Self {
+        self.local_symbol = local_symbol.into();
+        self
+    }
+
+    /// Build the [`Stock`].
+    ///
+    /// # Errors
+    /// Returns an error if the symbol is empty or if the primary exchange or currency have not
+    /// been set.
+    pub fn build(self) -> Result<Stock, BuildStockError> {
+        if self.symbol.trim().is_empty() {
+            return Err(BuildStockError::EmptySymbol);
+        }
+        Ok(Stock {
+            contract_id: self.contract_id,
+            min_tick: NotNan(DEFAULT_MIN_TICK),
+            symbol: self.symbol,
+            exchange: self.exchange,
+            primary_exchange: self
+                .primary_exchange
+                .ok_or(BuildStockError::MissingData("primary_exchange"))?,
+            stock_type: String::default(),
+            security_ids: Vec::default(),
+            sector: String::default(),
+            trading_class: String::default(),
+            currency: self
+                .currency
+                .ok_or(BuildStockError::MissingData("currency"))?,
+            local_symbol: self.local_symbol,
+            long_name: String::default(),
+            order_types: Vec::default(),
+            valid_exchanges: Vec::default(),
+            details: ContractDetails::default(),
+        })
+    }
+}
+
+/// The minimum tick size of a contract built without a round-trip to the IBKR API.
+const DEFAULT_MIN_TICK: f64 = 0.01;
+
+#[derive(Debug, Clone, PartialEq)]
+/// A builder for a [`Forex`] pair whose currencies are already known, which avoids a round-trip
+/// to the IBKR API in [`new`]. Like a [`StockBuilder`], the resulting [`Forex`] is only partially
+/// populated.
+pub struct ForexBuilder {
+    base: Currency,
+    quote: Currency,
+    exchange: Routing,
+    contract_id: ContractId,
+}
+
+impl ForexBuilder {
+    #[must_use]
+    #[inline]
+    /// Creates a new [`ForexBuilder`] that routes to IDEALPRO by default.
+    ///
+    /// # Arguments
+    /// * `base` - The currency being bought or sold.
+    /// * `quote` - The currency in which the price of `base` is given.
+    pub fn new(base: Currency, quote: Currency) -> Self {
+        Self {
+            base,
+            quote,
+            exchange: Routing::Primary(Primary::IbForexPro),
+            contract_id: ContractId(0),
+        }
+    }
+
+    #[must_use]
+    #[inline]
+    /// Set the exchange to which orders and data requests are routed.
+    pub fn exchange(mut self, exchange: Routing) -> Self {
+        self.exchange = exchange;
+        self
+    }
+
+    #[must_use]
+    #[inline]
+    /// Set the pair's contract ID, if it is already known.
+    pub fn contract_id(mut self, contract_id: ContractId) -> Self {
+        self.contract_id = contract_id;
+        self
+    }
+
+    /// Build the [`Forex`], with the symbol, local symbol, and trading class described in
+    /// [`Forex::from_pair`].
+    ///
+    /// # Errors
+    /// Returns an error if the base and quote currencies are the same.
+    pub fn build(self) -> Result<Forex, SameCurrencyPairError> {
+        let mut forex = Forex::from_pair(self.base, self.quote, self.exchange)?;
+        forex.contract_id = self.contract_id;
+        forex.min_tick = NotNan(DEFAULT_MIN_TICK);
+        Ok(forex)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// A builder for a [`SecOption`] whose symbol, class, strike, expiration date, and currency are
+/// already known, which avoids a round-trip to the IBKR API in [`new`]. Like a [`StockBuilder`],
+/// the resulting [`SecOption`] is only partially populated.
+pub struct SecOptionBuilder {
+    symbol: String,
+    class: SecOptionClass,
+    strike: f64,
+    expiration_date: NaiveDate,
+    exchange: Routing,
+    currency: Option<Currency>,
+    multiplier: f64,
+    contract_id: ContractId,
+    underlying_contract_id: ContractId,
+    trading_class: String,
+    local_symbol: String,
+}
+
+impl SecOptionBuilder {
+    #[must_use]
+    #[inline]
+    /// Creates a new [`SecOptionBuilder`] for an option with a multiplier of 100 that routes to
+    /// [`Routing::Smart`] by default.
+    ///
+    /// # Arguments
+    /// * `symbol` - The symbol of the option's underlying, like "AAPL".
+    /// * `class` - Whether the option is a call or a put.
+    /// * `strike` - The option's strike price.
+    /// * `expiration_date` - The option's last trading date.
+    pub fn new(
+        symbol: impl Into<String>,
+        class: SecOptionClass,
+        strike: f64,
+        expiration_date: NaiveDate,
+    ) -> Self {
+        Self {
+            symbol: symbol.into(),
+            class,
+            strike,
+            expiration_date,
+            exchange: Routing::Smart,
+            currency: None,
+            multiplier: 100.0,
+            contract_id: ContractId(0),
+            underlying_contract_id: ContractId(0),
+            trading_class: String::default(),
+            local_symbol: String::default(),
+        }
+    }
+
+    #[must_use]
+    #[inline]
+    /// Set the exchange to which orders and data requests are routed.
+    pub fn exchange(mut self, exchange: Routing) -> Self {
+        self.exchange = exchange;
+        self
+    }
+
+    #[must_use]
+    #[inline]
+    /// Set the option's currency.
+    pub fn currency(mut self, currency: Currency) -> Self {
+        self.currency = Some(currency);
+        self
+    }
+
+    #[must_use]
+    #[inline]
+    /// Set the option's multiplier, which may be fractional.
+    pub fn multiplier(mut self, multiplier: f64) -> Self {
+        self.multiplier = multiplier;
+        self
+    }
+
+    #[must_use]
+    #[inline]
+    /// Set the option's contract ID, if it is already known.
+    pub fn contract_id(mut self, contract_id: ContractId) -> Self {
+        self.contract_id = contract_id;
+        self
+    }
+
+    #[must_use]
+    #[inline]
+    /// Set the contract ID of the option's underlying, if it is already known.
+    pub fn underlying_contract_id(mut self, underlying_contract_id: ContractId) -> Self {
+        self.underlying_contract_id = underlying_contract_id;
+        self
+    }
+
+    #[must_use]
+    #[inline]
+    /// Set the option's trading class, like "SPXW" for the weekly SPX options.
+    pub fn trading_class(mut self, trading_class: impl Into<String>) -> Self {
+        self.trading_class = trading_class.into();
+        self
+    }
+
+    #[must_use]
+    #[inline]
+    /// Set the option's local symbol, like "AAPL  250117C00190000".
+    pub fn local_symbol(mut self, local_symbol: impl Into<String>) -> Self {
+        self.local_symbol = local_symbol.into();
+        self
+    }
+
+    /// Build the [`SecOption`]. Its contract month is the month of its expiration date.
+    ///
+    /// # Errors
+    /// Returns an error if the symbol is empty, if the currency has not been set, or if the
+    /// strike or multiplier is not a positive number.
+    pub fn build(self) -> Result<SecOption, BuildSecOptionError> {
+        if self.symbol.trim().is_empty() {
+            return Err(BuildSecOptionError::EmptySymbol);
+        }
+        let positive = |value: f64, field_name| {
+            if value > 0.0 {
+                Ok(NotNan(value))
+            } else {
+                Err(BuildSecOptionError::InvalidData(field_name))
+            }
+        };
+        let inner = SecOptionInner {
+            contract_id: self.contract_id,
+            min_tick: NotNan(DEFAULT_MIN_TICK),
+            symbol: self.symbol,
+            exchange: self.exchange,
+            strike: positive(self.strike, "strike")?,
+            multiplier: positive(self.multiplier, "multiplier")?,
+            expiration_date: Some(self.expiration_date),
+            contract_month: contract_month_of(self.expiration_date),
+            underlying_contract_id: self.underlying_contract_id,
+            sector: String::default(),
+            trading_class: self.trading_class,
+            currency: self
+                .currency
+                .ok_or(BuildSecOptionError::MissingData("currency"))?,
+            local_symbol: self.local_symbol,
+            long_name: String::default(),
+            order_types: Vec::default(),
+            valid_exchanges: Vec::default(),
+            details: ContractDetails::default(),
+        };
+        Ok(SecOption::from_components(self.class, inner))
+    }
+}
+
+/// Split the [`Display`](std::fmt::Display) summary of a contract, like
+/// "AAPL STK SMART/NASDAQ USD (conid 265598)", into its symbol, exchange, currency, and contract ID.
+/// The contract ID is optional and defaults to 0.
+fn parse_summary(
+    s: &str,
+    expected: ContractType,
+) -> Result<(&str, &str, Currency, ContractId), ParseContractError> {
+    let format_err = || ParseContractError::Format(s.to_owned());
+    let mut parts = s.split_whitespace();
+    let (Some(symbol), Some(contract_type), Some(exchange), Some(currency)) =
+        (parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        return Err(format_err());
+    };
+    let contract_id = match (parts.next(), parts.next(), parts.next()) {
+        (None, None, None) => ContractId(0),
+        (Some("(conid"), Some(id), None) => {
+            id.strip_suffix(')').ok_or_else(format_err)?.parse()?
+        }
+        _ => return Err(format_err()),
     };
+    let found = contract_type.parse()?;
+    if found != expected {
+        return Err(UnexpectedSecurityType { expected, found }.into());
+    }
+    Ok((symbol, exchange, currency.parse()?, contract_id))
+}
 
-    #[derive(Debug, Clone, PartialEq)]
-    pub struct SecurityOutMsg<'s> {
-        pub contract_id: ContractId,
-        pub symbol: &'s str,
-        pub security_type: &'static str,
-        pub expiration_date: Option<NaiveDate>,
-        pub strike: Option<f64>,
-        pub right: Option<&'static str>,
-        pub multiplier: Option<u32>,
-        pub exchange: Routing,
-        pub primary_exchange: Option<Primary>,
-        pub currency: Currency,
-        pub local_symbol: &'s str,
-        pub trading_class: Option<&'s str>,
+impl FromStr for Stock {
+    type Err = ParseContractError;
+
+    /// Parse a [`Stock`] from its [`Display`](std::fmt::Display) summary, like
+    /// "AAPL STK SMART/NASDAQ USD (conid 265598)". If the exchange is not of the form
+    /// "ROUTING/PRIMARY", orders are routed to the primary exchange. Like a [`StockBuilder`], the
+    /// resulting [`Stock`] is only partially populated.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (symbol, exchange, currency, contract_id) = parse_summary(s, ContractType::Stock)?;
+        let (exchange, primary_exchange) = match exchange.split_once('/') {
+            Some((exchange, primary)) => (exchange.parse()?, primary.parse()?),
+            None => {
+                let primary = exchange.parse()?;
+                (Routing::Primary(primary), primary)
+            }
+        };
+        let mut stock = StockBuilder::new(symbol)
+            .exchange(exchange)
+            .primary_exchange(primary_exchange)
+            .currency(currency)
+            .build()
+            .map_err(|_| ParseContractError::Format(s.to_owned()))?;
+        stock.contract_id = contract_id;
+        Ok(stock)
+    }
+}
+
+impl FromStr for Forex {
+    type Err = ParseContractError;
+
+    /// Parse a [`Forex`] from its [`Display`](std::fmt::Display) summary, like
+    /// "EUR CASH IDEALPRO USD (conid 12087792)". The resulting [`Forex`] is only partially
+    /// populated: its local symbol and trading class are inferred from the currency pair.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (symbol, exchange, currency, contract_id) = parse_summary(s, ContractType::Forex)?;
+        symbol.parse::<Currency>()?;
+        let pair = format!("{symbol}.{currency}");
+        Ok(Forex {
+            contract_id,
+            min_tick: NotNan::default(),
+            symbol: symbol.to_owned(),
+            exchange: exchange.parse()?,
+            trading_class: pair.clone(),
+            currency,
+            local_symbol: pair,
+            long_name: String::default(),
+            order_types: Vec::default(),
+            valid_exchanges: Vec::default(),
+            details: ContractDetails::default(),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Error)]
+/// An error returned when parsing a [`Stock`] or [`Forex`] from its summary fails.
+pub enum ParseContractError {
+    #[error("Invalid contract summary {0:?}. Expected \"SYMBOL TYPE EXCHANGE CURRENCY (conid ID)\"")]
+    /// The summary is not of the form "SYMBOL TYPE EXCHANGE CURRENCY (conid ID)"
+    Format(String),
+    #[error("{0}")]
+    /// The contract type is invalid
+    ContractType(#[from] ParseContractTypeError),
+    #[error("{0}")]
+    /// The contract type is valid, but it's not the one being parsed
+    UnexpectedSecurityType(#[from] UnexpectedSecurityType),
+    #[error("{0}")]
+    /// The exchange is invalid
+    Exchange(#[from] crate::exchange::ParseExchangeError),
+    #[error("{0}")]
+    /// The currency is invalid
+    Currency(#[from] crate::currency::ParseCurrencyError),
+    #[error("{0}")]
+    /// The contract ID is invalid
+    ContractId(#[from] ParseContractIdError),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Error)]
+/// An error returned when building a [`Stock`] with a [`StockBuilder`] fails.
+pub enum BuildStockError {
+    #[error("Cannot build a stock with an empty symbol.")]
+    /// The symbol is empty
+    EmptySymbol,
+    #[error("Missing data for field {0}")]
+    /// A required field was not set
+    MissingData(&'static str),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Error)]
+/// An error returned when building a [`SecOption`] with a [`SecOptionBuilder`] fails.
+pub enum BuildSecOptionError {
+    #[error("Cannot build an option with an empty symbol.")]
+    /// The symbol is empty
+    EmptySymbol,
+    #[error("Missing data for field {0}")]
+    /// A required field was not set
+    MissingData(&'static str),
+    #[error("Invalid data for field {0}. Expected a positive number.")]
+    /// A field was set to a value that is not a positive number
+    InvalidData(&'static str),
+}
+
+macro_rules! option_impl {
+    ($name: ident, $inner: ident) => {
+        impl $name {
+            #[must_use]
+            #[inline]
+            /// Construct a new option from its class (or right) and inner contract
+            pub fn from_components(class: SecOptionClass, inner: $inner) -> Self {
+                match class {
+                    SecOptionClass::Call => $name::Call(inner),
+                    SecOptionClass::Put => $name::Put(inner),
+                }
+            }
+
+            #[must_use]
+            #[inline]
+            /// Construct a new option from its right and inner contract, like
+            /// [`Self::from_components`].
+            pub fn new(right: OptionRight, inner: $inner) -> Self {
+                Self::from_components(right, inner)
+            }
+
+            #[must_use]
+            #[inline]
+            /// Return `true` if the option is a call option.
+            pub fn is_call(&self) -> bool {
+                matches!(self, $name::Call(_))
+            }
+
+            #[must_use]
+            #[inline]
+            /// Return `true` if the option is a put option.
+            pub fn is_put(&self) -> bool {
+                !self.is_call()
+            }
+
+            #[must_use]
+            #[inline]
+            /// Get the option's class (or right)
+            pub fn class(&self) -> SecOptionClass {
+                match self {
+                    $name::Call(_) => SecOptionClass::Call,
+                    $name::Put(_) => SecOptionClass::Put,
+                }
+            }
+
+            #[must_use]
+            #[inline]
+            /// Get the option's right, like [`Self::class`].
+            pub fn right(&self) -> OptionRight {
+                self.class()
+            }
+
+            #[must_use]
+            #[inline]
+            /// Get a reference to the inner contract's specifications.
+            pub fn as_inner_ref(&self) -> &$inner {
+                let ($name::Call(inner) | $name::Put(inner)) = self;
+                inner
+            }
+
+            #[must_use]
+            #[inline]
+            /// Transform the option into the inner contract
+            pub fn into_inner(self) -> $inner {
+                let ($name::Call(inner) | $name::Put(inner)) = self;
+                inner
+            }
+
+            #[must_use]
+            #[inline]
+            /// Unfold the option into its class and inner contract
+            pub fn unfold(self) -> (SecOptionClass, $inner) {
+                (self.class(), self.into_inner())
+            }
+
+            #[must_use]
+            #[inline]
+            /// Get the inner contract's exchange
+            pub fn exchange(&self) -> Routing {
+                self.as_inner_ref().exchange
+            }
+
+            #[must_use]
+            #[inline]
+            /// Get the inner contract's strike price
+            pub fn strike(&self) -> f64 {
+                self.as_inner_ref().strike.get()
+            }
+
+            #[must_use]
+            #[inline]
+            /// Get a reference to the inner contract's trading class
+            pub fn trading_class(&self) -> &str {
+                &self.as_inner_ref().trading_class
+            }
+
+            #[must_use]
+            #[inline]
+            /// Compute the notional value of `quantity` contracts at `price` with the inner
+            /// contract's multiplier. The result is in the contract's [`Security::currency`].
+            pub fn notional(&self, price: f64, quantity: f64) -> f64 {
+                self.as_inner_ref().notional(price, quantity)
+            }
+
+            #[must_use]
+            #[inline]
+            /// Compute the value of exercising the option when the underlying trades at
+            /// `underlying_price`, per unit of the underlying. This is never negative, as an option
+            /// that is out of the money is simply not exercised.
+            pub fn intrinsic_value(&self, underlying_price: f64) -> f64 {
+                let strike = self.strike();
+                match self.class() {
+                    SecOptionClass::Call => (underlying_price - strike).max(0.0),
+                    SecOptionClass::Put => (strike - underlying_price).max(0.0),
+                }
+            }
+
+            #[must_use]
+            #[inline]
+            /// Compute the value of exercising one contract when the underlying trades at
+            /// `underlying_price`: the [`Self::intrinsic_value`] per unit of the underlying, scaled
+            /// by the inner contract's multiplier. The result is in the contract's
+            /// [`Security::currency`].
+            pub fn intrinsic_value_per_contract(&self, underlying_price: f64) -> f64 {
+                self.intrinsic_value(underlying_price) * self.as_inner_ref().multiplier.get()
+            }
+
+            #[must_use]
+            #[inline]
+            /// Classify the option by how its strike compares to `underlying_price`. Only an
+            /// underlying price exactly at the strike is [`Moneyness::AtTheMoney`].
+            pub fn moneyness(&self, underlying_price: f64) -> Moneyness {
+                use std::cmp::Ordering;
+
+                match (self.class(), underlying_price.partial_cmp(&self.strike())) {
+                    (_, Some(Ordering::Equal)) => Moneyness::AtTheMoney,
+                    (SecOptionClass::Call, Some(Ordering::Greater))
+                    | (SecOptionClass::Put, Some(Ordering::Less)) => Moneyness::InTheMoney,
+                    _ => Moneyness::OutOfTheMoney,
+                }
+            }
+        }
+
+        impl From<(SecOptionClass, $inner)> for $name {
+            #[inline]
+            fn from(value: (SecOptionClass, $inner)) -> Self {
+                Self::from_components(value.0, value.1)
+            }
+        }
+
+        impl From<($inner, SecOptionClass)> for $name {
+            #[inline]
+            fn from(value: ($inner, SecOptionClass)) -> Self {
+                Self::from_components(value.1, value.0)
+            }
+        }
+    };
+}
+
+option_impl!(SecOption, SecOptionInner);
+
+impl SecOption {
+    #[must_use]
+    #[inline]
+    /// Get the inner contract's multiplier, which may be fractional
+    pub fn multiplier(&self) -> f64 {
+        self.as_inner_ref().multiplier.get()
     }
 
-    impl Serialize for SecurityOutMsg<'_> {
-        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
-        where
-            S: Serializer,
-        {
-            (
-                self.contract_id,
-                self.symbol,
-                self.security_type,
-                self.expiration_date.map(|d| d.format("%Y%m%d").to_string()),
-                self.strike,
-                self.right,
-                self.multiplier,
-                self.exchange,
-                self.primary_exchange,
-                self.currency,
-                self.local_symbol,
-                self.trading_class,
-            )
-                .serialize(serializer)
-        }
+    #[must_use]
+    #[inline]
+    /// Get the inner contract's expiration date, which is `None` if IBKR only reported the
+    /// contract month
+    pub fn expiration_date(&self) -> Option<NaiveDate> {
+        self.as_inner_ref().expiration_date
     }
 
-    pub trait Valid:
-        Serialize
-        + Send
-        + Sync
-        + TryFrom<Forex, Error: Into<UnexpectedSecurityType>>
-        + TryFrom<Crypto, Error: Into<UnexpectedSecurityType>>
-        + TryFrom<Stock, Error: Into<UnexpectedSecurityType>>
-        + TryFrom<Index, Error: Into<UnexpectedSecurityType>>
-        + TryFrom<SecFuture, Error: Into<UnexpectedSecurityType>>
-        + TryFrom<SecOption, Error: Into<UnexpectedSecurityType>>
-        + TryFrom<Commodity, Error: Into<UnexpectedSecurityType>>
-        + TryFrom<Contract, Error: Into<UnexpectedSecurityType>>
-        + Into<Contract>
-    {
-        fn as_out_msg(&self) -> SecurityOutMsg<'_>;
+    #[must_use]
+    #[inline]
+    /// Get the inner contract's contract month, as a year and a month from 1 to 12, which may
+    /// differ from the month of its expiration date
+    pub fn contract_month(&self) -> (i32, u8) {
+        self.as_inner_ref().contract_month
     }
 
-    impl Valid for Contract {
-        fn as_out_msg(&self) -> SecurityOutMsg<'_> {
-            match_poly!(self;
-                Self::Forex(t)
-                | Self::Crypto(t)
-                | Self::Stock(t)
-                | Self::Index(t)
-                | Self::SecFuture(t)
-                | Self::SecOption(t)
-                | Self::Commodity(t) => t.as_out_msg()
-            )
-        }
+    #[must_use]
+    #[inline]
+    /// Get the underlying security's contract ID for the inner contract
+    pub fn underlying_contract_id(&self) -> ContractId {
+        self.as_inner_ref().underlying_contract_id
     }
 
-    impl From<Infallible> for UnexpectedSecurityType {
-        fn from(_: Infallible) -> Self {
-            unreachable!()
-        }
+    #[must_use]
+    #[inline]
+    /// Get a reference to the inner contract's sector
+    pub fn sector(&self) -> &str {
+        &self.as_inner_ref().sector
     }
-}
 
-#[doc(alias = "Contract")]
-/// Attributes shared by a tradable contract or asset. All valid contracts implement this trait.
-pub trait Security: indicators::Valid {
-    /// Get the security's contract ID
-    ///
-    /// # Returns
-    /// The security's unique contract ID
-    fn contract_id(&self) -> ContractId;
-    /// Get the security's minimum tick size.
-    ///
-    /// # Returns
-    /// The security's minimum tick size
-    fn min_tick(&self) -> f64;
-    /// Get the security's symbol.
-    ///
-    /// # Returns
-    /// The security's symbol.
-    fn symbol(&self) -> &str;
-    /// Get the security's currency.
-    ///
-    /// # Returns
-    /// The security's currency.
-    fn currency(&self) -> Currency;
-    /// Get the security's local symbol.
-    ///
-    /// # Returns
-    /// The security's local symbol.
-    fn local_symbol(&self) -> &str;
-    /// Get the security's long name.
-    ///
-    /// # Returns
-    /// The security's long name.
-    fn long_name(&self) -> &str;
-    /// Get the security's order types.
+    /// Split an [OSI symbol](https://en.wikipedia.org/wiki/Option_symbol), like
+    /// "AAPL  250117C00190000", into its parts.
     ///
-    /// # Returns
-    /// The security's order types.
-    fn order_types(&self) -> &Vec<String>;
-    /// Get the security's valid exchanges.
+    /// An OSI symbol is the option's root symbol, padded with spaces to six characters, followed
+    /// by its expiration date as YYMMDD, "C" or "P", and its strike in thousandths as eight digits.
+    /// The unpadded form, like "AAPL250117C00190000", is accepted as well.
     ///
-    /// # Returns
-    /// The security's valid exchanges.
-    fn valid_exchanges(&self) -> &Vec<Routing>;
-    /// Get the security's contract type.
+    /// # Errors
+    /// Will error if `osi` is not a well-formed OSI symbol.
     ///
     /// # Returns
-    /// The security's contract type.
-    fn contract_type(&self) -> ContractType;
-}
+    /// The root symbol, expiration date, class, and strike of the option.
+    pub fn parse_osi(osi: &str) -> Result<OsiParts, InvalidOsi> {
+        // The expiration date, class, and strike always take up the last 15 characters
+        const SUFFIX_LEN: usize = 15;
 
-// =======================================
-// === Definitions of Contract Structs ===
-// =======================================
-
-macro_rules! make_contract {
-    ($( #[doc = $name_doc:expr] )? $name: ident $(,$trt: ident)?; $($field: ident: $f_type: ty),* $(,)?) => {
-        $( #[doc = $name_doc] )?
-        #[make_getters]
-        #[derive(Debug, Clone, PartialEq, $($trt)?)]
-        pub struct $name {
-            pub(crate) contract_id: ContractId,
-            pub(crate) min_tick: f64,
-            pub(crate) symbol: String,
-            $(pub(crate) $field: $f_type,)*
-            pub(crate) currency: Currency,
-            pub(crate) local_symbol: String,
-            pub(crate) long_name: String,
-            pub(crate) order_types: Vec<String>,
-            pub(crate) valid_exchanges: Vec<Routing>,
+        if !osi.is_ascii() || !(SUFFIX_LEN + 1..=SUFFIX_LEN + 6).contains(&osi.len()) {
+            return Err(InvalidOsi::Length(osi.to_owned()));
+        }
+        let (root, suffix) = osi.split_at(osi.len() - SUFFIX_LEN);
+        let symbol = root.trim_end();
+        if symbol.is_empty() || !symbol.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'.') {
+            return Err(InvalidOsi::Root(osi.to_owned()));
+        }
+        let expiration_date = NaiveDate::parse_from_str(&suffix[..6], "%y%m%d")
+            .map_err(|_| InvalidOsi::ExpirationDate(osi.to_owned()))?;
+        let class = match &suffix[6..7] {
+            "C" => SecOptionClass::Call,
+            "P" => SecOptionClass::Put,
+            _ => return Err(InvalidOsi::Class(osi.to_owned())),
+        };
+        let strike = &suffix[7..];
+        if !strike.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(InvalidOsi::Strike(osi.to_owned()));
         }
+        Ok(OsiParts {
+            symbol: symbol.to_owned(),
+            expiration_date,
+            class,
+            strike_thousandths: strike
+                .parse()
+                .map_err(|_| InvalidOsi::Strike(osi.to_owned()))?,
+        })
     }
 }
 
-make_contract!(
-    /// A [forex contract](https://interactivebrokers.github.io/tws-api/basic_contracts.html#cash), like GBPUSD.
-    Forex,
-    Security;
-    exchange: Routing,
-    trading_class: String
-);
-make_contract!(
-    /// A [crypto contract](https://interactivebrokers.github.io/tws-api/basic_contracts.html#crypto), like BTC.
-    Crypto,
-    Security;
-    trading_class: String
-);
-make_contract!(
-    /// An [equity contract](https://interactivebrokers.github.io/tws-api/basic_contracts.html#stk), like AAPL.
-    Stock,
-    Security;
-    exchange: Routing,
-    primary_exchange: Primary,
-    stock_type: String,
-    security_ids: Vec<SecurityId>,
-    sector: String,
-    trading_class: String
-);
-make_contract!(
-    /// An [index](https://interactivebrokers.github.io/tws-api/basic_contracts.html#ind), like SPX.
-    Index,
-    Security;
-    exchange: Routing
-);
-make_contract!(
-    /// A [commodity](https://interactivebrokers.github.io/tws-api/basic_contracts.html#Commodities), like XAUUSD.
-    Commodity,
-    Security;
-    exchange: Routing,
-    trading_class: String
-);
-make_contract!(
-    /// A [futures contract](https://interactivebrokers.github.io/tws-api/basic_contracts.html#fut), like FGBL MAR 23.
-    SecFuture,
-    Security;
-    exchange: Routing,
-    multiplier: u32,
-    expiration_date: NaiveDate,
-    trading_class: String,
-    underlying_contract_id: ContractId
-);
-
-make_contract!(
-    /// Helper struct to hold the fields of a [`SecOption`].
-    SecOptionInner;
-    exchange: Routing,
-    strike: f64,
-    multiplier: u32,
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// The parts of an OSI option symbol, as returned by [`SecOption::parse_osi`]. Its
+/// [`Display`](std::fmt::Display) implementation gives the padded OSI symbol.
+pub struct OsiParts {
+    symbol: String,
     expiration_date: NaiveDate,
-    underlying_contract_id: ContractId,
-    sector: String,
-    trading_class: String
-);
-
-#[derive(Debug, Clone, PartialEq, Security)]
-/// A [vanilla option contract](https://interactivebrokers.github.io/tws-api/basic_contracts.html#opt), like P BMW  20221216 72 M.
-pub enum SecOption {
-    /// A vanilla call option, defined by the following payoff function: max(S<sub>T</sub> - K, 0)
-    Call(SecOptionInner),
-    /// A vanilla put option, defined by the following payoff function: max(K - S<sub>T</sub>, 0)
-    Put(SecOptionInner),
+    class: SecOptionClass,
+    strike_thousandths: u32,
 }
 
-impl SecOption {
+impl OsiParts {
     #[must_use]
     #[inline]
-    /// Construct a new option from its class and inner contract
-    pub fn from_components(class: SecOptionClass, inner: SecOptionInner) -> Self {
-        match class {
-            SecOptionClass::Call => SecOption::Call(inner),
-            SecOptionClass::Put => SecOption::Put(inner),
-        }
+    /// Get the root symbol of the option, like "AAPL".
+    pub fn symbol(&self) -> &str {
+        &self.symbol
     }
 
     #[must_use]
     #[inline]
-    /// Return `true` if the option is a call option.
-    pub fn is_call(&self) -> bool {
-        matches!(self, SecOption::Call(_))
+    /// Get the expiration date of the option.
+    pub const fn expiration_date(&self) -> NaiveDate {
+        self.expiration_date
     }
 
     #[must_use]
     #[inline]
-    /// Return `true` if the option is a put option.
-    pub fn is_put(&self) -> bool {
-        !self.is_call()
+    /// Get whether the option is a call or a put.
+    pub const fn class(&self) -> SecOptionClass {
+        self.class
     }
 
     #[must_use]
     #[inline]
-    /// Get the option's class
-    pub fn class(&self) -> SecOptionClass {
-        match self {
-            SecOption::Call(_) => SecOptionClass::Call,
-            SecOption::Put(_) => SecOptionClass::Put,
-        }
+    /// Get the strike of the option. OSI strikes have exactly three decimal places, so the strike
+    /// is the nearest `f64` to a whole number of thousandths.
+    pub fn strike(&self) -> f64 {
+        f64::from(self.strike_thousandths) / 1000.0
+    }
+}
+
+impl std::fmt::Display for OsiParts {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:<6}{}{}{:08}",
+            self.symbol,
+            self.expiration_date.format("%y%m%d"),
+            char::from(self.class),
+            self.strike_thousandths
+        )
     }
+}
+
+#[derive(Debug, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Error)]
+/// Represents all the possible ways an OSI option symbol could be invalid.
+pub enum InvalidOsi {
+    /// The provided symbol is not between 16 and 21 ASCII characters.
+    #[error("Invalid length. An OSI symbol is a root of at most 6 characters followed by 15 more. {0}")]
+    Length(String),
+    /// The root symbol is empty or contains characters other than letters, digits, and `.`.
+    #[error("Invalid root symbol for {0}.")]
+    Root(String),
+    /// The expiration date is not a valid YYMMDD date.
+    #[error("Invalid expiration date for {0}. Expected YYMMDD after the root symbol.")]
+    ExpirationDate(String),
+    /// The class is neither "C" nor "P".
+    #[error("Invalid option class for {0}. Expected \"C\" or \"P\" after the expiration date.")]
+    Class(String),
+    /// The strike is not eight digits.
+    #[error("Invalid strike for {0}. Expected eight digits after the option class.")]
+    Strike(String),
+}
+
+make_contract!(
+    /// Helper struct to hold the fields of a [`SecFutureOption`].
+    SecFutureOptionInner;
+    exchange: Routing,
+    strike: NotNan,
+    multiplier: NotNan,
+    expiration_date: NaiveDate,
+    underlying_future_contract_id: ContractId,
+    trading_class: String
+);
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Security)]
+#[cfg_attr(feature = "serde-full", derive(Serialize, Deserialize))]
+/// An [option on a futures contract](https://interactivebrokers.github.io/tws-api/basic_contracts.html#fop), like C ESZ4 20241220 6000 M.
+pub enum SecFutureOption {
+    /// A call option on a future, defined by the following payoff function: max(F<sub>T</sub> - K, 0)
+    Call(SecFutureOptionInner),
+    /// A put option on a future, defined by the following payoff function: max(K - F<sub>T</sub>, 0)
+    Put(SecFutureOptionInner),
+}
+
+option_impl!(SecFutureOption, SecFutureOptionInner);
 
+impl SecFutureOption {
     #[must_use]
     #[inline]
-    /// Get a reference to the inner contract's specifications.
-    pub fn as_inner_ref(&self) -> &SecOptionInner {
-        let (SecOption::Call(inner) | SecOption::Put(inner)) = self;
-        inner
+    /// Get the inner contract's multiplier, which may be fractional
+    pub fn multiplier(&self) -> f64 {
+        self.as_inner_ref().multiplier.get()
     }
 
     #[must_use]
     #[inline]
-    /// Transform the option into the inner contract
-    pub fn into_inner(self) -> SecOptionInner {
-        let (SecOption::Call(inner) | SecOption::Put(inner)) = self;
-        inner
+    /// Get the inner contract's expiration date
+    pub fn expiration_date(&self) -> NaiveDate {
+        self.as_inner_ref().expiration_date
     }
 
     #[must_use]
     #[inline]
-    /// Unfold the option into its class and inner contract
-    pub fn unfold(self) -> (SecOptionClass, SecOptionInner) {
-        (self.class(), self.into_inner())
+    /// Get the contract ID of the underlying [`SecFuture`] for the inner contract
+    pub fn underlying_future_contract_id(&self) -> ContractId {
+        self.as_inner_ref().underlying_future_contract_id
     }
+}
+
+make_contract!(
+    /// Helper struct to hold the fields of a [`Warrant`].
+    WarrantInner;
+    exchange: Routing,
+    strike: NotNan,
+    multiplier: NotNan,
+    expiration_date: NaiveDate,
+    underlying_contract_id: ContractId,
+    trading_class: String,
+    issuer: String
+);
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Security)]
+#[cfg_attr(feature = "serde-full", derive(Serialize, Deserialize))]
+/// A [warrant](https://interactivebrokers.github.io/tws-api/basic_contracts.html#war), like an exchange-listed call warrant on SAP.
+pub enum Warrant {
+    /// A call warrant, defined by the following payoff function: max(S<sub>T</sub> - K, 0)
+    Call(WarrantInner),
+    /// A put warrant, defined by the following payoff function: max(K - S<sub>T</sub>, 0)
+    Put(WarrantInner),
+}
+
+option_impl!(Warrant, WarrantInner);
 
+impl Warrant {
     #[must_use]
     #[inline]
-    /// Get the inner contract's exchange
-    pub fn exchange(&self) -> Routing {
-        self.as_inner_ref().exchange
+    /// Get the inner contract's multiplier, which may be fractional
+    pub fn multiplier(&self) -> f64 {
+        self.as_inner_ref().multiplier.get()
     }
 
     #[must_use]
     #[inline]
-    /// Get the inner contract's strike price
-    pub fn strike(&self) -> f64 {
-        self.as_inner_ref().strike
+    /// Get the inner contract's expiration date
+    pub fn expiration_date(&self) -> NaiveDate {
+        self.as_inner_ref().expiration_date
     }
 
     #[must_use]
     #[inline]
-    /// Get the inner contract's multiplier
-    pub fn multiplier(&self) -> u32 {
-        self.as_inner_ref().multiplier
+    /// Get the underlying security's contract ID for the inner contract
+    pub fn underlying_contract_id(&self) -> ContractId {
+        self.as_inner_ref().underlying_contract_id
     }
 
     #[must_use]
     #[inline]
-    /// Get the inner contract's expiration date
-    pub fn expiration_date(&self) -> NaiveDate {
-        self.as_inner_ref().expiration_date
+    /// Get the issuer of the inner contract, which IBKR reports as the last word of its long name,
+    /// or an empty string for a partially populated warrant
+    pub fn issuer(&self) -> &str {
+        self.as_inner_ref().issuer.as_str()
     }
+}
+
+make_contract!(
+    /// A structured product, like a turbo or a certificate listed on a European exchange, which
+    /// IBKR reports with the security type "IOPT".
+    StructuredProduct,
+    Security;
+    exchange: Routing,
+    multiplier: NotNan,
+    expiration_date: NaiveDate,
+    trading_class: String
+);
+
+make_contract!(
+    /// A [combo contract](https://interactivebrokers.github.io/tws-api/basic_contracts.html#bag), like a vertical spread, whose legs are executed together as a single order.
+    Combo,
+    Security;
+    exchange: Routing,
+    legs: Vec<ComboLeg>,
+    delta_neutral_contract: Option<DeltaNeutralContract>
+);
 
+impl Combo {
     #[must_use]
-    #[inline]
-    /// Get the underlying security's contract ID for the inner contract
-    pub fn underlying_contract_id(&self) -> ContractId {
-        self.as_inner_ref().underlying_contract_id
+    /// Create a new combo from its legs.
+    ///
+    /// IBKR does not report contract details for combos, so the combo has no contract ID, order
+    /// types, valid exchanges, or details, and its minimum tick size defaults to 0.01.
+    ///
+    /// # Arguments
+    /// * `symbol` - The symbol of the legs' underlying, like "AAPL".
+    /// * `currency` - The currency in which the legs are traded.
+    /// * `exchange` - The exchange to which the combo will be routed.
+    /// * `legs` - The legs that make up the combo.
+    ///
+    /// # Returns
+    /// The new combo.
+    pub fn new(
+        symbol: impl Into<String>,
+        currency: Currency,
+        exchange: Routing,
+        legs: Vec<ComboLeg>,
+    ) -> Self {
+        Self {
+            contract_id: ContractId(0),
+            min_tick: NotNan(0.01),
+            symbol: symbol.into(),
+            exchange,
+            legs,
+            delta_neutral_contract: None,
+            currency,
+            local_symbol: String::default(),
+            long_name: String::default(),
+            order_types: Vec::default(),
+            valid_exchanges: Vec::default(),
+            details: ContractDetails::default(),
+        }
     }
 
     #[must_use]
     #[inline]
-    /// Get a reference to the inner contract's sector
-    pub fn sector(&self) -> &str {
-        &self.as_inner_ref().sector
+    /// Hedge the delta of the combo with `delta_neutral_contract` when it is ordered.
+    pub fn with_delta_neutral_contract(mut self, delta_neutral_contract: DeltaNeutralContract) -> Self {
+        self.delta_neutral_contract = Some(delta_neutral_contract);
+        self
     }
+}
 
-    #[must_use]
-    #[inline]
-    /// Get a reference to the inner contract's trading class
-    pub fn trading_class(&self) -> &str {
-        &self.as_inner_ref().trading_class
-    }
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+/// A single leg of a [`Combo`].
+pub struct ComboLeg {
+    /// The contract ID of the leg's security.
+    pub contract_id: ContractId,
+    /// The number of units of the leg's security traded per unit of the combo.
+    pub ratio: i32,
+    /// Whether the leg's security is bought or sold when the combo is bought.
+    pub action: Action,
+    /// The exchange to which the leg will be routed.
+    pub exchange: Routing,
 }
 
-impl From<(SecOptionClass, SecOptionInner)> for SecOption {
-    #[inline]
-    fn from(value: (SecOptionClass, SecOptionInner)) -> Self {
-        Self::from_components(value.0, value.1)
-    }
+#[make_getters]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+/// The underlying with which IBKR hedges the delta of an option order or a [`Combo`].
+pub struct DeltaNeutralContract {
+    /// The contract ID of the underlying.
+    pub(crate) contract_id: ContractId,
+    /// The delta of the hedge.
+    pub(crate) delta: NotNan,
+    /// The price of the underlying.
+    pub(crate) price: NotNan,
 }
 
-impl From<(SecOptionInner, SecOptionClass)> for SecOption {
+impl DeltaNeutralContract {
     #[inline]
-    fn from(value: (SecOptionInner, SecOptionClass)) -> Self {
-        Self::from_components(value.1, value.0)
+    /// Create a new delta-neutral contract.
+    ///
+    /// # Arguments
+    /// * `contract_id` - The contract ID of the underlying.
+    /// * `delta` - The delta of the hedge.
+    /// * `price` - The price of the underlying.
+    ///
+    /// # Errors
+    /// Returns an error if `delta` or `price` is NaN.
+    pub fn new(contract_id: ContractId, delta: f64, price: f64) -> Result<Self, NanError> {
+        Ok(Self {
+            contract_id,
+            delta: NotNan::new(delta)?,
+            price: NotNan::new(price)?,
+        })
     }
 }
 
-// ===============================
-// === Unimplemented Contracts ===
-// ===============================
-
-// make_contract!(Cfd; exchange: Routing);
-// make_contract!(Bond; exchange: Routing);
-// make_contract!(MutualFund; exchange: Routing);
-// make_contract!(StructuredProduct; exchange: Routing, multiplier: u32, expiration_date: NaiveDate);
-
-// #[derive(Debug, Clone, PartialEq)]
-// pub enum SecFutureOption {
-//     Call(SecOptionInner),
-//     Put(SecOptionInner),
-// }
-
-// #[derive(Debug, Clone, PartialEq)]
-// pub enum Warrant {
-//     Call(SecOptionInner),
-//     Put(SecOptionInner),
-// }
-
 macro_rules! proxy_impl {
     ($sec_type: ty, $pat: pat_param => $exp: expr, $func_name: ident) => {
         #[inline]
@@ -974,6 +4825,32 @@ fn _dummy_de_2<'de, E: ProxyExchange, Sec: Security + Clone + Debug, De: Deseria
     unreachable!()
 }
 
+#[cfg(not(feature = "serde-full"))]
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+/// Holds the fields written by the [`Serialize`] implementation of each [`Security`].
+struct SerContractHelp {
+    contract_id: ContractId,
+    security_type: String,
+    symbol: String,
+    long_name: String,
+    min_tick: NotNan,
+    exchange: Routing,
+    primary_exchange: Option<Primary>,
+    currency: Currency,
+    local_symbol: String,
+    trading_class: Option<String>,
+    expiration_date: Option<String>,
+    strike: Option<f64>,
+    option_class: Option<String>,
+    multiplier: Option<f64>,
+    #[serde(default)]
+    legs: Vec<ComboLeg>,
+    #[serde(default)]
+    delta_neutral_contract: Option<DeltaNeutralContract>,
+    #[serde(default)]
+    issuer: String,
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 struct SerProxyHelp {
     contract_type: ContractType,
@@ -985,13 +4862,19 @@ struct SerProxyHelp {
     trading_class: Option<String>,
     primary_exchange: Option<Primary>,
     expiration_date: Option<NaiveDate>,
-    multiplier: Option<u32>,
+    #[serde(default)]
+    contract_month: Option<(i32, u8)>,
+    multiplier: Option<f64>,
     option_type: Option<SecOptionClass>,
     strike: Option<f64>,
 }
 
 #[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Serialize, Deserialize)]
-/// The possible option classes
+/// The possible option classes, also known as an option's right.
+///
+/// An option can be built from its class with `from_components`, like
+/// [`SecOption::from_components`], which avoids matching on the class when iterating over both
+/// classes of an option chain.
 pub enum SecOptionClass {
     /// A call option
     Call,
@@ -999,6 +4882,11 @@ pub enum SecOptionClass {
     Put,
 }
 
+/// The right of an option, whether it is a call or a put. This is the same type as
+/// [`SecOptionClass`], so it renders as "C" or "P" and parses from those codes or from "CALL" and
+/// "PUT".
+pub type OptionRight = SecOptionClass;
+
 impl From<SecOptionClass> for char {
     fn from(value: SecOptionClass) -> Self {
         match value {
@@ -1008,6 +4896,47 @@ impl From<SecOptionClass> for char {
     }
 }
 
+impl std::fmt::Display for SecOptionClass {
+    /// Write the class as its TWS code, "C" or "P".
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", char::from(*self))
+    }
+}
+
+#[derive(Debug, Clone, Error)]
+#[error(
+    "Invalid value encountered when attempting to parse option class. Expected C, P, CALL, or PUT; found: {0}"
+)]
+/// An error returned when parsing a [`SecOptionClass`] fails.
+pub struct ParseSecOptionClassError(pub String);
+
+impl FromStr for SecOptionClass {
+    type Err = ParseSecOptionClassError;
+
+    /// Parse an option class from its TWS code, "C" or "P", or from "CALL" or "PUT", ignoring
+    /// case.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_uppercase().as_str() {
+            "C" | "CALL" => Ok(Self::Call),
+            "P" | "PUT" => Ok(Self::Put),
+            _ => Err(ParseSecOptionClassError(s.to_owned())),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Serialize, Deserialize)]
+/// How the strike of an option compares to the price of its underlying, from
+/// [`SecOption::moneyness`].
+pub enum Moneyness {
+    /// Exercising the option is worth something, like a call whose strike is below the underlying
+    /// price
+    InTheMoney,
+    /// The strike is exactly the underlying price
+    AtTheMoney,
+    /// Exercising the option is worthless, like a call whose strike is above the underlying price
+    OutOfTheMoney,
+}
+
 impl<S: Security + Clone + Debug, E: ProxyExchange> From<Proxy<S, E>> for SerProxyHelp {
     #[allow(clippy::too_many_lines)]
     fn from(value: Proxy<S, E>) -> Self {
@@ -1026,16 +4955,13 @@ impl<S: Security + Clone + Debug, E: ProxyExchange> From<Proxy<S, E>> for SerPro
                 trading_class: Some(stk.trading_class),
                 primary_exchange: E::get_primary(stk.primary_exchange),
                 expiration_date: None,
+                contract_month: None,
                 multiplier: None,
                 strike: None,
                 option_type: None,
             },
             Contract::SecOption(opt) => {
-                let option_type = Some(if opt.is_call() {
-                    SecOptionClass::Call
-                } else {
-                    SecOptionClass::Put
-                });
+                let option_type = Some(opt.class());
                 let opt = opt.into_inner();
                 Self {
                     contract_type,
@@ -1046,9 +4972,48 @@ impl<S: Security + Clone + Debug, E: ProxyExchange> From<Proxy<S, E>> for SerPro
                     exchange: E::get_exchange(opt.exchange),
                     trading_class: Some(opt.trading_class),
                     primary_exchange: None,
-                    expiration_date: Some(opt.expiration_date),
-                    multiplier: Some(opt.multiplier),
-                    strike: Some(opt.strike),
+                    expiration_date: opt.expiration_date,
+                    contract_month: Some(opt.contract_month),
+                    multiplier: Some(opt.multiplier.get()),
+                    strike: Some(opt.strike.get()),
+                    option_type,
+                }
+            }
+            Contract::SecFutureOption(fop) => {
+                let option_type = Some(fop.class());
+                let fop = fop.into_inner();
+                Self {
+                    contract_type,
+                    contract_id,
+                    symbol: fop.symbol,
+                    currency,
+                    local_symbol: fop.local_symbol,
+                    exchange: E::get_exchange(fop.exchange),
+                    trading_class: Some(fop.trading_class),
+                    primary_exchange: None,
+                    expiration_date: Some(fop.expiration_date),
+                    contract_month: None,
+                    multiplier: Some(fop.multiplier.get()),
+                    strike: Some(fop.strike.get()),
+                    option_type,
+                }
+            }
+            Contract::Warrant(war) => {
+                let option_type = Some(war.class());
+                let war = war.into_inner();
+                Self {
+                    contract_type,
+                    contract_id,
+                    symbol: war.symbol,
+                    currency,
+                    local_symbol: war.local_symbol,
+                    exchange: E::get_exchange(war.exchange),
+                    trading_class: Some(war.trading_class),
+                    primary_exchange: None,
+                    expiration_date: Some(war.expiration_date),
+                    contract_month: None,
+                    multiplier: Some(war.multiplier.get()),
+                    strike: Some(war.strike.get()),
                     option_type,
                 }
             }
@@ -1061,8 +5026,24 @@ impl<S: Security + Clone + Debug, E: ProxyExchange> From<Proxy<S, E>> for SerPro
                 exchange: E::get_exchange(fut.exchange),
                 trading_class: Some(fut.trading_class),
                 primary_exchange: None,
-                expiration_date: Some(fut.expiration_date),
-                multiplier: Some(fut.multiplier),
+                expiration_date: fut.expiration_date,
+                contract_month: Some(fut.contract_month),
+                multiplier: Some(fut.multiplier.get()),
+                strike: None,
+                option_type: None,
+            },
+            Contract::StructuredProduct(prod) => Self {
+                contract_type,
+                contract_id,
+                symbol: prod.symbol,
+                currency,
+                local_symbol: prod.local_symbol,
+                exchange: E::get_exchange(prod.exchange),
+                trading_class: Some(prod.trading_class),
+                primary_exchange: None,
+                expiration_date: Some(prod.expiration_date),
+                contract_month: None,
+                multiplier: Some(prod.multiplier.get()),
                 strike: None,
                 option_type: None,
             },
@@ -1076,6 +5057,7 @@ impl<S: Security + Clone + Debug, E: ProxyExchange> From<Proxy<S, E>> for SerPro
                 trading_class: Some(cmdty.trading_class),
                 primary_exchange: None,
                 expiration_date: None,
+                contract_month: None,
                 multiplier: None,
                 strike: None,
                 option_type: None,
@@ -1086,10 +5068,26 @@ impl<S: Security + Clone + Debug, E: ProxyExchange> From<Proxy<S, E>> for SerPro
                 symbol: crypto.symbol,
                 currency,
                 local_symbol: crypto.local_symbol,
-                exchange: Some(Routing::Primary(Primary::PaxosCryptoExchange)),
+                exchange: E::get_exchange(crypto.exchange),
                 trading_class: Some(crypto.trading_class),
                 primary_exchange: None,
                 expiration_date: None,
+                contract_month: None,
+                multiplier: None,
+                strike: None,
+                option_type: None,
+            },
+            Contract::Combo(combo) => Self {
+                contract_type,
+                contract_id,
+                symbol: combo.symbol,
+                currency,
+                local_symbol: combo.local_symbol,
+                exchange: E::get_exchange(combo.exchange),
+                trading_class: None,
+                primary_exchange: None,
+                expiration_date: None,
+                contract_month: None,
                 multiplier: None,
                 strike: None,
                 option_type: None,
@@ -1104,6 +5102,67 @@ impl<S: Security + Clone + Debug, E: ProxyExchange> From<Proxy<S, E>> for SerPro
                 trading_class: None,
                 primary_exchange: None,
                 expiration_date: None,
+                contract_month: None,
+                multiplier: None,
+                strike: None,
+                option_type: None,
+            },
+            Contract::Bond(bond) => Self {
+                contract_type,
+                contract_id,
+                symbol: bond.symbol,
+                currency,
+                local_symbol: bond.local_symbol,
+                exchange: E::get_exchange(bond.exchange),
+                trading_class: Some(bond.trading_class),
+                primary_exchange: None,
+                expiration_date: Some(bond.maturity_date),
+                contract_month: None,
+                multiplier: None,
+                strike: None,
+                option_type: None,
+            },
+            Contract::Cfd(cfd) => Self {
+                contract_type,
+                contract_id,
+                symbol: cfd.symbol,
+                currency,
+                local_symbol: cfd.local_symbol,
+                exchange: E::get_exchange(cfd.exchange),
+                trading_class: Some(cfd.trading_class),
+                primary_exchange: None,
+                expiration_date: None,
+                contract_month: None,
+                multiplier: None,
+                strike: None,
+                option_type: None,
+            },
+            Contract::ContinuousFuture(cont) => Self {
+                contract_type,
+                contract_id,
+                symbol: cont.symbol,
+                currency,
+                local_symbol: cont.local_symbol,
+                exchange: E::get_exchange(cont.exchange),
+                trading_class: Some(cont.trading_class),
+                primary_exchange: None,
+                expiration_date: None,
+                contract_month: None,
+                multiplier: None,
+                strike: None,
+                option_type: None,
+            },
+            Contract::MutualFund(fund) => Self {
+                contract_type,
+                contract_id,
+                symbol: fund.symbol,
+                currency,
+                local_symbol: fund.local_symbol,
+                exchange: E::get_exchange(fund.exchange),
+                trading_class: Some(fund.trading_class),
+                primary_exchange: None,
+                expiration_date: None,
+                contract_month: None,
                 multiplier: None,
                 strike: None,
                 option_type: None,
@@ -1118,6 +5177,7 @@ impl<S: Security + Clone + Debug, E: ProxyExchange> From<Proxy<S, E>> for SerPro
                 trading_class: Some(fx.trading_class),
                 primary_exchange: None,
                 expiration_date: None,
+                contract_month: None,
                 multiplier: None,
                 strike: None,
                 option_type: None,
@@ -1144,14 +5204,20 @@ impl<S: Security + Clone + Debug, E: ProxyExchange> TryFrom<SerProxyHelp> for Pr
             strike,
             option_type,
             expiration_date,
+            contract_month,
         } = value;
 
         let (exchange, primary_exchange) = E::deserialize(exchange, primary_exchange);
+        // Only a future or an option may lack an expiration date, as long as its contract month is
+        // known
+        let contract_month = contract_month.or_else(|| expiration_date.map(contract_month_of));
 
         let inner: Result<S, UnexpectedSecurityType> = match contract_type {
+            // The legs of a combo can't be recovered from the other fields
+            ContractType::Combo => return Err(SerializeProxyError::MissingData("legs")),
             ContractType::Stock => Stock {
                 contract_id,
-                min_tick: f64::default(),
+                min_tick: NotNan::default(),
                 symbol,
                 currency,
                 local_symbol,
@@ -1166,12 +5232,13 @@ impl<S: Security + Clone + Debug, E: ProxyExchange> TryFrom<SerProxyHelp> for Pr
                 exchange: exchange.ok_or(SerializeProxyError::MissingData("exchange"))?,
                 sector: String::default(),
                 valid_exchanges: Vec::default(),
+                details: ContractDetails::default(),
             }
             .try_into()
             .map_err(|e: <S as TryFrom<Stock>>::Error| e.into()),
             ContractType::Index => Index {
                 contract_id,
-                min_tick: f64::default(),
+                min_tick: NotNan::default(),
                 symbol,
                 exchange: exchange.ok_or(SerializeProxyError::MissingData("exchange"))?,
                 currency,
@@ -1179,14 +5246,16 @@ impl<S: Security + Clone + Debug, E: ProxyExchange> TryFrom<SerProxyHelp> for Pr
                 long_name: String::default(),
                 order_types: Vec::default(),
                 valid_exchanges: Vec::default(),
+                details: ContractDetails::default(),
             }
             .try_into()
             .map_err(|e: <S as TryFrom<Index>>::Error| e.into()),
             ContractType::Commodity => Commodity {
                 contract_id,
-                min_tick: f64::default(),
+                min_tick: NotNan::default(),
                 symbol,
                 exchange: exchange.ok_or(SerializeProxyError::MissingData("exchange"))?,
+                commodity_type: String::default(),
                 trading_class: trading_class
                     .ok_or(SerializeProxyError::MissingData("trading_class"))?,
                 currency,
@@ -1194,13 +5263,15 @@ impl<S: Security + Clone + Debug, E: ProxyExchange> TryFrom<SerProxyHelp> for Pr
                 long_name: String::default(),
                 order_types: Vec::default(),
                 valid_exchanges: Vec::default(),
+                details: ContractDetails::default(),
             }
             .try_into()
             .map_err(|e: <S as TryFrom<Commodity>>::Error| e.into()),
             ContractType::Crypto => Crypto {
                 contract_id,
-                min_tick: f64::default(),
+                min_tick: NotNan::default(),
                 symbol,
+                exchange: exchange.ok_or(SerializeProxyError::MissingData("exchange"))?,
                 trading_class: trading_class
                     .ok_or(SerializeProxyError::MissingData("trading_class"))?,
                 currency,
@@ -1208,13 +5279,17 @@ impl<S: Security + Clone + Debug, E: ProxyExchange> TryFrom<SerProxyHelp> for Pr
                 long_name: String::default(),
                 order_types: Vec::default(),
                 valid_exchanges: Vec::default(),
+                details: ContractDetails::default(),
             }
             .try_into()
             .map_err(|e: <S as TryFrom<Crypto>>::Error| e.into()),
             ContractType::Forex => Forex {
                 contract_id,
-                min_tick: f64::default(),
-                symbol,
+                min_tick: NotNan::default(),
+                symbol: symbol
+                    .parse::<Currency>()
+                    .map_err(|_| SerializeProxyError::InvalidData("symbol"))
+                    .map(|_| symbol)?,
                 exchange: exchange.ok_or(SerializeProxyError::MissingData("exchange"))?,
                 trading_class: trading_class
                     .ok_or(SerializeProxyError::MissingData("trading_class"))?,
@@ -1223,16 +5298,18 @@ impl<S: Security + Clone + Debug, E: ProxyExchange> TryFrom<SerProxyHelp> for Pr
                 long_name: String::default(),
                 order_types: Vec::default(),
                 valid_exchanges: Vec::default(),
+                details: ContractDetails::default(),
             }
             .try_into()
             .map_err(|e: <S as TryFrom<Forex>>::Error| e.into()),
             ContractType::SecFuture => SecFuture {
                 contract_id,
-                min_tick: f64::default(),
+                min_tick: NotNan::default(),
                 symbol,
                 exchange: exchange.ok_or(SerializeProxyError::MissingData("exchange"))?,
-                multiplier: multiplier.ok_or(SerializeProxyError::MissingData("multiplier"))?,
-                expiration_date: expiration_date
+                multiplier: not_nan(multiplier, "multiplier")?,
+                expiration_date,
+                contract_month: contract_month
                     .ok_or(SerializeProxyError::MissingData("expiration_date"))?,
                 trading_class: trading_class
                     .ok_or(SerializeProxyError::MissingData("trading_class"))?,
@@ -1242,18 +5319,39 @@ impl<S: Security + Clone + Debug, E: ProxyExchange> TryFrom<SerProxyHelp> for Pr
                 long_name: String::default(),
                 order_types: Vec::default(),
                 valid_exchanges: Vec::default(),
+                details: ContractDetails::default(),
             }
             .try_into()
             .map_err(|e: <S as TryFrom<SecFuture>>::Error| e.into()),
+            ContractType::StructuredProduct => StructuredProduct {
+                contract_id,
+                min_tick: NotNan::default(),
+                symbol,
+                exchange: exchange.ok_or(SerializeProxyError::MissingData("exchange"))?,
+                multiplier: not_nan(multiplier, "multiplier")?,
+                expiration_date: expiration_date
+                    .ok_or(SerializeProxyError::MissingData("expiration_date"))?,
+                trading_class: trading_class
+                    .ok_or(SerializeProxyError::MissingData("trading_class"))?,
+                currency,
+                local_symbol,
+                long_name: String::default(),
+                order_types: Vec::default(),
+                valid_exchanges: Vec::default(),
+                details: ContractDetails::default(),
+            }
+            .try_into()
+            .map_err(|e: <S as TryFrom<StructuredProduct>>::Error| e.into()),
             ContractType::SecOption => {
                 let inner = SecOptionInner {
                     contract_id,
-                    min_tick: f64::default(),
+                    min_tick: NotNan::default(),
                     symbol,
                     exchange: exchange.ok_or(SerializeProxyError::MissingData("exchange"))?,
-                    strike: strike.ok_or(SerializeProxyError::MissingData("strike"))?,
-                    multiplier: multiplier.ok_or(SerializeProxyError::MissingData("multiplier"))?,
-                    expiration_date: expiration_date
+                    strike: not_nan(strike, "strike")?,
+                    multiplier: not_nan(multiplier, "multiplier")?,
+                    expiration_date,
+                    contract_month: contract_month
                         .ok_or(SerializeProxyError::MissingData("expiration_date"))?,
                     underlying_contract_id: contract_id,
                     sector: String::default(),
@@ -1264,6 +5362,7 @@ impl<S: Security + Clone + Debug, E: ProxyExchange> TryFrom<SerProxyHelp> for Pr
                     long_name: String::default(),
                     order_types: Vec::default(),
                     valid_exchanges: Vec::default(),
+                    details: ContractDetails::default(),
                 };
                 match option_type.ok_or(SerializeProxyError::MissingData("option_type"))? {
                     SecOptionClass::Call => SecOption::Call(inner),
@@ -1272,6 +5371,146 @@ impl<S: Security + Clone + Debug, E: ProxyExchange> TryFrom<SerProxyHelp> for Pr
             }
             .try_into()
             .map_err(|e: <S as TryFrom<SecOption>>::Error| e.into()),
+            ContractType::SecFutureOption => {
+                let inner = SecFutureOptionInner {
+                    contract_id,
+                    min_tick: NotNan::default(),
+                    symbol,
+                    exchange: exchange.ok_or(SerializeProxyError::MissingData("exchange"))?,
+                    strike: not_nan(strike, "strike")?,
+                    multiplier: not_nan(multiplier, "multiplier")?,
+                    expiration_date: expiration_date
+                        .ok_or(SerializeProxyError::MissingData("expiration_date"))?,
+                    underlying_future_contract_id: contract_id,
+                    trading_class: trading_class
+                        .ok_or(SerializeProxyError::MissingData("trading_class"))?,
+                    currency,
+                    local_symbol,
+                    long_name: String::default(),
+                    order_types: Vec::default(),
+                    valid_exchanges: Vec::default(),
+                    details: ContractDetails::default(),
+                };
+                SecFutureOption::from_components(
+                    option_type.ok_or(SerializeProxyError::MissingData("option_type"))?,
+                    inner,
+                )
+            }
+            .try_into()
+            .map_err(|e: <S as TryFrom<SecFutureOption>>::Error| e.into()),
+            ContractType::Warrant => {
+                let inner = WarrantInner {
+                    contract_id,
+                    min_tick: NotNan::default(),
+                    symbol,
+                    exchange: exchange.ok_or(SerializeProxyError::MissingData("exchange"))?,
+                    strike: not_nan(strike, "strike")?,
+                    multiplier: not_nan(multiplier, "multiplier")?,
+                    expiration_date: expiration_date
+                        .ok_or(SerializeProxyError::MissingData("expiration_date"))?,
+                    underlying_contract_id: contract_id,
+                    trading_class: trading_class
+                        .ok_or(SerializeProxyError::MissingData("trading_class"))?,
+                    issuer: String::default(),
+                    currency,
+                    local_symbol,
+                    long_name: String::default(),
+                    order_types: Vec::default(),
+                    valid_exchanges: Vec::default(),
+                    details: ContractDetails::default(),
+                };
+                Warrant::from_components(
+                    option_type.ok_or(SerializeProxyError::MissingData("option_type"))?,
+                    inner,
+                )
+            }
+            .try_into()
+            .map_err(|e: <S as TryFrom<Warrant>>::Error| e.into()),
+            ContractType::Bond => Bond {
+                contract_id,
+                min_tick: NotNan::default(),
+                symbol,
+                exchange: exchange.ok_or(SerializeProxyError::MissingData("exchange"))?,
+                cusip: String::default(),
+                coupon: NotNan::default(),
+                maturity_date: expiration_date
+                    .ok_or(SerializeProxyError::MissingData("expiration_date"))?,
+                issue_date: String::default(),
+                ratings: String::default(),
+                bond_type: String::default(),
+                coupon_type: String::default(),
+                callable: bool::default(),
+                putable: bool::default(),
+                convertible: bool::default(),
+                security_ids: Vec::default(),
+                trading_class: trading_class
+                    .ok_or(SerializeProxyError::MissingData("trading_class"))?,
+                currency,
+                local_symbol,
+                long_name: String::default(),
+                order_types: Vec::default(),
+                valid_exchanges: Vec::default(),
+                details: ContractDetails::default(),
+            }
+            .try_into()
+            .map_err(|e: <S as TryFrom<Bond>>::Error| e.into()),
+            ContractType::Cfd => Cfd {
+                contract_id,
+                min_tick: NotNan::default(),
+                symbol,
+                exchange: exchange.ok_or(SerializeProxyError::MissingData("exchange"))?,
+                underlying_contract_id: contract_id,
+                cfd_type: String::default(),
+                trading_class: trading_class
+                    .ok_or(SerializeProxyError::MissingData("trading_class"))?,
+                currency,
+                local_symbol,
+                long_name: String::default(),
+                order_types: Vec::default(),
+                valid_exchanges: Vec::default(),
+                details: ContractDetails::default(),
+            }
+            .try_into()
+            .map_err(|e: <S as TryFrom<Cfd>>::Error| e.into()),
+            ContractType::ContinuousFuture => ContinuousFuture {
+                contract_id,
+                min_tick: NotNan::default(),
+                symbol,
+                exchange: exchange.ok_or(SerializeProxyError::MissingData("exchange"))?,
+                trading_class: trading_class
+                    .ok_or(SerializeProxyError::MissingData("trading_class"))?,
+                underlying_contract_id: contract_id,
+                currency,
+                local_symbol,
+                long_name: String::default(),
+                order_types: Vec::default(),
+                valid_exchanges: Vec::default(),
+                details: ContractDetails::default(),
+            }
+            .try_into()
+            .map_err(|e: <S as TryFrom<ContinuousFuture>>::Error| e.into()),
+            ContractType::MutualFund => MutualFund {
+                contract_id,
+                min_tick: NotNan::default(),
+                symbol,
+                exchange: exchange.ok_or(SerializeProxyError::MissingData("exchange"))?,
+                fund_family: String::default(),
+                fund_type: String::default(),
+                fund_front_load: None,
+                fund_back_load: None,
+                fund_back_load_time_interval: String::default(),
+                fund_management_fee: None,
+                trading_class: trading_class
+                    .ok_or(SerializeProxyError::MissingData("trading_class"))?,
+                currency,
+                local_symbol,
+                long_name: String::default(),
+                order_types: Vec::default(),
+                valid_exchanges: Vec::default(),
+                details: ContractDetails::default(),
+            }
+            .try_into()
+            .map_err(|e: <S as TryFrom<MutualFund>>::Error| e.into()),
         };
 
         Ok(Self {
@@ -1281,6 +5520,12 @@ impl<S: Security + Clone + Debug, E: ProxyExchange> TryFrom<SerProxyHelp> for Pr
     }
 }
 
+#[inline]
+fn not_nan(value: Option<f64>, field_name: &'static str) -> Result<NotNan, SerializeProxyError> {
+    NotNan::new(value.ok_or(SerializeProxyError::MissingData(field_name))?)
+        .map_err(|_| SerializeProxyError::InvalidData(field_name))
+}
+
 impl<S: Security + Clone + Debug, E: ProxyExchange> From<Proxy<S, E>> for ContractId {
     fn from(value: Proxy<S, E>) -> Self {
         value.inner.contract_id()
@@ -1306,15 +5551,71 @@ impl<S: Security + Clone + Debug, E: ProxyExchange> Proxy<S, E> {
     }
 
     #[inline]
-    /// Get the underlying Security's currency.
-    pub fn currency(&self) -> Currency {
-        self.inner.currency()
+    /// Get the underlying Security's currency.
+    pub fn currency(&self) -> Currency {
+        self.inner.currency()
+    }
+
+    #[inline]
+    /// Get the underlying Security's local symbol.
+    pub fn local_symbol(&self) -> &str {
+        self.inner.local_symbol()
+    }
+
+    #[inline]
+    /// Get the underlying Security's long name.
+    pub fn long_name(&self) -> &str {
+        self.inner.long_name()
+    }
+
+    #[inline]
+    /// Get the underlying Security's minimum tick size.
+    pub fn min_tick(&self) -> f64 {
+        self.inner.min_tick()
+    }
+
+    /// Resolve the proxy into the full security that it stands for, which can be used for market
+    /// data, placing orders, etc.
+    ///
+    /// This queries the contract details for the proxy's contract ID, so a position or portfolio
+    /// row can be hydrated without building a [`Query::IbContractId`] by hand. A
+    /// `Proxy<Contract, E>` hydrates into a [`Contract`] of whichever type IBKR reports.
+    ///
+    /// # Arguments
+    /// * `client` - The client with which to send the contract query.
+    ///
+    /// # Errors
+    /// Returns the same errors as [`new`]. In particular, returns
+    /// [`NewSecurityError::UnexpectedSecurityType`] if the returned contract is not an `S`.
+    ///
+    /// # Returns
+    /// The fully-defined security with the proxy's contract ID.
+    pub async fn hydrate(
+        &self,
+        client: &mut impl ClientPort,
+    ) -> Result<S, NewSecurityError> {
+        new(client, self.contract_id().into()).await
+    }
+
+    #[inline]
+    #[must_use]
+    /// Get a reference to the underlying security.
+    ///
+    /// The security only holds the information from which the proxy was decoded, so details that
+    /// IBKR does not send with it, like the long name or the valid exchanges, are left empty. Use
+    /// [`Proxy::hydrate`] to get the fully-defined security instead.
+    pub fn as_inner_ref(&self) -> &S {
+        &self.inner
     }
 
     #[inline]
-    /// Get the underlying Security's symbol.
-    pub fn local_symbol(&self) -> &str {
-        self.inner.symbol()
+    #[must_use]
+    /// Transform the proxy into the underlying security, which can be used with the order and
+    /// market data APIs.
+    ///
+    /// See [`Proxy::as_inner_ref`] for the information that the security holds.
+    pub fn into_inner(self) -> S {
+        self.inner
     }
 }
 
@@ -1326,162 +5627,483 @@ impl<E: ProxyExchange> Proxy<Contract, E> {
     proxy_impl!(Commodity, (Contract::Commodity(t), e) => Proxy::<Commodity, E> { inner: t, _exch: e }, commodity);
     proxy_impl!(SecFuture, (Contract::SecFuture(t), e) => Proxy::<SecFuture, E> { inner: t, _exch: e }, sec_future);
     proxy_impl!(SecOption, (Contract::SecOption(t), e) => Proxy::<SecOption, E> { inner: t, _exch: e }, sec_option);
+    proxy_impl!(Bond, (Contract::Bond(t), e) => Proxy::<Bond, E> { inner: t, _exch: e }, bond);
+    proxy_impl!(Cfd, (Contract::Cfd(t), e) => Proxy::<Cfd, E> { inner: t, _exch: e }, cfd);
+    proxy_impl!(MutualFund, (Contract::MutualFund(t), e) => Proxy::<MutualFund, E> { inner: t, _exch: e }, mutual_fund);
+    proxy_impl!(SecFutureOption, (Contract::SecFutureOption(t), e) => Proxy::<SecFutureOption, E> { inner: t, _exch: e }, sec_future_option);
+    proxy_impl!(Warrant, (Contract::Warrant(t), e) => Proxy::<Warrant, E> { inner: t, _exch: e }, warrant);
+    proxy_impl!(ContinuousFuture, (Contract::ContinuousFuture(t), e) => Proxy::<ContinuousFuture, E> { inner: t, _exch: e }, continuous_future);
+    proxy_impl!(StructuredProduct, (Contract::StructuredProduct(t), e) => Proxy::<StructuredProduct, E> { inner: t, _exch: e }, structured_product);
 }
 
-impl<E: ProxyExchange> Proxy<Forex, E> {
+impl<S: Security + Clone + Debug + TradingClass, E: ProxyExchange> Proxy<S, E> {
     #[inline]
     #[must_use]
-    /// Get the [`Forex`] trading class.
+    /// Get the underlying Security's trading class.
     pub fn trading_class(&self) -> &str {
         self.inner.trading_class()
     }
 }
 
-impl<E: ProxyExchange> Proxy<Crypto, E> {
+impl Proxy<Stock, NoExchange> {
     #[inline]
     #[must_use]
-    /// Get the [`Crypto`] trading class.
-    pub fn trading_class(&self) -> &str {
-        self.inner.trading_class()
+    /// Get the [`Stock`] primary exchange.
+    pub fn primary_exchange(&self) -> Primary {
+        self.inner.primary_exchange
     }
 }
 
-impl<E: ProxyExchange> Proxy<Stock, E> {
+impl<E: ProxyExchange> Proxy<SecFuture, E> {
     #[inline]
     #[must_use]
-    /// Get the [`Stock`] trading class.
-    pub fn trading_class(&self) -> &str {
-        self.inner.trading_class()
+    /// Get the [`SecFuture`] `expiration_date`, if it is known.
+    pub fn expiration_date(&self) -> Option<NaiveDate> {
+        self.inner.expiration_date
     }
-}
 
-impl Proxy<Stock, NoExchange> {
     #[inline]
     #[must_use]
-    /// Get the [`Stock`] primary exchange.
-    pub fn primary_exchange(&self) -> Primary {
-        self.inner.primary_exchange
+    /// Get the [`SecFuture`] `contract_month`, as a year and a month from 1 to 12. Position and
+    /// portfolio messages only report the expiration date, so a proxy takes its contract month
+    /// from that date.
+    pub fn contract_month(&self) -> (i32, u8) {
+        self.inner.contract_month
     }
-}
 
-impl<E: ProxyExchange> Proxy<Commodity, E> {
     #[inline]
     #[must_use]
-    /// Get the [`Commodity`] trading class.
-    pub fn trading_class(&self) -> &str {
-        self.inner.trading_class()
+    /// Get the [`SecFuture`] `multiplier`, which may be fractional.
+    pub fn multiplier(&self) -> f64 {
+        self.inner.multiplier.get()
+    }
+
+    #[inline]
+    #[must_use]
+    /// Compute the notional value of `quantity` contracts at `price`, in the contract's currency.
+    pub fn notional(&self, price: f64, quantity: f64) -> f64 {
+        self.inner.notional(price, quantity)
     }
 }
 
-impl<E: ProxyExchange> Proxy<SecFuture, E> {
+impl<E: ProxyExchange> Proxy<SecOption, E> {
     #[inline]
     #[must_use]
-    /// Get the [`SecFuture`] trading class.
-    pub fn trading_class(&self) -> &str {
-        self.inner.trading_class()
+    /// Get the [`SecOption`] `expiration_date`, if it is known.
+    pub fn expiration_date(&self) -> Option<NaiveDate> {
+        self.inner.as_inner_ref().expiration_date
     }
 
     #[inline]
     #[must_use]
-    /// Get the [`SecFuture`] `expiration_date`.
-    pub fn expiration_date(&self) -> NaiveDate {
-        self.inner.expiration_date
+    /// Get the [`SecOption`] `contract_month`, as a year and a month from 1 to 12. Position and
+    /// portfolio messages only report the expiration date, so a proxy takes its contract month
+    /// from that date.
+    pub fn contract_month(&self) -> (i32, u8) {
+        self.inner.as_inner_ref().contract_month
+    }
+
+    #[inline]
+    #[must_use]
+    /// Get the [`SecOption`] `strike` price.
+    pub fn strike(&self) -> f64 {
+        self.inner.as_inner_ref().strike.get()
+    }
+
+    #[inline]
+    #[must_use]
+    /// Return true if the [`SecOption`] is a call.
+    pub fn is_call(&self) -> bool {
+        self.inner.is_call()
+    }
+
+    #[inline]
+    #[must_use]
+    /// Return true if the [`SecOption`] is a put.
+    pub fn is_put(&self) -> bool {
+        self.inner.is_put()
     }
 
     #[inline]
     #[must_use]
-    /// Get the [`SecFuture`] `multiplier`.
-    pub fn multiplier(&self) -> u32 {
-        self.inner.multiplier
+    /// Get the [`SecOption`] class.
+    pub fn class(&self) -> SecOptionClass {
+        self.inner.class()
+    }
+
+    #[inline]
+    #[must_use]
+    /// Get the [`SecOption`] right.
+    pub fn right(&self) -> OptionRight {
+        self.inner.right()
+    }
+
+    #[inline]
+    #[must_use]
+    /// Get the [`SecOption`] `multiplier`, which may be fractional.
+    pub fn multiplier(&self) -> f64 {
+        self.inner.as_inner_ref().multiplier.get()
+    }
+
+    #[inline]
+    #[must_use]
+    /// Compute the notional value of `quantity` contracts at `price`, in the contract's currency.
+    pub fn notional(&self, price: f64, quantity: f64) -> f64 {
+        self.inner.notional(price, quantity)
+    }
+
+    #[inline]
+    #[must_use]
+    /// Compute the [`SecOption`] intrinsic value when the underlying trades at
+    /// `underlying_price`, per unit of the underlying. See [`SecOption::intrinsic_value`].
+    pub fn intrinsic_value(&self, underlying_price: f64) -> f64 {
+        self.inner.intrinsic_value(underlying_price)
+    }
+
+    #[inline]
+    #[must_use]
+    /// Compute the [`SecOption`] intrinsic value of one contract when the underlying trades at
+    /// `underlying_price`, scaled by its multiplier. See
+    /// [`SecOption::intrinsic_value_per_contract`].
+    pub fn intrinsic_value_per_contract(&self, underlying_price: f64) -> f64 {
+        self.inner.intrinsic_value_per_contract(underlying_price)
+    }
+
+    #[inline]
+    #[must_use]
+    /// Classify the [`SecOption`] by how its strike compares to `underlying_price`. See
+    /// [`SecOption::moneyness`].
+    pub fn moneyness(&self, underlying_price: f64) -> Moneyness {
+        self.inner.moneyness(underlying_price)
     }
 }
 
-impl<E: ProxyExchange> Proxy<SecOption, E> {
+impl<E: ProxyExchange> Proxy<SecFutureOption, E> {
     #[inline]
     #[must_use]
-    /// Get the [`SecOption`] trading class.
-    pub fn trading_class(&self) -> &str {
-        self.inner.as_inner_ref().trading_class.as_str()
+    /// Get the [`SecFutureOption`] `expiration_date`.
+    pub fn expiration_date(&self) -> NaiveDate {
+        self.inner.as_inner_ref().expiration_date
+    }
+
+    #[inline]
+    #[must_use]
+    /// Get the [`SecFutureOption`] `strike` price.
+    pub fn strike(&self) -> f64 {
+        self.inner.as_inner_ref().strike.get()
+    }
+
+    #[inline]
+    #[must_use]
+    /// Return true if the [`SecFutureOption`] is a call.
+    pub fn is_call(&self) -> bool {
+        self.inner.is_call()
+    }
+
+    #[inline]
+    #[must_use]
+    /// Return true if the [`SecFutureOption`] is a put.
+    pub fn is_put(&self) -> bool {
+        self.inner.is_put()
+    }
+
+    #[inline]
+    #[must_use]
+    /// Get the [`SecFutureOption`] `multiplier`, which may be fractional.
+    pub fn multiplier(&self) -> f64 {
+        self.inner.as_inner_ref().multiplier.get()
+    }
+
+    #[inline]
+    #[must_use]
+    /// Get the contract ID of the underlying [`SecFuture`].
+    pub fn underlying_future_contract_id(&self) -> ContractId {
+        self.inner.as_inner_ref().underlying_future_contract_id
+    }
+
+    #[inline]
+    #[must_use]
+    /// Compute the notional value of `quantity` contracts at `price`, in the contract's currency.
+    pub fn notional(&self, price: f64, quantity: f64) -> f64 {
+        self.inner.notional(price, quantity)
+    }
+
+    #[inline]
+    #[must_use]
+    /// Compute the [`SecFutureOption`] intrinsic value when the underlying trades at
+    /// `underlying_price`, per unit of the underlying. See [`SecFutureOption::intrinsic_value`].
+    pub fn intrinsic_value(&self, underlying_price: f64) -> f64 {
+        self.inner.intrinsic_value(underlying_price)
+    }
+
+    #[inline]
+    #[must_use]
+    /// Compute the [`SecFutureOption`] intrinsic value of one contract when the underlying trades at
+    /// `underlying_price`, scaled by its multiplier. See
+    /// [`SecFutureOption::intrinsic_value_per_contract`].
+    pub fn intrinsic_value_per_contract(&self, underlying_price: f64) -> f64 {
+        self.inner.intrinsic_value_per_contract(underlying_price)
+    }
+
+    #[inline]
+    #[must_use]
+    /// Classify the [`SecFutureOption`] by how its strike compares to `underlying_price`. See
+    /// [`SecFutureOption::moneyness`].
+    pub fn moneyness(&self, underlying_price: f64) -> Moneyness {
+        self.inner.moneyness(underlying_price)
     }
+}
 
+impl<E: ProxyExchange> Proxy<Warrant, E> {
     #[inline]
     #[must_use]
-    /// Get the [`SecOption`] `expiration_date`.
+    /// Get the [`Warrant`] `expiration_date`.
     pub fn expiration_date(&self) -> NaiveDate {
         self.inner.as_inner_ref().expiration_date
     }
 
     #[inline]
     #[must_use]
-    /// Get the [`SecOption`] `strike` price.
+    /// Get the [`Warrant`] `strike` price.
     pub fn strike(&self) -> f64 {
-        self.inner.as_inner_ref().strike
+        self.inner.as_inner_ref().strike.get()
     }
 
     #[inline]
     #[must_use]
-    /// Return true if the [`SecOption`] is a call.
+    /// Get the [`Warrant`] `issuer`.
+    pub fn issuer(&self) -> &str {
+        self.inner.issuer()
+    }
+
+    #[inline]
+    #[must_use]
+    /// Return true if the [`Warrant`] is a call.
     pub fn is_call(&self) -> bool {
         self.inner.is_call()
     }
 
     #[inline]
     #[must_use]
-    /// Return true if the [`SecOption`] is a put.
+    /// Return true if the [`Warrant`] is a put.
     pub fn is_put(&self) -> bool {
         self.inner.is_put()
     }
 
-    #[inline]
+    #[inline]
+    #[must_use]
+    /// Get the [`Warrant`] `multiplier`.
+    pub fn multiplier(&self) -> f64 {
+        self.inner.as_inner_ref().multiplier.get()
+    }
+
+    #[inline]
+    #[must_use]
+    /// Compute the notional value of `quantity` contracts at `price`, in the contract's currency.
+    pub fn notional(&self, price: f64, quantity: f64) -> f64 {
+        self.inner.notional(price, quantity)
+    }
+
+    #[inline]
+    #[must_use]
+    /// Compute the [`Warrant`] intrinsic value when the underlying trades at
+    /// `underlying_price`, per unit of the underlying. See [`Warrant::intrinsic_value`].
+    pub fn intrinsic_value(&self, underlying_price: f64) -> f64 {
+        self.inner.intrinsic_value(underlying_price)
+    }
+
+    #[inline]
+    #[must_use]
+    /// Compute the [`Warrant`] intrinsic value of one contract when the underlying trades at
+    /// `underlying_price`, scaled by its multiplier. See
+    /// [`Warrant::intrinsic_value_per_contract`].
+    pub fn intrinsic_value_per_contract(&self, underlying_price: f64) -> f64 {
+        self.inner.intrinsic_value_per_contract(underlying_price)
+    }
+
+    #[inline]
+    #[must_use]
+    /// Classify the [`Warrant`] by how its strike compares to `underlying_price`. See
+    /// [`Warrant::moneyness`].
+    pub fn moneyness(&self, underlying_price: f64) -> Moneyness {
+        self.inner.moneyness(underlying_price)
+    }
+}
+
+impl<E: ProxyExchange> Proxy<StructuredProduct, E> {
+    #[inline]
+    #[must_use]
+    /// Get the [`StructuredProduct`] `expiration_date`.
+    pub fn expiration_date(&self) -> NaiveDate {
+        self.inner.expiration_date
+    }
+
+    #[inline]
+    #[must_use]
+    /// Get the [`StructuredProduct`] `multiplier`, which may be fractional.
+    pub fn multiplier(&self) -> f64 {
+        self.inner.multiplier.get()
+    }
+
+    #[inline]
+    #[must_use]
+    /// Compute the notional value of `quantity` contracts at `price`, in the contract's currency.
+    pub fn notional(&self, price: f64, quantity: f64) -> f64 {
+        self.inner.notional(price, quantity)
+    }
+}
+
+impl<E: ProxyExchange> Proxy<Commodity, E> {
+    #[inline]
+    #[must_use]
+    /// Get the [`Commodity`] `commodity_type`, which distinguishes a spot commodity from a
+    /// commodity contract, like spot gold from a gold future.
+    pub fn commodity_type(&self) -> &str {
+        &self.inner.commodity_type
+    }
+}
+
+impl<E: ProxyExchange> Proxy<Bond, E> {
+    #[inline]
+    #[must_use]
+    /// Get the [`Bond`] `maturity_date`.
+    pub fn maturity_date(&self) -> NaiveDate {
+        self.inner.maturity_date
+    }
+}
+
+impl<E: ProxyExchange> Proxy<Cfd, E> {
+    #[inline]
+    #[must_use]
+    /// Get the [`Cfd`] `underlying_contract_id`, which identifies the reference instrument.
+    pub fn underlying_contract_id(&self) -> ContractId {
+        self.inner.underlying_contract_id
+    }
+}
+
+impl<E: ProxyExchange> Proxy<ContinuousFuture, E> {
+    #[inline]
+    #[must_use]
+    /// Get the [`ContinuousFuture`] `underlying_contract_id`.
+    pub fn underlying_contract_id(&self) -> ContractId {
+        self.inner.underlying_contract_id
+    }
+}
+
+impl Proxy<Crypto, HasExchange> {
+    #[must_use]
+    /// Get the [`Crypto`] `exchange`
+    pub fn exchange(&self) -> Routing {
+        self.inner.exchange()
+    }
+}
+
+impl Proxy<Forex, HasExchange> {
+    #[must_use]
+    /// Get the [`Forex`] `exchange`
+    pub fn exchange(&self) -> Routing {
+        self.inner.exchange()
+    }
+}
+
+impl Proxy<Stock, HasExchange> {
+    #[must_use]
+    /// Get the [`Stock`] `exchange`
+    pub fn exchange(&self) -> Routing {
+        self.inner.exchange()
+    }
+}
+
+impl Proxy<Index, HasExchange> {
+    #[must_use]
+    /// Get the [`Index`] `exchange`
+    pub fn exchange(&self) -> Routing {
+        self.inner.exchange()
+    }
+}
+
+impl Proxy<Commodity, HasExchange> {
+    #[must_use]
+    /// Get the [`Commodity`] `exchange`
+    pub fn exchange(&self) -> Routing {
+        self.inner.exchange()
+    }
+}
+
+impl Proxy<SecFuture, HasExchange> {
+    #[must_use]
+    /// Get the [`SecFuture`] `exchange`
+    pub fn exchange(&self) -> Routing {
+        self.inner.exchange()
+    }
+}
+
+impl Proxy<SecOption, HasExchange> {
+    #[must_use]
+    /// Get the [`SecOption`] `exchange`
+    pub fn exchange(&self) -> Routing {
+        self.inner.exchange()
+    }
+}
+
+impl Proxy<SecFutureOption, HasExchange> {
+    #[must_use]
+    /// Get the [`SecFutureOption`] `exchange`
+    pub fn exchange(&self) -> Routing {
+        self.inner.exchange()
+    }
+}
+
+impl Proxy<Warrant, HasExchange> {
     #[must_use]
-    /// Get the [`SecOption`] `multiplier`.
-    pub fn multiplier(&self) -> u32 {
-        self.inner.as_inner_ref().multiplier
+    /// Get the [`Warrant`] `exchange`
+    pub fn exchange(&self) -> Routing {
+        self.inner.exchange()
     }
 }
 
-impl Proxy<Forex, HasExchange> {
+impl Proxy<Bond, HasExchange> {
     #[must_use]
-    /// Get the [`Forex`] `exchange`
+    /// Get the [`Bond`] `exchange`
     pub fn exchange(&self) -> Routing {
         self.inner.exchange()
     }
 }
 
-impl Proxy<Stock, HasExchange> {
+impl Proxy<Cfd, HasExchange> {
     #[must_use]
-    /// Get the [`Stock`] `exchange`
+    /// Get the [`Cfd`] `exchange`
     pub fn exchange(&self) -> Routing {
         self.inner.exchange()
     }
 }
 
-impl Proxy<Commodity, HasExchange> {
+impl Proxy<MutualFund, HasExchange> {
     #[must_use]
-    /// Get the [`Commodity`] `exchange`
+    /// Get the [`MutualFund`] `exchange`
     pub fn exchange(&self) -> Routing {
         self.inner.exchange()
     }
 }
 
-impl Proxy<SecFuture, HasExchange> {
+impl Proxy<ContinuousFuture, HasExchange> {
     #[must_use]
-    /// Get the [`SecFuture`] `exchange`
+    /// Get the [`ContinuousFuture`] `exchange`
     pub fn exchange(&self) -> Routing {
         self.inner.exchange()
     }
 }
 
-impl Proxy<SecOption, HasExchange> {
+impl Proxy<StructuredProduct, HasExchange> {
     #[must_use]
-    /// Get the [`SecOption`] `exchange`
+    /// Get the [`StructuredProduct`] `exchange`
     pub fn exchange(&self) -> Routing {
         self.inner.exchange()
     }
 }
 
 #[allow(clippy::module_name_repetitions)]
-#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
 /// The possible contract types
 pub enum ContractType {
     #[serde(rename = "CASH")]
@@ -1496,21 +6118,39 @@ pub enum ContractType {
     #[serde(rename = "IND")]
     /// An [`Index`] contract.
     Index,
-    //Cfd,
+    #[serde(rename = "CFD")]
+    /// A [`Cfd`] contract.
+    Cfd,
     #[serde(rename = "FUT")]
     /// A [`SecFuture`] contract.
     SecFuture,
     #[serde(rename = "OPT")]
     /// A [`SecOption`] contract.
     SecOption,
-    //FutureSecOption,
-    //Bond,
-    //MutualFund,
+    #[serde(rename = "FOP")]
+    /// A [`SecFutureOption`] contract.
+    SecFutureOption,
+    #[serde(rename = "BOND")]
+    /// A [`Bond`] contract.
+    Bond,
+    #[serde(rename = "FUND")]
+    /// A [`MutualFund`] contract.
+    MutualFund,
     #[serde(rename = "CMDTY")]
     /// A [`Commodity`] contract.
     Commodity,
-    //Warrant,
-    //StructuredProduct,
+    #[serde(rename = "WAR")]
+    /// A [`Warrant`] contract.
+    Warrant,
+    #[serde(rename = "CONTFUT")]
+    /// A [`ContinuousFuture`] contract.
+    ContinuousFuture,
+    #[serde(rename = "IOPT")]
+    /// A [`StructuredProduct`] contract.
+    StructuredProduct,
+    #[serde(rename = "BAG")]
+    /// A [`Combo`] contract.
+    Combo,
 }
 
 #[derive(Debug, Clone, Error)]
@@ -1523,8 +6163,9 @@ pub struct ParseContractTypeError(pub String);
 impl FromStr for ContractType {
     type Err = ParseContractTypeError;
 
+    /// Parse a contract type from its TWS code, like "STK" or "opt", ignoring case.
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        Ok(match s {
+        Ok(match s.to_ascii_uppercase().as_str() {
             "CASH" => Self::Forex,
             "CRYPTO" => Self::Crypto,
             "STK" => Self::Stock,
@@ -1532,14 +6173,28 @@ impl FromStr for ContractType {
             "FUT" => Self::SecFuture,
             "OPT" => Self::SecOption,
             "CMDTY" => Self::Commodity,
-            v => return Err(ParseContractTypeError(v.to_owned())),
+            "BOND" => Self::Bond,
+            "CFD" => Self::Cfd,
+            "FUND" => Self::MutualFund,
+            "FOP" => Self::SecFutureOption,
+            "WAR" => Self::Warrant,
+            "CONTFUT" => Self::ContinuousFuture,
+            "IOPT" => Self::StructuredProduct,
+            "BAG" => Self::Combo,
+            _ => return Err(ParseContractTypeError(s.to_owned())),
         })
     }
 }
 
-impl std::fmt::Display for ContractType {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let s = match self {
+impl ContractType {
+    #[must_use]
+    #[inline]
+    /// Get the code with which TWS identifies the contract type, like "STK" for a [`Stock`].
+    ///
+    /// # Returns
+    /// The contract type's TWS code.
+    pub const fn as_ib_str(&self) -> &'static str {
+        match self {
             Self::Forex => "CASH",
             Self::Crypto => "CRYPTO",
             Self::Stock => "STK",
@@ -1547,8 +6202,21 @@ impl std::fmt::Display for ContractType {
             Self::SecFuture => "FUT",
             Self::SecOption => "OPT",
             Self::Commodity => "CMDTY",
-        };
-        write!(f, "{s}")
+            Self::Bond => "BOND",
+            Self::Cfd => "CFD",
+            Self::MutualFund => "FUND",
+            Self::SecFutureOption => "FOP",
+            Self::Warrant => "WAR",
+            Self::ContinuousFuture => "CONTFUT",
+            Self::StructuredProduct => "IOPT",
+            Self::Combo => "BAG",
+        }
+    }
+}
+
+impl std::fmt::Display for ContractType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_ib_str())
     }
 }
 
@@ -1558,7 +6226,1789 @@ pub enum SerializeProxyError {
     #[error("Missing data for field {0}")]
     /// Missing data
     MissingData(&'static str),
+    #[error("Invalid data for field {0}")]
+    /// Invalid data
+    InvalidData(&'static str),
     #[error("Unexpected security type {0}")]
     /// Unexpected security type
     UnexpectedContractType(#[from] UnexpectedSecurityType),
 }
+
+#[test]
+fn test_cfd_out_msg() {
+    use indicators::Valid;
+
+    let cfd = Cfd {
+        contract_id: ContractId(143_916_318),
+        min_tick: NotNan(0.01),
+        symbol: "IBUS500".to_owned(),
+        exchange: Routing::Smart,
+        underlying_contract_id: ContractId(416_904),
+        cfd_type: "IND".to_owned(),
+        trading_class: "IBUS500".to_owned(),
+        currency: Currency::UsDollar,
+        local_symbol: "IBUS500".to_owned(),
+        long_name: "US 500 Index".to_owned(),
+        order_types: Vec::default(),
+        valid_exchanges: vec![Routing::Smart],
+        details: ContractDetails::default(),
+    };
+    let msg = cfd.as_out_msg();
+    assert_eq!(msg.security_type, "CFD");
+    assert_eq!(msg.exchange, Routing::Smart);
+    assert_eq!(msg.trading_class, Some("IBUS500"));
+    let contract = Contract::from(cfd);
+    assert_eq!(contract.contract_type(), ContractType::Cfd);
+    assert_eq!(contract.security_type(), ContractType::Cfd);
+}
+
+#[test]
+fn test_crypto_out_msg() {
+    use indicators::Valid;
+
+    let zero_hash = Routing::Primary(Primary::ZeroHashCryptoExchange);
+    let crypto = Crypto {
+        contract_id: ContractId(479_624_278),
+        min_tick: NotNan(0.25),
+        symbol: "BTC".to_owned(),
+        exchange: zero_hash,
+        trading_class: "BTC".to_owned(),
+        currency: Currency::UsDollar,
+        local_symbol: "BTC.USD".to_owned(),
+        long_name: "Bitcoin".to_owned(),
+        order_types: Vec::default(),
+        valid_exchanges: vec![zero_hash],
+        details: ContractDetails::default(),
+    };
+    assert_eq!(crypto.as_out_msg().exchange, zero_hash);
+    assert_eq!(Contract::from(crypto).exchange(), zero_hash);
+    assert_eq!("ZEROHASH".parse::<Routing>().ok(), Some(zero_hash));
+}
+
+#[test]
+fn test_stock_builder() -> Result<(), BuildStockError> {
+    let stock = StockBuilder::new("AAPL")
+        .primary_exchange(Primary::NationalAssociationOfSecurityDealers)
+        .currency(Currency::UsDollar)
+        .build()?;
+    assert_eq!(stock.symbol(), "AAPL");
+    assert_eq!(stock.exchange(), Routing::Smart);
+    assert_eq!(stock.primary_exchange(), Primary::NationalAssociationOfSecurityDealers);
+    // A partially populated stock has no size increment to round to
+    assert!((stock.round_size(1.5) - 1.5).abs() < f64::EPSILON);
+
+    assert_eq!(
+        StockBuilder::new(" ").build(),
+        Err(BuildStockError::EmptySymbol)
+    );
+    assert_eq!(
+        StockBuilder::new("AAPL").currency(Currency::UsDollar).build(),
+        Err(BuildStockError::MissingData("primary_exchange"))
+    );
+
+    let stock = StockBuilder::new("BRK B")
+        .primary_exchange(Primary::NewYorkStockExchange)
+        .currency(Currency::UsDollar)
+        .contract_id(ContractId(72_063_691))
+        .local_symbol("BRK B")
+        .build()?;
+    assert_eq!(stock.contract_id(), ContractId(72_063_691));
+    assert_eq!(stock.local_symbol(), "BRK B");
+    assert!((stock.min_tick() - 0.01).abs() < f64::EPSILON);
+    Ok(())
+}
+
+#[test]
+fn test_index_new() -> Result<(), EmptyIndexSymbolError> {
+    let cboe = Routing::Primary(Primary::ChicagoBoardOptionsExchange);
+    let spx = Index::new("SPX", cboe, Currency::UsDollar)?;
+    assert_eq!(spx.symbol(), "SPX");
+    assert_eq!(spx.local_symbol(), "SPX");
+    assert_eq!(spx.exchange(), cboe);
+    assert_eq!(spx.currency(), Currency::UsDollar);
+    assert_eq!(spx.contract_id(), ContractId(0));
+    assert!((spx.min_tick() - 0.01).abs() < f64::EPSILON);
+    assert_eq!(spx.to_string(), "SPX IND CBOE USD (conid 0)");
+    assert_eq!(
+        Index::new(" ", cboe, Currency::UsDollar),
+        Err(EmptyIndexSymbolError)
+    );
+    Ok(())
+}
+
+#[test]
+fn test_sec_future_new() -> Result<(), InvalidSecFutureError> {
+    let cme = Routing::Primary(Primary::ChicagoMercantileExchange);
+    let date = NaiveDate::from_ymd_opt(2024, 12, 20).unwrap();
+    let es = SecFuture::new("ES", date, cme, Currency::UsDollar)?;
+    assert_eq!((es.symbol(), es.local_symbol()), ("ES", ""));
+    assert_eq!((es.expiration_date, es.contract_month), (Some(date), (2024, 12)));
+    assert_eq!(es.contract_id(), ContractId(0));
+    // The unknown multiplier is sent as an empty field
+    assert_eq!(es.to_ib_fields()[3..8], ["20241220", "", "", "", "CME"]);
+
+    let es = es.with_local_symbol("ESZ4");
+    assert_eq!(es.local_symbol(), "ESZ4");
+    assert_eq!(es.to_ib_fields()[10], "ESZ4");
+
+    assert_eq!(
+        SecFuture::new("", date, cme, Currency::UsDollar),
+        Err(InvalidSecFutureError::EmptySymbol)
+    );
+    let date = NaiveDate::from_ymd_opt(10_000, 1, 1).unwrap();
+    assert_eq!(
+        SecFuture::new("ES", date, cme, Currency::UsDollar),
+        Err(InvalidSecFutureError::ExpirationDate(date))
+    );
+    Ok(())
+}
+
+#[test]
+fn test_forex_builder() -> Result<(), SameCurrencyPairError> {
+    let forex = ForexBuilder::new(Currency::Euro, Currency::UsDollar)
+        .contract_id(ContractId(12_087_792))
+        .build()?;
+    assert_eq!(forex.contract_id(), ContractId(12_087_792));
+    assert_eq!(forex.exchange(), Routing::Primary(Primary::IbForexPro));
+    assert_eq!(forex.local_symbol(), "EUR.USD");
+    assert_eq!(
+        ForexBuilder::new(Currency::Euro, Currency::Euro).build(),
+        Err(SameCurrencyPairError(Currency::Euro))
+    );
+    Ok(())
+}
+
+#[test]
+fn test_sec_option_builder() -> Result<(), BuildSecOptionError> {
+    let expiration_date = NaiveDate::from_ymd_opt(2025, 1, 17).unwrap();
+    let option = SecOptionBuilder::new("AAPL", SecOptionClass::Call, 190.0, expiration_date)
+        .currency(Currency::UsDollar)
+        .build()?;
+    assert_eq!(option.class(), SecOptionClass::Call);
+    assert!((option.strike() - 190.0).abs() < f64::EPSILON);
+    assert!((option.multiplier() - 100.0).abs() < f64::EPSILON);
+    assert_eq!(option.contract_month(), (2025, 1));
+    assert_eq!(option.exchange(), Routing::Smart);
+
+    assert_eq!(
+        SecOptionBuilder::new("", SecOptionClass::Call, 190.0, expiration_date).build(),
+        Err(BuildSecOptionError::EmptySymbol)
+    );
+    assert_eq!(
+        SecOptionBuilder::new("AAPL", SecOptionClass::Call, 190.0, expiration_date).build(),
+        Err(BuildSecOptionError::MissingData("currency"))
+    );
+    assert_eq!(
+        SecOptionBuilder::new("AAPL", SecOptionClass::Put, f64::NAN, expiration_date)
+            .currency(Currency::UsDollar)
+            .build(),
+        Err(BuildSecOptionError::InvalidData("strike"))
+    );
+    Ok(())
+}
+
+#[test]
+fn test_fractional_multiplier() {
+    assert_eq!(not_nan(Some(100.0), "multiplier"), Ok(NotNan(100.0)));
+    assert_eq!(not_nan(Some(0.1), "multiplier"), Ok(NotNan(0.1)));
+    assert_eq!(
+        not_nan(Some(f64::NAN), "multiplier"),
+        Err(SerializeProxyError::InvalidData("multiplier"))
+    );
+    assert_eq!(
+        not_nan(None, "multiplier"),
+        Err(SerializeProxyError::MissingData("multiplier"))
+    );
+}
+
+#[test]
+fn test_contract_eq_hash() {
+    use std::collections::{BTreeSet, HashSet};
+
+    let contracts = sample_contracts();
+    let hashed = contracts.iter().cloned().collect::<HashSet<_>>();
+    let ordered = contracts.iter().cloned().collect::<BTreeSet<_>>();
+    assert_eq!(hashed.len(), contracts.len());
+    assert_eq!(ordered.len(), contracts.len());
+    for contract in &contracts {
+        assert!(hashed.contains(contract));
+        assert!(ordered.contains(contract));
+    }
+
+    let state = std::hash::RandomState::new();
+    let hash = |value: NotNan| std::hash::BuildHasher::hash_one(&state, value);
+    let (zero, neg_zero) = (NotNan::new(0.0).unwrap(), NotNan::new(-0.0).unwrap());
+    assert_eq!(zero, neg_zero);
+    assert_eq!(hash(zero), hash(neg_zero));
+    assert!(NotNan::new(f64::NAN).is_err());
+    assert!(matches!("NaN".parse::<NotNan>(), Err(ParseNotNanError::Nan(NanError))));
+    assert!(matches!("abc".parse::<NotNan>(), Err(ParseNotNanError::Float(_))));
+}
+
+#[test]
+fn test_display_key() {
+    let stock = |symbol, primary_exchange, currency| {
+        Contract::Stock(
+            StockBuilder::new(symbol)
+                .primary_exchange(primary_exchange)
+                .currency(currency)
+                .build()
+                .unwrap(),
+        )
+    };
+    let option = Contract::SecOption(
+        SecOptionBuilder::new(
+            "AAPL",
+            SecOptionClass::Call,
+            190.0,
+            NaiveDate::from_ymd_opt(2025, 1, 17).unwrap(),
+        )
+        .currency(Currency::UsDollar)
+        .build()
+        .unwrap(),
+    );
+    let forex = Contract::Forex(
+        ForexBuilder::new(Currency::Euro, Currency::UsDollar)
+            .build()
+            .unwrap(),
+    );
+    let mut watchlist = [
+        forex,
+        option,
+        stock("SAP", Primary::IntegriertesBoersenhandelsUndInformationsSystem, Currency::Euro),
+        stock("AAPL", Primary::NationalAssociationOfSecurityDealers, Currency::UsDollar),
+        stock("SAP", Primary::NewYorkStockExchange, Currency::UsDollar),
+    ];
+    watchlist.sort_by_key(Contract::display_key);
+    assert_eq!(
+        watchlist
+            .iter()
+            .map(Contract::display_key)
+            .collect::<Vec<_>>(),
+        [
+            ("AAPL".to_owned(), "USD".to_owned(), ContractType::Stock),
+            ("AAPL".to_owned(), "USD".to_owned(), ContractType::SecOption),
+            ("EUR".to_owned(), "USD".to_owned(), ContractType::Forex),
+            ("SAP".to_owned(), "EUR".to_owned(), ContractType::Stock),
+            ("SAP".to_owned(), "USD".to_owned(), ContractType::Stock),
+        ]
+    );
+
+    let mut contracts = sample_contracts();
+    contracts.sort_by_key(Contract::display_key);
+    assert!(contracts
+        .windows(2)
+        .all(|w| w[0].display_key() <= w[1].display_key()));
+}
+
+#[test]
+fn test_canonicalize() {
+    let nasdaq = Routing::Primary(Primary::NationalAssociationOfSecurityDealers);
+    let arca = Routing::Primary(Primary::Archipelago);
+    let mut stock = sample_stock();
+    let mut resolved = stock.clone();
+    stock.order_types = vec![OrderTypeCode::Limit, OrderTypeCode::Market, OrderTypeCode::Limit];
+    stock.valid_exchanges = vec![Routing::Smart, nasdaq, arca];
+    let rule = MarketRuleId(26);
+    stock.details.market_rule_ids = vec![(nasdaq, rule), (Routing::Smart, rule)];
+    resolved.symbol = " AAPL ".to_owned();
+    resolved.long_name = "AAPL Long Name\n".to_owned();
+    resolved.order_types = vec![OrderTypeCode::Market, OrderTypeCode::Limit];
+    resolved.valid_exchanges = vec![arca, nasdaq, Routing::Smart];
+    resolved.details.market_rule_ids = vec![(Routing::Smart, rule), (nasdaq, rule)];
+
+    let (mut first, mut second) = (Contract::Stock(stock), Contract::Stock(resolved));
+    assert_ne!(first, second);
+    first.canonicalize();
+    second.canonicalize();
+    assert_eq!(first, second);
+    assert_eq!(first.symbol(), "AAPL");
+    assert_eq!(first.long_name(), "AAPL Long Name");
+    assert_eq!(first.order_types().len(), 2);
+    assert_eq!(first.valid_exchanges().first(), Some(&Routing::Smart));
+
+    // Canonicalizing twice changes nothing, nor does it change an already canonical contract
+    let canonical = first.clone();
+    first.canonicalize();
+    assert_eq!(first, canonical);
+    for contract in sample_contracts() {
+        let mut canonical = contract.clone();
+        canonical.canonicalize();
+        assert_eq!(canonical, contract);
+    }
+}
+
+#[test]
+fn test_same_contract() {
+    let stock = &sample_stock();
+    let mut partial = stock.clone();
+    partial.long_name = String::default();
+    partial.order_types = Vec::default();
+    assert_ne!(&partial, stock);
+    assert!(partial.same_contract(stock));
+    assert_eq!(ContractKey::new(&partial), ContractKey::new(stock));
+
+    let mut positions = std::collections::HashMap::new();
+    *positions.entry(ContractKey::new(stock).unwrap()).or_insert(0.0) += 100.0;
+    *positions.entry(ContractKey::new(&partial).unwrap()).or_insert(0.0) += 50.0;
+    let key = ContractKey::new(stock).unwrap();
+    assert_eq!(positions.get(&key), Some(&150.0));
+    assert_eq!(ContractId::from(key), stock.contract_id());
+
+    let unresolved = StockBuilder::new("AAPL")
+        .primary_exchange(Primary::NationalAssociationOfSecurityDealers)
+        .currency(Currency::UsDollar)
+        .build()
+        .unwrap();
+    assert_eq!(ContractKey::new(&unresolved), None);
+    assert!(matches!(
+        ContractKey::try_from(&Contract::Stock(unresolved)),
+        Err(InvalidContractId(0))
+    ));
+    assert_eq!(ContractKey::new(&sample_combo()), None);
+    assert!(Contract::from(partial).same_contract(stock));
+    assert!(sample_contracts()
+        .iter()
+        .filter(|c| !matches!(c, Contract::Stock(_)))
+        .all(|c| !c.same_contract(stock)));
+}
+
+#[test]
+fn test_security_multiplier() {
+    for contract in sample_contracts() {
+        let expected = contract.multiplier().unwrap_or(1.0);
+        assert!((Security::multiplier(&contract) - expected).abs() < f64::EPSILON);
+        match_poly!(&contract;
+            Contract::Forex(t)
+            | Contract::Crypto(t)
+            | Contract::Stock(t)
+            | Contract::Index(t)
+            | Contract::SecFuture(t)
+            | Contract::SecOption(t)
+            | Contract::Commodity(t)
+            | Contract::Bond(t)
+            | Contract::Cfd(t)
+            | Contract::MutualFund(t)
+            | Contract::SecFutureOption(t)
+            | Contract::Warrant(t)
+            | Contract::ContinuousFuture(t)
+            | Contract::StructuredProduct(t)
+            | Contract::Combo(t) => assert!((Security::multiplier(t) - expected).abs() < f64::EPSILON)
+        );
+    }
+}
+
+#[test]
+fn test_round_price() {
+    let (mut future, mut stock) = (sample_future(), sample_stock());
+    let close = |a: f64, b: f64| (a - b).abs() < 1e-9;
+
+    // 100.03 / 0.01 is 10002.999..., which must not be floored to 100.02
+    assert!(close(stock.round_price(100.03, None), 100.03));
+    assert!(close(stock.round_price_down(100.03, None), 100.03));
+    assert!(close(stock.round_price_up(100.03, None), 100.03));
+    assert!(close(stock.round_price(0.1 + 0.2, None), 0.3));
+    assert!(close(stock.round_price_up(0.1 + 0.2, None), 0.3));
+    assert!(close(stock.round_price(100.034, None), 100.03));
+    assert!(close(stock.round_price(100.036, None), 100.04));
+    assert!(close(stock.round_price_down(100.039, None), 100.03));
+    assert!(close(stock.round_price_up(100.031, None), 100.04));
+    assert!(close(stock.round_price_down(-1.015, None), -1.02));
+
+    future.min_tick = NotNan(0.25);
+    assert!(close(future.round_price(6000.12, None), 6000.0));
+    assert!(close(future.round_price(6000.13, None), 6000.25));
+    assert!(close(future.round_price_down(6000.24, None), 6000.0));
+    assert!(close(future.round_price_up(6000.01, None), 6000.25));
+    assert!(close(future.round_price_up(6000.75, None), 6000.75));
+    assert!(close(Contract::SecFuture(future).round_price_down(6000.5, None), 6000.5));
+
+    stock.min_tick = NotNan(0.0);
+    assert!(close(stock.round_price(100.037, None), 100.037));
+    assert!(close(stock.round_price_up(100.037, None), 100.037));
+    assert!(stock.round_price(f64::NAN, None).is_nan());
+
+    // A market rule sets the tick size by price band, across the boundary at 1.0
+    let rule = MarketRule {
+        id: MarketRuleId(26),
+        increments: vec![
+            PriceIncrement { low_edge: 0.0, increment: 0.0001 },
+            PriceIncrement { low_edge: 1.0, increment: 0.01 },
+        ],
+    };
+    stock.min_tick = NotNan(0.01);
+    assert!(close(stock.tick_for_price(0.5, Some(&rule)), 0.0001));
+    assert!(close(stock.tick_for_price(0.5, None), 0.01));
+    assert!(close(stock.round_price(0.61237, Some(&rule)), 0.6124));
+    assert!(close(stock.round_price(0.61237, None), 0.61));
+    assert!(close(stock.round_price_up(0.99996, Some(&rule)), 1.0));
+    assert!(close(stock.round_price(0.99996, Some(&rule)), 1.0));
+    assert!(close(stock.round_price_down(1.0099, Some(&rule)), 1.0));
+    assert!(close(stock.round_price_up(1.0001, Some(&rule)), 1.01));
+    assert!(close(stock.round_price_down(0.99999, Some(&rule)), 0.9999));
+    // A rule without increments falls back to the minimum tick
+    let empty = MarketRule { id: MarketRuleId(0), increments: Vec::new() };
+    assert!(close(stock.round_price(0.61237, Some(&empty)), 0.61));
+}
+
+#[test]
+fn test_parse_security_id_query() {
+    // Apple, Microsoft, SAP, and GSK ISINs
+    for isin in ["US0378331005", "US5949181045", "DE0007164600", "GB0002634946"] {
+        assert_eq!(
+            isin.parse::<Query>().unwrap(),
+            Query::SecurityId(SecurityId::Isin(isin.to_owned()), Routing::Smart)
+        );
+    }
+    // Alphabet and Accenture CUSIPs
+    for cusip in ["02079K305", "G1151C101"] {
+        assert_eq!(
+            cusip.parse::<Query>().unwrap(),
+            Query::SecurityId(SecurityId::Cusip(cusip.to_owned()), Routing::Smart)
+        );
+    }
+    // Apple and Microsoft FIGIs. The latter also has a valid ISIN check digit.
+    for figi in ["BBG000B9XRY4", "BBG000BPH459"] {
+        assert!(matches!(figi.parse::<Query>().unwrap(), Query::Figi(_, Routing::Smart)));
+    }
+    // Apple's CUSIP is purely numeric, so it's indistinguishable from a contract ID
+    assert_eq!(
+        "037833100".parse::<Query>().unwrap(),
+        Query::IbContractId(ContractId(37_833_100), Routing::Smart)
+    );
+
+    assert!(matches!(
+        "US0378331006".parse::<Query>(),
+        Err(ParseQueryError::Isin(InvalidIsin::Checksum(_)))
+    ));
+    assert!(matches!(
+        "BBG000B9XRY5".parse::<Query>(),
+        Err(ParseQueryError::Figi(_))
+    ));
+    assert!(matches!(
+        "02079K306".parse::<Query>(),
+        Err(ParseQueryError::Cusip(InvalidCusip::Checksum(_)))
+    ));
+    assert_eq!(
+        SecurityId::isin("0S0378331005"),
+        Err(InvalidIsin::CountryCode("0S0378331005".to_owned()))
+    );
+}
+
+#[test]
+fn test_parse_security_id() {
+    // Apple, Microsoft, and Tesla, plus lowercase forms that are stored in uppercase
+    for (s, expected) in [
+        ("US0378331005", SecurityId::Isin("US0378331005".to_owned())),
+        ("us0378331005", SecurityId::Isin("US0378331005".to_owned())),
+        ("594918104", SecurityId::Cusip("594918104".to_owned())),
+        ("88160r101", SecurityId::Cusip("88160R101".to_owned())),
+        ("2046251", SecurityId::Sedol("2046251".to_owned())),
+        ("B0YBKJ7", SecurityId::Sedol("B0YBKJ7".to_owned())),
+        ("b0ybkj7", SecurityId::Sedol("B0YBKJ7".to_owned())),
+        ("AAPL.OQ", SecurityId::Ric("AAPL.OQ".to_owned())),
+        ("BRKb.N", SecurityId::Ric("BRKb.N".to_owned())),
+    ] {
+        assert_eq!(s.parse::<SecurityId>(), Ok(expected), "{s}");
+    }
+
+    assert_eq!(
+        "2046252".parse::<SecurityId>(),
+        Err(InvalidSecurityId::Sedol(InvalidSedol::Checksum("2046252".to_owned())))
+    );
+    assert_eq!(
+        SecurityId::sedol("B0YAKJ7"),
+        Err(InvalidSedol::Character("B0YAKJ7".to_owned()))
+    );
+    assert_eq!(SecurityId::sedol("204625"), Err(InvalidSedol::Length("204625".to_owned())));
+    assert!(matches!(
+        "594918105".parse::<SecurityId>(),
+        Err(InvalidSecurityId::Cusip(InvalidCusip::Checksum(_)))
+    ));
+    assert!(matches!(
+        "US0378331006".parse::<SecurityId>(),
+        Err(InvalidSecurityId::Isin(InvalidIsin::Checksum(_)))
+    ));
+    assert_eq!(
+        "AAPL".parse::<SecurityId>(),
+        Err(InvalidSecurityId::Unrecognized("AAPL".to_owned()))
+    );
+}
+
+#[test]
+fn test_parse_query_routing() {
+    let nasdaq = Routing::Primary(Primary::NationalAssociationOfSecurityDealers);
+    assert_eq!(
+        "265598@ISLAND".parse::<Query>().unwrap(),
+        Query::IbContractId(ContractId(265_598), Routing::Primary(Primary::Island))
+    );
+    assert_eq!(
+        "BBG000B9XRY4@NASDAQ".parse::<Query>().unwrap(),
+        Query::Figi("BBG000B9XRY4".parse().unwrap(), nasdaq)
+    );
+    assert_eq!(
+        "US0378331005@nasdaq".parse::<Query>().unwrap(),
+        Query::SecurityId(SecurityId::Isin("US0378331005".to_owned()), nasdaq)
+    );
+    assert_eq!(
+        "265598@SMART".parse::<Query>().unwrap(),
+        "265598".parse::<Query>().unwrap()
+    );
+
+    assert!(matches!(
+        "265598@NOWHERE".parse::<Query>(),
+        Err(ParseQueryError::Routing(_))
+    ));
+    assert!(matches!("@ISLAND".parse::<Query>(), Err(ParseQueryError::Empty)));
+}
+
+#[test]
+fn test_continuous_future_out_msg() {
+    use indicators::Valid;
+
+    let cont = ContinuousFuture {
+        contract_id: ContractId(495_512_563),
+        min_tick: NotNan(0.25),
+        symbol: "ES".to_owned(),
+        exchange: Routing::Primary(Primary::ChicagoMercantileExchange),
+        trading_class: "ES".to_owned(),
+        underlying_contract_id: ContractId(11_004_968),
+        currency: Currency::UsDollar,
+        local_symbol: "ESZ4".to_owned(),
+        long_name: "E-mini S&P 500".to_owned(),
+        order_types: Vec::default(),
+        valid_exchanges: Vec::default(),
+        details: ContractDetails::default(),
+    };
+    let msg = cont.as_out_msg();
+    assert_eq!(msg.security_type, "CONTFUT");
+    assert_eq!(msg.expiration_date, None);
+    assert_eq!(msg.multiplier, None);
+    assert_eq!(
+        Contract::from(cont).security_type(),
+        ContractType::ContinuousFuture
+    );
+    assert_eq!("CONTFUT".parse::<ContractType>().ok(), Some(ContractType::ContinuousFuture));
+}
+
+#[test]
+fn test_expiring() {
+    let expiration_date = NaiveDate::from_ymd_opt(2024, 12, 20).unwrap();
+    let future = SecFuture {
+        contract_id: ContractId(495_512_563),
+        min_tick: NotNan(0.25),
+        symbol: "ES".to_owned(),
+        exchange: Routing::Primary(Primary::ChicagoMercantileExchange),
+        multiplier: NotNan(50.0),
+        expiration_date: Some(expiration_date),
+        contract_month: (2024, 12),
+        trading_class: "ES".to_owned(),
+        underlying_contract_id: ContractId(11_004_968),
+        currency: Currency::UsDollar,
+        local_symbol: "ESZ4".to_owned(),
+        long_name: "E-mini S&P 500".to_owned(),
+        order_types: Vec::default(),
+        valid_exchanges: Vec::default(),
+        details: ContractDetails::default(),
+    };
+    assert_eq!(Expiring::expiration_date(&future), Some(expiration_date));
+    assert_eq!(HasUnderlying::underlying_contract_id(&future), ContractId(11_004_968));
+    assert_eq!(Contract::from(future.clone()).underlying_contract_id(), Some(ContractId(11_004_968)));
+    assert_eq!(Contract::from(future).expiration_date(), Some(expiration_date));
+
+    let stock = StockBuilder::new("AAPL")
+        .primary_exchange(Primary::NationalAssociationOfSecurityDealers)
+        .currency(Currency::UsDollar)
+        .build()
+        .unwrap();
+    let stock = Contract::from(stock);
+    assert_eq!(stock.expiration_date(), None);
+    assert_eq!(stock.underlying_contract_id(), None);
+}
+
+#[tokio::test]
+async fn test_bond_cusip_query() -> Result<(), Box<dyn std::error::Error>> {
+    use tokio::io::AsyncReadExt;
+
+    // T 3 1/2 02/15/39
+    let query = "912810qa9".parse::<Query>()?;
+    assert_eq!(
+        query,
+        Query::SecurityId(SecurityId::Cusip("912810QA9".to_owned()), Routing::Smart)
+    );
+    assert_eq!(QueryOptions::for_query::<Bond>(&query), QueryOptions::default());
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let stream = tokio::net::TcpStream::connect(listener.local_addr()?).await?;
+    let (mut server, _) = listener.accept().await?;
+    let mut writer = crate::comm::Writer::new(stream.into_split().1);
+    writer.add_body(QueryOutMsg {
+        query: &query,
+        options: QueryOptions::default(),
+    })?;
+    writer.send().await?;
+
+    let len = server.read_u32().await?;
+    let mut body = vec![0; len as usize];
+    server.read_exact(&mut body).await?;
+    let body = String::from_utf8(body)?;
+    let fields = body.split('\0').collect::<Vec<_>>();
+    assert_eq!(fields[7], "SMART");
+    assert_eq!((fields[13], fields[14]), ("CUSIP", "912810QA9"));
+    assert_eq!(fields[14].parse::<Query>()?, query);
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Watchlist {
+        queries: Vec<Query>,
+    }
+
+    let watchlist = Watchlist {
+        queries: vec![query],
+    };
+    assert_eq!(toml::from_str::<Watchlist>(&toml::to_string(&watchlist)?)?, watchlist);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_query_include_expired() -> Result<(), std::io::Error> {
+    use tokio::io::AsyncReadExt;
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let stream = tokio::net::TcpStream::connect(listener.local_addr()?).await?;
+    let (mut server, _) = listener.accept().await?;
+    let mut writer = crate::comm::Writer::new(stream.into_split().1);
+
+    let queries = [
+        Query::IbContractId(ContractId(495_512_563), Routing::Smart),
+        "BBG000B9XRY4".parse().expect("valid FIGI"),
+        "US0378331005".parse().expect("valid ISIN"),
+        Query::ContinuousFuture("ES".to_owned(), Routing::Smart),
+        ContractSpec::new("ES", ContractType::SecFuture, Routing::Smart, Currency::UsDollar)
+            .trading_class("ES")
+            .expiration_date(NaiveDate::from_ymd_opt(2024, 12, 20).unwrap())
+            .into(),
+    ];
+    for query in &queries {
+        for include_expired in [false, true] {
+            let options = QueryOptions { include_expired };
+            writer.add_body(QueryOutMsg { query, options })?;
+            writer.send().await?;
+
+            let len = server.read_u32().await?;
+            let mut body = vec![0; len as usize];
+            server.read_exact(&mut body).await?;
+            let body = String::from_utf8(body).expect("valid UTF-8");
+            let fields = body.split('\0').collect::<Vec<_>>();
+            // Every query has the same 16 fields (plus a trailing empty split)
+            assert_eq!(fields.len(), 17);
+            assert_eq!(fields[12], if include_expired { "1" } else { "0" });
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_parse_osi() -> Result<(), InvalidOsi> {
+    let parts = SecOption::parse_osi("AAPL  250117C00190000")?;
+    assert_eq!(parts.symbol(), "AAPL");
+    assert_eq!(parts.expiration_date(), NaiveDate::from_ymd_opt(2025, 1, 17).unwrap());
+    assert_eq!(parts.class(), SecOptionClass::Call);
+    assert!((parts.strike() - 190.0).abs() < f64::EPSILON);
+    assert_eq!(parts.to_string(), "AAPL  250117C00190000");
+
+    // The unpadded form, a six-character root, and a strike with three decimal places
+    assert_eq!(SecOption::parse_osi("AAPL250117C00190000")?, parts);
+    let parts = SecOption::parse_osi("GOOGL1250620P00172500")?;
+    assert_eq!(parts.symbol(), "GOOGL1");
+    assert_eq!(parts.class(), SecOptionClass::Put);
+    assert!((parts.strike() - 172.5).abs() < f64::EPSILON);
+    assert!((SecOption::parse_osi("F     250117C00012125")?.strike() - 12.125).abs() < f64::EPSILON);
+
+    for (osi, expected) in [
+        ("C00190000", InvalidOsi::Length("C00190000".to_owned())),
+        ("AAPL   250117C00190000", InvalidOsi::Length("AAPL   250117C00190000".to_owned())),
+        ("      250117C00190000", InvalidOsi::Root("      250117C00190000".to_owned())),
+        ("AAPL  251317C00190000", InvalidOsi::ExpirationDate("AAPL  251317C00190000".to_owned())),
+        ("AAPL  250117X00190000", InvalidOsi::Class("AAPL  250117X00190000".to_owned())),
+        ("AAPL  250117C0019000.", InvalidOsi::Strike("AAPL  250117C0019000.".to_owned())),
+    ] {
+        assert_eq!(SecOption::parse_osi(osi), Err(expected));
+    }
+
+    assert_eq!(
+        Query::from_osi("AAPL250117C00190000", Routing::Smart)?,
+        Query::Pattern(
+            ContractSpec::new("AAPL", ContractType::SecOption, Routing::Smart, Currency::UsDollar)
+                .expiration_date(NaiveDate::from_ymd_opt(2025, 1, 17).unwrap())
+                .local_symbol("AAPL  250117C00190000")
+        )
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_security_id_out_msg() -> Result<(), Box<dyn std::error::Error>> {
+    use indicators::Valid;
+    use tokio::io::AsyncReadExt;
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let stream = tokio::net::TcpStream::connect(listener.local_addr()?).await?;
+    let (mut server, _) = listener.accept().await?;
+    let mut writer = crate::comm::Writer::new(stream.into_split().1);
+
+    let mut stock = StockBuilder::new("AAPL")
+        .primary_exchange(Primary::NationalAssociationOfSecurityDealers)
+        .currency(Currency::UsDollar)
+        .build()?;
+    let cases = [
+        (vec![SecurityId::isin("US0378331005")?], ["ISIN", "US0378331005"]),
+        (Vec::default(), ["", ""]),
+    ];
+    for (security_ids, expected) in cases {
+        stock.security_ids = security_ids;
+        writer.add_body((stock.as_out_msg(), stock.security_id_out_msg()))?;
+        writer.send().await?;
+
+        let len = server.read_u32().await?;
+        let mut body = vec![0; len as usize];
+        server.read_exact(&mut body).await?;
+        let body = String::from_utf8(body)?;
+        let fields = body.split('\0').collect::<Vec<_>>();
+        // The 12 contract fields, then the security ID type and value (plus a trailing empty split)
+        assert_eq!(fields.len(), 15);
+        assert_eq!(fields[12..14], expected);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_to_ib_fields() -> Result<(), Box<dyn std::error::Error>> {
+    let stock = StockBuilder::new("AAPL")
+        .primary_exchange(Primary::NationalAssociationOfSecurityDealers)
+        .currency(Currency::UsDollar)
+        .build()?;
+    assert_eq!(
+        stock.to_ib_fields(),
+        ["0", "AAPL", "STK", "", "", "", "", "SMART", "NASDAQ", "USD", "", ""]
+    );
+    assert_eq!(
+        sample_option().to_ib_fields(),
+        [
+            "552083996", "BMW", "OPT", "20241220", "72.5", "P", "100", "SMART", "", "USD",
+            "BMW-LOCAL", "BMW",
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_display() {
+    let stock = StockBuilder::new("AAPL")
+        .primary_exchange(Primary::NationalAssociationOfSecurityDealers)
+        .currency(Currency::UsDollar)
+        .contract_id(ContractId(265_598))
+        .build()
+        .unwrap();
+    assert_eq!(stock.to_string(), "AAPL STK SMART/NASDAQ USD (conid 265598)");
+    assert_eq!(
+        Contract::from(stock).to_string(),
+        "AAPL STK SMART/NASDAQ USD (conid 265598)"
+    );
+
+    let option = SecOptionBuilder::new(
+        "BMW",
+        SecOptionClass::Put,
+        72.0,
+        NaiveDate::from_ymd_opt(2022, 12, 16).unwrap(),
+    )
+    .currency(Currency::Euro)
+    .contract_id(ContractId(552_083_996))
+    .underlying_contract_id(ContractId(14_171))
+    .trading_class("BMW")
+    .local_symbol("P BMW  20221216 72 M")
+    .build()
+    .unwrap();
+    assert_eq!(
+        option.to_string(),
+        "BMW OPT 20221216 P 72 SMART 100x EUR (conid 552083996)"
+    );
+
+    assert_eq!(
+        sample_future().to_string(),
+        "ES FUT 20241220 CME 50x USD (conid 495512563)"
+    );
+    assert_eq!(
+        sample_warrant().to_string(),
+        "DAI WAR 20241220 C 60 SMART 0.1x USD (conid 448814012)"
+    );
+}
+
+#[test]
+fn test_structured_product() -> Result<(), Box<dyn std::error::Error>> {
+    let product = sample_structured_product();
+    assert_eq!(
+        product.to_string(),
+        "DAX IOPT 20241220 SWB 0.01x USD (conid 654432711)"
+    );
+    assert_eq!("iopt".parse::<ContractType>()?, ContractType::StructuredProduct);
+    assert_eq!(ContractType::StructuredProduct.as_ib_str(), "IOPT");
+    assert!((product.notional(120.0, 500.0) - 600.0).abs() < 1e-9);
+
+    let contract = Contract::from(product.clone());
+    assert_eq!(contract.structured_product_ref(), Some(&product));
+    assert!(contract.clone().warrant().is_none());
+    assert!(Stock::try_from(contract.clone()).is_err());
+    assert_eq!(contract.trading_class(), Some("TURBO"));
+    assert_eq!(StructuredProduct::try_from(contract)?, product);
+
+    let proxy = Proxy::<Contract, HasExchange> {
+        inner: Contract::StructuredProduct(product),
+        _exch: std::marker::PhantomData,
+    };
+    let serialized = toml::to_string(&proxy)?;
+    let deserialized: Proxy<Contract, HasExchange> = toml::from_str(&serialized)?;
+    let Some(product) = deserialized.structured_product() else {
+        panic!("Expected a structured product");
+    };
+    assert_eq!(product.exchange(), Routing::Primary(Primary::StuttgartWertpapierboerse));
+    assert_eq!(product.expiration_date(), NaiveDate::from_ymd_opt(2024, 12, 20).unwrap());
+    assert!((product.multiplier() - 0.01).abs() < f64::EPSILON);
+    assert_eq!(product.trading_class(), "TURBO");
+    Ok(())
+}
+
+#[test]
+fn test_parse_summary() -> Result<(), ParseContractError> {
+    for s in [
+        "AAPL STK SMART/NASDAQ USD (conid 265598)",
+        "BMW STK IBIS/IBIS EUR (conid 14171)",
+    ] {
+        let stock: Stock = s.parse()?;
+        assert_eq!(stock.to_string(), s);
+        assert_eq!(stock.to_string().parse::<Stock>()?, stock);
+    }
+    let stock: Stock = "AAPL STK NASDAQ USD".parse()?;
+    assert_eq!(stock.exchange, Routing::Primary(Primary::NationalAssociationOfSecurityDealers));
+    assert_eq!(stock.primary_exchange, Primary::NationalAssociationOfSecurityDealers);
+    assert_eq!(stock.contract_id, ContractId(0));
+
+    let s = "EUR CASH IDEALPRO USD (conid 12087792)";
+    let forex: Forex = s.parse()?;
+    assert_eq!(forex.to_string(), s);
+    assert_eq!(forex.to_string().parse::<Forex>()?, forex);
+    assert_eq!(forex.local_symbol, "EUR.USD");
+
+    // A fully-populated contract's summary parses to the same contract, less the details
+    let contracts: [Contract; 3] =
+        [sample_forex().into(), sample_crypto().into(), sample_stock().into()];
+    for contract in contracts {
+        let summary = contract.to_string();
+        match contract {
+            Contract::Forex(forex) => {
+                let parsed: Forex = summary.parse()?;
+                assert_eq!((parsed.contract_id, parsed.exchange), (forex.contract_id, forex.exchange));
+            }
+            Contract::Stock(stock) => {
+                let parsed: Stock = summary.parse()?;
+                assert_eq!(parsed.symbol, stock.symbol);
+                assert_eq!(parsed.primary_exchange, stock.primary_exchange);
+            }
+            Contract::Crypto(_) => assert!(matches!(
+                summary.parse::<Forex>(),
+                Err(ParseContractError::UnexpectedSecurityType(_))
+            )),
+            _ => unreachable!(),
+        }
+    }
+
+    assert!(matches!(
+        "AAPL STK SMART/NASDAQ".parse::<Stock>(),
+        Err(ParseContractError::Format(_))
+    ));
+    assert!(matches!(
+        "AAPL STK SMART/NASDAQ USD (id 1)".parse::<Stock>(),
+        Err(ParseContractError::Format(_))
+    ));
+    assert!(matches!(
+        "AAPL STK SMART/NASDAQ XYZ".parse::<Stock>(),
+        Err(ParseContractError::Currency(_))
+    ));
+    assert!(matches!(
+        "EUR CASH IDEALPRO USD".parse::<Stock>(),
+        Err(ParseContractError::UnexpectedSecurityType(_))
+    ));
+    assert!(matches!(
+        "XYZ CASH IDEALPRO USD".parse::<Forex>(),
+        Err(ParseContractError::Currency(_))
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_forex_pair() -> Result<(), ParseContractError> {
+    use Currency::{
+        AustralianDollar, BritishPound, CanadianDollar, Euro, JapaneseYen, NewZealandDollar,
+        SwissFranc, UsDollar,
+    };
+
+    let forex: Forex = "EUR CASH IDEALPRO USD (conid 12087792)".parse()?;
+    assert_eq!(forex.base(), Euro);
+    assert_eq!(forex.quote(), UsDollar);
+    assert_eq!(forex.pair(), (Euro, UsDollar));
+
+    // Each requested pair, and the pair as IBKR lists it
+    let majors = [
+        ((Euro, UsDollar), "EUR", UsDollar),
+        ((UsDollar, Euro), "EUR", UsDollar),
+        ((UsDollar, JapaneseYen), "USD", JapaneseYen),
+        ((JapaneseYen, UsDollar), "USD", JapaneseYen),
+        ((BritishPound, UsDollar), "GBP", UsDollar),
+        ((UsDollar, SwissFranc), "USD", SwissFranc),
+        ((SwissFranc, UsDollar), "USD", SwissFranc),
+        ((AustralianDollar, UsDollar), "AUD", UsDollar),
+        ((UsDollar, CanadianDollar), "USD", CanadianDollar),
+        ((CanadianDollar, UsDollar), "USD", CanadianDollar),
+        ((NewZealandDollar, UsDollar), "NZD", UsDollar),
+        ((Euro, BritishPound), "EUR", BritishPound),
+        ((JapaneseYen, Euro), "EUR", JapaneseYen),
+        ((BritishPound, JapaneseYen), "GBP", JapaneseYen),
+        ((SwissFranc, JapaneseYen), "CHF", JapaneseYen),
+        ((AustralianDollar, NewZealandDollar), "AUD", NewZealandDollar),
+    ];
+    for ((base, quote), symbol, currency) in majors {
+        assert_eq!(
+            Query::forex_pair(base, quote),
+            Query::Pattern(ContractSpec::new(
+                symbol,
+                ContractType::Forex,
+                Routing::Primary(Primary::IbForexPro),
+                currency,
+            )),
+            "{base}{quote}"
+        );
+    }
+
+    let cable = Forex::from_pair(BritishPound, UsDollar, Routing::Primary(Primary::IbForexPro))
+        .expect("GBP and USD differ");
+    assert_eq!(cable.pair(), (BritishPound, UsDollar));
+    assert_eq!(cable.symbol(), "GBP");
+    assert_eq!(cable.local_symbol(), "GBP.USD");
+    assert_eq!(cable.trading_class(), "GBP.USD");
+    assert_eq!(cable.currency(), UsDollar);
+    assert_eq!(
+        Forex::from_pair(Euro, Euro, Routing::Smart),
+        Err(SameCurrencyPairError(Euro))
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+/// Build a sample contract of type `$name` with the fields that every contract shares.
+macro_rules! sample {
+    ($name: ident, $id: expr, $symbol: expr; $($field: ident: $value: expr),* $(,)?) => {
+        $name {
+            contract_id: ContractId($id),
+            min_tick: NotNan(0.01),
+            symbol: $symbol.to_owned(),
+            $($field: $value,)*
+            currency: Currency::UsDollar,
+            local_symbol: format!("{}-LOCAL", $symbol),
+            long_name: format!("{} Long Name", $symbol),
+            order_types: Vec::default(),
+            valid_exchanges: Vec::default(),
+            details: ContractDetails::default(),
+        }
+    };
+}
+
+#[cfg(test)]
+fn sample_date() -> NaiveDate {
+    NaiveDate::from_ymd_opt(2024, 12, 20).unwrap()
+}
+
+#[cfg(test)]
+fn sample_forex() -> Forex {
+    sample!(Forex, 12_087_792, "EUR";
+        exchange: Routing::Smart, trading_class: "EUR.USD".to_owned())
+}
+
+#[cfg(test)]
+fn sample_crypto() -> Crypto {
+    sample!(Crypto, 479_624_278, "BTC";
+        exchange: Routing::Primary(Primary::PaxosCryptoExchange), trading_class: "BTC".to_owned())
+}
+
+#[cfg(test)]
+fn sample_stock() -> Stock {
+    sample!(Stock, 265_598, "AAPL";
+        exchange: Routing::Smart,
+        primary_exchange: Primary::NationalAssociationOfSecurityDealers,
+        stock_type: String::default(),
+        security_ids: Vec::default(),
+        sector: String::default(),
+        trading_class: "NMS".to_owned())
+}
+
+#[cfg(test)]
+fn sample_index() -> Index {
+    sample!(Index, 416_904, "SPX"; exchange: Routing::Smart)
+}
+
+#[cfg(test)]
+fn sample_future() -> SecFuture {
+    sample!(SecFuture, 495_512_563, "ES";
+        exchange: Routing::Primary(Primary::ChicagoMercantileExchange), multiplier: NotNan(50.0),
+        expiration_date: Some(sample_date()), contract_month: (2024, 12), trading_class: "ES".to_owned(),
+        underlying_contract_id: ContractId(495_512_563))
+}
+
+#[cfg(test)]
+fn sample_option() -> SecOption {
+    SecOption::Put(sample!(SecOptionInner, 552_083_996, "BMW";
+        exchange: Routing::Smart, strike: NotNan(72.5), multiplier: NotNan(100.0),
+        expiration_date: Some(sample_date()), contract_month: (2024, 12),
+        underlying_contract_id: ContractId(552_083_996), sector: String::default(),
+        trading_class: "BMW".to_owned()))
+}
+
+#[cfg(test)]
+fn sample_commodity() -> Commodity {
+    sample!(Commodity, 69_067_924, "XAUUSD";
+        exchange: Routing::Smart, commodity_type: String::default(),
+        trading_class: "XAUUSD".to_owned())
+}
+
+#[cfg(test)]
+fn sample_bond() -> Bond {
+    sample!(Bond, 504_497_158, "US-T";
+        exchange: Routing::Smart, cusip: String::default(), coupon: NotNan::default(),
+        maturity_date: sample_date(), issue_date: String::default(), ratings: String::default(),
+        bond_type: String::default(), coupon_type: String::default(), callable: false,
+        putable: false, convertible: false, security_ids: Vec::default(),
+        trading_class: "UST".to_owned())
+}
+
+#[cfg(test)]
+fn sample_cfd() -> Cfd {
+    sample!(Cfd, 143_916_318, "IBUS500";
+        exchange: Routing::Smart, underlying_contract_id: ContractId(143_916_318),
+        cfd_type: String::default(), trading_class: "IBUS500".to_owned())
+}
+
+#[cfg(test)]
+fn sample_mutual_fund() -> MutualFund {
+    sample!(MutualFund, 10_660_241, "VINIX";
+        exchange: Routing::Smart, fund_family: String::default(),
+        fund_type: String::default(), fund_front_load: None, fund_back_load: None,
+        fund_back_load_time_interval: String::default(), fund_management_fee: None,
+        trading_class: "VINIX".to_owned())
+}
+
+#[cfg(test)]
+fn sample_future_option() -> SecFutureOption {
+    SecFutureOption::Call(sample!(SecFutureOptionInner, 725_930_319, "ES";
+        exchange: Routing::Primary(Primary::ChicagoMercantileExchange), strike: NotNan(6000.0),
+        multiplier: NotNan(50.0), expiration_date: sample_date(),
+        underlying_future_contract_id: ContractId(725_930_319),
+        trading_class: "ES".to_owned()))
+}
+
+#[cfg(test)]
+fn sample_warrant() -> Warrant {
+    Warrant::Call(sample!(WarrantInner, 448_814_012, "DAI";
+        exchange: Routing::Smart, strike: NotNan(60.0), multiplier: NotNan(0.1),
+        expiration_date: sample_date(), underlying_contract_id: ContractId(448_814_012),
+        trading_class: "DAI".to_owned(), issuer: "DB".to_owned()))
+}
+
+#[cfg(test)]
+fn sample_continuous_future() -> ContinuousFuture {
+    sample!(ContinuousFuture, 11_004_968, "ES";
+        exchange: Routing::Primary(Primary::ChicagoMercantileExchange),
+        trading_class: "ES".to_owned(), underlying_contract_id: ContractId(11_004_968))
+}
+
+#[cfg(test)]
+fn sample_structured_product() -> StructuredProduct {
+    sample!(StructuredProduct, 654_432_711, "DAX";
+        exchange: Routing::Primary(Primary::StuttgartWertpapierboerse),
+        multiplier: NotNan(0.01), expiration_date: sample_date(), trading_class: "TURBO".to_owned())
+}
+
+#[cfg(test)]
+fn sample_combo() -> Combo {
+    sample!(Combo, 0, "SPX";
+        exchange: Routing::Smart,
+        legs: vec![
+            ComboLeg {
+                contract_id: ContractId(720_708_753),
+                ratio: 1,
+                action: Action::Buy,
+                exchange: Routing::Smart,
+            },
+            ComboLeg {
+                contract_id: ContractId(720_708_761),
+                ratio: 1,
+                action: Action::Sell,
+                exchange: Routing::Smart,
+            },
+        ],
+        delta_neutral_contract: Some(DeltaNeutralContract {
+            contract_id: ContractId(416_904),
+            delta: NotNan(0.5),
+            price: NotNan(5_800.0),
+        }))
+}
+
+#[cfg(test)]
+/// One sample contract of every type, for tests that check every type of contract. Tests of a
+/// single type build it with its own constructor, like [`sample_stock`].
+fn sample_contracts() -> Vec<Contract> {
+    vec![
+        sample_forex().into(),
+        sample_crypto().into(),
+        sample_stock().into(),
+        sample_index().into(),
+        sample_future().into(),
+        sample_option().into(),
+        sample_commodity().into(),
+        sample_bond().into(),
+        sample_cfd().into(),
+        sample_mutual_fund().into(),
+        sample_future_option().into(),
+        sample_warrant().into(),
+        sample_continuous_future().into(),
+        sample_structured_product().into(),
+        sample_combo().into(),
+    ]
+}
+
+#[test]
+fn test_contract_cache() {
+    let (stock, forex) = (Contract::from(sample_stock()), Contract::from(sample_forex()));
+    let aapl = Query::IbContractId(ContractId(265_598), Routing::Smart);
+    let aapl_island = Query::IbContractId(ContractId(265_598), Routing::Primary(Primary::Island));
+
+    let mut cache = ContractCache::new();
+    assert!(cache.is_empty());
+    cache.insert(aapl.clone(), stock.clone());
+    assert_eq!(cache.get(&aapl), Some(&stock));
+    // The routing is part of the key
+    assert_eq!(cache.get(&aapl_island), None);
+    cache.insert(aapl_island.clone(), forex);
+    assert_eq!(cache.len(), 2);
+    assert_eq!(cache.get(&aapl), Some(&stock));
+    cache.evict_expired();
+    assert_eq!(cache.len(), 2);
+    cache.clear();
+    assert!(cache.is_empty());
+
+    let mut cache = ContractCache::with_ttl(chrono::Duration::zero());
+    cache.insert(aapl.clone(), stock);
+    assert_eq!(cache.get(&aapl), None);
+    cache.evict_expired();
+    assert!(cache.is_empty());
+}
+
+#[cfg(feature = "serde-full")]
+#[test]
+fn test_contract_cache_warm_start() -> Result<(), ContractCacheError> {
+    let mut cache = ContractCache::new();
+    let queries = sample_contracts()
+        .into_iter()
+        .enumerate()
+        .map(|(i, contract)| {
+            let query = match i % 3 {
+                0 => Query::IbContractId(contract.contract_id(), Routing::Smart),
+                1 => Query::SecurityId(SecurityId::Cusip(format!("{i:09}")), Routing::Smart),
+                _ => Query::Pattern(
+                    ContractSpec::new(
+                        contract.symbol(),
+                        contract.contract_type(),
+                        contract.exchange(),
+                        contract.currency(),
+                    )
+                    .local_symbol(contract.local_symbol()),
+                ),
+            };
+            cache.insert(query.clone(), contract.clone());
+            (query, contract)
+        })
+        .collect::<Vec<_>>();
+
+    let mut buf = Vec::new();
+    cache.serialize_to(&mut buf)?;
+    let mut loaded = ContractCache::new();
+    loaded.load_from(buf.as_slice())?;
+    assert_eq!(loaded.len(), queries.len());
+    for (query, contract) in &queries {
+        assert_eq!(loaded.get(query), Some(contract));
+    }
+
+    // Entries that expired while the cache was saved are dropped on load
+    let mut expired = ContractCache::with_ttl(chrono::Duration::zero());
+    expired.load_from(buf.as_slice())?;
+    assert!(expired.is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_contract_serde_round_trip() -> Result<(), Box<dyn std::error::Error>> {
+    for contract in sample_contracts() {
+        let serialized = toml::to_string(&contract)?;
+        let deserialized: Contract = toml::from_str(&serialized)?;
+        assert_eq!(deserialized, contract, "{serialized}");
+    }
+
+    let stock = sample_stock();
+    let serialized = toml::to_string(&stock)?;
+    assert_eq!(toml::from_str::<Stock>(&serialized)?, stock);
+    assert!(toml::from_str::<Forex>(&serialized).is_err());
+    Ok(())
+}
+
+#[cfg(feature = "serde-full")]
+#[test]
+fn test_contract_serde_full_round_trip() -> Result<(), Box<dyn std::error::Error>> {
+    // A small linear congruential generator keeps the generated cases reproducible
+    let mut state = 0x2545_f491_u64;
+    let mut next = move || {
+        state = state
+            .wrapping_mul(6_364_136_223_846_793_005)
+            .wrapping_add(1_442_695_040_888_963_407);
+        state >> 33
+    };
+    let exchanges = [
+        Routing::Smart,
+        Routing::Primary(Primary::ChicagoMercantileExchange),
+        Routing::Primary(Primary::NationalAssociationOfSecurityDealers),
+    ];
+
+    for _ in 0..16 {
+        for mut contract in sample_contracts() {
+            let n = next();
+            let order_types = (0..n % 4)
+                .map(|i| OrderTypeCode::Other(format!("TYPE{i}")))
+                .collect::<Vec<_>>();
+            #[allow(clippy::cast_possible_truncation)]
+            let valid_exchanges = exchanges[..(n % 4) as usize].to_vec();
+            #[allow(clippy::cast_precision_loss)]
+            let min_tick = NotNan((n % 1000) as f64 / 10_000.0);
+            match_poly!(&mut contract;
+                Contract::Forex(s)
+                | Contract::Crypto(s)
+                | Contract::Stock(s)
+                | Contract::Index(s)
+                | Contract::SecFuture(s)
+                | Contract::SecOption(SecOption::Call(s) | SecOption::Put(s))
+                | Contract::Commodity(s)
+                | Contract::Bond(s)
+                | Contract::Cfd(s)
+                | Contract::MutualFund(s)
+                | Contract::SecFutureOption(SecFutureOption::Call(s) | SecFutureOption::Put(s))
+                | Contract::Warrant(Warrant::Call(s) | Warrant::Put(s))
+                | Contract::ContinuousFuture(s)
+                | Contract::StructuredProduct(s)
+                | Contract::Combo(s) => {
+                    s.order_types.clone_from(&order_types);
+                    s.valid_exchanges.clone_from(&valid_exchanges);
+                    s.min_tick = min_tick;
+                }
+            );
+            if let Contract::Stock(s) = &mut contract {
+                s.sector = format!("SECTOR{n}");
+                s.security_ids = vec![
+                    SecurityId::Isin("US0378331005".to_owned()),
+                    SecurityId::Cusip("037833100".to_owned()),
+                ];
+            }
+
+            let serialized = toml::to_string(&contract)?;
+            assert!(serialized.contains("contract_type = "), "{serialized}");
+            let deserialized: Contract = toml::from_str(&serialized)?;
+            assert_eq!(deserialized, contract, "{serialized}");
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_contract_id_validation() {
+    assert_eq!(ContractId::new(265_598).map(ContractId::get), Ok(265_598));
+    assert_eq!(ContractId::try_from(0), Err(InvalidContractId(0)));
+    assert_eq!(ContractId::new(-1), Err(InvalidContractId(-1)));
+    assert_eq!("265598".parse::<ContractId>().ok(), Some(ContractId(265_598)));
+    assert!(matches!(
+        "0".parse::<ContractId>(),
+        Err(ParseContractIdError::Invalid(InvalidContractId(0)))
+    ));
+    assert!(matches!("AAPL".parse::<ContractId>(), Err(ParseContractIdError::Int(_))));
+    assert!(matches!(
+        "0".parse::<Query>(),
+        Err(ParseQueryError::IbContractId(ParseContractIdError::Invalid(_)))
+    ));
+
+    #[derive(Debug, Deserialize)]
+    struct Row {
+        contract_id: ContractId,
+    }
+
+    let row = |s: &str| toml::from_str::<Row>(s).map(|row| row.contract_id);
+    assert_eq!(row("contract_id = 265598").ok(), Some(ContractId(265_598)));
+    // Partially populated contracts are serialized with a contract ID of 0
+    assert_eq!(row("contract_id = 0").ok(), Some(ContractId(0)));
+    let Err(e) = row("contract_id = -5") else {
+        panic!("A negative contract ID must not deserialize");
+    };
+    assert!(e.to_string().contains("Invalid contract ID -5"));
+    assert!(toml::from_str::<Query>("IbContractId = [-5, \"SMART\"]").is_err());
+}
+
+#[test]
+fn test_query_options_for_query() {
+    let query = Query::from(ContractId(495_512_563));
+    assert!(QueryOptions::for_query::<SecFuture>(&query).include_expired);
+    assert!(QueryOptions::for_query::<SecOption>(&query).include_expired);
+    assert!(QueryOptions::for_query::<SecFutureOption>(&query).include_expired);
+    assert!(!QueryOptions::for_query::<Stock>(&query).include_expired);
+    assert!(!QueryOptions::for_query::<Warrant>(&query).include_expired);
+    // The contract of an ID may be an expired derivative
+    assert!(QueryOptions::for_query::<Contract>(&query).include_expired);
+
+    let spec = |contract_type| {
+        Query::Pattern(ContractSpec::new(
+            "ES",
+            contract_type,
+            Routing::Primary(Primary::ChicagoMercantileExchange),
+            Currency::UsDollar,
+        ))
+    };
+    assert!(QueryOptions::for_query::<Contract>(&spec(ContractType::SecFuture)).include_expired);
+    assert!(!QueryOptions::for_query::<Contract>(&spec(ContractType::Index)).include_expired);
+    // The type of the requested security takes precedence
+    assert!(!QueryOptions::for_query::<Stock>(&spec(ContractType::SecFuture)).include_expired);
+}
+
+#[test]
+fn test_query_serde_round_trip() -> Result<(), Box<dyn std::error::Error>> {
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Watchlist {
+        queries: Vec<Query>,
+    }
+
+    let watchlist = Watchlist {
+        queries: vec![
+            Query::IbContractId(ContractId(265_598), Routing::Smart),
+            Query::Figi(
+                "BBG000B9XRY4".parse()?,
+                Routing::Primary(Primary::NationalAssociationOfSecurityDealers),
+            ),
+        ],
+    };
+    let serialized = toml::to_string(&watchlist)?;
+    assert!(serialized.contains(r#"IbContractId = [265598, "SMART"]"#));
+    assert!(serialized.contains(r#"Figi = ["BBG000B9XRY4", { Primary = "NASDAQ" }]"#));
+    assert_eq!(toml::from_str::<Watchlist>(&serialized)?, watchlist);
+    Ok(())
+}
+
+#[test]
+fn test_valid_exchanges() {
+    let cme = Routing::Primary(Primary::ChicagoMercantileExchange);
+    let cboe = Routing::Primary(Primary::ChicagoBoardOptionsExchange);
+    let mut stock = sample_stock();
+    assert!(!stock.is_valid_exchange(&Routing::Smart));
+    assert_eq!(stock.best_exchange(), None);
+
+    stock.valid_exchanges = vec![cme, cboe];
+    assert!(stock.is_valid_exchange(&cboe));
+    assert!(!stock.is_valid_exchange(&Routing::Smart));
+    assert_eq!(stock.best_exchange(), Some(cme));
+
+    stock.valid_exchanges.push(Routing::Smart);
+    assert_eq!(stock.best_exchange(), Some(Routing::Smart));
+    assert_eq!(Contract::Stock(stock).best_exchange(), Some(Routing::Smart));
+}
+
+#[test]
+fn test_try_from_contract() {
+    for contract in sample_contracts() {
+        let contract_type = contract.contract_type();
+        let as_stock = Stock::try_from(contract.clone());
+        let as_future = SecFuture::try_from(contract);
+        match contract_type {
+            ContractType::Stock => assert!(as_stock.is_ok()),
+            ContractType::SecFuture => assert!(as_future.is_ok()),
+            _ => {
+                let err = as_stock.unwrap_err();
+                assert_eq!((err.expected(), err.found()), (ContractType::Stock, contract_type));
+                let err = as_future.unwrap_err();
+                assert_eq!((err.expected(), err.found()), (ContractType::SecFuture, contract_type));
+                assert_eq!(
+                    err.to_string(),
+                    format!("Unexpected security type. Expected SecFuture; found {contract_type:?}.")
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn test_front_month_future() {
+    let mut contracts = sample_contracts();
+    let template = sample_future();
+    // ES expires quarterly, on the third Friday of March, June, September, and December
+    let expirations = [(2024, 12, 20), (2025, 3, 21), (2025, 6, 20), (2024, 9, 20)];
+    contracts.retain(|c| !matches!(c, Contract::SecFuture(_)));
+    for (id, (y, m, d)) in (1..).zip(expirations) {
+        let mut fut = template.clone();
+        fut.contract_id = ContractId(id);
+        fut.expiration_date = NaiveDate::from_ymd_opt(y, m, d);
+        contracts.push(Contract::SecFuture(fut));
+    }
+
+    let front = |today: (i32, u32, u32), roll_days| {
+        let today = NaiveDate::from_ymd_opt(today.0, today.1, today.2).unwrap();
+        front_month_future(contracts.clone(), today, roll_days).map(|fut| fut.contract_id)
+    };
+    assert_eq!(front((2024, 10, 1), 0), Some(ContractId(1)));
+    assert_eq!(front((2024, 12, 20), 0), Some(ContractId(1)));
+    assert_eq!(front((2024, 12, 21), 0), Some(ContractId(2)));
+    // Roll a week ahead of expiry
+    assert_eq!(front((2024, 12, 13), 7), Some(ContractId(1)));
+    assert_eq!(front((2024, 12, 14), 7), Some(ContractId(2)));
+    assert_eq!(front((2025, 6, 21), 0), None);
+
+    let curve = futures_curve(contracts).into_iter().map(|fut| fut.contract_id);
+    assert!(curve.eq([4, 1, 2, 3].map(ContractId)));
+}
+
+#[test]
+fn test_notional_sum() {
+    // 100 AAPL shares, 2 ES futures, and 3 short options on a 100 multiplier
+    let mut positions = vec![
+        (Contract::from(sample_stock()), 190.0, 100.0),
+        (Contract::from(sample_future()), 6_000.0, 2.0),
+        (Contract::from(sample_option()), 70.0, -3.0),
+    ];
+    let (notional, currency) = notional_sum(&positions).unwrap();
+    assert_eq!(currency, Currency::UsDollar);
+    assert!((notional - (19_000.0 + 600_000.0 - 21_000.0)).abs() < 1e-6);
+
+    if let Contract::SecFuture(fut) = &mut positions[1].0 {
+        fut.currency = Currency::Euro;
+    }
+    let error = notional_sum(&positions).unwrap_err();
+    assert_eq!(error.currencies, [Currency::Euro, Currency::UsDollar]);
+    assert!(notional_sum::<Contract>(&[]).unwrap_err().currencies.is_empty());
+}
+
+#[test]
+fn test_option_moneyness() {
+    let put = sample_option();
+    // Both options are struck at 72.5
+    let call = SecOption::from_components(SecOptionClass::Call, put.clone().into_inner());
+    assert_eq!((call.class(), put.class()), (SecOptionClass::Call, SecOptionClass::Put));
+    for right in [OptionRight::Call, OptionRight::Put] {
+        let option = SecOption::new(right, put.clone().into_inner());
+        assert_eq!(option.right(), right);
+        assert_eq!(option.is_call(), right == OptionRight::Call);
+        assert_eq!(right.to_string().parse::<OptionRight>().ok(), Some(right));
+    }
+
+    for (underlying_price, call_value, put_value, call_moneyness, put_moneyness) in [
+        (150.0, 77.5, 0.0, Moneyness::InTheMoney, Moneyness::OutOfTheMoney),
+        (10.0, 0.0, 62.5, Moneyness::OutOfTheMoney, Moneyness::InTheMoney),
+        (72.5, 0.0, 0.0, Moneyness::AtTheMoney, Moneyness::AtTheMoney),
+        (72.51, 0.01, 0.0, Moneyness::InTheMoney, Moneyness::OutOfTheMoney),
+    ] {
+        assert!((call.intrinsic_value(underlying_price) - call_value).abs() < 1e-9);
+        assert!((put.intrinsic_value(underlying_price) - put_value).abs() < 1e-9);
+        assert_eq!(call.moneyness(underlying_price), call_moneyness);
+        assert_eq!(put.moneyness(underlying_price), put_moneyness);
+    }
+    // A proxy, like that of a position, agrees with its option
+    let proxy = Proxy::<SecOption, NoExchange> {
+        inner: put.clone(),
+        _exch: std::marker::PhantomData,
+    };
+    assert!((proxy.intrinsic_value(60.0) - 12.5).abs() < 1e-9);
+    assert_eq!(proxy.moneyness(60.0), Moneyness::InTheMoney);
+    // The intrinsic value of a position scales with its multiplier and quantity
+    assert!((proxy.notional(proxy.intrinsic_value(60.0), 2.0) - 2_500.0).abs() < 1e-9);
+    // A contract of the 100x option is worth 100 times its per-unit intrinsic value
+    assert!((put.multiplier() - 100.0).abs() < f64::EPSILON);
+    assert!((put.intrinsic_value_per_contract(60.0) - 1_250.0).abs() < 1e-9);
+    assert!((proxy.intrinsic_value_per_contract(60.0) - 1_250.0).abs() < 1e-9);
+    assert!(call.intrinsic_value_per_contract(60.0).abs() < f64::EPSILON);
+
+    assert_eq!(SecOptionClass::Call.to_string(), "C");
+    assert_eq!(SecOptionClass::Put.to_string(), "P");
+    for s in ["C", "c", "CALL", "Call"] {
+        assert_eq!(s.parse::<SecOptionClass>().ok(), Some(SecOptionClass::Call));
+    }
+    for s in ["P", "p", "PUT", "put"] {
+        assert_eq!(s.parse::<SecOptionClass>().ok(), Some(SecOptionClass::Put));
+    }
+    assert!("X".parse::<SecOptionClass>().is_err());
+}
+
+#[test]
+fn test_proxy_local_symbol() {
+    let option = SecOptionBuilder::new(
+        "AAPL",
+        SecOptionClass::Call,
+        190.0,
+        NaiveDate::from_ymd_opt(2025, 1, 17).unwrap(),
+    )
+    .currency(Currency::UsDollar)
+    .local_symbol("AAPL  250117C00190000")
+    .build()
+    .unwrap();
+    let proxy = Proxy::<SecOption, HasExchange> {
+        inner: option,
+        _exch: std::marker::PhantomData,
+    };
+    assert_eq!(proxy.symbol(), "AAPL");
+    assert_eq!(proxy.local_symbol(), "AAPL  250117C00190000");
+    assert_eq!(proxy.as_inner_ref().strike(), 190.0);
+
+    let stock = sample_stock();
+    let proxy = Proxy::<Contract, HasExchange> {
+        inner: Contract::Stock(stock.clone()),
+        _exch: std::marker::PhantomData,
+    };
+    assert_eq!(proxy.stock().map(Proxy::into_inner), Some(stock));
+}
+
+#[test]
+fn test_proxy_getters() {
+    let (option, future, index) = (sample_option(), sample_future(), sample_index());
+
+    let option = Proxy::<SecOption, HasExchange> {
+        inner: option,
+        _exch: std::marker::PhantomData,
+    };
+    assert_eq!(option.symbol(), "BMW");
+    assert_eq!(option.local_symbol(), "BMW-LOCAL");
+    assert_eq!(option.long_name(), "BMW Long Name");
+    assert!((option.min_tick() - 0.01).abs() < f64::EPSILON);
+    assert_eq!(option.contract_id(), ContractId(552_083_996));
+    assert_eq!(option.currency(), Currency::UsDollar);
+    assert_eq!(option.trading_class(), "BMW");
+
+    let future = Proxy::<SecFuture, HasExchange> {
+        inner: future,
+        _exch: std::marker::PhantomData,
+    };
+    assert_eq!(future.symbol(), "ES");
+    assert_eq!(future.local_symbol(), "ES-LOCAL");
+    assert_eq!(future.long_name(), "ES Long Name");
+    assert_eq!(future.contract_type(), ContractType::SecFuture);
+    assert!((future.multiplier() - 50.0).abs() < f64::EPSILON);
+    assert_eq!(future.trading_class(), "ES");
+    assert_eq!(
+        future.exchange(),
+        Routing::Primary(Primary::ChicagoMercantileExchange)
+    );
+
+    let index = Proxy::<Index, HasExchange> {
+        inner: index,
+        _exch: std::marker::PhantomData,
+    };
+    assert_eq!(index.symbol(), "SPX");
+    assert_eq!(index.exchange(), Routing::Smart);
+
+    // 2 ES futures at 6000 and 3 BMW puts at 1.5
+    assert!((future.notional(6000.0, 2.0) - 600_000.0).abs() < f64::EPSILON);
+    assert!((option.notional(1.5, 3.0) - 450.0).abs() < f64::EPSILON);
+    assert!((option.inner.notional(1.5, -3.0) + 450.0).abs() < f64::EPSILON);
+}
+
+#[test]
+fn test_merge_option_chains() {
+    let date = |d| NaiveDate::from_ymd_opt(2025, 1, d).unwrap();
+    let chain = |exchange, trading_class: &str, multiplier, expirations, strikes| OptionChain {
+        exchange,
+        underlying_contract_id: ContractId(416_904),
+        trading_classes: vec![trading_class.to_owned()],
+        multiplier,
+        expirations,
+        strikes,
+    };
+    let cboe = Routing::Primary(Primary::ChicagoBoardOptionsExchange);
+    let ise = Routing::Primary(Primary::InternationalSecuritiesExchange);
+
+    assert_eq!(OptionChain::merge(Vec::new()), None);
+
+    // Without a SMART chain, differing strike sets are combined
+    let merged = OptionChain::merge(vec![
+        chain(cboe, "SPX", 100.0, vec![date(17)], vec![5900.0, 6000.0]),
+        chain(ise, "SPXW", 100.0, vec![date(10), date(17)], vec![6000.0, 5950.0]),
+        chain(ise, "XSP", 10.0, vec![date(3)], vec![590.0]),
+    ])
+    .unwrap();
+    assert_eq!(merged.exchange, Routing::Smart);
+    assert_eq!(merged.trading_classes, ["SPX", "SPXW"]);
+    assert!((merged.multiplier - 100.0).abs() < f64::EPSILON);
+    assert_eq!(merged.expirations, [date(10), date(17)]);
+    assert_eq!(merged.strikes, [5900.0, 5950.0, 6000.0]);
+
+    // A SMART chain already covers every exchange
+    let merged = OptionChain::merge(vec![
+        chain(cboe, "SPX", 100.0, vec![date(17)], vec![5900.0]),
+        chain(Routing::Smart, "SPX", 100.0, vec![date(17)], vec![6000.0]),
+    ])
+    .unwrap();
+    assert_eq!(merged.exchange, Routing::Smart);
+    assert_eq!(merged.strikes, [6000.0]);
+
+    // A single exchange keeps its own routing
+    let merged =
+        OptionChain::merge(vec![chain(cboe, "SPX", 100.0, vec![date(17)], vec![6000.0])]).unwrap();
+    assert_eq!(merged.exchange, cboe);
+}
+
+#[test]
+fn test_option_chain_nearest() {
+    let date = |d| NaiveDate::from_ymd_opt(2025, 1, d).unwrap();
+    let mut chain = OptionChain {
+        exchange: Routing::Smart,
+        underlying_contract_id: ContractId(265_598),
+        trading_classes: vec!["AAPL".to_owned()],
+        multiplier: 100.0,
+        expirations: vec![date(24), date(10), date(17)],
+        strikes: vec![200.0, 190.0, 195.0],
+    };
+    assert_eq!(chain.nearest_strike(193.1), Some(195.0));
+    assert_eq!(chain.nearest_strike(1000.0), Some(200.0));
+    // Ties prefer the lower strike and the earlier expiration
+    assert_eq!(chain.nearest_strike(192.5), Some(190.0));
+    assert_eq!(chain.nearest_strike(f64::NAN), None);
+    assert_eq!(chain.nearest_expiration(date(15)), Some(date(17)));
+    assert_eq!(chain.nearest_expiration(date(1)), Some(date(10)));
+    assert_eq!(chain.nearest_expiration(date(20)), Some(date(17)));
+
+    chain.strikes.clear();
+    chain.expirations.clear();
+    assert_eq!(chain.nearest_strike(193.1), None);
+    assert_eq!(chain.nearest_expiration(date(15)), None);
+}
+
+#[test]
+fn test_combo() {
+    use indicators::Valid;
+
+    let spread = sample_combo();
+    let combo = Combo::new("SPX", Currency::UsDollar, Routing::Smart, spread.legs.clone());
+    assert_eq!(combo.contract_id(), ContractId(0));
+    assert_eq!(combo.contract_type(), ContractType::Combo);
+    assert_eq!(combo.to_string(), "SPX BAG SMART USD (conid 0)");
+    assert_eq!(combo.as_out_msg().security_type, "BAG");
+    assert_eq!(combo.combo_legs(), Some(spread.legs.as_slice()));
+    assert_eq!(Contract::Combo(spread).combo_legs().map(<[ComboLeg]>::len), Some(2));
+    assert_eq!(Contract::from(combo.clone()).combo(), Some(combo));
+    assert_eq!("BAG".parse::<ContractType>().ok(), Some(ContractType::Combo));
+}
+
+#[test]
+fn test_parse_schedule() -> Result<(), ParseScheduleError> {
+    use chrono::{NaiveTime, TimeZone};
+
+    let tz = chrono_tz::US::Eastern;
+    let schedule = Schedule::parse("20240304:0930-1200,1300-1600;20240305:CLOSED;", tz)?;
+    let date = NaiveDate::from_ymd_opt(2024, 3, 4).unwrap();
+    assert_eq!(
+        schedule.sessions,
+        [
+            Session {
+                date,
+                open: NaiveTime::from_hms_opt(9, 30, 0),
+                close: NaiveTime::from_hms_opt(12, 0, 0),
+                closed: false,
+            },
+            Session {
+                date,
+                open: NaiveTime::from_hms_opt(13, 0, 0),
+                close: NaiveTime::from_hms_opt(16, 0, 0),
+                closed: false,
+            },
+            Session {
+                date: NaiveDate::from_ymd_opt(2024, 3, 5).unwrap(),
+                open: None,
+                close: None,
+                closed: true,
+            },
+        ]
+    );
+    let at = |d, h, m| tz.with_ymd_and_hms(2024, 3, d, h, m, 0).unwrap();
+    assert!(schedule.is_open_at(at(4, 9, 30)));
+    assert!(!schedule.is_open_at(at(4, 12, 30)));
+    assert!(!schedule.is_open_at(at(4, 16, 0)));
+    assert!(!schedule.is_open_at(at(5, 10, 0)));
+    // Times in other time zones are converted to the schedule's time zone
+    assert!(schedule.is_open_at(chrono_tz::UTC.with_ymd_and_hms(2024, 3, 4, 15, 0, 0).unwrap()));
+    // Naive times are taken to be in the schedule's time zone
+    assert!(schedule.is_open(at(4, 15, 0).naive_local()));
+    assert!(!schedule.is_open(at(4, 12, 30).naive_local()));
+
+    // An overnight futures session closes on the following day
+    let ct = chrono_tz::US::Central;
+    let schedule = Schedule::parse("20240303:1700-20240304:1600;20240304:1700-20240305:1600", ct)?;
+    let at = |d, h| ct.with_ymd_and_hms(2024, 3, d, h, 0, 0).unwrap();
+    assert!(schedule.is_open_at(at(3, 23)));
+    assert!(schedule.is_open_at(at(4, 2)));
+    assert!(!schedule.is_open_at(at(4, 16)));
+    assert!(schedule.is_open_at(at(5, 15)));
+    assert!(!schedule.is_open_at(at(3, 16)));
+
+    assert!(Schedule::parse("20240304:0930", tz).is_err());
+    assert!(Schedule::parse("20240304:0930-25:00", tz).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_parse_contract_type() {
+    for contract in sample_contracts() {
+        let contract_type = contract.contract_type();
+        let code = contract_type.as_ib_str();
+        assert_eq!(code, contract_type.to_string());
+        assert_eq!(code.parse::<ContractType>().ok(), Some(contract_type));
+        assert_eq!(code.to_lowercase().parse::<ContractType>().ok(), Some(contract_type));
+    }
+    assert_eq!("Opt".parse::<ContractType>().ok(), Some(ContractType::SecOption));
+    assert!(matches!(
+        "stock".parse::<ContractType>(),
+        Err(ParseContractTypeError(s)) if s == "stock"
+    ));
+}
+
+#[test]
+fn test_market_rule() {
+    let rule = MarketRule {
+        id: MarketRuleId(26),
+        increments: vec![
+            PriceIncrement { low_edge: 0.0, increment: 0.0001 },
+            PriceIncrement { low_edge: 1.0, increment: 0.01 },
+        ],
+    };
+    assert!((rule.tick_for_price(0.5) - 0.0001).abs() < f64::EPSILON);
+    assert!((rule.tick_for_price(1.0) - 0.01).abs() < f64::EPSILON);
+    assert!((rule.tick_for_price(250.0) - 0.01).abs() < f64::EPSILON);
+    assert!((rule.round_to_tick(0.123_46) - 0.1235).abs() < 1e-9);
+    assert!((rule.round_to_tick(187.456) - 187.46).abs() < 1e-9);
+    assert!((rule.round_to_tick_down(187.459) - 187.45).abs() < 1e-9);
+    assert!((rule.round_to_tick_up(187.451) - 187.46).abs() < 1e-9);
+    assert!((rule.round_to_tick_down(0.123_49) - 0.1234).abs() < 1e-9);
+    assert!((rule.round_to_tick_up(0.5) - 0.5).abs() < 1e-9);
+    // 1.15 / 0.01 is just below 115 in floating point
+    assert!((rule.round_to_tick_up(1.15) - 1.15).abs() < 1e-9);
+    assert!((rule.round_to_tick_down(1.15) - 1.15).abs() < 1e-9);
+
+    let empty = MarketRule {
+        id: MarketRuleId(1),
+        increments: Vec::new(),
+    };
+    assert!(empty.tick_for_price(10.0).abs() < f64::EPSILON);
+    assert!((empty.round_to_tick(10.123) - 10.123).abs() < f64::EPSILON);
+}
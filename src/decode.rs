@@ -7,16 +7,19 @@ use tracing::warn;
 use crate::{
     currency::Currency,
     exchange::Routing,
-    message::{ToClient, ToWrapper},
+    message::{In, Subscriber, ToClient, ToWrapper, UpdatingBar},
     wrapper,
 };
 use crate::account::{self, ParseAttributeError, Tag, TagValue};
 use crate::contract::{
-    Commodity, Contract, ContractId, ContractType, Crypto, Forex, Index, Proxy, SecFuture,
-    SecOption, SecOptionInner, SecurityId, Stock,
+    contract_month_of, Bond, Cfd, Commodity, ContinuousFuture, Contract, ContractDetails, ContractId, ContractType, Crypto, DeltaNeutralContract, Forex,
+    Index, MarketRule, MarketRuleId, MutualFund, NotNan, OptionChain, PriceIncrement, Proxy, SecFuture, SecFutureOption, SecFutureOptionInner, SecOption,
+    Schedule, SecOptionInner, SecurityId, Stock, StructuredProduct, Warrant, WarrantInner,
 };
+use crate::error_code::{ErrorCode, Notification, TwsError};
 use crate::exchange::Primary;
 use crate::execution::{CommissionReport, Exec, Execution, OrderSide, ParseOrderSideError};
+use crate::order::OrderTypeCode;
 use crate::payload::{
     Bar,
     BarCore, BidAsk, ExchangeId, Fill, HistogramEntry, Last, market_depth::{CompleteEntry, Entry, Operation}, MarketDataClass, Midpoint,
@@ -26,11 +29,12 @@ use crate::tick::{
     Accessibility, AuctionData, CalculationResult, Class, Dividends, EtfNav, ExtremeValue, Ipo,
     MarkPrice, OpenInterest, Period, Price, PriceFactor, QuotingExchanges, Rate, RealTimeVolume,
     RealTimeVolumeBase, SecOptionCalculationResults, SecOptionCalculations,
-    SecOptionCalculationSource, SecOptionVolume, Size, SummaryVolume, TimeStamp, Volatility, Yield,
+    SecOptionCalculationSource, SecOptionVolume, Size, SummaryVolume, Tick, TickAttrib, TimeStamp,
+    Volatility, Yield,
 };
 
 type Tx = tokio::sync::mpsc::Sender<ToClient>;
-type Rx = tokio::sync::mpsc::Receiver<ToWrapper>;
+type Rx = PendingQueries;
 type Fields = std::vec::IntoIter<String>;
 type DecodeResult = Result<(), DecodeError>;
 
@@ -414,9 +418,15 @@ pub trait Local: wrapper::LocalWrapper {
             } else {
                 Some(parent_id)
             };
+            let delta_neutral_contract = decode_delta_neutral_contract(fields)?;
             wrapper
                 .open_order(order_id, proxy, client_id, parent_id, permanent_id)
                 .await;
+            if let Some(delta_neutral_contract) = delta_neutral_contract {
+                wrapper
+                    .open_order_delta_neutral_contract(order_id, delta_neutral_contract)
+                    .await;
+            }
 
             Ok(())
         }
@@ -799,7 +809,7 @@ pub trait Local: wrapper::LocalWrapper {
         async move {
             decode_fields!(
                 fields =>
-                    req_id @ 1: i64,
+                    req_id @ 2: i64,
                     position @ 0: u64,
                     operation @ 0: i64,
                     side @ 0: u32,
@@ -953,12 +963,11 @@ pub trait Local: wrapper::LocalWrapper {
     #[inline]
     fn bond_contract_data_msg(
         fields: &mut Fields,
-        wrapper: &mut Self,
+        _wrapper: &mut Self,
+        tx: &mut Tx,
+        rx: &mut Rx,
     ) -> impl Future<Output = DecodeResult> {
-        async move {
-            warn!("Unimplemented incoming message. Fields: {:?}", &fields);
-            Ok(())
-        }
+        async move { decode_bond_contract_no_wrapper(fields, tx, rx).await }
     }
 
     #[inline]
@@ -1220,8 +1229,14 @@ pub trait Local: wrapper::LocalWrapper {
     fn current_time_msg(
         fields: &mut Fields,
         wrapper: &mut Self,
+        tx: &mut Tx,
+        rx: &mut Rx,
     ) -> impl Future<Output = DecodeResult> {
         async move {
+            // The time is returned to the client if it awaits one from `Client::current_time`
+            if rx.take_current_time() {
+                return decode_current_time_no_wrapper(fields, tx).await;
+            }
             decode_fields!(
                 fields =>
                     req_id @ 1: i64,
@@ -1299,9 +1314,15 @@ pub trait Local: wrapper::LocalWrapper {
     fn contract_data_end_msg(
         fields: &mut Fields,
         wrapper: &mut Self,
+        tx: &mut Tx,
+        rx: &mut Rx,
     ) -> impl Future<Output = DecodeResult> {
         async move {
             decode_fields!(fields => req_id @ 2: i64);
+            rx.remove(req_id);
+            tx.send(ToClient::ContractQueryEnd(req_id))
+                .await
+                .map_err(Box::new)?;
             wrapper.contract_data_end(req_id).await;
             Ok(())
         }
@@ -1630,23 +1651,21 @@ pub trait Local: wrapper::LocalWrapper {
     #[inline]
     fn security_definition_option_parameter_msg(
         fields: &mut Fields,
-        wrapper: &mut Self,
+        _wrapper: &mut Self,
+        tx: &mut Tx,
+        _rx: &mut Rx,
     ) -> impl Future<Output = DecodeResult> {
-        async move {
-            warn!("Unimplemented incoming message. Fields: {:?}", &fields);
-            Ok(())
-        }
+        async move { decode_option_chain_no_wrapper(fields, tx).await }
     }
 
     #[inline]
     fn security_definition_option_parameter_end_msg(
         fields: &mut Fields,
-        wrapper: &mut Self,
+        _wrapper: &mut Self,
+        tx: &mut Tx,
+        _rx: &mut Rx,
     ) -> impl Future<Output = DecodeResult> {
-        async move {
-            warn!("Unimplemented incoming message. Fields: {:?}", &fields);
-            Ok(())
-        }
+        async move { decode_option_chain_end_no_wrapper(fields, tx).await }
     }
 
     #[inline]
@@ -1674,12 +1693,11 @@ pub trait Local: wrapper::LocalWrapper {
     #[inline]
     fn symbol_samples_msg(
         fields: &mut Fields,
-        wrapper: &mut Self,
+        _wrapper: &mut Self,
+        tx: &mut Tx,
+        _rx: &mut Rx,
     ) -> impl Future<Output = DecodeResult> {
-        async move {
-            warn!("Unimplemented incoming message. Fields: {:?}", &fields);
-            Ok(())
-        }
+        async move { decode_symbol_samples_no_wrapper(fields, tx).await }
     }
 
     #[inline]
@@ -1783,20 +1801,24 @@ pub trait Local: wrapper::LocalWrapper {
     fn head_timestamp_msg(
         fields: &mut Fields,
         wrapper: &mut Self,
+        tx: &mut Tx,
+        rx: &mut Rx,
     ) -> impl Future<Output = DecodeResult> {
         async move {
             decode_fields!(
                 fields =>
                     req_id @ 1: i64,
-                    timestamp @ 0: i64
+                    timestamp @ 0: String
             );
-            wrapper
-                .head_timestamp(
-                    req_id,
-                    DateTime::from_timestamp(timestamp, 0)
-                        .ok_or(("timestamp", ParseDateTimeError::Timestamp))?,
-                )
-                .await;
+            let timestamp = parse_historical_datetime(&timestamp).map_err(|e| ("timestamp", e))?;
+            if rx.take_wrapper_request(req_id) {
+                #[allow(deprecated)]
+                wrapper.head_timestamp(req_id, timestamp).await;
+            } else {
+                tx.send(ToClient::HeadTimestamp(req_id, timestamp))
+                    .await
+                    .map_err(Box::new)?;
+            }
             Ok(())
         }
     }
@@ -1805,27 +1827,19 @@ pub trait Local: wrapper::LocalWrapper {
     fn histogram_data_msg(
         fields: &mut Fields,
         wrapper: &mut Self,
+        tx: &mut Tx,
+        rx: &mut Rx,
     ) -> impl Future<Output = DecodeResult> {
         async move {
-            decode_fields!(
-                fields =>
-                    req_id @ 1: i64,
-                    num_points @ 0: usize
-            );
-            let mut hist = std::collections::HashMap::with_capacity(num_points);
-            for (bin, chunk) in fields
-                .take(num_points * 2)
-                .map(|v| v.parse())
-                .collect::<Result<Vec<f64>, _>>()
-                .map_err(|e| ("chunk", e))?
-                .chunks_exact(2)
-                .enumerate()
-            {
-                if let [price, size] = *chunk {
-                    hist.insert(bin, HistogramEntry { price, size });
-                }
+            let (req_id, entries) = decode_histogram(fields)?;
+            if rx.take_wrapper_request(req_id) {
+                #[allow(deprecated)]
+                wrapper.histogram(req_id, entries.into_iter().enumerate().collect()).await;
+            } else {
+                tx.send(ToClient::Histogram(req_id, entries))
+                    .await
+                    .map_err(Box::new)?;
             }
-            wrapper.histogram(req_id, hist).await;
             Ok(())
         }
     }
@@ -1849,10 +1863,7 @@ pub trait Local: wrapper::LocalWrapper {
                     volume @ 0: f64
             );
             let core = BarCore {
-                datetime: NaiveDateTime::parse_and_remainder(datetime_str.as_str(), "%Y%m%d %T")
-                    .map_err(|e| ("datetime", ParseDateTimeError::Parse(e)))?
-                    .0
-                    .and_utc(),
+                datetime: parse_historical_datetime(&datetime_str).map_err(|e| ("datetime", e))?,
                 open,
                 high,
                 low,
@@ -1902,12 +1913,11 @@ pub trait Local: wrapper::LocalWrapper {
     #[inline]
     fn market_rule_msg(
         fields: &mut Fields,
-        wrapper: &mut Self,
+        _wrapper: &mut Self,
+        tx: &mut Tx,
+        _rx: &mut Rx,
     ) -> impl Future<Output = DecodeResult> {
-        async move {
-            warn!("Unimplemented incoming message. Fields: {:?}", &fields);
-            Ok(())
-        }
+        async move { decode_market_rule_no_wrapper(fields, tx).await }
     }
 
     #[inline]
@@ -2353,301 +2363,1486 @@ pub(crate) async fn decode_contract_no_wrapper(
             symbol @ 0: String,
             sec_type @ 0: ContractType,
             expiration_date @ 0: String,
-            strike @ 0: f64,
+            strike @ 0: NotNan,
             class @ 0: String,
             exchange @ 0: Routing,
             currency @ 0: Currency,
             local_symbol @ 0: String,
-            trading_class @ 1: String,
+            market_name @ 0: String,
+            trading_class @ 0: String,
             contract_id @ 0: ContractId,
-            min_tick @ 0: f64,
+            min_tick @ 0: NotNan,
             multiplier @ 0: String,
             order_types @ 0: String,
             valid_exchanges @ 0: String,
-            underlying_contract_id @ 1: ContractId,
+            underlying_contract_id @ 1: i64,
             long_name @ 0: String,
             primary_exchange @ 0: String,
-            sector @ 1: String,
-            security_id_count @ 7: usize
+            contract_month @ 0: String,
+            sector @ 0: String,
+            category @ 0: String,
+            subcategory @ 0: String,
+            time_zone_id @ 0: String,
+            trading_hours @ 0: String,
+            liquid_hours @ 0: String,
+            security_id_count @ 2: usize
     );
 
     let order_types = order_types
         .split(',')
-        .map(std::borrow::ToOwned::to_owned)
+        .filter(|code| !code.is_empty())
+        .map(|code| OrderTypeCode::from(code.to_owned()))
         .collect();
     let valid_exchanges = valid_exchanges
         .split(',')
         .map(str::parse)
-        .collect::<Result<_, _>>()
+        .collect::<Result<Vec<_>, _>>()
         .map_err(|e| ("valid_exchanges", e))?;
-    let security_ids = (0..security_id_count)
-        .map(
-            |_| match nth(fields, 0, "security_ids")?.to_uppercase().as_str() {
-                "CUSIP" => Ok(SecurityId::Cusip(nth(fields, 0, "security_id")?)),
-                "SEDOL" => Ok(SecurityId::Sedol(nth(fields, 0, "security_id")?)),
-                "ISIN" => Ok(SecurityId::Isin(nth(fields, 0, "security_id")?)),
-                "RIC" => Ok(SecurityId::Ric(nth(fields, 0, "security_id")?)),
-                _ => Err(DecodeError::UnexpectedData(
-                    "Invalid security_id type found in STK contract_data_msg",
-                )),
-            },
-        )
-        .collect::<Result<_, _>>()?;
+    let security_ids = decode_security_ids(fields, security_id_count)?;
+    decode_fields!(fields => agg_group @ 0: Option<i64>);
+    decode_fields!(
+        fields =>
+            under_symbol @ 0: String,
+            under_security_type @ 0: String,
+            market_rule_ids @ 0: String,
+            real_expiration_date @ 0: String,
+            stock_type @ 0: String
+    );
+    decode_fields!(fields => min_size @ 0: Option<NotNan>);
+    decode_fields!(fields => size_increment @ 0: Option<NotNan>);
+    decode_fields!(fields => suggested_size_increment @ 0: Option<NotNan>);
+    let time_zone = if time_zone_id.is_empty() {
+        chrono_tz::UTC
+    } else {
+        time_zone_id
+            .parse()
+            .map_err(|e| ("time_zone_id", ParseDateTimeError::Timezone(e)))?
+    };
+    let details = ContractDetails {
+        market_name,
+        contract_month,
+        industry: sector.clone(),
+        category,
+        subcategory,
+        time_zone_id,
+        agg_group,
+        under_symbol,
+        under_security_type,
+        real_expiration_date: decode_date(&real_expiration_date, "real_expiration_date")?,
+        last_trade_time: last_trade_time(&expiration_date),
+        trading_hours: Schedule::parse(&trading_hours, time_zone)
+            .map_err(|e| ("trading_hours", e))?,
+        liquid_hours: Schedule::parse(&liquid_hours, time_zone)
+            .map_err(|e| ("liquid_hours", e))?,
+        min_size,
+        size_increment,
+        suggested_size_increment,
+        market_rule_ids: decode_market_rule_ids(&market_rule_ids, &valid_exchanges)?,
+    };
+    // IBKR reports an underlying contract ID of 0 for contracts that have no underlying
+    let underlying_contract_id = ContractId(underlying_contract_id);
+    if sec_type == ContractType::Forex {
+        // The symbol of a forex contract is its base currency
+        symbol.parse::<Currency>().map_err(|e| ("symbol", e))?;
+    }
 
-    if let Ok(ToWrapper::ContractQuery((query_client, req_id_client))) = rx.try_recv() {
-        if let crate::contract::Query::IbContractId(con_id_client, routing_client) = query_client {
-            if con_id_client != contract_id {
-                return Err(DecodeError::UnexpectedData("Unexpected contract ID"));
-            }
-            if exchange != routing_client {
-                return Err(DecodeError::UnexpectedData("Unexpected routing exchange"));
-            }
-        }
-        if req_id_client != req_id {
-            return Err(DecodeError::UnexpectedData("Unexpected request ID"));
-        }
-        let contract = match sec_type {
-            ContractType::Stock => Some(Contract::Stock(Stock {
-                symbol,
-                exchange,
-                currency,
-                local_symbol,
-                trading_class,
-                contract_id,
-                min_tick,
-                primary_exchange: primary_exchange
-                    .parse()
-                    .map_err(|e| ("primary_exchange", e))?,
-                long_name,
-                sector,
-                order_types,
-                valid_exchanges,
-                security_ids,
-                stock_type: nth(fields, 5, "stock_type")?,
-            })),
-            ContractType::SecOption => {
-                let inner = SecOptionInner {
-                    contract_id,
-                    min_tick,
-                    symbol,
-                    exchange,
-                    strike,
-                    multiplier: multiplier.parse().map_err(|e| ("multiplier", e))?,
-                    expiration_date: NaiveDate::parse_and_remainder(
-                        expiration_date.as_str(),
-                        "%Y%m%d",
-                    )
-                    .map_err(|e| ("expiration_date", ParseDateTimeError::Parse(e)))?
-                    .0,
-                    underlying_contract_id,
-                    sector,
-                    trading_class,
-                    currency,
-                    local_symbol,
-                    long_name,
-                    order_types,
-                    valid_exchanges,
-                };
-                match class.as_str() {
-                    "C" => Some(Contract::SecOption(SecOption::Call(inner))),
-                    "P" => Some(Contract::SecOption(SecOption::Put(inner))),
-                    _ => return Err(DecodeError::UnexpectedData("Unexpected option class")),
-                }
-            }
-            ContractType::Crypto => Some(Contract::Crypto(Crypto {
-                contract_id,
-                min_tick,
-                symbol,
-                trading_class,
-                currency,
-                local_symbol,
-                long_name,
-                order_types,
-                valid_exchanges,
-            })),
-            ContractType::Forex => Some(Contract::Forex(Forex {
+    check_contract_query(rx, req_id, contract_id, exchange)?;
+    let contract = match sec_type {
+        ContractType::Stock => Some(Contract::Stock(Stock {
+            symbol,
+            exchange,
+            currency,
+            local_symbol,
+            trading_class,
+            contract_id,
+            min_tick,
+            primary_exchange: primary_exchange
+                .parse()
+                .map_err(|e| ("primary_exchange", e))?,
+            long_name,
+            sector,
+            order_types,
+            valid_exchanges,
+            details,
+            security_ids,
+            stock_type,
+        })),
+        ContractType::SecOption => {
+            let (expiration_date, contract_month) =
+                decode_expiration(&expiration_date, &details.contract_month)?;
+            let inner = SecOptionInner {
                 contract_id,
                 min_tick,
                 symbol,
                 exchange,
+                strike,
+                multiplier: multiplier.parse().map_err(|e| ("multiplier", e))?,
+                expiration_date,
+                contract_month,
+                underlying_contract_id,
+                sector,
                 trading_class,
                 currency,
                 local_symbol,
                 long_name,
                 order_types,
                 valid_exchanges,
-            })),
-            ContractType::Index => Some(Contract::Index(Index {
-                contract_id,
-                min_tick,
-                symbol,
-                exchange,
-                currency,
-                local_symbol,
-                long_name,
-                order_types,
-                valid_exchanges,
-            })),
-            ContractType::SecFuture => Some(Contract::SecFuture(SecFuture {
+                details,
+            };
+            match class.as_str() {
+                "C" => Some(Contract::SecOption(SecOption::Call(inner))),
+                "P" => Some(Contract::SecOption(SecOption::Put(inner))),
+                _ => return Err(DecodeError::UnexpectedData("Unexpected option class")),
+            }
+        }
+        ContractType::SecFutureOption => {
+            let inner = SecFutureOptionInner {
                 contract_id,
                 min_tick,
                 symbol,
                 exchange,
+                strike,
                 multiplier: multiplier.parse().map_err(|e| ("multiplier", e))?,
-                expiration_date: NaiveDate::parse_and_remainder(expiration_date.as_str(), "%Y%m%d")
-                    .map_err(|e| ("expiration_date", ParseDateTimeError::Parse(e)))?
-                    .0,
+                expiration_date: NaiveDate::parse_and_remainder(
+                    expiration_date.as_str(),
+                    "%Y%m%d",
+                )
+                .map_err(|e| ("expiration_date", ParseDateTimeError::Parse(e)))?
+                .0,
+                underlying_future_contract_id: underlying_contract_id,
                 trading_class,
-                underlying_contract_id,
                 currency,
+                // FOP local symbols follow exchange conventions, so keep them verbatim
                 local_symbol,
                 long_name,
                 order_types,
                 valid_exchanges,
-            })),
-            ContractType::Commodity => Some(Contract::Commodity(Commodity {
+                details,
+            };
+            match class.as_str() {
+                "C" => Some(Contract::SecFutureOption(SecFutureOption::Call(inner))),
+                "P" => Some(Contract::SecFutureOption(SecFutureOption::Put(inner))),
+                _ => return Err(DecodeError::UnexpectedData("Unexpected option class")),
+            }
+        }
+        ContractType::Warrant => {
+            let inner = WarrantInner {
                 contract_id,
                 min_tick,
                 symbol,
                 exchange,
+                strike,
+                multiplier: multiplier.parse().map_err(|e| ("multiplier", e))?,
+                expiration_date: NaiveDate::parse_and_remainder(
+                    expiration_date.as_str(),
+                    "%Y%m%d",
+                )
+                .map_err(|e| ("expiration_date", ParseDateTimeError::Parse(e)))?
+                .0,
+                underlying_contract_id,
                 trading_class,
+                issuer: warrant_issuer(&long_name),
                 currency,
                 local_symbol,
                 long_name,
                 order_types,
                 valid_exchanges,
-            })),
-        };
-
-        tx.send(ToClient::NewContract(contract.ok_or(
-            DecodeError::UnexpectedData("No contract was created"),
-        )?))
-        .await
-        .map_err(Box::new)?;
-    }
-    Ok(())
-}
-
-#[inline]
-fn deserialize_contract_proxy<E: crate::contract::ProxyExchange + Clone>(
-    fields: &mut Fields,
-) -> Result<Proxy<Contract, E>, DecodeError> {
-    decode_fields!(
-        fields =>
-            contract_id @ 0: ContractId,
-            symbol @ 0: String,
-            sec_type @ 0: ContractType,
-            expiration_date @ 0: String,
-            strike @ 0: String,
-            right @ 0: String,
-            multiplier @ 0: String,
-            exch_or_primary @ 0: String,
-            currency @ 0: Currency,
-            local_symbol @ 0: String,
-            trading_class @ 0: String
-    );
-    let (exchange, primary_exchange) = E::decode(exch_or_primary)?;
-
-    let inner = match sec_type {
-        ContractType::Stock => Contract::Stock(Stock {
+                details,
+            };
+            match class.as_str() {
+                "C" => Some(Contract::Warrant(Warrant::Call(inner))),
+                "P" => Some(Contract::Warrant(Warrant::Put(inner))),
+                _ => return Err(DecodeError::UnexpectedData("Unexpected option class")),
+            }
+        }
+        ContractType::Crypto => Some(Contract::Crypto(Crypto {
             contract_id,
-            min_tick: f64::default(),
+            min_tick,
             symbol,
             exchange,
-            primary_exchange,
-            stock_type: String::default(),
-            security_ids: Vec::default(),
-            sector: String::default(),
             trading_class,
             currency,
             local_symbol,
-            long_name: String::default(),
-            order_types: Vec::default(),
-            valid_exchanges: Vec::default(),
-        }),
-        ContractType::Crypto => Contract::Crypto(Crypto {
+            long_name,
+            order_types,
+            valid_exchanges,
+            details,
+        })),
+        ContractType::Forex => Some(Contract::Forex(Forex {
             contract_id,
-            min_tick: f64::default(),
+            min_tick,
             symbol,
+            exchange,
             trading_class,
             currency,
             local_symbol,
-            long_name: String::default(),
-            order_types: Vec::default(),
-            valid_exchanges: Vec::default(),
-        }),
-        ContractType::Index => Contract::Index(Index {
+            long_name,
+            order_types,
+            valid_exchanges,
+            details,
+        })),
+        ContractType::Index => Some(Contract::Index(Index {
             contract_id,
-            min_tick: f64::default(),
+            min_tick,
             symbol,
             exchange,
             currency,
             local_symbol,
-            long_name: String::default(),
-            order_types: Vec::default(),
-            valid_exchanges: Vec::default(),
-        }),
-        ContractType::Commodity => Contract::Commodity(Commodity {
+            long_name,
+            order_types,
+            valid_exchanges,
+            details,
+        })),
+        ContractType::SecFuture => {
+            let (expiration_date, contract_month) =
+                decode_expiration(&expiration_date, &details.contract_month)?;
+            Some(Contract::SecFuture(SecFuture {
+                contract_id,
+                min_tick,
+                symbol,
+                exchange,
+                multiplier: multiplier.parse().map_err(|e| ("multiplier", e))?,
+                expiration_date,
+                contract_month,
+                trading_class,
+                underlying_contract_id,
+                currency,
+                local_symbol,
+                long_name,
+                order_types,
+                valid_exchanges,
+                details,
+            }))
+        }
+        ContractType::ContinuousFuture => Some(Contract::ContinuousFuture(ContinuousFuture {
             contract_id,
-            min_tick: f64::default(),
+            min_tick,
             symbol,
             exchange,
             trading_class,
+            underlying_contract_id,
             currency,
             local_symbol,
-            long_name: String::default(),
-            order_types: Vec::default(),
-            valid_exchanges: Vec::default(),
-        }),
-        ContractType::Forex => Contract::Forex(Forex {
+            long_name,
+            order_types,
+            valid_exchanges,
+            details,
+        })),
+        ContractType::StructuredProduct => Some(Contract::StructuredProduct(StructuredProduct {
+            contract_id,
+            min_tick,
+            symbol,
+            exchange,
+            multiplier: multiplier.parse().map_err(|e| ("multiplier", e))?,
+            expiration_date: decode_last_trade_date(&expiration_date)?,
+            trading_class,
+            currency,
+            local_symbol,
+            long_name,
+            order_types,
+            valid_exchanges,
+            details,
+        })),
+        ContractType::Commodity => Some(Contract::Commodity(Commodity {
             contract_id,
-            min_tick: f64::default(),
+            min_tick,
             symbol,
             exchange,
+            // IBKR reports the type of a commodity in the same field as the type of a stock
+            commodity_type: stock_type,
             trading_class,
             currency,
             local_symbol,
-            long_name: String::default(),
-            order_types: Vec::default(),
-            valid_exchanges: Vec::default(),
-        }),
-        ContractType::SecFuture => Contract::SecFuture(SecFuture {
+            long_name,
+            order_types,
+            valid_exchanges,
+            details,
+        })),
+        ContractType::Cfd => Some(Contract::Cfd(Cfd {
             contract_id,
-            min_tick: f64::default(),
+            min_tick,
             symbol,
             exchange,
-            multiplier: multiplier.parse().map_err(|e| ("multiplier", e))?,
-            expiration_date: NaiveDate::parse_and_remainder(expiration_date.as_str(), "%Y%m%d")
-                .map_err(|e| ("expiration_date", ParseDateTimeError::Parse(e)))?
-                .0,
+            underlying_contract_id,
+            // The underlying security type
+            cfd_type: nth(fields, 2, "cfd_type")?,
             trading_class,
-            underlying_contract_id: contract_id,
             currency,
             local_symbol,
-            long_name: String::default(),
-            order_types: Vec::default(),
-            valid_exchanges: Vec::default(),
-        }),
-        ContractType::SecOption => {
-            let op_inner = SecOptionInner {
+            long_name,
+            order_types,
+            valid_exchanges,
+            details,
+        })),
+        ContractType::MutualFund => {
+            // Skip the fund name
+            decode_fields!(fields => fund_family @ 1: String);
+            decode_fields!(fields => fund_type @ 0: String);
+            decode_fields!(fields => fund_front_load @ 0: Option<NotNan>);
+            decode_fields!(fields => fund_back_load @ 0: Option<NotNan>);
+            decode_fields!(fields => fund_back_load_time_interval @ 0: String);
+            decode_fields!(fields => fund_management_fee @ 0: Option<NotNan>);
+            Some(Contract::MutualFund(MutualFund {
                 contract_id,
-                min_tick: f64::default(),
+                min_tick,
                 symbol,
                 exchange,
-                strike: strike.parse().map_err(|e| ("strike", e))?,
-                multiplier: multiplier.parse().map_err(|e| ("multiplier", e))?,
-                expiration_date: NaiveDate::parse_and_remainder(expiration_date.as_str(), "%Y%m%d")
-                    .map_err(|e| ("expiration_date", ParseDateTimeError::Parse(e)))?
-                    .0,
-                underlying_contract_id: contract_id,
-                sector: String::default(),
+                fund_family,
+                fund_type,
+                fund_front_load,
+                fund_back_load,
+                fund_back_load_time_interval,
+                fund_management_fee,
                 trading_class,
                 currency,
                 local_symbol,
-                long_name: String::default(),
-                order_types: Vec::default(),
-                valid_exchanges: Vec::default(),
-            };
+                long_name,
+                order_types,
+                valid_exchanges,
+                details,
+            }))
+        }
+        // Bond details are always sent in a separate bond contract data message
+        ContractType::Bond => {
+            return Err(DecodeError::UnexpectedData(
+                "Unexpected BOND contract in contract_data_msg",
+            ))
+        }
+        // IBKR doesn't report contract details for combos
+        ContractType::Combo => {
+            return Err(DecodeError::UnexpectedData(
+                "Unexpected BAG contract in contract_data_msg",
+            ))
+        }
+    };
+
+    tx.send(ToClient::NewContract(
+        req_id,
+        contract.ok_or(DecodeError::UnexpectedData("No contract was created"))?,
+    ))
+    .await
+    .map_err(Box::new)?;
+    Ok(())
+}
+
+#[derive(Debug)]
+/// The contract queries that the client has sent and that have not ended yet, keyed on their
+/// request IDs, the number of current time requests whose responses the client awaits, and the
+/// subscriptions whose streams the client holds.
+///
+/// A query stays pending until the end of its contract data or an error message with its ID
+/// arrives, so every contract that answers it is checked, however the answers to concurrent
+/// queries interleave. A query that is never answered, like one that times out, is removed once
+/// the client forgets it. Likewise, a subscription lasts until an error ends it or its stream is
+/// dropped.
+pub(crate) struct PendingQueries {
+    rx: tokio::sync::mpsc::UnboundedReceiver<ToWrapper>,
+    queries: std::collections::HashMap<i64, crate::contract::Query>,
+    current_time: usize,
+    subscriptions: std::collections::HashMap<i64, Subscriber>,
+    wrapper_requests: std::collections::HashSet<i64>,
+}
+
+impl PendingQueries {
+    #[inline]
+    pub(crate) fn new(rx: tokio::sync::mpsc::UnboundedReceiver<ToWrapper>) -> Self {
+        Self {
+            rx,
+            queries: std::collections::HashMap::new(),
+            current_time: 0,
+            subscriptions: std::collections::HashMap::new(),
+            wrapper_requests: std::collections::HashSet::new(),
+        }
+    }
+
+    /// Add the requests that the client has sent since the last call.
+    fn sync(&mut self) {
+        while let Ok(request) = self.rx.try_recv() {
+            match request {
+                ToWrapper::ContractQuery((query, req_id)) => {
+                    self.queries.insert(req_id, query);
+                }
+                ToWrapper::ForgetContractQuery(req_id) => {
+                    self.queries.remove(&req_id);
+                }
+                ToWrapper::CurrentTime => self.current_time += 1,
+                ToWrapper::CancelCurrentTime => {
+                    self.current_time = self.current_time.saturating_sub(1);
+                }
+                ToWrapper::Subscribe((req_id, subscriber)) => {
+                    self.subscriptions.insert(req_id, subscriber);
+                }
+                ToWrapper::Unsubscribe(req_id) => {
+                    self.subscriptions.remove(&req_id);
+                }
+                ToWrapper::WrapperRequest(req_id) => {
+                    self.wrapper_requests.insert(req_id);
+                }
+            }
+        }
+    }
+
+    #[inline]
+    /// Whether the client awaits a current time, in which case the next one is returned to it
+    /// rather than to the wrapper.
+    pub(crate) fn take_current_time(&mut self) -> bool {
+        self.sync();
+        let pending = self.current_time > 0;
+        self.current_time = self.current_time.saturating_sub(1);
+        pending
+    }
+
+    #[inline]
+    /// Whether the response to `req_id` goes to the wrapper, because a deprecated method sent the
+    /// request, rather than to the client.
+    pub(crate) fn take_wrapper_request(&mut self, req_id: i64) -> bool {
+        self.sync();
+        self.wrapper_requests.remove(&req_id)
+    }
+
+    #[cfg(test)]
+    /// Whether no contract query is pending.
+    pub(crate) fn is_empty(&mut self) -> bool {
+        self.sync();
+        self.queries.is_empty()
+    }
+
+    #[inline]
+    fn get(&mut self, req_id: i64) -> Option<&crate::contract::Query> {
+        self.sync();
+        self.queries.get(&req_id)
+    }
+
+    #[inline]
+    fn remove(&mut self, req_id: i64) -> Option<crate::contract::Query> {
+        self.sync();
+        self.queries.remove(&req_id)
+    }
+
+    #[inline]
+    fn subscriber(&mut self, req_id: i64) -> Option<&Subscriber> {
+        self.sync();
+        self.subscriptions.get(&req_id)
+    }
+
+    #[inline]
+    fn unsubscribe(&mut self, req_id: i64) -> Option<Subscriber> {
+        self.sync();
+        self.subscriptions.remove(&req_id)
+    }
+}
+
+impl Subscriber {
+    /// End the subscription with the error that TWS rejected it with.
+    fn fail(self, error: crate::error_code::Error) {
+        // The stream may already be dropped
+        match self {
+            Self::Ticks(tx) => {
+                let _ = tx.send(Err(error));
+            }
+            Self::Depth(tx) => {
+                let _ = tx.send(Err(error));
+            }
+            Self::RealTimeBars(tx) => {
+                let _ = tx.send(Err(error));
+            }
+            Self::UpdatingBars(tx) => {
+                let _ = tx.send(Err(error));
+            }
+        }
+    }
+}
+
+/// Forwards the ticks of a [`crate::market_data::TickStream`] from the wrapper callbacks by which
+/// they are decoded.
+struct TickSink<'s> {
+    tx: &'s tokio::sync::mpsc::UnboundedSender<Result<Tick, crate::error_code::Error>>,
+    attrib: TickAttrib,
+}
+
+impl TickSink<'_> {
+    #[inline]
+    fn send(&self, tick: Tick) -> impl Future {
+        // A dropped stream is unsubscribed once the decoder learns of it
+        let _ = self.tx.send(Ok(tick));
+        async {}
+    }
+}
+
+/// Forwards the data of a [`crate::market_data::Subscription`] other than a
+/// [`crate::market_data::TickStream`] from the wrapper callbacks by which it is decoded.
+struct StreamSink<'s, T>(
+    &'s tokio::sync::mpsc::UnboundedSender<Result<T, crate::error_code::Error>>,
+);
+
+impl<T> StreamSink<'_, T> {
+    #[inline]
+    fn send(&self, value: T) -> impl Future {
+        // A dropped stream is unsubscribed once the decoder learns of it
+        let _ = self.0.send(Ok(value));
+        async {}
+    }
+}
+
+impl wrapper::LocalWrapper for StreamSink<'_, Operation> {
+    fn update_market_depth(&mut self, _req_id: i64, operation: Operation) -> impl Future {
+        self.send(operation)
+    }
+}
+
+impl wrapper::LocalWrapper for StreamSink<'_, Bar> {
+    fn real_time_bar(&mut self, _req_id: i64, bar: Bar) -> impl Future {
+        self.send(bar)
+    }
+}
+
+impl wrapper::LocalWrapper for StreamSink<'_, UpdatingBar> {
+    fn historical_bars(
+        &mut self,
+        _req_id: i64,
+        _start_datetime: DateTime<chrono::Utc>,
+        _end_datetime: DateTime<chrono::Utc>,
+        bars: Vec<Bar>,
+    ) -> impl Future {
+        self.send(UpdatingBar::Backlog(bars))
+    }
+
+    fn updating_historical_bar(&mut self, _req_id: i64, bar: Bar) -> impl Future {
+        self.send(UpdatingBar::Update(bar))
+    }
+}
+
+impl wrapper::LocalWrapper for TickSink<'_> {
+    fn etf_nav(&mut self, _req_id: i64, nav: EtfNav) -> impl Future {
+        self.send(Tick::EtfNav(nav))
+    }
+    fn price_data(&mut self, _req_id: i64, price: Class<Price>) -> impl Future {
+        self.send(Tick::Price {
+            price,
+            attrib: self.attrib,
+        })
+    }
+    fn size_data(&mut self, _req_id: i64, size: Class<Size>) -> impl Future {
+        self.send(Tick::Size(size))
+    }
+    fn yield_data(&mut self, _req_id: i64, yld: Yield) -> impl Future {
+        self.send(Tick::Yield(yld))
+    }
+    fn extreme_data(&mut self, _req_id: i64, value: ExtremeValue) -> impl Future {
+        self.send(Tick::Extreme(value))
+    }
+    fn sec_option_computation(
+        &mut self,
+        _req_id: i64,
+        calc: Class<SecOptionCalculationSource>,
+    ) -> impl Future {
+        self.send(Tick::SecOptionComputation(calc))
+    }
+    fn quoting_exchanges(
+        &mut self,
+        _req_id: i64,
+        quoting_exchanges: QuotingExchanges,
+    ) -> impl Future {
+        self.send(Tick::QuotingExchanges(quoting_exchanges))
+    }
+    fn open_interest(&mut self, _req_id: i64, open_interest: OpenInterest) -> impl Future {
+        self.send(Tick::OpenInterest(open_interest))
+    }
+    fn volatility(&mut self, _req_id: i64, vol: Volatility) -> impl Future {
+        self.send(Tick::Volatility(vol))
+    }
+    fn timestamp(&mut self, _req_id: i64, timestamp: Class<TimeStamp>) -> impl Future {
+        self.send(Tick::TimeStamp(timestamp))
+    }
+    fn auction(&mut self, _req_id: i64, auction: AuctionData) -> impl Future {
+        self.send(Tick::Auction(auction))
+    }
+    fn mark_price(&mut self, _req_id: i64, mark: MarkPrice) -> impl Future {
+        self.send(Tick::MarkPrice(mark))
+    }
+    fn price_factor(&mut self, _req_id: i64, factor: PriceFactor) -> impl Future {
+        self.send(Tick::PriceFactor(factor))
+    }
+    fn accessibility(&mut self, _req_id: i64, access: Accessibility) -> impl Future {
+        self.send(Tick::Accessibility(access))
+    }
+    fn dividends(&mut self, _req_id: i64, dividends: Dividends) -> impl Future {
+        self.send(Tick::Dividends(dividends))
+    }
+    fn news(&mut self, _req_id: i64, news: crate::tick::News) -> impl Future {
+        self.send(Tick::News(news))
+    }
+    fn ipo(&mut self, _req_id: i64, ipo: Ipo) -> impl Future {
+        self.send(Tick::Ipo(ipo))
+    }
+    fn summary_volume(&mut self, _req_id: i64, volume: SummaryVolume) -> impl Future {
+        self.send(Tick::SummaryVolume(volume))
+    }
+    fn sec_option_volume(&mut self, _req_id: i64, volume: SecOptionVolume) -> impl Future {
+        self.send(Tick::SecOptionVolume(volume))
+    }
+    fn trade_count(&mut self, _req_id: i64, trade_count: crate::tick::TradeCount) -> impl Future {
+        self.send(Tick::TradeCount(trade_count))
+    }
+    fn rate(&mut self, _req_id: i64, rate: Rate) -> impl Future {
+        self.send(Tick::Rate(rate))
+    }
+    fn volume(&mut self, _req_id: i64, volume: crate::tick::Volume) -> impl Future {
+        self.send(Tick::Volume(volume))
+    }
+    fn real_time_volume(&mut self, _req_id: i64, volume: RealTimeVolume) -> impl Future {
+        self.send(Tick::RealTimeVolume(volume))
+    }
+    fn tick_params(
+        &mut self,
+        _req_id: i64,
+        min_tick: f64,
+        exchange_id: ExchangeId,
+        snapshot_permissions: u32,
+    ) -> impl Future {
+        self.send(Tick::Params {
+            min_tick,
+            exchange_id,
+            snapshot_permissions,
+        })
+    }
+    fn market_data_class(&mut self, _req_id: i64, class: MarketDataClass) -> impl Future {
+        self.send(Tick::MarketDataClass(class))
+    }
+}
+
+/// Decode a message of a subscription whose stream the client holds, like a
+/// [`crate::market_data::TickStream`], into its stream rather than the wrapper.
+///
+/// # Returns
+/// The fields of the message, if it belongs to no such subscription.
+pub(crate) async fn decode_subscription_msg(
+    fields: Vec<String>,
+    rx: &mut Rx,
+) -> Result<Option<Vec<String>>, DecodeError> {
+    let Some(msg) = fields.first().and_then(|t| t.parse::<In>().ok()) else {
+        return Ok(Some(fields));
+    };
+    let req_id_index = match msg {
+        In::TickOptionComputation
+        | In::TickReqParams
+        | In::HistoricalData
+        | In::HistoricalDataUpdate => 1,
+        In::TickPrice
+        | In::TickSize
+        | In::TickGeneric
+        | In::TickString
+        | In::TickSnapshotEnd
+        | In::MarketDataType
+        | In::MarketDepth
+        | In::MarketDepthL2
+        | In::RealTimeBars => 2,
+        _ => return Ok(Some(fields)),
+    };
+    let Some(req_id) = fields.get(req_id_index).and_then(|id| id.parse::<i64>().ok()) else {
+        return Ok(Some(fields));
+    };
+    let Some(subscriber) = rx.subscriber(req_id) else {
+        return Ok(Some(fields));
+    };
+    match (subscriber, msg) {
+        (
+            Subscriber::Ticks(tx),
+            In::TickPrice
+            | In::TickSize
+            | In::TickGeneric
+            | In::TickString
+            | In::TickOptionComputation
+            | In::TickReqParams
+            | In::TickSnapshotEnd
+            | In::MarketDataType,
+        ) => {
+            // The attributes are decoded here, since the wrapper callbacks do not carry them
+            let attrib = match msg {
+                In::TickPrice => fields
+                    .get(6)
+                    .and_then(|mask| mask.parse().ok())
+                    .map(TickAttrib::from_mask)
+                    .unwrap_or_default(),
+                _ => TickAttrib::default(),
+            };
+            let mut sink = TickSink { tx, attrib };
+            let fields = &mut fields.into_iter();
+            match msg {
+                In::TickPrice => Local::tick_price_msg(fields, &mut sink).await,
+                In::TickSize => Local::tick_size_msg(fields, &mut sink).await,
+                In::TickGeneric => Local::tick_generic_msg(fields, &mut sink).await,
+                In::TickString => Local::tick_string_msg(fields, &mut sink).await,
+                In::TickOptionComputation => {
+                    Local::tick_option_computation_msg(fields, &mut sink).await
+                }
+                In::TickReqParams => Local::tick_req_params_msg(fields, &mut sink).await,
+                In::MarketDataType => Local::market_data_type_msg(fields, &mut sink).await,
+                // A snapshot sends no more ticks, so its stream ends
+                _ => {
+                    rx.unsubscribe(req_id);
+                    Ok(())
+                }
+            }?;
+        }
+        (Subscriber::Depth(tx), In::MarketDepth | In::MarketDepthL2) => {
+            let mut sink = StreamSink(tx);
+            let fields = &mut fields.into_iter();
+            match msg {
+                In::MarketDepth => Local::market_depth_msg(fields, &mut sink).await,
+                _ => Local::market_depth_l2_msg(fields, &mut sink).await,
+            }?;
+        }
+        (Subscriber::RealTimeBars(tx), In::RealTimeBars) => {
+            Local::real_time_bars_msg(&mut fields.into_iter(), &mut StreamSink(tx)).await?;
+        }
+        (Subscriber::UpdatingBars(tx), In::HistoricalData | In::HistoricalDataUpdate) => {
+            let mut sink = StreamSink(tx);
+            let fields = &mut fields.into_iter();
+            match msg {
+                In::HistoricalData => Local::historical_data_msg(fields, &mut sink).await,
+                _ => Local::historical_data_update_msg(fields, &mut sink).await,
+            }?;
+        }
+        // The request ID belongs to a subscription of another kind
+        _ => return Ok(Some(fields)),
+    }
+    Ok(None)
+}
+
+#[inline]
+/// Check a contract against its pending contract query, if any. The query stays pending, so
+/// every contract that answers it is checked.
+fn check_contract_query(
+    rx: &mut Rx,
+    req_id: i64,
+    contract_id: ContractId,
+    exchange: Routing,
+) -> Result<(), DecodeError> {
+    if let Some(&crate::contract::Query::IbContractId(con_id_client, routing_client)) =
+        rx.get(req_id)
+    {
+        if con_id_client != contract_id {
+            return Err(DecodeError::UnexpectedData("Unexpected contract ID"));
+        }
+        if exchange != routing_client {
+            return Err(DecodeError::UnexpectedData("Unexpected routing exchange"));
+        }
+    }
+    Ok(())
+}
+
+#[inline]
+/// Return an error message to the client if it rejects a pending contract query, end the stream of
+/// the subscription that it rejects with it, or broadcast it as a [`Notification`] if it carries no
+/// request ID. Either way, the wrapper still receives the message afterwards.
+pub(crate) async fn decode_err_msg_no_wrapper(
+    fields: &mut Fields,
+    tx: &mut Tx,
+    rx: &mut Rx,
+    notifications: &tokio::sync::broadcast::Sender<Notification>,
+) -> DecodeResult {
+    decode_fields!(
+        fields =>
+            req_id @ 2: i64,
+            error_code @ 0: i64,
+            message @ 0: String
+    );
+    let code = ErrorCode::from(error_code);
+    if req_id == -1 {
+        // Nobody may be subscribed to the notifications
+        let _ = notifications.send(Notification { code, message });
+    } else if !code.is_warning() {
+        let error = TwsError {
+            req_id,
+            code,
+            message,
+        };
+        if let Some(subscriber) = rx.unsubscribe(req_id) {
+            // The code sorts the error into its variant, like `MarketDataNotSubscribed` for 354
+            subscriber.fail(error.into());
+        } else if rx.remove(req_id).is_some() {
+            tx.send(ToClient::RequestError(error))
+                .await
+                .map_err(Box::new)?;
+        }
+    }
+    Ok(())
+}
+
+#[inline]
+pub(crate) async fn decode_contract_data_end_no_wrapper(
+    fields: &mut Fields,
+    tx: &mut Tx,
+    rx: &mut Rx,
+) -> DecodeResult {
+    decode_fields!(fields => req_id @ 2: i64);
+    rx.remove(req_id);
+    tx.send(ToClient::ContractQueryEnd(req_id))
+        .await
+        .map_err(Box::new)?;
+    Ok(())
+}
+
+#[inline]
+pub(crate) async fn decode_option_chain_no_wrapper(
+    fields: &mut Fields,
+    tx: &mut Tx,
+) -> DecodeResult {
+    decode_fields!(
+        fields =>
+            _req_id @ 1: i64,
+            exchange @ 0: Routing,
+            underlying_contract_id @ 0: ContractId,
+            trading_class @ 0: String,
+            multiplier @ 0: f64,
+            expiration_count @ 0: usize
+    );
+    let expirations = (0..expiration_count)
+        .map(|_| {
+            let expiration = nth(fields, 0, "expiration")?;
+            NaiveDate::parse_from_str(&expiration, "%Y%m%d")
+                .map_err(|e| ("expiration", ParseDateTimeError::Parse(e)).into())
+        })
+        .collect::<Result<Vec<_>, DecodeError>>()?;
+    decode_fields!(fields => strike_count @ 0: usize);
+    let strikes = (0..strike_count)
+        .map(|_| {
+            nth(fields, 0, "strike")?
+                .parse::<f64>()
+                .map_err(|e| ("strike", e).into())
+        })
+        .collect::<Result<Vec<_>, DecodeError>>()?;
+
+    tx.send(ToClient::OptionChain(OptionChain {
+        exchange,
+        underlying_contract_id,
+        trading_classes: vec![trading_class],
+        multiplier,
+        expirations,
+        strikes,
+    }))
+    .await
+    .map_err(Box::new)?;
+    Ok(())
+}
+
+#[inline]
+pub(crate) async fn decode_option_chain_end_no_wrapper(
+    fields: &mut Fields,
+    tx: &mut Tx,
+) -> DecodeResult {
+    decode_fields!(fields => req_id @ 1: i64);
+    tx.send(ToClient::OptionChainEnd(req_id))
+        .await
+        .map_err(Box::new)?;
+    Ok(())
+}
+
+#[inline]
+pub(crate) async fn decode_current_time_no_wrapper(
+    fields: &mut Fields,
+    tx: &mut Tx,
+) -> DecodeResult {
+    decode_fields!(fields => datetime @ 2: i64);
+    let datetime = DateTime::from_timestamp(datetime, 0)
+        .ok_or(("datetime", ParseDateTimeError::Timestamp))?;
+    tx.send(ToClient::CurrentTime(datetime))
+        .await
+        .map_err(Box::new)?;
+    Ok(())
+}
+
+#[inline]
+pub(crate) async fn decode_histogram_no_wrapper(fields: &mut Fields, tx: &mut Tx) -> DecodeResult {
+    let (req_id, entries) = decode_histogram(fields)?;
+    tx.send(ToClient::Histogram(req_id, entries))
+        .await
+        .map_err(Box::new)?;
+    Ok(())
+}
+
+#[inline]
+/// Decode the request ID of a histogram and its entries, in the order in which IBKR sent them.
+fn decode_histogram(fields: &mut Fields) -> Result<(i64, Vec<HistogramEntry>), DecodeError> {
+    decode_fields!(
+        fields =>
+            req_id @ 1: i64,
+            num_points @ 0: usize
+    );
+    let mut entries = Vec::with_capacity(num_points);
+    for _ in 0..num_points {
+        decode_fields!(
+            fields =>
+                price @ 0: f64,
+                size @ 0: f64
+        );
+        entries.push(HistogramEntry { price, size });
+    }
+    Ok((req_id, entries))
+}
+
+#[inline]
+pub(crate) async fn decode_head_timestamp_no_wrapper(
+    fields: &mut Fields,
+    tx: &mut Tx,
+) -> DecodeResult {
+    decode_fields!(
+        fields =>
+            req_id @ 1: i64,
+            timestamp @ 0: String
+    );
+    let timestamp = parse_historical_datetime(&timestamp).map_err(|e| ("timestamp", e))?;
+    tx.send(ToClient::HeadTimestamp(req_id, timestamp))
+        .await
+        .map_err(Box::new)?;
+    Ok(())
+}
+
+#[inline]
+pub(crate) async fn decode_market_rule_no_wrapper(
+    fields: &mut Fields,
+    tx: &mut Tx,
+) -> DecodeResult {
+    decode_fields!(
+        fields =>
+            id @ 1: MarketRuleId,
+            increment_count @ 0: usize
+    );
+    let increments = (0..increment_count)
+        .map(|_| {
+            decode_fields!(
+                fields =>
+                    low_edge @ 0: f64,
+                    increment @ 0: f64
+            );
+            Ok(PriceIncrement {
+                low_edge,
+                increment,
+            })
+        })
+        .collect::<Result<Vec<_>, DecodeError>>()?;
+
+    tx.send(ToClient::MarketRule(MarketRule { id, increments }))
+        .await
+        .map_err(Box::new)?;
+    Ok(())
+}
+
+#[inline]
+pub(crate) async fn decode_symbol_samples_no_wrapper(
+    fields: &mut Fields,
+    tx: &mut Tx,
+) -> DecodeResult {
+    decode_fields!(
+        fields =>
+            _req_id @ 1: i64,
+            match_count @ 0: usize
+    );
+    let mut matches = Vec::with_capacity(match_count);
+    for _ in 0..match_count {
+        decode_fields!(
+            fields =>
+                contract_id @ 0: String,
+                symbol @ 0: String,
+                sec_type @ 0: String,
+                primary_exchange @ 0: String,
+                currency @ 0: String,
+                derivative_type_count @ 0: usize
+        );
+        let derivative_types = (0..derivative_type_count)
+            .map(|_| nth(fields, 0, "derivative_type"))
+            .collect::<Result<Vec<_>, _>>()?
+            .into_iter()
+            .filter_map(|t| t.parse().ok())
+            .collect();
+        decode_fields!(
+            fields =>
+                description @ 0: String,
+                _issuer_id @ 0: String
+        );
+
+        let primary = primary_exchange.parse().ok();
+        // The sample has the same fields as a contract proxy, less those that only derivatives have
+        let mut proxy_fields = [
+            contract_id,
+            symbol,
+            sec_type,
+            String::new(),
+            String::new(),
+            String::new(),
+            String::new(),
+            primary_exchange,
+            currency,
+            String::new(),
+            String::new(),
+        ]
+        .to_vec()
+        .into_iter();
+        match deserialize_contract_proxy(&mut proxy_fields) {
+            Ok(contract) => matches.push(crate::contract::SymbolMatch {
+                contract,
+                derivative_types,
+                description,
+                primary_exchange: primary,
+            }),
+            Err(e) => warn!("Skipping unsupported symbol sample. Cause: {e}"),
+        }
+    }
+
+    tx.send(ToClient::SymbolMatches(matches))
+        .await
+        .map_err(Box::new)?;
+    Ok(())
+}
+
+#[inline]
+/// Get the last trade time that follows the date in a "lastTradeDateOrContractMonth" field, like
+/// "20241220 15:00:00" or "20241220-15:00:00".
+fn last_trade_time(last_trade_date: &str) -> String {
+    last_trade_date
+        .split([' ', '-'])
+        .nth(1)
+        .unwrap_or_default()
+        .to_owned()
+}
+
+#[inline]
+/// Get the issuer of a warrant, which IBKR reports after the terms in its long name, like
+/// "GOLDMAN SACHS" in "SAP 18DEC26 200 C GOLDMAN SACHS". The terms end with the last word that
+/// is a right or contains a digit, like an expiration date or a strike. A long name without
+/// terms is the issuer itself.
+fn warrant_issuer(long_name: &str) -> String {
+    let words = long_name.split_whitespace().collect::<Vec<_>>();
+    let is_term = |word: &&str| {
+        ["C", "P", "CALL", "PUT"].contains(word) || word.chars().any(|c| c.is_ascii_digit())
+    };
+    let issuer = words.iter().rposition(is_term).map_or(&words[..], |i| &words[i + 1..]);
+    issuer.join(" ")
+}
+
+#[inline]
+/// Decode an optional date in the "YYYYMMDD" format.
+fn decode_date(date: &str, field_name: &'static str) -> Result<Option<NaiveDate>, DecodeError> {
+    if date.is_empty() {
+        return Ok(None);
+    }
+    NaiveDate::parse_from_str(date, "%Y%m%d")
+        .map(Some)
+        .map_err(|e| (field_name, ParseDateTimeError::Parse(e)).into())
+}
+
+#[inline]
+/// Decode the last trade date of a derivative, like "20241220" or "20241220 15:00:00".
+fn decode_last_trade_date(expiration_date: &str) -> Result<NaiveDate, DecodeError> {
+    Ok(NaiveDate::parse_and_remainder(expiration_date, "%Y%m%d")
+        .map_err(|e| ("expiration_date", ParseDateTimeError::Parse(e)))?
+        .0)
+}
+
+#[inline]
+/// Decode the contract month of a derivative, like "202412".
+fn decode_contract_month(contract_month: &str) -> Result<(i32, u8), DecodeError> {
+    NaiveDate::parse_from_str(&format!("{contract_month}01"), "%Y%m%d")
+        .map(contract_month_of)
+        .map_err(|e| ("contract_month", ParseDateTimeError::Parse(e)).into())
+}
+
+#[inline]
+/// Decode the last trade date of a derivative, like "20241220" or "20241220 15:00:00", along with
+/// its contract month, like "202412". The contract month is taken from the last trade date when
+/// IBKR does not report one, like in position messages. IBKR may report only a contract month in
+/// place of the last trade date, in which case the expiration date is `None`.
+fn decode_expiration(
+    expiration_date: &str,
+    contract_month: &str,
+) -> Result<(Option<NaiveDate>, (i32, u8)), DecodeError> {
+    if expiration_date.len() == 6 {
+        return Ok((None, decode_contract_month(expiration_date)?));
+    }
+    let expiration_date = decode_last_trade_date(expiration_date)?;
+    if contract_month.is_empty() {
+        return Ok((Some(expiration_date), contract_month_of(expiration_date)));
+    }
+    Ok((Some(expiration_date), decode_contract_month(contract_month)?))
+}
+
+#[inline]
+fn decode_security_ids(
+    fields: &mut Fields,
+    security_id_count: usize,
+) -> Result<Vec<SecurityId>, DecodeError> {
+    (0..security_id_count)
+        .map(
+            |_| match nth(fields, 0, "security_ids")?.to_uppercase().as_str() {
+                "CUSIP" => Ok(SecurityId::Cusip(nth(fields, 0, "security_id")?)),
+                "SEDOL" => Ok(SecurityId::Sedol(nth(fields, 0, "security_id")?)),
+                "ISIN" => Ok(SecurityId::Isin(nth(fields, 0, "security_id")?)),
+                "RIC" => Ok(SecurityId::Ric(nth(fields, 0, "security_id")?)),
+                _ => Err(DecodeError::UnexpectedData(
+                    "Invalid security_id type found in contract_data_msg",
+                )),
+            },
+        )
+        .collect()
+}
+
+// Skip `n` fields, then return the one after them
+fn skip_nth(fields: &mut Fields, n: usize) -> Result<String, DecodeError> {
+    nth(fields, n, "open_order")
+}
+
+// Reads the open order fields between the parent ID and the delta-neutral contract block,
+// which IBKR appends after the volatility, combo, scale, hedge and clearing parameters
+fn decode_delta_neutral_contract(
+    fields: &mut Fields,
+) -> Result<Option<DeltaNeutralContract>, DecodeError> {
+    // triggerMethod, volatility, volatilityType, deltaNeutralOrderType
+    if !skip_nth(fields, 3)?.is_empty() {
+        // The eight delta-neutral order attributes sent after deltaNeutralAuxPrice
+        skip_nth(fields, 7)?;
+    }
+    // deltaNeutralAuxPrice, continuousUpdate, referencePriceType, trailStopPrice,
+    // trailingPercent, basisPoints, basisPointsType, comboLegsDescrip
+    decode_fields!(
+        fields =>
+            combo_leg_count @ 8: usize
+    );
+    if combo_leg_count > 0 {
+        skip_nth(fields, 8 * combo_leg_count - 1)?;
+    }
+    decode_fields!(
+        fields =>
+            order_combo_leg_count @ 0: usize
+    );
+    if order_combo_leg_count > 0 {
+        skip_nth(fields, order_combo_leg_count - 1)?;
+    }
+    decode_fields!(
+        fields =>
+            smart_combo_routing_count @ 0: usize
+    );
+    if smart_combo_routing_count > 0 {
+        skip_nth(fields, 2 * smart_combo_routing_count - 1)?;
+    }
+    // scaleInitLevelSize, scaleSubsLevelSize, scalePriceIncrement
+    let scale_price_increment = skip_nth(fields, 2)?.parse::<f64>().unwrap_or_default();
+    if scale_price_increment > 0.0 && scale_price_increment < f64::MAX {
+        skip_nth(fields, 6)?;
+    }
+    if !skip_nth(fields, 0)?.is_empty() {
+        // hedgeParam
+        skip_nth(fields, 0)?;
+    }
+    // optOutSmartRouting, clearingAccount, clearingIntent, notHeld
+    decode_fields!(
+        fields =>
+            has_delta_neutral_contract @ 4: u8
+    );
+    if has_delta_neutral_contract == 0 {
+        return Ok(None);
+    }
+    decode_fields!(
+        fields =>
+            contract_id @ 0: ContractId,
+            delta @ 0: NotNan,
+            price @ 0: NotNan
+    );
+    Ok(Some(DeltaNeutralContract {
+        contract_id,
+        delta,
+        price,
+    }))
+}
+
+// IBKR lists one market rule ID for each valid exchange, in the same order
+fn decode_market_rule_ids(
+    market_rule_ids: &str,
+    valid_exchanges: &[Routing],
+) -> Result<Vec<(Routing, MarketRuleId)>, DecodeError> {
+    market_rule_ids
+        .split(',')
+        .filter(|id| !id.is_empty())
+        .zip(valid_exchanges)
+        .map(|(id, exchange)| {
+            Ok((
+                *exchange,
+                id.parse().map_err(|e| ("market_rule_ids", e))?,
+            ))
+        })
+        .collect()
+}
+
+#[inline]
+pub(crate) async fn decode_bond_contract_no_wrapper(
+    fields: &mut Fields,
+    tx: &mut Tx,
+    rx: &mut Rx,
+) -> DecodeResult {
+    decode_fields!(
+        fields =>
+            req_id @ 1: i64,
+            symbol @ 0: String,
+            sec_type @ 0: ContractType,
+            cusip @ 0: String,
+            coupon @ 0: NotNan,
+            maturity_date @ 0: String,
+            issue_date @ 0: String,
+            ratings @ 0: String,
+            bond_type @ 0: String,
+            coupon_type @ 0: String,
+            convertible @ 0: u8,
+            callable @ 0: u8,
+            putable @ 0: u8,
+            exchange @ 1: Routing,
+            currency @ 0: Currency,
+            market_name @ 0: String,
+            trading_class @ 0: String,
+            contract_id @ 0: ContractId,
+            min_tick @ 0: NotNan,
+            order_types @ 0: String,
+            valid_exchanges @ 0: String,
+            long_name @ 4: String,
+            security_id_count @ 2: usize
+    );
+    if sec_type != ContractType::Bond {
+        return Err(DecodeError::UnexpectedData(
+            "Unexpected security type in bond_contract_data_msg",
+        ));
+    }
+
+    let order_types = order_types
+        .split(',')
+        .filter(|code| !code.is_empty())
+        .map(|code| OrderTypeCode::from(code.to_owned()))
+        .collect();
+    let valid_exchanges = valid_exchanges
+        .split(',')
+        .map(str::parse)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| ("valid_exchanges", e))?;
+    let security_ids = decode_security_ids(fields, security_id_count)?;
+    decode_fields!(fields => agg_group @ 0: Option<i64>);
+    decode_fields!(fields => market_rule_ids @ 0: String);
+    decode_fields!(fields => min_size @ 0: Option<NotNan>);
+    decode_fields!(fields => size_increment @ 0: Option<NotNan>);
+    decode_fields!(fields => suggested_size_increment @ 0: Option<NotNan>);
+    // A bond's maturity date is followed by its last trade time and its time zone
+    let details = ContractDetails {
+        market_name,
+        time_zone_id: maturity_date
+            .split([' ', '-'])
+            .nth(2)
+            .unwrap_or_default()
+            .to_owned(),
+        agg_group,
+        last_trade_time: last_trade_time(&maturity_date),
+        min_size,
+        size_increment,
+        suggested_size_increment,
+        market_rule_ids: decode_market_rule_ids(&market_rule_ids, &valid_exchanges)?,
+        ..ContractDetails::default()
+    };
+
+    check_contract_query(rx, req_id, contract_id, exchange)?;
+    let bond = Bond {
+        contract_id,
+        min_tick,
+        symbol,
+        exchange,
+        cusip,
+        coupon,
+        maturity_date: NaiveDate::parse_and_remainder(maturity_date.as_str(), "%Y%m%d")
+            .map_err(|e| ("maturity_date", ParseDateTimeError::Parse(e)))?
+            .0,
+        issue_date,
+        ratings,
+        bond_type,
+        coupon_type,
+        callable: callable != 0,
+        putable: putable != 0,
+        convertible: convertible != 0,
+        security_ids,
+        trading_class,
+        currency,
+        // Bond contract details do not include a local symbol
+        local_symbol: String::default(),
+        long_name,
+        order_types,
+        valid_exchanges,
+        details,
+    };
+
+    tx.send(ToClient::NewContract(req_id, Contract::Bond(bond)))
+        .await
+        .map_err(Box::new)?;
+    Ok(())
+}
+
+#[inline]
+fn deserialize_contract_proxy<E: crate::contract::ProxyExchange + Clone>(
+    fields: &mut Fields,
+) -> Result<Proxy<Contract, E>, DecodeError> {
+    decode_fields!(
+        fields =>
+            contract_id @ 0: ContractId,
+            symbol @ 0: String,
+            sec_type @ 0: ContractType,
+            expiration_date @ 0: String,
+            strike @ 0: String,
+            right @ 0: String,
+            multiplier @ 0: String,
+            exch_or_primary @ 0: String,
+            currency @ 0: Currency,
+            local_symbol @ 0: String,
+            trading_class @ 0: String
+    );
+    let (exchange, primary_exchange) = E::decode(exch_or_primary)?;
+    if sec_type == ContractType::Forex {
+        // The symbol of a forex contract is its base currency
+        symbol.parse::<Currency>().map_err(|e| ("symbol", e))?;
+    }
+
+    let inner = match sec_type {
+        ContractType::Stock => Contract::Stock(Stock {
+            contract_id,
+            min_tick: NotNan::default(),
+            symbol,
+            exchange,
+            primary_exchange,
+            stock_type: String::default(),
+            security_ids: Vec::default(),
+            sector: String::default(),
+            trading_class,
+            currency,
+            local_symbol,
+            long_name: String::default(),
+            order_types: Vec::default(),
+            valid_exchanges: Vec::default(),
+            details: ContractDetails::default(),
+        }),
+        ContractType::Crypto => Contract::Crypto(Crypto {
+            contract_id,
+            min_tick: NotNan::default(),
+            symbol,
+            exchange,
+            trading_class,
+            currency,
+            local_symbol,
+            long_name: String::default(),
+            order_types: Vec::default(),
+            valid_exchanges: Vec::default(),
+            details: ContractDetails::default(),
+        }),
+        ContractType::Index => Contract::Index(Index {
+            contract_id,
+            min_tick: NotNan::default(),
+            symbol,
+            exchange,
+            currency,
+            local_symbol,
+            long_name: String::default(),
+            order_types: Vec::default(),
+            valid_exchanges: Vec::default(),
+            details: ContractDetails::default(),
+        }),
+        ContractType::Commodity => Contract::Commodity(Commodity {
+            contract_id,
+            min_tick: NotNan::default(),
+            symbol,
+            exchange,
+            commodity_type: String::default(),
+            trading_class,
+            currency,
+            local_symbol,
+            long_name: String::default(),
+            order_types: Vec::default(),
+            valid_exchanges: Vec::default(),
+            details: ContractDetails::default(),
+        }),
+        ContractType::Forex => Contract::Forex(Forex {
+            contract_id,
+            min_tick: NotNan::default(),
+            symbol,
+            exchange,
+            trading_class,
+            currency,
+            local_symbol,
+            long_name: String::default(),
+            order_types: Vec::default(),
+            valid_exchanges: Vec::default(),
+            details: ContractDetails::default(),
+        }),
+        ContractType::SecFuture => {
+            let (expiration_date, contract_month) = decode_expiration(&expiration_date, "")?;
+            Contract::SecFuture(SecFuture {
+                contract_id,
+                min_tick: NotNan::default(),
+                symbol,
+                exchange,
+                multiplier: multiplier.parse().map_err(|e| ("multiplier", e))?,
+                expiration_date,
+                contract_month,
+                trading_class,
+                underlying_contract_id: contract_id,
+                currency,
+                local_symbol,
+                long_name: String::default(),
+                order_types: Vec::default(),
+                valid_exchanges: Vec::default(),
+                details: ContractDetails::default(),
+            })
+        }
+        ContractType::ContinuousFuture => Contract::ContinuousFuture(ContinuousFuture {
+            contract_id,
+            min_tick: NotNan::default(),
+            symbol,
+            exchange,
+            trading_class,
+            underlying_contract_id: contract_id,
+            currency,
+            local_symbol,
+            long_name: String::default(),
+            order_types: Vec::default(),
+            valid_exchanges: Vec::default(),
+            details: ContractDetails::default(),
+        }),
+        ContractType::StructuredProduct => Contract::StructuredProduct(StructuredProduct {
+            contract_id,
+            min_tick: NotNan::default(),
+            symbol,
+            exchange,
+            multiplier: multiplier.parse().map_err(|e| ("multiplier", e))?,
+            expiration_date: decode_last_trade_date(&expiration_date)?,
+            trading_class,
+            currency,
+            local_symbol,
+            long_name: String::default(),
+            order_types: Vec::default(),
+            valid_exchanges: Vec::default(),
+            details: ContractDetails::default(),
+        }),
+        ContractType::SecOption => {
+            let (expiration_date, contract_month) = decode_expiration(&expiration_date, "")?;
+            let op_inner = SecOptionInner {
+                contract_id,
+                min_tick: NotNan::default(),
+                symbol,
+                exchange,
+                strike: strike.parse().map_err(|e| ("strike", e))?,
+                multiplier: multiplier.parse().map_err(|e| ("multiplier", e))?,
+                expiration_date,
+                contract_month,
+                underlying_contract_id: contract_id,
+                sector: String::default(),
+                trading_class,
+                currency,
+                local_symbol,
+                long_name: String::default(),
+                order_types: Vec::default(),
+                valid_exchanges: Vec::default(),
+                details: ContractDetails::default(),
+            };
             let op_outer = match right.as_str() {
                 "C" => SecOption::Call(op_inner),
                 "P" => SecOption::Put(op_inner),
@@ -2657,8 +3852,135 @@ fn deserialize_contract_proxy<E: crate::contract::ProxyExchange + Clone>(
                     )))
                 }
             };
-            Contract::SecOption(op_outer)
+            Contract::SecOption(op_outer)
+        }
+        ContractType::SecFutureOption => {
+            let fop_inner = SecFutureOptionInner {
+                contract_id,
+                min_tick: NotNan::default(),
+                symbol,
+                exchange,
+                strike: strike.parse().map_err(|e| ("strike", e))?,
+                multiplier: multiplier.parse().map_err(|e| ("multiplier", e))?,
+                expiration_date: NaiveDate::parse_and_remainder(expiration_date.as_str(), "%Y%m%d")
+                    .map_err(|e| ("expiration_date", ParseDateTimeError::Parse(e)))?
+                    .0,
+                underlying_future_contract_id: contract_id,
+                trading_class,
+                currency,
+                local_symbol,
+                long_name: String::default(),
+                order_types: Vec::default(),
+                valid_exchanges: Vec::default(),
+                details: ContractDetails::default(),
+            };
+            let fop_outer = match right.as_str() {
+                "C" => SecFutureOption::Call(fop_inner),
+                "P" => SecFutureOption::Put(fop_inner),
+                other => {
+                    return Err(DecodeError::Other(format!(
+                        "Unexpected option right. Expected \'C\' or \'P\'. Found {other}."
+                    )))
+                }
+            };
+            Contract::SecFutureOption(fop_outer)
+        }
+        ContractType::Warrant => {
+            let war_inner = WarrantInner {
+                contract_id,
+                min_tick: NotNan::default(),
+                symbol,
+                exchange,
+                strike: strike.parse().map_err(|e| ("strike", e))?,
+                multiplier: multiplier.parse().map_err(|e| ("multiplier", e))?,
+                expiration_date: NaiveDate::parse_and_remainder(expiration_date.as_str(), "%Y%m%d")
+                    .map_err(|e| ("expiration_date", ParseDateTimeError::Parse(e)))?
+                    .0,
+                underlying_contract_id: contract_id,
+                trading_class,
+                issuer: String::default(),
+                currency,
+                local_symbol,
+                long_name: String::default(),
+                order_types: Vec::default(),
+                valid_exchanges: Vec::default(),
+                details: ContractDetails::default(),
+            };
+            let war_outer = match right.as_str() {
+                "C" => Warrant::Call(war_inner),
+                "P" => Warrant::Put(war_inner),
+                other => {
+                    return Err(DecodeError::Other(format!(
+                        "Unexpected option right. Expected \'C\' or \'P\'. Found {other}."
+                    )))
+                }
+            };
+            Contract::Warrant(war_outer)
+        }
+        ContractType::Bond => Contract::Bond(Bond {
+            contract_id,
+            min_tick: NotNan::default(),
+            symbol,
+            exchange,
+            cusip: String::default(),
+            coupon: NotNan::default(),
+            maturity_date: NaiveDate::parse_and_remainder(expiration_date.as_str(), "%Y%m%d")
+                .map_err(|e| ("maturity_date", ParseDateTimeError::Parse(e)))?
+                .0,
+            issue_date: String::default(),
+            ratings: String::default(),
+            bond_type: String::default(),
+            coupon_type: String::default(),
+            callable: bool::default(),
+            putable: bool::default(),
+            convertible: bool::default(),
+            security_ids: Vec::default(),
+            trading_class,
+            currency,
+            local_symbol,
+            long_name: String::default(),
+            order_types: Vec::default(),
+            valid_exchanges: Vec::default(),
+            details: ContractDetails::default(),
+        }),
+        // The legs of a combo follow the other contract fields, so they can't be recovered here
+        ContractType::Combo => {
+            return Err(DecodeError::UnexpectedData("Unexpected BAG contract"))
         }
+        ContractType::Cfd => Contract::Cfd(Cfd {
+            contract_id,
+            min_tick: NotNan::default(),
+            symbol,
+            exchange,
+            underlying_contract_id: contract_id,
+            cfd_type: String::default(),
+            trading_class,
+            currency,
+            local_symbol,
+            long_name: String::default(),
+            order_types: Vec::default(),
+            valid_exchanges: Vec::default(),
+            details: ContractDetails::default(),
+        }),
+        ContractType::MutualFund => Contract::MutualFund(MutualFund {
+            contract_id,
+            min_tick: NotNan::default(),
+            symbol,
+            exchange,
+            fund_family: String::default(),
+            fund_type: String::default(),
+            fund_front_load: None,
+            fund_back_load: None,
+            fund_back_load_time_interval: String::default(),
+            fund_management_fee: None,
+            trading_class,
+            currency,
+            local_symbol,
+            long_name: String::default(),
+            order_types: Vec::default(),
+            valid_exchanges: Vec::default(),
+            details: ContractDetails::default(),
+        }),
     };
 
     Ok(Proxy {
@@ -2741,6 +4063,18 @@ pub(crate) enum DecodeError {
         field_name: &'static str,
         datetime_error: ParseDateTimeError,
     },
+    #[error("Failed to parse float field {field_name}. Cause: {not_nan_error}")]
+    /// Failed to parse a contract price or size, which must not be NaN
+    ParseNotNanError {
+        field_name: &'static str,
+        not_nan_error: crate::contract::ParseNotNanError,
+    },
+    #[error("Failed to parse schedule field {field_name}. Cause: {schedule_error}")]
+    /// Failed to parse a [`Schedule`] field
+    ParseScheduleError {
+        field_name: &'static str,
+        schedule_error: crate::contract::ParseScheduleError,
+    },
     #[error("Failed to parse order side field {field_name}")]
     ParseOrderSideError {
         field_name: &'static str,
@@ -2861,6 +4195,24 @@ impl From<(&'static str, ParsePayloadError)> for DecodeError {
     }
 }
 
+impl From<(&'static str, crate::contract::ParseNotNanError)> for DecodeError {
+    fn from(value: (&'static str, crate::contract::ParseNotNanError)) -> Self {
+        Self::ParseNotNanError {
+            field_name: value.0,
+            not_nan_error: value.1,
+        }
+    }
+}
+
+impl From<(&'static str, crate::contract::ParseScheduleError)> for DecodeError {
+    fn from(value: (&'static str, crate::contract::ParseScheduleError)) -> Self {
+        Self::ParseScheduleError {
+            field_name: value.0,
+            schedule_error: value.1,
+        }
+    }
+}
+
 impl From<(&'static str, ParseOrderSideError)> for DecodeError {
     fn from(value: (&'static str, ParseOrderSideError)) -> Self {
         Self::ParseOrderSideError {
@@ -2909,6 +4261,15 @@ pub enum ParseDateTimeError {
 }
 
 fn parse_historical_datetime(s: &str) -> Result<DateTime<chrono::Utc>, ParseDateTimeError> {
+    // Option 0: Seconds since the epoch, for requests made with formatDate 2
+    if s.len() > 8 && s.bytes().all(|b| b.is_ascii_digit()) {
+        return s
+            .parse()
+            .ok()
+            .and_then(|secs| DateTime::from_timestamp(secs, 0))
+            .ok_or(ParseDateTimeError::Timestamp);
+    }
+
     // Option 1: UTC datetime YYYYmmdd-HH:MM:SS
     if s.get(8..9).is_some_and(|c| c.eq("-")) {
         return Ok(NaiveDateTime::parse_from_str(s, "%Y%m%d-%T").map(|ref dt| dt.and_utc())?);
@@ -2929,3 +4290,1157 @@ fn parse_historical_datetime(s: &str) -> Result<DateTime<chrono::Utc>, ParseDate
         .ok_or(ParseDateTimeError::Single)?
         .to_utc())
 }
+
+#[tokio::test]
+async fn test_decode_bond_contract() -> Result<(), DecodeError> {
+    let (mut tx, mut rx_client) = tokio::sync::mpsc::channel(1);
+    let (tx_client, rx) = tokio::sync::mpsc::unbounded_channel();
+    let mut rx = PendingQueries::new(rx);
+    let query = crate::contract::Query::IbContractId(ContractId(1_234), Routing::Smart);
+    tx_client
+        .send(ToWrapper::ContractQuery((query, 7)))
+        .map_err(|_| DecodeError::UnexpectedData("Failed to send query"))?;
+
+    // T 3 1/2 02/15/39, CUSIP 912810QA9
+    let fields = [
+        "18", "7", "912810QA9", "BOND", "912810QA9", "3.5", "20390215", "20090215", "AA+",
+        "FIXED", "FIXED", "0", "0", "0", "", "SMART", "USD", "", "GOVT", "1234", "0.0001",
+        "LMT,MKT", "SMART", "", "", "0", "", "United States Treasury", "", "1", "1", "ISIN",
+        "US912810QA97", "2", "26", "1", "1", "1",
+    ];
+    decode_bond_contract_no_wrapper(
+        &mut fields.map(ToOwned::to_owned).to_vec().into_iter(),
+        &mut tx,
+        &mut rx,
+    )
+    .await?;
+
+    let Some(ToClient::NewContract(7, Contract::Bond(bond))) = rx_client.recv().await else {
+        return Err(DecodeError::UnexpectedData("No bond received"));
+    };
+    assert_eq!(bond.cusip, "912810QA9");
+    assert!((bond.coupon() - 3.5).abs() < f64::EPSILON);
+    assert_eq!(bond.maturity_date, NaiveDate::from_ymd_opt(2039, 2, 15).unwrap());
+    assert!(!bond.callable);
+    assert_eq!(
+        bond.security_ids,
+        vec![SecurityId::Isin("US912810QA97".to_owned())]
+    );
+    assert_eq!(
+        crate::contract::Security::market_rule_ids(&bond),
+        [(Routing::Smart, MarketRuleId(26))]
+    );
+    assert_eq!(
+        crate::contract::Security::market_rule_id(&bond, Routing::Smart),
+        Some(MarketRuleId(26))
+    );
+    assert_eq!(
+        crate::contract::Security::market_rule_id(
+            &bond,
+            Routing::Primary(Primary::NewYorkStockExchange)
+        ),
+        None
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_decode_future_option_contract() -> Result<(), DecodeError> {
+    let (mut tx, mut rx_client) = tokio::sync::mpsc::channel(1);
+    let (tx_client, rx) = tokio::sync::mpsc::unbounded_channel();
+    let mut rx = PendingQueries::new(rx);
+    let cme = Routing::Primary(Primary::ChicagoMercantileExchange);
+    // An earlier query that was never answered with a contract must be skipped
+    for (query, req_id) in [
+        (crate::contract::Query::IbContractId(ContractId(1), cme), 2),
+        (crate::contract::Query::IbContractId(ContractId(725_930_319), cme), 3),
+    ] {
+        tx_client
+            .send(ToWrapper::ContractQuery((query, req_id)))
+            .map_err(|_| DecodeError::UnexpectedData("Failed to send query"))?;
+    }
+
+    let fields = [
+        "10", "3", "ES", "FOP", "20241220 15:00:00", "6000", "C", "CME", "USD", "ESZ4 C6000",
+        "ES", "ES", "725930319", "0.05", "50", "LMT,MKT", "CME", "1", "568550526",
+        "E-mini S&P 500", "", "202412", "", "", "", "US/Central",
+        "20241219:1700-20241220:1600;20241221:CLOSED", "20241220:0830-20241220:1600;20241221:CLOSED",
+        "", "", "0", "4", "ES", "FUT", "67", "20241220", "", "1", "1", "1",
+    ];
+    decode_contract_no_wrapper(
+        &mut fields.map(ToOwned::to_owned).to_vec().into_iter(),
+        &mut tx,
+        &mut rx,
+    )
+    .await?;
+
+    let Some(ToClient::NewContract(3, Contract::SecFutureOption(fop))) = rx_client.recv().await
+    else {
+        return Err(DecodeError::UnexpectedData("No future option received"));
+    };
+    assert!(fop.is_call());
+    assert!((fop.multiplier() - 50.0).abs() < f64::EPSILON);
+    assert_eq!(fop.underlying_future_contract_id(), ContractId(568_550_526));
+    assert_eq!(crate::contract::Security::local_symbol(&fop), "ESZ4 C6000");
+
+    let details = crate::contract::Security::details(&fop);
+    assert_eq!(details.market_name(), "ES");
+    assert_eq!(details.contract_month(), "202412");
+    assert_eq!(details.time_zone_id(), "US/Central");
+    assert_eq!(details.agg_group(), Some(4));
+    assert_eq!(details.under_symbol(), "ES");
+    assert_eq!(details.under_security_type(), "FUT");
+    assert_eq!(details.real_expiration_date(), NaiveDate::from_ymd_opt(2024, 12, 20));
+    assert_eq!(details.last_trade_time(), "15:00:00");
+    assert_eq!(details.trading_hours().time_zone, chrono_tz::US::Central);
+    assert_eq!(details.trading_hours().sessions.len(), 2);
+    assert!(details.liquid_hours().sessions[1].closed);
+    assert_eq!(details.market_rule_ids(), &[(cme, MarketRuleId(67))]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_decode_crypto_contract() -> Result<(), DecodeError> {
+    let (mut tx, mut rx_client) = tokio::sync::mpsc::channel(1);
+    let (_tx_client, rx) = tokio::sync::mpsc::unbounded_channel();
+    let mut rx = PendingQueries::new(rx);
+
+    let fields = [
+        "10", "5", "BTC", "CRYPTO", "", "0", "", "PAXOS", "USD", "BTC.USD", "BTC", "BTC",
+        "479624278", "0.25", "", "ADJUST,ALERT,LMT,MKT,STP LMT,TRAIL LIMIT,STPLMT", "PAXOS", "1", "0", "Bitcoin", "", "",
+        "", "", "", "US/Central", "", "", "", "", "0", "1", "", "", "239", "", "", "0.0001",
+        "0.00000001", "0.0001",
+    ];
+    decode_contract_no_wrapper(
+        &mut fields.map(ToOwned::to_owned).to_vec().into_iter(),
+        &mut tx,
+        &mut rx,
+    )
+    .await?;
+
+    let Some(ToClient::NewContract(5, Contract::Crypto(crypto))) = rx_client.recv().await else {
+        return Err(DecodeError::UnexpectedData("No crypto received"));
+    };
+    assert_eq!(crypto.exchange(), Routing::Primary(Primary::PaxosCryptoExchange));
+    // Multi-word codes are kept whole, and both spellings of a stop limit order are recognized
+    assert_eq!(
+        crypto.order_types(),
+        &vec![
+            OrderTypeCode::Other("ADJUST".to_owned()),
+            OrderTypeCode::Other("ALERT".to_owned()),
+            OrderTypeCode::Limit,
+            OrderTypeCode::Market,
+            OrderTypeCode::StopLimit,
+            OrderTypeCode::TrailLimit,
+            OrderTypeCode::StopLimit,
+        ]
+    );
+    assert_eq!(
+        crate::contract::Security::order_types_raw(&crypto),
+        ["ADJUST", "ALERT", "LMT", "MKT", "STP LMT", "TRAIL LIMIT", "STP LMT"]
+    );
+    assert!(crate::contract::Security::supports_order_type(&crypto, OrderTypeCode::TrailLimit));
+    assert!(!crate::contract::Security::supports_order_type(&crypto, OrderTypeCode::Stop));
+    assert_eq!(crate::contract::Security::min_size(&crypto), Some(0.0001));
+    assert_eq!(crate::contract::Security::size_increment(&crypto), Some(0.000_000_01));
+    assert_eq!(crypto.details().suggested_size_increment(), Some(0.0001));
+    let round_size = |qty| crate::contract::Security::round_size(&crypto, qty);
+    assert!((round_size(0.123_456_789) - 0.123_456_79).abs() < 1e-12);
+    // Quantities below the minimum order size are raised to it
+    assert!((round_size(0.000_000_02) - 0.0001).abs() < 1e-12);
+    assert!(round_size(0.0).abs() < f64::EPSILON);
+
+    let mut nan_fields = fields.map(ToOwned::to_owned).to_vec();
+    nan_fields[13] = "NaN".to_owned();
+    assert!(matches!(
+        decode_contract_no_wrapper(&mut nan_fields.into_iter(), &mut tx, &mut rx).await,
+        Err(DecodeError::ParseNotNanError { field_name: "min_tick", .. })
+    ));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_decode_commodity_contract() -> Result<(), DecodeError> {
+    let (mut tx, mut rx_client) = tokio::sync::mpsc::channel(1);
+    let (_tx_client, rx) = tokio::sync::mpsc::unbounded_channel();
+    let mut rx = PendingQueries::new(rx);
+
+    let fields = [
+        "10", "6", "XAUUSD", "CMDTY", "", "0", "", "SMART", "USD", "XAUUSD", "XAUUSD", "XAUUSD",
+        "69067924", "0.01", "", "LMT,MKT", "SMART", "1", "0", "London Gold Spot", "", "",
+        "", "Precious Metals", "", "US/Eastern", "", "", "", "", "0", "1", "", "", "239", "", "SPOT",
+        "1", "1", "1",
+    ];
+    decode_contract_no_wrapper(
+        &mut fields.map(ToOwned::to_owned).to_vec().into_iter(),
+        &mut tx,
+        &mut rx,
+    )
+    .await?;
+
+    let Some(ToClient::NewContract(6, Contract::Commodity(cmdty))) = rx_client.recv().await else {
+        return Err(DecodeError::UnexpectedData("No commodity received"));
+    };
+    assert_eq!(cmdty.commodity_type(), "SPOT");
+    let proxy = Proxy::<Commodity, crate::contract::proxy_indicators::HasExchange> {
+        inner: cmdty,
+        _exch: std::marker::PhantomData,
+    };
+    assert_eq!(proxy.commodity_type(), "SPOT");
+    assert_eq!(proxy.trading_class(), "XAUUSD");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_decode_structured_product_contract() -> Result<(), DecodeError> {
+    let (mut tx, mut rx_client) = tokio::sync::mpsc::channel(1);
+    let (_tx_client, rx) = tokio::sync::mpsc::unbounded_channel();
+    let mut rx = PendingQueries::new(rx);
+
+    // A turbo call certificate on the DAX, listed in Stuttgart
+    let fields = [
+        "10", "6", "DAX", "IOPT", "20250620", "0", "", "SWB", "EUR", "DE000VU5ZAA1", "TURBO",
+        "TURBO", "654432711", "0.001", "0.01", "LMT,MKT", "SWB", "1", "0", "DAX TURBO CALL", "",
+        "", "", "", "", "MET", "", "", "", "", "0", "", "", "", "", "", "", "", "", "",
+    ];
+    decode_contract_no_wrapper(
+        &mut fields.map(ToOwned::to_owned).to_vec().into_iter(),
+        &mut tx,
+        &mut rx,
+    )
+    .await?;
+
+    let Some(ToClient::NewContract(6, Contract::StructuredProduct(product))) =
+        rx_client.recv().await
+    else {
+        return Err(DecodeError::UnexpectedData("No structured product received"));
+    };
+    assert_eq!(product.exchange(), Routing::Primary(Primary::StuttgartWertpapierboerse));
+    assert_eq!(product.expiration_date(), NaiveDate::from_ymd_opt(2025, 6, 20).unwrap());
+    assert!((product.multiplier() - 0.01).abs() < f64::EPSILON);
+    assert_eq!(product.trading_class(), "TURBO");
+    assert_eq!(product.local_symbol(), "DE000VU5ZAA1");
+    let contract = Contract::StructuredProduct(product);
+    assert_eq!(contract.security_type(), ContractType::StructuredProduct);
+    assert_eq!(contract.expiration_date(), NaiveDate::from_ymd_opt(2025, 6, 20));
+    assert_eq!(contract.multiplier(), Some(0.01));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_decode_fractional_multiplier() -> Result<(), DecodeError> {
+    let (mut tx, mut rx_client) = tokio::sync::mpsc::channel(1);
+    let (_tx_client, rx) = tokio::sync::mpsc::unbounded_channel();
+    let mut rx = PendingQueries::new(rx);
+
+    // Micro Bitcoin and Micro EUR/USD futures
+    for (symbol, multiplier, expected) in [("MBT", "0.1", 0.1), ("M6E", "12500", 12_500.0)] {
+        let fields = [
+            "10", "6", symbol, "FUT", "20241227", "0", "", "CME", "USD", "MBTZ4", symbol, symbol,
+            "735648722", "5", multiplier, "LMT,MKT", "CME", "1", "0", "Micro Future", "", "", "",
+            "", "", "", "", "", "", "", "0", "", "", "", "", "", "", "", "", "",
+        ];
+        decode_contract_no_wrapper(
+            &mut fields.map(ToOwned::to_owned).to_vec().into_iter(),
+            &mut tx,
+            &mut rx,
+        )
+        .await?;
+
+        let Some(ToClient::NewContract(6, Contract::SecFuture(future))) = rx_client.recv().await
+        else {
+            return Err(DecodeError::UnexpectedData("No future received"));
+        };
+        assert!((future.multiplier() - expected).abs() < f64::EPSILON);
+        assert!((future.notional(100.0, 2.0) - 200.0 * expected).abs() < 1e-9);
+        assert!((crate::contract::Security::multiplier(&future) - expected).abs() < f64::EPSILON);
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_decode_contract_month() -> Result<(), DecodeError> {
+    let (mut tx, mut rx_client) = tokio::sync::mpsc::channel(1);
+    let (_tx_client, rx) = tokio::sync::mpsc::unbounded_channel();
+    let mut rx = PendingQueries::new(rx);
+
+    // The January 2025 crude oil future stops trading in December 2024
+    let fields = |expiration_date: &str, contract_month: &str| {
+        [
+            "10", "7", "CL", "FUT", expiration_date, "0", "", "NYMEX", "USD", "CLF5", "CL", "CL",
+            "212921504", "0.01", "1000", "LMT,MKT", "NYMEX", "1", "0", "Light Sweet Crude Oil", "",
+            contract_month, "", "", "", "", "", "", "", "", "0", "", "", "", "", "", "", "", "",
+            "",
+        ]
+        .map(ToOwned::to_owned)
+        .to_vec()
+    };
+    for (contract_month, expected) in [("202501", (2025, 1)), ("", (2024, 12))] {
+        decode_contract_no_wrapper(
+            &mut fields("20241219", contract_month).into_iter(),
+            &mut tx,
+            &mut rx,
+        )
+        .await?;
+        let Some(ToClient::NewContract(7, Contract::SecFuture(future))) = rx_client.recv().await
+        else {
+            return Err(DecodeError::UnexpectedData("No future received"));
+        };
+        assert_eq!(future.expiration_date(), NaiveDate::from_ymd_opt(2024, 12, 19));
+        assert_eq!(future.contract_month(), expected);
+    }
+
+    // Only the contract month is known, so the day is left unset
+    decode_contract_no_wrapper(&mut fields("202501", "").into_iter(), &mut tx, &mut rx).await?;
+    let Some(ToClient::NewContract(7, Contract::SecFuture(future))) = rx_client.recv().await else {
+        return Err(DecodeError::UnexpectedData("No future received"));
+    };
+    assert_eq!(future.expiration_date(), None);
+    assert_eq!(future.contract_month(), (2025, 1));
+    assert_eq!(future.to_string(), "CL FUT 202501 NYMEX 1000x USD (conid 212921504)");
+
+    assert!(matches!(
+        decode_contract_no_wrapper(&mut fields("20241219", "202513").into_iter(), &mut tx, &mut rx).await,
+        Err(DecodeError::ParseDateTimeError { field_name: "contract_month", .. })
+    ));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_decode_warrant_issuer() -> Result<(), DecodeError> {
+    let (mut tx, mut rx_client) = tokio::sync::mpsc::channel(1);
+    let (_tx_client, rx) = tokio::sync::mpsc::unbounded_channel();
+    let mut rx = PendingQueries::new(rx);
+
+    let fields = |long_name: &str| {
+        [
+            "10", "8", "SAP", "WAR", "20261218", "200", "C", "SMART", "EUR", "DB1ABC", "SAP",
+            "SAP", "448814012", "0.001", "0.1", "LMT,MKT", "SMART", "1", "14204", long_name, "",
+            "", "", "", "", "", "", "", "", "", "0", "", "", "", "", "", "", "", "", "",
+        ]
+        .map(ToOwned::to_owned)
+        .to_vec()
+    };
+    for (long_name, expected) in [
+        ("SAP 18DEC26 200 C DB", "DB"),
+        ("SAP 18DEC26 200 C GOLDMAN SACHS", "GOLDMAN SACHS"),
+        ("DEUTSCHE BANK", "DEUTSCHE BANK"),
+        ("", ""),
+    ] {
+        decode_contract_no_wrapper(&mut fields(long_name).into_iter(), &mut tx, &mut rx).await?;
+        let Some(ToClient::NewContract(8, Contract::Warrant(warrant))) = rx_client.recv().await
+        else {
+            return Err(DecodeError::UnexpectedData("No warrant received"));
+        };
+        assert!(warrant.is_call());
+        assert_eq!(warrant.issuer(), expected);
+        assert!((warrant.multiplier() - 0.1).abs() < f64::EPSILON);
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_decode_several_contracts() -> Result<(), DecodeError> {
+    let (mut tx, mut rx_client) = tokio::sync::mpsc::channel(3);
+    let (tx_client, rx) = tokio::sync::mpsc::unbounded_channel();
+    let mut rx = PendingQueries::new(rx);
+    let spec = crate::contract::ContractSpec::new(
+        "ES",
+        ContractType::SecFutureOption,
+        Routing::Primary(Primary::ChicagoMercantileExchange),
+        Currency::UsDollar,
+    );
+    tx_client
+        .send(ToWrapper::ContractQuery((spec.into(), 4)))
+        .map_err(|_| DecodeError::UnexpectedData("Failed to send query"))?;
+
+    for (strike, contract_id) in [("6000", "725930319"), ("6100", "725930355")] {
+        let fields = [
+            "10", "4", "ES", "FOP", "20241220", strike, "C", "CME", "USD", "ESZ4 C6000", "ES",
+            "ES", contract_id, "0.05", "50", "LMT,MKT", "CME", "1", "568550526", "E-mini S&P 500",
+            "", "", "", "", "", "", "", "", "", "", "0", "", "", "", "", "", "", "", "", "",
+        ];
+        // Both contracts are checked against the query, which stays pending until its end
+        decode_contract_no_wrapper(
+            &mut fields.map(ToOwned::to_owned).to_vec().into_iter(),
+            &mut tx,
+            &mut rx,
+        )
+        .await?;
+    }
+    decode_contract_data_end_no_wrapper(
+        &mut ["52", "1", "4"].map(ToOwned::to_owned).to_vec().into_iter(),
+        &mut tx,
+        &mut rx,
+    )
+    .await?;
+
+    let mut contract_ids = Vec::new();
+    while let Some(ToClient::NewContract(4, Contract::SecFutureOption(fop))) =
+        rx_client.recv().await
+    {
+        contract_ids.push(crate::contract::Security::contract_id(&fop));
+    }
+    assert_eq!(contract_ids, [ContractId(725_930_319), ContractId(725_930_355)]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_decode_concurrent_contract_queries() -> Result<(), DecodeError> {
+    let (mut tx, mut rx_client) = tokio::sync::mpsc::channel(4);
+    let (tx_client, rx) = tokio::sync::mpsc::unbounded_channel();
+    let mut rx = PendingQueries::new(rx);
+    let (notifications, _) = tokio::sync::broadcast::channel(1);
+    let cme = Routing::Primary(Primary::ChicagoMercantileExchange);
+    let spec = crate::contract::ContractSpec::new(
+        "ES",
+        ContractType::SecFutureOption,
+        cme,
+        Currency::UsDollar,
+    );
+    for (query, req_id) in [
+        (spec.into(), 4),
+        (crate::contract::Query::IbContractId(ContractId(725_930_355), cme), 5),
+        (crate::contract::Query::IbContractId(ContractId(1), cme), 6),
+    ] {
+        tx_client
+            .send(ToWrapper::ContractQuery((query, req_id)))
+            .map_err(|_| DecodeError::UnexpectedData("Failed to send query"))?;
+    }
+    let fields = |req_id: &str, strike: &str, contract_id: &str| {
+        [
+            "10", req_id, "ES", "FOP", "20241220", strike, "C", "CME", "USD", "ESZ4 C6000", "ES",
+            "ES", contract_id, "0.05", "50", "LMT,MKT", "CME", "1", "568550526", "E-mini S&P 500",
+            "", "", "", "", "", "", "", "", "", "", "0", "", "", "", "", "", "", "", "", "",
+        ]
+        .map(ToOwned::to_owned)
+        .to_vec()
+    };
+
+    // The error for the second query arrives before the end of the first one
+    for (strike, contract_id) in [("6000", "725930319"), ("6100", "725930355")] {
+        let mut fields = fields("4", strike, contract_id).into_iter();
+        decode_contract_no_wrapper(&mut fields, &mut tx, &mut rx).await?;
+    }
+    let no_definition = "No security definition has been found for the request";
+    let mut err_fields = ["4", "2", "5", "200", no_definition, ""]
+        .map(ToOwned::to_owned)
+        .to_vec()
+        .into_iter();
+    decode_err_msg_no_wrapper(&mut err_fields, &mut tx, &mut rx, &notifications).await?;
+    let mut end_fields = ["52", "1", "4"].map(ToOwned::to_owned).to_vec().into_iter();
+    decode_contract_data_end_no_wrapper(&mut end_fields, &mut tx, &mut rx).await?;
+
+    assert!(matches!(rx_client.try_recv(), Ok(ToClient::NewContract(4, _))));
+    assert!(matches!(rx_client.try_recv(), Ok(ToClient::NewContract(4, _))));
+    let Ok(ToClient::RequestError(e)) = rx_client.try_recv() else {
+        return Err(DecodeError::UnexpectedData("No request error received"));
+    };
+    assert_eq!((e.req_id, e.code), (5, ErrorCode::NoSecurityDefinition));
+    assert_eq!(rx_client.try_recv().ok(), Some(ToClient::ContractQueryEnd(4)));
+
+    // The third query is still checked after the others have ended
+    let mut fields = fields("6", "6000", "725930319").into_iter();
+    assert!(matches!(
+        decode_contract_no_wrapper(&mut fields, &mut tx, &mut rx).await,
+        Err(DecodeError::UnexpectedData("Unexpected contract ID"))
+    ));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_decode_option_chain() -> Result<(), DecodeError> {
+    let (mut tx, mut rx_client) = tokio::sync::mpsc::channel(2);
+    let fields = [
+        "75", "9", "SMART", "265598", "AAPL", "100", "2", "20250117", "20241220", "3", "180",
+        "185", "182.5",
+    ];
+    decode_option_chain_no_wrapper(
+        &mut fields.map(ToOwned::to_owned).to_vec().into_iter(),
+        &mut tx,
+    )
+    .await?;
+    decode_option_chain_end_no_wrapper(
+        &mut ["76", "9"].map(ToOwned::to_owned).to_vec().into_iter(),
+        &mut tx,
+    )
+    .await?;
+
+    let Some(ToClient::OptionChain(chain)) = rx_client.recv().await else {
+        panic!("Expected an option chain");
+    };
+    assert_eq!(chain.exchange, Routing::Smart);
+    assert_eq!(chain.underlying_contract_id, ContractId(265_598));
+    assert_eq!(chain.trading_classes, ["AAPL"]);
+    assert!((chain.multiplier - 100.0).abs() < f64::EPSILON);
+    assert_eq!(
+        chain.expirations,
+        [
+            NaiveDate::from_ymd_opt(2025, 1, 17).unwrap(),
+            NaiveDate::from_ymd_opt(2024, 12, 20).unwrap()
+        ]
+    );
+    assert_eq!(chain.strikes, [180.0, 185.0, 182.5]);
+    assert_eq!(rx_client.recv().await, Some(ToClient::OptionChainEnd(9)));
+
+    // Some options, like those on Micro Bitcoin futures, have a fractional multiplier
+    let fields = ["75", "10", "CME", "629002763", "MBT", "0.1", "1", "20250131", "1", "95000"];
+    decode_option_chain_no_wrapper(
+        &mut fields.map(ToOwned::to_owned).to_vec().into_iter(),
+        &mut tx,
+    )
+    .await?;
+    let Some(ToClient::OptionChain(chain)) = rx_client.recv().await else {
+        panic!("Expected an option chain");
+    };
+    assert!((chain.multiplier - 0.1).abs() < f64::EPSILON);
+    assert_eq!(chain.strikes, [95_000.0]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_decode_market_rule() -> Result<(), DecodeError> {
+    let (mut tx, mut rx_client) = tokio::sync::mpsc::channel(1);
+    let fields = ["93", "26", "2", "0", "0.0001", "1", "0.01"];
+    decode_market_rule_no_wrapper(
+        &mut fields.map(ToOwned::to_owned).to_vec().into_iter(),
+        &mut tx,
+    )
+    .await?;
+
+    let Some(ToClient::MarketRule(rule)) = rx_client.recv().await else {
+        panic!("Expected a market rule");
+    };
+    assert_eq!(rule.id, MarketRuleId(26));
+    assert_eq!(
+        rule.increments,
+        [
+            PriceIncrement { low_edge: 0.0, increment: 0.0001 },
+            PriceIncrement { low_edge: 1.0, increment: 0.01 },
+        ]
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_decode_current_time() -> Result<(), DecodeError> {
+    #[derive(Default)]
+    struct Times(Vec<DateTime<chrono::Utc>>);
+
+    impl wrapper::LocalWrapper for Times {
+        fn current_time(&mut self, _req_id: i64, datetime: DateTime<chrono::Utc>) -> impl Future {
+            self.0.push(datetime);
+            async {}
+        }
+    }
+
+    let (mut tx, mut rx_client) = tokio::sync::mpsc::channel(1);
+    let (tx_client, rx) = tokio::sync::mpsc::unbounded_channel();
+    let mut rx = PendingQueries::new(rx);
+    let fields = || ["49", "1", "1735828200"].map(ToOwned::to_owned).to_vec().into_iter();
+    let expected = DateTime::from_timestamp(1_735_828_200, 0).unwrap();
+
+    // Without an awaiting client, the time goes to the wrapper
+    let mut times = Times::default();
+    Local::current_time_msg(&mut fields(), &mut times, &mut tx, &mut rx).await?;
+    assert_eq!(times.0, [expected]);
+    assert!(rx_client.try_recv().is_err());
+
+    tx_client
+        .send(ToWrapper::CurrentTime)
+        .map_err(|_| DecodeError::UnexpectedData("Failed to send request"))?;
+    Local::current_time_msg(&mut fields(), &mut times, &mut tx, &mut rx).await?;
+    assert_eq!(times.0.len(), 1);
+    assert_eq!(rx_client.recv().await, Some(ToClient::CurrentTime(expected)));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_decode_histogram() -> Result<(), DecodeError> {
+    let (mut tx, mut rx_client) = tokio::sync::mpsc::channel(1);
+    let fields = ["89", "6", "3", "187.5", "12900", "187.51", "300", "187.52", "5841"];
+    decode_histogram_no_wrapper(&mut fields.map(ToOwned::to_owned).to_vec().into_iter(), &mut tx)
+        .await?;
+
+    let entry = |price, size| HistogramEntry { price, size };
+    assert_eq!(
+        rx_client.recv().await,
+        Some(ToClient::Histogram(
+            6,
+            vec![entry(187.5, 12_900.0), entry(187.51, 300.0), entry(187.52, 5_841.0)]
+        ))
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_decode_histogram_to_wrapper() -> Result<(), DecodeError> {
+    #[derive(Default)]
+    struct Histograms(Vec<(i64, std::collections::HashMap<usize, HistogramEntry>)>);
+
+    impl wrapper::LocalWrapper for Histograms {
+        fn histogram(
+            &mut self,
+            req_id: i64,
+            histogram: std::collections::HashMap<usize, HistogramEntry>,
+        ) -> impl Future {
+            self.0.push((req_id, histogram));
+            async {}
+        }
+    }
+
+    let (mut tx, mut rx_client) = tokio::sync::mpsc::channel(1);
+    let (tx_client, rx) = tokio::sync::mpsc::unbounded_channel();
+    let mut rx = PendingQueries::new(rx);
+    let fields = |req_id: &str| {
+        ["89", req_id, "2", "187.5", "12900", "187.51", "300"].map(ToOwned::to_owned).to_vec()
+    };
+    let entry = |price, size| HistogramEntry { price, size };
+
+    // Only the response to a request of the deprecated method goes to the wrapper, by bin
+    tx_client
+        .send(ToWrapper::WrapperRequest(6))
+        .map_err(|_| DecodeError::UnexpectedData("Failed to send request"))?;
+    let mut histograms = Histograms::default();
+    Local::histogram_data_msg(&mut fields("6").into_iter(), &mut histograms, &mut tx, &mut rx)
+        .await?;
+    assert_eq!(
+        histograms.0,
+        [(6, [(0, entry(187.5, 12_900.0)), (1, entry(187.51, 300.0))].into_iter().collect())]
+    );
+    assert!(rx_client.try_recv().is_err());
+
+    Local::histogram_data_msg(&mut fields("7").into_iter(), &mut histograms, &mut tx, &mut rx)
+        .await?;
+    assert_eq!(histograms.0.len(), 1);
+    assert_eq!(
+        rx_client.recv().await,
+        Some(ToClient::Histogram(7, vec![entry(187.5, 12_900.0), entry(187.51, 300.0)]))
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_decode_head_timestamp() -> Result<(), DecodeError> {
+    let (mut tx, mut rx_client) = tokio::sync::mpsc::channel(3);
+    // Seconds since the epoch with formatDate 2, and UTC or local times with formatDate 1
+    for timestamp in ["345479400", "19801212-14:30:00", "19801212 09:30:00 US/Eastern"] {
+        let fields = ["88", "5", timestamp];
+        decode_head_timestamp_no_wrapper(
+            &mut fields.map(ToOwned::to_owned).to_vec().into_iter(),
+            &mut tx,
+        )
+        .await?;
+        assert_eq!(
+            rx_client.recv().await,
+            Some(ToClient::HeadTimestamp(
+                5,
+                DateTime::from_timestamp(345_479_400, 0).unwrap()
+            ))
+        );
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_decode_head_timestamp_to_wrapper() -> Result<(), DecodeError> {
+    #[derive(Default)]
+    struct Times(Vec<(i64, DateTime<chrono::Utc>)>);
+
+    impl wrapper::LocalWrapper for Times {
+        fn head_timestamp(&mut self, req_id: i64, timestamp: DateTime<chrono::Utc>) -> impl Future {
+            self.0.push((req_id, timestamp));
+            async {}
+        }
+    }
+
+    let (mut tx, mut rx_client) = tokio::sync::mpsc::channel(1);
+    let (tx_client, rx) = tokio::sync::mpsc::unbounded_channel();
+    let mut rx = PendingQueries::new(rx);
+    let fields =
+        |req_id: &str| ["88", req_id, "345479400"].map(ToOwned::to_owned).to_vec().into_iter();
+    let expected = DateTime::from_timestamp(345_479_400, 0).unwrap();
+
+    // Only the response to a request of the deprecated method goes to the wrapper
+    tx_client
+        .send(ToWrapper::WrapperRequest(3))
+        .map_err(|_| DecodeError::UnexpectedData("Failed to send request"))?;
+    let mut times = Times::default();
+    Local::head_timestamp_msg(&mut fields("3"), &mut times, &mut tx, &mut rx).await?;
+    assert_eq!(times.0, [(3, expected)]);
+    assert!(rx_client.try_recv().is_err());
+
+    Local::head_timestamp_msg(&mut fields("4"), &mut times, &mut tx, &mut rx).await?;
+    assert_eq!(times.0.len(), 1);
+    assert_eq!(rx_client.recv().await, Some(ToClient::HeadTimestamp(4, expected)));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_decode_err_msg() -> Result<(), DecodeError> {
+    let (mut tx, mut rx_client) = tokio::sync::mpsc::channel(4);
+    let (tx_client, rx) = tokio::sync::mpsc::unbounded_channel();
+    let mut rx = PendingQueries::new(rx);
+    let (notifications, mut rx_notifications) = tokio::sync::broadcast::channel(4);
+    let query = crate::contract::Query::IbContractId(ContractId(1), Routing::Smart);
+    for req_id in [3, 5] {
+        tx_client
+            .send(ToWrapper::ContractQuery((query.clone(), req_id)))
+            .map_err(|_| DecodeError::UnexpectedData("Failed to send query"))?;
+    }
+    let no_definition = "No security definition has been found for the request";
+    for fields in [
+        ["4", "2", "-1", "2104", "Market data farm connection is OK:usfarm", ""],
+        // Warnings never end a query, and errors for queries that are not pending are left alone
+        ["4", "2", "5", "2176", "Fractional share warning", ""],
+        ["4", "2", "5", "200", no_definition, ""],
+        ["4", "2", "9", "200", no_definition, ""],
+    ] {
+        let mut fields = fields.map(ToOwned::to_owned).to_vec().into_iter();
+        decode_err_msg_no_wrapper(&mut fields, &mut tx, &mut rx, &notifications).await?;
+    }
+
+    let notification = rx_notifications
+        .try_recv()
+        .map_err(|_| DecodeError::UnexpectedData("No notification received"))?;
+    assert_eq!(notification.code, ErrorCode::MarketDataFarmConnected);
+    assert_eq!(notification.message, "Market data farm connection is OK:usfarm");
+    assert!(rx_notifications.try_recv().is_err());
+
+    let Ok(ToClient::RequestError(e)) = rx_client.try_recv() else {
+        return Err(DecodeError::UnexpectedData("No request error received"));
+    };
+    assert_eq!((e.req_id, e.code), (5, ErrorCode::NoSecurityDefinition));
+    assert!(rx_client.try_recv().is_err());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_decode_err_msg_subscription() -> Result<(), DecodeError> {
+    use crate::error_code::Error;
+
+    let (mut tx, _rx_client) = tokio::sync::mpsc::channel(4);
+    let (tx_client, rx) = tokio::sync::mpsc::unbounded_channel();
+    let mut rx = PendingQueries::new(rx);
+    let notifications = tokio::sync::broadcast::channel(4).0;
+    let mut streams = Vec::new();
+    for req_id in [3, 5, 7] {
+        let (tx_stream, rx_stream) = tokio::sync::mpsc::unbounded_channel();
+        tx_client
+            .send(ToWrapper::Subscribe((req_id, Subscriber::Ticks(tx_stream))))
+            .map_err(|_| DecodeError::UnexpectedData("Failed to subscribe"))?;
+        streams.push(rx_stream);
+    }
+    for fields in [
+        ["4", "2", "3", "200", "No security definition has been found for the request", ""],
+        ["4", "2", "5", "354", "Requested market data is not subscribed", ""],
+        ["4", "2", "7", "10167", "Displaying delayed market data", ""],
+    ] {
+        let mut fields = fields.map(ToOwned::to_owned).to_vec().into_iter();
+        decode_err_msg_no_wrapper(&mut fields, &mut tx, &mut rx, &notifications).await?;
+    }
+
+    // Errors end their subscriptions with a dedicated variant for their codes
+    let [no_definition, not_subscribed, delayed] = streams.as_mut_slice() else {
+        unreachable!("Three streams are subscribed");
+    };
+    assert!(matches!(
+        no_definition.try_recv(),
+        Ok(Err(Error::NoSecurityDefinition(e))) if e.req_id == 3
+    ));
+    assert!(no_definition.is_closed());
+    assert!(matches!(
+        not_subscribed.try_recv(),
+        Ok(Err(Error::MarketDataNotSubscribed(e))) if e.req_id == 5
+    ));
+    assert!(not_subscribed.is_closed());
+    // Delayed market data is only a warning, so its subscription goes on
+    assert!(delayed.try_recv().is_err());
+    assert!(!delayed.is_closed());
+    assert!(rx.subscriber(7).is_some());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_decode_market_data_ticks() -> Result<(), DecodeError> {
+    #[derive(Default)]
+    struct Ticks(Vec<String>);
+
+    impl wrapper::LocalWrapper for Ticks {
+        fn price_data(&mut self, req_id: i64, price: Class<Price>) -> impl Future {
+            self.0.push(format!("{req_id} {price:?}"));
+            async {}
+        }
+        fn size_data(&mut self, req_id: i64, size: Class<Size>) -> impl Future {
+            self.0.push(format!("{req_id} {size:?}"));
+            async {}
+        }
+        fn volume(&mut self, req_id: i64, volume: Class<f64>) -> impl Future {
+            self.0.push(format!("{req_id} Volume({volume:?})"));
+            async {}
+        }
+        fn accessibility(&mut self, req_id: i64, access: Accessibility) -> impl Future {
+            self.0.push(format!("{req_id} {access:?}"));
+            async {}
+        }
+    }
+
+    let mut ticks = Ticks::default();
+    // Tick types 1, 2, 4, 6, and 7, and a delayed bid
+    for fields in [
+        ["1", "6", "3", "1", "189.5", "100", "1"],
+        ["1", "6", "3", "2", "189.52", "300", "0"],
+        ["1", "6", "3", "4", "189.51", "5", "0"],
+        ["1", "6", "3", "6", "191.0", "0", "0"],
+        ["1", "6", "3", "7", "188.25", "0", "0"],
+        ["1", "6", "3", "66", "189.4", "200", "0"],
+        // A price of -1 means that no price is available
+        ["1", "6", "3", "1", "-1", "0", "0"],
+    ] {
+        let mut fields = fields.map(ToOwned::to_owned).to_vec().into_iter();
+        Local::tick_price_msg(&mut fields, &mut ticks).await?;
+    }
+    // Tick types 0, 3, 5, and 8, and the halted and delayed ticks
+    for fields in [
+        ["2", "6", "3", "0", "100"],
+        ["2", "6", "3", "3", "300"],
+        ["2", "6", "3", "5", "5"],
+        ["2", "6", "3", "8", "25000"],
+        ["2", "6", "3", "70", "400"],
+        ["2", "6", "3", "74", "12000"],
+        ["45", "6", "3", "49", "1"],
+    ] {
+        let mut fields = fields.map(ToOwned::to_owned).to_vec().into_iter();
+        match fields.as_slice()[0].as_str() {
+            "45" => Local::tick_generic_msg(&mut fields, &mut ticks).await?,
+            _ => Local::tick_size_msg(&mut fields, &mut ticks).await?,
+        }
+    }
+
+    assert_eq!(
+        ticks.0,
+        [
+            "3 Live(Bid(189.5))",
+            "3 Live(Bid(100.0))",
+            "3 Live(Ask(189.52))",
+            "3 Live(Ask(300.0))",
+            "3 Live(Last(189.51))",
+            "3 Live(Last(5.0))",
+            "3 Live(High(191.0))",
+            "3 Live(Low(188.25))",
+            "3 Delayed(Bid(189.4))",
+            "3 Delayed(Bid(200.0))",
+            "3 Live(Bid(100.0))",
+            "3 Live(Ask(300.0))",
+            "3 Live(Last(5.0))",
+            "3 Volume(Live(25000.0))",
+            "3 Delayed(Ask(400.0))",
+            "3 Volume(Delayed(12000.0))",
+            "3 Halted(1.0)",
+        ]
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_decode_market_depth() -> Result<(), DecodeError> {
+    use crate::exchange::Primary;
+    use crate::payload::market_depth::{Book, Level, Source};
+
+    #[derive(Default)]
+    struct Books(std::collections::HashMap<i64, Book>);
+
+    impl wrapper::LocalWrapper for Books {
+        fn update_market_depth(&mut self, req_id: i64, operation: Operation) -> impl Future {
+            self.0.entry(req_id).or_default().apply(operation);
+            async {}
+        }
+    }
+
+    let mut books = Books::default();
+    // A SMART depth subscription that interleaves the rows of ISLAND and ARCA
+    for [mm, operation, side, position, price, size] in [
+        ["ISLAND", "0", "1", "0", "189.50", "300"],
+        ["ISLAND", "0", "1", "1", "189.48", "100"],
+        ["ARCA", "0", "1", "0", "189.49", "200"],
+        ["ISLAND", "0", "0", "0", "189.53", "400"],
+        ["ARCA", "0", "0", "0", "189.52", "100"],
+        ["ISLAND", "1", "1", "0", "189.51", "250"],
+        ["ARCA", "2", "0", "0", "189.52", "100"],
+        ["ISLAND", "0", "0", "0", "189.52", "150"],
+        ["ARCA", "0", "1", "1", "189.47", "500"],
+    ] {
+        let fields = ["13", "1", "7", position, mm, operation, side, price, size, "1"];
+        let mut fields = fields.map(ToOwned::to_owned).to_vec().into_iter();
+        Local::market_depth_l2_msg(&mut fields, &mut books).await?;
+    }
+    // A single exchange's book, whose rows report no participant
+    for [operation, side, position, price, size] in [
+        ["0", "1", "0", "4.25", "10"],
+        ["0", "0", "0", "4.50", "5"],
+        ["2", "1", "0", "4.25", "10"],
+    ] {
+        let fields = ["12", "1", "8", position, operation, side, price, size];
+        let mut fields = fields.map(ToOwned::to_owned).to_vec().into_iter();
+        Local::market_depth_msg(&mut fields, &mut books).await?;
+    }
+
+    let island = Source::Exchange(Primary::Island);
+    let arca = Source::Exchange(Primary::Archipelago);
+    let level = |source, price, size| Level { source, price, size };
+    let book = &books.0[&7];
+    assert_eq!(
+        book.bids(),
+        [
+            level(island, 189.51, 250.0),
+            level(arca, 189.49, 200.0),
+            level(island, 189.48, 100.0),
+            level(arca, 189.47, 500.0),
+        ]
+    );
+    assert_eq!(book.asks(), [level(island, 189.52, 150.0), level(island, 189.53, 400.0)]);
+    assert_eq!(book.best_bid(), Some(level(island, 189.51, 250.0)));
+    assert_eq!(book.best_ask(), Some(level(island, 189.52, 150.0)));
+    assert!(book.mid().is_some_and(|mid| (mid - 189.515).abs() < 1e-9));
+    assert_eq!(
+        book.level(1),
+        (Some(level(arca, 189.49, 200.0)), Some(level(island, 189.53, 400.0)))
+    );
+    assert_eq!(book.level(2), (Some(level(island, 189.48, 100.0)), None));
+
+    let book = &books.0[&8];
+    assert!(book.bids().is_empty());
+    assert_eq!(book.best_ask(), Some(level(Source::Unknown, 4.5, 5.0)));
+    assert_eq!(book.mid(), None);
+    Ok(())
+}
+
+#[test]
+fn test_decode_delta_neutral_contract() -> Result<(), DecodeError> {
+    // Everything after the parent ID of an open order message
+    let hedged = [
+        "0", "", "0", "", "", "0", "0", "1.7976931348623157E308", "", "", "",
+        "BUY 1 SPX 5800 C, SELL 1 SPX 5850 C", "2",
+        "720708753", "1", "BUY", "SMART", "0", "0", "", "-1",
+        "720708761", "1", "SELL", "SMART", "0", "0", "", "-1",
+        "0", "0", "", "", "1.7976931348623157E308", "", "0", "", "", "0",
+        "1", "416904", "0.5", "5800.0", "next",
+    ];
+    let mut fields = hedged.map(ToOwned::to_owned).to_vec().into_iter();
+    assert_eq!(
+        decode_delta_neutral_contract(&mut fields)?,
+        DeltaNeutralContract::new(ContractId(416_904), 0.5, 5_800.0).ok()
+    );
+    assert_eq!(fields.next().as_deref(), Some("next"));
+
+    let unhedged = [
+        "0", "0.2", "2", "LMT", "1.5", "0", "", "", "", "O", "0", "", "", "0", "1",
+        "1.7976931348623157E308", "", "", "", "", "0", "1", "1.25", "1", "NonGuaranteed", "1",
+        "100", "100", "0.5", "0.1", "60", "0.2", "0", "", "", "", "D", "0.3", "0", "", "", "0",
+        "0", "next",
+    ];
+    let mut fields = unhedged.map(ToOwned::to_owned).to_vec().into_iter();
+    assert_eq!(decode_delta_neutral_contract(&mut fields)?, None);
+    assert_eq!(fields.next().as_deref(), Some("next"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_decode_symbol_samples() -> Result<(), DecodeError> {
+    let (mut tx, mut rx_client) = tokio::sync::mpsc::channel(1);
+    let fields = [
+        "79", "5", "2", "265598", "AAPL", "STK", "NASDAQ", "USD", "4", "CFD", "OPT", "IOPT",
+        "WAR", "APPLE INC", "", "38708077", "APLE", "STK", "NYSE", "USD", "0",
+        "APPLE HOSPITALITY REIT INC", "",
+    ];
+    decode_symbol_samples_no_wrapper(
+        &mut fields.map(ToOwned::to_owned).to_vec().into_iter(),
+        &mut tx,
+    )
+    .await?;
+
+    let Some(ToClient::SymbolMatches(matches)) = rx_client.recv().await else {
+        panic!("Expected symbol matches");
+    };
+    assert_eq!(matches.len(), 2);
+    let Some(stock) = matches[0].contract.clone().stock() else {
+        panic!("Expected a stock");
+    };
+    assert_eq!(stock.contract_id(), ContractId(265_598));
+    assert_eq!(stock.symbol(), "AAPL");
+    assert_eq!(stock.primary_exchange(), Primary::NationalAssociationOfSecurityDealers);
+    assert_eq!(
+        matches[0].derivative_types,
+        [
+            ContractType::Cfd,
+            ContractType::SecOption,
+            ContractType::StructuredProduct,
+            ContractType::Warrant
+        ]
+    );
+    assert_eq!(matches[0].description, "APPLE INC");
+    assert!(matches[1].derivative_types.is_empty());
+    assert_eq!(matches[1].description, "APPLE HOSPITALITY REIT INC");
+    assert_eq!(matches[1].contract_id(), ContractId(38_708_077));
+    assert_eq!(matches[1].symbol(), "APLE");
+    assert_eq!(matches[1].security_type(), ContractType::Stock);
+    assert_eq!(matches[1].currency(), crate::currency::Currency::UsDollar);
+    assert_eq!(matches[1].primary_exchange, Some(Primary::NewYorkStockExchange));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_decode_historical_bars() -> Result<(), DecodeError> {
+    use chrono::TimeZone;
+
+    type Utc = DateTime<chrono::Utc>;
+
+    #[derive(Default)]
+    struct Bars(Vec<(i64, Utc, Utc, Vec<Bar>)>);
+
+    impl wrapper::LocalWrapper for Bars {
+        fn historical_bars(
+            &mut self,
+            req_id: i64,
+            start_datetime: Utc,
+            end_datetime: Utc,
+            bars: Vec<Bar>,
+        ) -> impl Future {
+            self.0.push((req_id, start_datetime, end_datetime, bars));
+            async {}
+        }
+    }
+
+    let mut bars = Bars::default();
+    // 3 five-minute TRADES bars of AAPL, requested with formatDate 1
+    let fields = [
+        "17", "3", "20240102 09:30:00 US/Eastern", "20240102 09:45:00 US/Eastern", "3",
+        "20240102 09:30:00 US/Eastern", "187.15", "188.44", "186.99", "188.05", "4212.37",
+        "187.64", "9384", "20240102 09:35:00 US/Eastern", "188.05", "188.10", "187.30",
+        "187.52", "1530.8", "187.71", "4211", "20240102 09:40:00 US/Eastern", "187.52",
+        "187.60", "186.92", "187.03", "1277.04", "187.25", "3307",
+    ];
+    let mut fields = fields.map(ToOwned::to_owned).to_vec().into_iter();
+    Local::historical_data_msg(&mut fields, &mut bars).await?;
+    // 2 hourly MIDPOINT bars of EUR.USD, requested with formatDate 2
+    let fields = [
+        "17", "4", "1704186000", "1704193200", "2", "1704186000", "1.10305", "1.10355",
+        "1.10175", "1.10215", "-1", "-1", "-1", "1704189600", "1.10215", "1.10240", "1.09980",
+        "1.10025", "-1", "-1", "-1",
+    ];
+    let mut fields = fields.map(ToOwned::to_owned).to_vec().into_iter();
+    Local::historical_data_msg(&mut fields, &mut bars).await?;
+    // 2 daily BID_ASK bars, whose datetimes have no time
+    let fields = [
+        "17", "5", "20231229", "20240102", "2", "20231229", "192.51", "193.10", "192.02",
+        "192.55", "-1", "-1", "-1", "20240102", "187.12", "188.46", "183.88", "185.66", "-1",
+        "-1", "-1",
+    ];
+    let mut fields = fields.map(ToOwned::to_owned).to_vec().into_iter();
+    Local::historical_data_msg(&mut fields, &mut bars).await?;
+
+    let eastern = |h, m| {
+        chrono_tz::US::Eastern
+            .with_ymd_and_hms(2024, 1, 2, h, m, 0)
+            .unwrap()
+            .to_utc()
+    };
+    let [(req_id, start, end, trades), (_, mid_start, mid_end, mids), (_, _, _, bid_asks)] =
+        bars.0.as_slice()
+    else {
+        panic!("Expected 3 historical_bars callbacks, got {}", bars.0.len());
+    };
+    assert_eq!((*req_id, *start, *end), (3, eastern(9, 30), eastern(9, 45)));
+    assert_eq!(trades.len(), 3);
+    assert_eq!(
+        trades[1],
+        Bar::Trades(Trade {
+            bar: BarCore {
+                datetime: eastern(9, 35),
+                open: 188.05,
+                high: 188.10,
+                low: 187.30,
+                close: 187.52,
+            },
+            volume: 1530.8,
+            wap: 187.71,
+            trade_count: 4211,
+        })
+    );
+
+    assert_eq!(mid_start.to_string(), "2024-01-02 09:00:00 UTC");
+    assert_eq!(mid_end.to_string(), "2024-01-02 11:00:00 UTC");
+    assert_eq!(
+        mids[1],
+        Bar::Ordinary(BarCore {
+            datetime: chrono::Utc.with_ymd_and_hms(2024, 1, 2, 10, 0, 0).unwrap(),
+            open: 1.10215,
+            high: 1.10240,
+            low: 1.09980,
+            close: 1.10025,
+        })
+    );
+
+    assert_eq!(bid_asks.len(), 2);
+    assert!(bid_asks.iter().all(|bar| matches!(bar, Bar::Ordinary(_))));
+    assert_eq!(bid_asks[0].core().datetime.to_string(), "2023-12-29 00:00:00 UTC");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_decode_updating_historical_bars() -> Result<(), DecodeError> {
+    use crate::payload::{BarUpdate, UpdatingBars};
+
+    #[derive(Default)]
+    struct Chart(UpdatingBars, Vec<BarUpdate>);
+
+    impl wrapper::LocalWrapper for Chart {
+        fn historical_bars(
+            &mut self,
+            _req_id: i64,
+            _start_datetime: DateTime<chrono::Utc>,
+            _end_datetime: DateTime<chrono::Utc>,
+            bars: Vec<Bar>,
+        ) -> impl Future {
+            self.0 = UpdatingBars::new(bars);
+            async {}
+        }
+
+        fn updating_historical_bar(&mut self, _req_id: i64, bar: Bar) -> impl Future {
+            self.1.push(self.0.update(bar));
+            async {}
+        }
+    }
+
+    let mut chart = Chart::default();
+    // The backlog of 1-minute MIDPOINT bars, whose last bar is still forming
+    let fields = [
+        "17", "9", "20240102 09:58:00 US/Eastern", "20240102 10:00:00 US/Eastern", "2",
+        "20240102 09:58:00 US/Eastern", "187.40", "187.55", "187.32", "187.50", "-1", "-1", "-1",
+        "20240102 09:59:00 US/Eastern", "187.50", "187.61", "187.47", "187.58", "-1", "-1", "-1",
+    ];
+    let mut fields = fields.map(ToOwned::to_owned).to_vec().into_iter();
+    Local::historical_data_msg(&mut fields, &mut chart).await?;
+    for [datetime, high, close] in [
+        ["20240102 09:59:00 US/Eastern", "187.64", "187.63"],
+        ["20240102 10:00:00 US/Eastern", "187.63", "187.60"],
+        ["20240102 10:00:00 US/Eastern", "187.70", "187.69"],
+    ] {
+        let fields = ["90", "9", "-1", datetime, "187.58", high, "187.47", close, "-1", "-1"];
+        let mut fields = fields.map(ToOwned::to_owned).to_vec().into_iter();
+        Local::historical_data_update_msg(&mut fields, &mut chart).await?;
+    }
+
+    let Chart(bars, updates) = chart;
+    assert!(matches!(
+        updates.as_slice(),
+        [BarUpdate::Replace(_), BarUpdate::New(_), BarUpdate::Replace(_)]
+    ));
+    // The updates are dated in the same time zone as the backlog
+    assert_eq!(bars.bars().len(), 3);
+    assert_eq!(bars.bars()[1].core().high, 187.64);
+    let last = bars.bars()[2].core();
+    assert_eq!(last.datetime.to_string(), "2024-01-02 15:00:00 UTC");
+    assert_eq!((last.high, last.close), (187.70, 187.69));
+    Ok(())
+}
@@ -0,0 +1,357 @@
+use thiserror::Error;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+/// The code with which the IBKR trading systems classify an error or notification message. See
+/// the [list of codes](https://interactivebrokers.github.io/tws-api/message_codes.html).
+///
+/// Only the most common codes have their own variants. Every other code is kept as
+/// [`ErrorCode::Other`].
+pub enum ErrorCode {
+    /// 100: The maximum rate of messages per second has been exceeded.
+    MaxRateExceeded,
+    /// 162: The historical market data service reported an error, like a pacing violation.
+    HistoricalDataService,
+    /// 200: No security definition has been found for the request.
+    NoSecurityDefinition,
+    /// 321: The request could not be validated.
+    InvalidRequest,
+    /// 354: The requested market data is not subscribed.
+    MarketDataNotSubscribed,
+    /// 502: Could not connect to TWS.
+    CouldNotConnect,
+    /// 504: Not connected to TWS.
+    NotConnected,
+    /// 1100: The connectivity between TWS and the IBKR trading systems has been lost.
+    ConnectivityLost,
+    /// 1101: The connectivity has been restored, but market data was lost and has to be requested
+    /// again.
+    ConnectivityRestoredDataLost,
+    /// 1102: The connectivity has been restored and market data was maintained.
+    ConnectivityRestored,
+    /// 1300: The socket port has been reset, so the connection is being dropped.
+    SocketPortReset,
+    /// 2103: A market data farm connection is broken.
+    MarketDataFarmDisconnected,
+    /// 2104: A market data farm connection is OK.
+    MarketDataFarmConnected,
+    /// 2105: A historical data farm connection is broken.
+    HistoricalDataFarmDisconnected,
+    /// 2106: A historical data farm connection is OK.
+    HistoricalDataFarmConnected,
+    /// 2158: A security definition data farm connection is OK.
+    SecurityDefinitionFarmConnected,
+    /// 10167: The requested market data is not subscribed, so delayed market data is displayed.
+    DelayedMarketData,
+    /// Any other code.
+    Other(i64),
+}
+
+impl From<i64> for ErrorCode {
+    fn from(value: i64) -> Self {
+        match value {
+            100 => Self::MaxRateExceeded,
+            162 => Self::HistoricalDataService,
+            200 => Self::NoSecurityDefinition,
+            321 => Self::InvalidRequest,
+            354 => Self::MarketDataNotSubscribed,
+            502 => Self::CouldNotConnect,
+            504 => Self::NotConnected,
+            1100 => Self::ConnectivityLost,
+            1101 => Self::ConnectivityRestoredDataLost,
+            1102 => Self::ConnectivityRestored,
+            1300 => Self::SocketPortReset,
+            2103 => Self::MarketDataFarmDisconnected,
+            2104 => Self::MarketDataFarmConnected,
+            2105 => Self::HistoricalDataFarmDisconnected,
+            2106 => Self::HistoricalDataFarmConnected,
+            2158 => Self::SecurityDefinitionFarmConnected,
+            10167 => Self::DelayedMarketData,
+            other => Self::Other(other),
+        }
+    }
+}
+
+impl From<ErrorCode> for i64 {
+    #[inline]
+    fn from(value: ErrorCode) -> Self {
+        value.code()
+    }
+}
+
+impl std::fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.code())
+    }
+}
+
+impl ErrorCode {
+    #[must_use]
+    /// Return the numeric code, like 200 for [`ErrorCode::NoSecurityDefinition`].
+    pub const fn code(self) -> i64 {
+        match self {
+            Self::MaxRateExceeded => 100,
+            Self::HistoricalDataService => 162,
+            Self::NoSecurityDefinition => 200,
+            Self::InvalidRequest => 321,
+            Self::MarketDataNotSubscribed => 354,
+            Self::CouldNotConnect => 502,
+            Self::NotConnected => 504,
+            Self::ConnectivityLost => 1100,
+            Self::ConnectivityRestoredDataLost => 1101,
+            Self::ConnectivityRestored => 1102,
+            Self::SocketPortReset => 1300,
+            Self::MarketDataFarmDisconnected => 2103,
+            Self::MarketDataFarmConnected => 2104,
+            Self::HistoricalDataFarmDisconnected => 2105,
+            Self::HistoricalDataFarmConnected => 2106,
+            Self::SecurityDefinitionFarmConnected => 2158,
+            Self::DelayedMarketData => 10167,
+            Self::Other(code) => code,
+        }
+    }
+
+    #[must_use]
+    #[inline]
+    /// Whether the message is only informational, so the request or the connection that it
+    /// concerns is unaffected. These are the warning codes from 2100 to 2199, the restored
+    /// connectivity (1102), and the fallback to delayed market data (10167).
+    pub const fn is_warning(self) -> bool {
+        matches!(self.code(), 1102 | 2100..=2199 | 10167)
+    }
+
+    #[must_use]
+    #[inline]
+    /// Whether the connection to TWS can no longer be used, like when it could not be made (502),
+    /// it was lost (504), or its socket port was reset (1300).
+    pub const fn is_fatal(self) -> bool {
+        matches!(self.code(), 502 | 504 | 1300)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Error)]
+#[error("IBKR rejected request {req_id} with error {code}: {message}")]
+/// An error message with which the IBKR trading systems rejected a request, like error 200 for a
+/// contract query that matches no contract.
+pub struct TwsError {
+    /// The ID of the rejected request.
+    pub req_id: i64,
+    /// The code of the error.
+    pub code: ErrorCode,
+    /// The text of the error, like "No security definition has been found for the request".
+    pub message: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// A message that the IBKR trading systems send without a request ID, like a change in the
+/// connectivity of TWS or in the status of its data farms. See
+/// [`crate::client::Client::notifications`].
+pub struct Notification {
+    /// The code of the notification.
+    pub code: ErrorCode,
+    /// The text of the notification, like "Market data farm connection is OK:usfarm".
+    pub message: String,
+}
+
+#[derive(Debug, Error)]
+/// An error from any part of the crate, so that an application can return a single type with `?`
+/// and still match on the cases it handles, like a contract that does not exist or market data
+/// that is not subscribed.
+///
+/// Each of the crate's own error types converts into it, and an error message from IBKR is sorted
+/// by its code into a dedicated variant where there is one, or into [`Error::Tws`] otherwise.
+pub enum Error {
+    /// Failed to send a request to, or to receive a response from, the IBKR API
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    /// Failed to connect to the IBKR API or to complete the handshake
+    #[error(transparent)]
+    Connection(#[from] crate::client::ConnectionError),
+    /// The connection to the IBKR API was lost before a response was received
+    #[error("The connection to the IBKR API was lost before a response was received.")]
+    Disconnected,
+    /// Failed to receive a valid response from the IBKR API
+    #[error("No valid response received from the IBKR API.")]
+    BadResponse,
+    /// A security of another type than the requested one was received from the IBKR API
+    #[error(transparent)]
+    UnexpectedSecurityType(#[from] crate::contract::UnexpectedSecurityType),
+    /// A query could not be parsed
+    #[error(transparent)]
+    InvalidQuery(#[from] crate::contract::ParseQueryError),
+    /// More than one contract matched a query
+    #[error("Query matched {} contracts.", .0.len())]
+    Ambiguous(Vec<crate::contract::Contract>),
+    /// The IBKR API did not answer a request within the client's request timeout
+    #[error(transparent)]
+    Timeout(#[from] crate::contract::RequestTimeout),
+    /// 200: No security definition has been found for the request
+    #[error(transparent)]
+    NoSecurityDefinition(TwsError),
+    /// 354: The requested market data is not subscribed
+    #[error(transparent)]
+    MarketDataNotSubscribed(TwsError),
+    /// 10167: The requested market data is not subscribed, so delayed market data is displayed
+    #[error(transparent)]
+    DelayedMarketData(TwsError),
+    /// Any other error message with which the IBKR API rejected a request
+    #[error(transparent)]
+    Tws(TwsError),
+    /// Any other error, like a symbol search that was paced or an option chain without options
+    #[error(transparent)]
+    Other(Box<dyn std::error::Error + Send + Sync>),
+}
+
+impl From<TwsError> for Error {
+    fn from(value: TwsError) -> Self {
+        match value.code {
+            ErrorCode::NoSecurityDefinition => Self::NoSecurityDefinition(value),
+            ErrorCode::MarketDataNotSubscribed => Self::MarketDataNotSubscribed(value),
+            ErrorCode::DelayedMarketData => Self::DelayedMarketData(value),
+            _ => Self::Tws(value),
+        }
+    }
+}
+
+impl From<crate::contract::NewSecurityError> for Error {
+    fn from(value: crate::contract::NewSecurityError) -> Self {
+        use crate::contract::NewSecurityError;
+
+        match value {
+            NewSecurityError::Io(e) => Self::Io(e),
+            NewSecurityError::BadResponse => Self::BadResponse,
+            NewSecurityError::Disconnected => Self::Disconnected,
+            NewSecurityError::UnexpectedSecurityType(e) => Self::UnexpectedSecurityType(e),
+            NewSecurityError::Ambiguous(contracts) => Self::Ambiguous(contracts),
+            NewSecurityError::Timeout(e) => Self::Timeout(e),
+            NewSecurityError::Rejected(e) => e.into(),
+        }
+    }
+}
+
+impl From<crate::contract::SearchSymbolsError> for Error {
+    fn from(value: crate::contract::SearchSymbolsError) -> Self {
+        use crate::contract::SearchSymbolsError;
+
+        match value {
+            SearchSymbolsError::Io(e) => Self::Io(e),
+            SearchSymbolsError::BadResponse => Self::BadResponse,
+            SearchSymbolsError::Disconnected => Self::Disconnected,
+            SearchSymbolsError::Timeout(e) => Self::Timeout(e),
+            e @ SearchSymbolsError::Pacing(_) => Self::Other(Box::new(e)),
+        }
+    }
+}
+
+impl From<crate::contract::OptionChainError> for Error {
+    fn from(value: crate::contract::OptionChainError) -> Self {
+        use crate::contract::OptionChainError;
+
+        match value {
+            OptionChainError::Io(e) => Self::Io(e),
+            OptionChainError::BadResponse => Self::BadResponse,
+            OptionChainError::Disconnected => Self::Disconnected,
+            e @ OptionChainError::NoOptions => Self::Other(Box::new(e)),
+        }
+    }
+}
+
+impl From<crate::contract::MarketRuleError> for Error {
+    fn from(value: crate::contract::MarketRuleError) -> Self {
+        use crate::contract::MarketRuleError;
+
+        match value {
+            MarketRuleError::Io(e) => Self::Io(e),
+            MarketRuleError::BadResponse => Self::BadResponse,
+            MarketRuleError::Disconnected => Self::Disconnected,
+        }
+    }
+}
+
+impl From<crate::market_data::historical_ticks::HeadTimestampError> for Error {
+    fn from(value: crate::market_data::historical_ticks::HeadTimestampError) -> Self {
+        use crate::market_data::historical_ticks::HeadTimestampError;
+
+        match value {
+            HeadTimestampError::Io(e) => Self::Io(e),
+            HeadTimestampError::Disconnected => Self::Disconnected,
+            HeadTimestampError::Timeout(e) => Self::Timeout(e),
+        }
+    }
+}
+
+impl From<crate::market_data::histogram::HistogramError> for Error {
+    fn from(value: crate::market_data::histogram::HistogramError) -> Self {
+        use crate::market_data::histogram::HistogramError;
+
+        match value {
+            HistogramError::Io(e) => Self::Io(e),
+            HistogramError::Disconnected => Self::Disconnected,
+            HistogramError::Timeout(e) => Self::Timeout(e),
+        }
+    }
+}
+
+impl Error {
+    #[must_use]
+    #[inline]
+    /// Return the code of the IBKR error message that caused the error, if any.
+    pub const fn code(&self) -> Option<ErrorCode> {
+        match self {
+            Self::NoSecurityDefinition(e)
+            | Self::MarketDataNotSubscribed(e)
+            | Self::DelayedMarketData(e)
+            | Self::Tws(e) => Some(e.code),
+            _ => None,
+        }
+    }
+}
+
+#[test]
+fn test_error_code() {
+    for code in [100, 200, 354, 1100, 2104, 10167, 399] {
+        assert_eq!(ErrorCode::from(code).code(), code);
+    }
+    assert_eq!(ErrorCode::from(200), ErrorCode::NoSecurityDefinition);
+    assert_eq!(ErrorCode::from(399), ErrorCode::Other(399));
+    assert!(ErrorCode::MarketDataFarmConnected.is_warning());
+    assert!(ErrorCode::Other(2119).is_warning());
+    assert!(ErrorCode::DelayedMarketData.is_warning());
+    assert!(!ErrorCode::NoSecurityDefinition.is_warning());
+    assert!(!ErrorCode::ConnectivityLost.is_warning());
+    assert!(ErrorCode::NotConnected.is_fatal());
+    assert!(!ErrorCode::NoSecurityDefinition.is_fatal());
+    assert!(ErrorCode::Other(1300).is_fatal());
+
+    let error = TwsError {
+        req_id: 7,
+        code: ErrorCode::NoSecurityDefinition,
+        message: "No security definition has been found for the request".to_owned(),
+    };
+    assert_eq!(
+        error.to_string(),
+        "IBKR rejected request 7 with error 200: No security definition has been found for the request"
+    );
+}
+
+#[test]
+fn test_error_from_tws_error() {
+    let tws_error = |code: i64| TwsError {
+        req_id: 7,
+        code: code.into(),
+        message: "The message".to_owned(),
+    };
+    assert!(matches!(Error::from(tws_error(200)), Error::NoSecurityDefinition(e) if e.req_id == 7));
+    assert!(matches!(Error::from(tws_error(354)), Error::MarketDataNotSubscribed(_)));
+    assert!(matches!(Error::from(tws_error(10167)), Error::DelayedMarketData(_)));
+    assert!(matches!(Error::from(tws_error(321)), Error::Tws(_)));
+    assert_eq!(Error::from(tws_error(10167)).code(), Some(ErrorCode::DelayedMarketData));
+
+    // The rejection of a contract query keeps its dedicated variant
+    let rejected = crate::contract::NewSecurityError::Rejected(tws_error(200));
+    assert!(matches!(Error::from(rejected), Error::NoSecurityDefinition(_)));
+    assert!(matches!(
+        Error::from(crate::contract::NewSecurityError::Disconnected),
+        Error::Disconnected
+    ));
+    assert_eq!(Error::from(tws_error(200)).to_string(), tws_error(200).to_string());
+}
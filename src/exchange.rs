@@ -4,7 +4,7 @@ use serde::{Deserialize, Serialize};
 
 // === Type definitions ===
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 /// Represents a "routing" exchange where orders and market data requests can be directed.
 pub enum Routing {
     #[serde(rename = "SMART")]
@@ -42,7 +42,7 @@ impl std::error::Error for ParseExchangeError {
 
 // Docs here would be somewhat ridiculous
 #[allow(missing_docs)]
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 /// Represents all the valid physical trading venues for various contracts.
 pub enum Primary {
     #[serde(rename = "AEB")]
@@ -448,6 +448,8 @@ pub enum Primary {
     WellsFargoForex,
     #[serde(rename = "WSE")]
     WarsawStockExchange,
+    #[serde(rename = "ZEROHASH")]
+    ZeroHashCryptoExchange,
 }
 
 // === Type implementations ===
@@ -671,6 +673,7 @@ impl FromStr for Primary {
             "VSE" => Self::ViennaStockExchange,
             "WFFX" => Self::WellsFargoForex,
             "WSE" => Self::WarsawStockExchange,
+            "ZEROHASH" => Self::ZeroHashCryptoExchange,
             s => return Err(ParseExchangeError(s.to_owned())),
         })
     }
@@ -881,6 +884,7 @@ impl std::fmt::Display for Primary {
             Self::ViennaStockExchange => "VSE",
             Self::WellsFargoForex => "WFFX",
             Self::WarsawStockExchange => "WSE",
+            Self::ZeroHashCryptoExchange => "ZEROHASH",
         };
         write!(f, "{s}")
     }
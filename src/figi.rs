@@ -286,7 +286,7 @@ impl std::str::FromStr for Figi {
     }
 }
 
-impl<'a> From<&'a Figi> for String {
+impl From<&Figi> for String {
     fn from(value: &Figi) -> Self {
         let mut s = String::with_capacity(12);
         s.push(value.pos_1.into());
@@ -422,3 +422,28 @@ fn test_figi() -> Result<(), InvalidFigi> {
     assert!(tsm.is_valid());
     Ok(())
 }
+
+#[test]
+fn test_invalid_figi() -> Result<(), InvalidFigi> {
+    for figi in ["BBG000BLNNH6", "BBG000B9XRY4", "BBG000BVPV84"] {
+        assert_eq!(figi.parse::<Figi>()?.to_string(), figi);
+    }
+
+    let err = |s: &str| s.parse::<Figi>().unwrap_err();
+    assert!(matches!(err("BBG000BLNNH"), InvalidFigi::Length(_)));
+    assert!(matches!(err("BBG000BLNNH66"), InvalidFigi::Length(_)));
+    assert!(matches!(err(""), InvalidFigi::Length(_)));
+    // Reserved prefixes that could be confused with ISINs
+    assert!(matches!(err("BSG000BLNNH6"), InvalidFigi::FirstTwo(_)));
+    assert!(matches!(err("GGG000BLNNH6"), InvalidFigi::FirstTwo(_)));
+    assert!(matches!(err("ABG000BLNNH6"), InvalidFigi::Consonant(_)));
+    assert!(matches!(err("bBG000BLNNH6"), InvalidFigi::Consonant(_)));
+    assert!(matches!(err("BBX000BLNNH6"), InvalidFigi::Third(_)));
+    assert!(matches!(err("BBG000BLANH6"), InvalidFigi::ConsonantOrNumeric(_)));
+    assert!(matches!(err("BBG000BLNN-6"), InvalidFigi::ConsonantOrNumeric(_)));
+    // A single changed character or a transposition breaks the check digit
+    assert!(matches!(err("BBG000BLNNH7"), InvalidFigi::Checksum(_)));
+    assert!(matches!(err("BBG000BLNHN6"), InvalidFigi::Checksum(_)));
+    assert!(matches!(err("BBG000BLNNHB"), InvalidFigi::Checksum(_)));
+    Ok(())
+}
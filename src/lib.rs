@@ -30,6 +30,11 @@ pub mod contract;
 /// Contains the definition of a [`currency::Currency`] enum, which represents the possible trading
 /// currencies available in the API.
 pub mod currency;
+/// Contains the [`error_code::ErrorCode`] with which the IBKR trading systems classify their error
+/// and notification messages, the [`error_code::TwsError`] and [`error_code::Notification`]
+/// types that carry them, and the [`error_code::Error`] into which every error of the crate
+/// converts.
+pub mod error_code;
 #[allow(
     unused_variables,
     clippy::print_stdout,
@@ -51,6 +56,9 @@ pub mod figi;
 /// and 3) Any types associated with implementors of the indicator types.
 pub mod market_data;
 mod message;
+/// Contains the [`mock::MockClient`], which replays recorded messages in place of a connection to
+/// the IBKR trading systems so that code which resolves contracts can be tested offline.
+pub mod mock;
 /// Contains types and traits related to orders.
 pub mod order;
 /// Contains the types that are parsed from API callbacks. They are used in the [`wrapper::LocalWrapper`] and
@@ -59,6 +67,10 @@ pub mod payload;
 /// Convenience module containing commonly-used types, functions, and modules.
 pub mod prelude;
 mod reader;
+/// Contains the format of the recordings made by [`client::Client::record_to`], which capture every
+/// message exchanged with the IBKR trading systems, and a [`record::ReplayClient`] that decodes
+/// them again without a connection.
+pub mod record;
 /// Contains modules, types, and functions related to live data subscriptions, namely those
 /// that are created in [`client::Client::req_market_data`].
 ///
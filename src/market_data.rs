@@ -86,13 +86,414 @@ macro_rules! impl_data_type {
     };
 }
 
+#[derive(Debug)]
+/// A stream of the data of a subscription, like the ticks of [`subscribe`]. Unlike the data of a
+/// request like [`crate::client::Client::req_market_data`], it is not passed to the wrapper.
+///
+/// The stream ends when TWS rejects the subscription, after which [`Subscription::error`]
+/// returns why, when the subscription is over, like after a snapshot, or when the connection is
+/// lost. Dropping the stream before then cancels the subscription.
+pub struct Subscription<T> {
+    req_id: i64,
+    rx: tokio::sync::mpsc::UnboundedReceiver<Result<T, crate::error_code::Error>>,
+    error: Option<crate::error_code::Error>,
+    tx_wrapper: tokio::sync::mpsc::UnboundedSender<crate::message::ToWrapper>,
+    cancel: Option<crate::comm::SendOnDrop>,
+}
+
+/// The ticks of a [`subscribe`] subscription.
+pub type TickStream = Subscription<crate::tick::Tick>;
+
+/// The market depth operations of a [`depth`] subscription.
+pub type DepthStream = Subscription<crate::payload::market_depth::Operation>;
+
+/// The 5-second bars of a [`realtime_bars`] subscription.
+pub type BarStream = Subscription<crate::payload::Bar>;
+
+impl<T> Subscription<T> {
+    #[inline]
+    pub(crate) const fn new(
+        req_id: i64,
+        rx: tokio::sync::mpsc::UnboundedReceiver<Result<T, crate::error_code::Error>>,
+        tx_wrapper: tokio::sync::mpsc::UnboundedSender<crate::message::ToWrapper>,
+        cancel: crate::comm::SendOnDrop,
+    ) -> Self {
+        Self {
+            req_id,
+            rx,
+            error: None,
+            tx_wrapper,
+            cancel: Some(cancel),
+        }
+    }
+
+    #[must_use]
+    #[inline]
+    /// Return the ID of the subscription's request.
+    pub const fn req_id(&self) -> i64 {
+        self.req_id
+    }
+
+    #[must_use]
+    #[inline]
+    /// Return the error with which TWS rejected the subscription, like
+    /// [`crate::error_code::Error::MarketDataNotSubscribed`], once the stream has ended because
+    /// of it.
+    pub const fn error(&self) -> Option<&crate::error_code::Error> {
+        self.error.as_ref()
+    }
+
+    /// Receive the next value of the subscription.
+    ///
+    /// # Returns
+    /// The next value, or `None` once the stream has ended.
+    pub async fn next(&mut self) -> Option<T> {
+        std::future::poll_fn(|cx| self.poll_next(cx)).await
+    }
+
+    /// Poll for the next value of the subscription, like [`Subscription::next`].
+    ///
+    /// # Returns
+    /// `Poll::Ready(Some(value))` for the next value, `Poll::Ready(None)` once the stream has
+    /// ended, and `Poll::Pending` while no value is available yet.
+    pub fn poll_next(&mut self, cx: &mut std::task::Context<'_>) -> std::task::Poll<Option<T>> {
+        if self.cancel.is_none() {
+            return std::task::Poll::Ready(None);
+        }
+        match self.rx.poll_recv(cx) {
+            std::task::Poll::Ready(Some(Ok(value))) => std::task::Poll::Ready(Some(value)),
+            std::task::Poll::Ready(end) => {
+                // The subscription is already over, so there is nothing left to cancel
+                self.error = end.and_then(Result::err);
+                if let Some(cancel) = self.cancel.take() {
+                    cancel.disarm();
+                }
+                std::task::Poll::Ready(None)
+            }
+            std::task::Poll::Pending => std::task::Poll::Pending,
+        }
+    }
+}
+
+impl BarStream {
+    #[must_use]
+    #[inline]
+    /// Roll the 5-second bars of the stream into bars of a longer period with a
+    /// [`crate::payload::BarAggregator`].
+    ///
+    /// # Arguments
+    /// * `period` - The period of the rolled bars, like 1 or 5 minutes.
+    ///
+    /// # Returns
+    /// The stream of rolled bars, which cancels the subscription when it is dropped.
+    pub fn aggregate(self, period: chrono::Duration) -> AggregatedBarStream {
+        AggregatedBarStream {
+            bars: self,
+            aggregator: crate::payload::BarAggregator::new(period),
+        }
+    }
+}
+
+#[derive(Debug)]
+/// The bars of a [`BarStream`], rolled into bars of a longer period by
+/// [`BarStream::aggregate`]. A rolled bar is returned once its period is over, and the bar of
+/// the unfinished period is returned when the stream ends.
+pub struct AggregatedBarStream {
+    bars: BarStream,
+    aggregator: crate::payload::BarAggregator,
+}
+
+impl AggregatedBarStream {
+    #[must_use]
+    #[inline]
+    /// Return the ID of the subscription's request.
+    pub const fn req_id(&self) -> i64 {
+        self.bars.req_id()
+    }
+
+    #[must_use]
+    #[inline]
+    /// Return the error with which TWS rejected the subscription, like [`Subscription::error`].
+    pub const fn error(&self) -> Option<&crate::error_code::Error> {
+        self.bars.error()
+    }
+
+    /// Receive the next rolled bar.
+    ///
+    /// # Returns
+    /// The next rolled bar, or `None` once the stream has ended.
+    pub async fn next(&mut self) -> Option<crate::payload::Bar> {
+        std::future::poll_fn(|cx| self.poll_next(cx)).await
+    }
+
+    /// Poll for the next rolled bar, like [`AggregatedBarStream::next`].
+    ///
+    /// # Returns
+    /// `Poll::Ready(Some(bar))` for the next rolled bar, `Poll::Ready(None)` once the stream has
+    /// ended, and `Poll::Pending` while the current period is not over yet.
+    pub fn poll_next(
+        &mut self,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Option<crate::payload::Bar>> {
+        loop {
+            match self.bars.poll_next(cx) {
+                std::task::Poll::Ready(Some(bar)) => {
+                    if let Some(rolled) = self.aggregator.push(bar) {
+                        return std::task::Poll::Ready(Some(rolled));
+                    }
+                }
+                std::task::Poll::Ready(None) => {
+                    return std::task::Poll::Ready(self.aggregator.flush());
+                }
+                std::task::Poll::Pending => return std::task::Poll::Pending,
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+/// The updated bars of a [`bars_updating`] subscription, after its backlog. Each one either
+/// replaces the last bar, which is still forming, or starts a new one, like with
+/// [`crate::payload::UpdatingBars::update`].
+pub struct BarUpdateStream {
+    updates: Subscription<crate::message::UpdatingBar>,
+    last: Option<crate::payload::Bar>,
+}
+
+impl BarUpdateStream {
+    #[must_use]
+    #[inline]
+    /// Return the ID of the subscription's request.
+    pub const fn req_id(&self) -> i64 {
+        self.updates.req_id()
+    }
+
+    #[must_use]
+    #[inline]
+    /// Return the error with which TWS rejected the subscription, like [`Subscription::error`].
+    pub const fn error(&self) -> Option<&crate::error_code::Error> {
+        self.updates.error()
+    }
+
+    /// Receive the next updated bar.
+    ///
+    /// # Returns
+    /// The next update, or `None` once the stream has ended.
+    pub async fn next(&mut self) -> Option<crate::payload::BarUpdate> {
+        std::future::poll_fn(|cx| self.poll_next(cx)).await
+    }
+
+    /// Poll for the next updated bar, like [`BarUpdateStream::next`].
+    ///
+    /// # Returns
+    /// `Poll::Ready(Some(update))` for the next update, `Poll::Ready(None)` once the stream has
+    /// ended, and `Poll::Pending` while no update is available yet.
+    pub fn poll_next(
+        &mut self,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Option<crate::payload::BarUpdate>> {
+        use crate::message::UpdatingBar;
+        use crate::payload::BarUpdate;
+
+        loop {
+            match self.updates.poll_next(cx) {
+                std::task::Poll::Ready(Some(UpdatingBar::Update(bar))) => {
+                    let update = match self.last.replace(bar) {
+                        Some(last) if last.core().datetime == bar.core().datetime => {
+                            BarUpdate::Replace(bar)
+                        }
+                        _ => BarUpdate::New(bar),
+                    };
+                    return std::task::Poll::Ready(Some(update));
+                }
+                // Only the first callback carries a backlog
+                std::task::Poll::Ready(Some(UpdatingBar::Backlog(_))) => (),
+                std::task::Poll::Ready(None) => return std::task::Poll::Ready(None),
+                std::task::Poll::Pending => return std::task::Poll::Pending,
+            }
+        }
+    }
+}
+
+impl<T> Drop for Subscription<T> {
+    fn drop(&mut self) {
+        // The decoder is gone if the connection is lost
+        let _ = self
+            .tx_wrapper
+            .send(crate::message::ToWrapper::Unsubscribe(self.req_id));
+    }
+}
+
+/// Subscribe to the market data of a security, like [`crate::client::Client::req_market_data`],
+/// but receive its ticks from the returned stream rather than the wrapper.
+///
+/// # Arguments
+/// * `client` - The client with which to subscribe.
+/// * `security` - The security for which to request data.
+/// * `additional_data` - The additional data to request, beyond the default ticks.
+/// * `refresh_type` - Whether to stream ticks or to take a single snapshot, after which the
+///   stream ends.
+///
+/// # Errors
+/// Returns any error encountered while writing the outgoing message.
+///
+/// # Returns
+/// The stream of ticks, which cancels the subscription when it is dropped.
+pub async fn subscribe<S, D>(
+    client: &mut crate::client::ActiveClient,
+    security: &S,
+    additional_data: Vec<D>,
+    refresh_type: live_data::RefreshType,
+) -> Result<TickStream, std::io::Error>
+where
+    S: crate::contract::Security,
+    D: live_data::DataType<S>,
+{
+    client
+        .market_data_stream(security, additional_data, refresh_type)
+        .await
+}
+
+/// Subscribe to the market depth of a security, like
+/// [`crate::client::Client::req_market_depth`], but receive its operations from the returned
+/// stream rather than the wrapper.
+///
+/// # Arguments
+/// * `client` - The client with which to subscribe.
+/// * `security` - The security for which to request market depth.
+/// * `number_of_rows` - The maximum number of rows in the order book.
+/// * `smart_depth` - Whether to aggregate the order book across the SMART exchange's components.
+///
+/// # Errors
+/// Returns any error encountered while writing the outgoing message.
+///
+/// # Returns
+/// The stream of operations on the order book, which cancels the subscription when it is
+/// dropped.
+pub async fn depth<S>(
+    client: &mut crate::client::ActiveClient,
+    security: &S,
+    number_of_rows: u32,
+    smart_depth: bool,
+) -> Result<DepthStream, std::io::Error>
+where
+    S: crate::contract::Security,
+{
+    client
+        .market_depth_stream(security, number_of_rows, smart_depth)
+        .await
+}
+
+/// Subscribe to the real-time, 5-second bars of a security, like
+/// [`crate::client::Client::req_real_time_bars`], but receive them from the returned stream
+/// rather than the wrapper. Roll them into bars of a longer period with
+/// [`BarStream::aggregate`].
+///
+/// # Arguments
+/// * `client` - The client with which to subscribe.
+/// * `security` - The security for which to request the bars.
+/// * `data` - The type of data to return (trades, bid, ask, midpoint).
+/// * `regular_trading_hours_only` - When [`true`], only return bars from regular trading hours.
+///
+/// # Errors
+/// Returns any error encountered while writing the outgoing message.
+///
+/// # Returns
+/// The stream of bars, which cancels the subscription when it is dropped.
+pub async fn realtime_bars<S, D>(
+    client: &mut crate::client::ActiveClient,
+    security: &S,
+    data: D,
+    regular_trading_hours_only: bool,
+) -> Result<BarStream, std::io::Error>
+where
+    S: crate::contract::Security,
+    D: live_bar::DataType<S>,
+{
+    client
+        .real_time_bars_stream(security, data, regular_trading_hours_only)
+        .await
+}
+
+/// Request historical bars that remain updated, like
+/// [`crate::client::Client::req_updating_historical_bar`], and wait for the backlog, but receive
+/// the updated bars from the returned stream rather than the wrapper.
+///
+/// # Arguments
+/// * `client` - The client with which to subscribe.
+/// * `security` - The security for which to request data.
+/// * `duration` - The duration for which historical data be returned (i.e. the difference
+///   between the first bar's datetime and the last bar's datetime).
+/// * `bar_size` - The size of each individual bar.
+/// * `data` - The type of data that to return (price, volume, volatility, etc.).
+/// * `regular_trading_hours_only` - When [`true`], only return bars from regular trading hours.
+///
+/// # Errors
+/// Returns an [`std::io::ErrorKind::InvalidInput`] error if `bar_size` is not valid for
+/// `duration` (see [`updating_historical_bar::Duration::validate`]), or any error encountered
+/// while writing the outgoing message. Also returns the error with which TWS rejects the
+/// subscription, or [`crate::error_code::Error::Disconnected`] if the connection closes before
+/// the backlog is received.
+///
+/// # Returns
+/// The backlog of bars, from the earliest to the latest, which is still forming, and the stream
+/// of updated bars, which cancels the subscription when it is dropped.
+pub async fn bars_updating<S, D>(
+    client: &mut crate::client::ActiveClient,
+    security: &S,
+    duration: updating_historical_bar::Duration,
+    bar_size: updating_historical_bar::Size,
+    data: D,
+    regular_trading_hours_only: bool,
+) -> Result<(Vec<crate::payload::Bar>, BarUpdateStream), crate::error_code::Error>
+where
+    S: crate::contract::Security,
+    D: updating_historical_bar::DataType<S>,
+{
+    let updates = client
+        .updating_historical_bar_stream(
+            security,
+            duration,
+            bar_size,
+            data,
+            regular_trading_hours_only,
+        )
+        .await?;
+    backlog(updates).await
+}
+
+#[inline]
+async fn backlog(
+    mut updates: Subscription<crate::message::UpdatingBar>,
+) -> Result<(Vec<crate::payload::Bar>, BarUpdateStream), crate::error_code::Error> {
+    loop {
+        match updates.next().await {
+            Some(crate::message::UpdatingBar::Backlog(bars)) => {
+                let last = bars.last().copied();
+                return Ok((bars, BarUpdateStream { updates, last }));
+            }
+            // TWS sends the backlog first
+            Some(crate::message::UpdatingBar::Update(_)) => (),
+            None => {
+                return Err(updates
+                    .error
+                    .take()
+                    .unwrap_or(crate::error_code::Error::Disconnected))
+            }
+        }
+    }
+}
+
 /// Contains types and traits used by [`crate::client::Client::req_historical_bar`].
 pub mod historical_bar {
     use chrono_tz::Tz;
     use ibapi_macros::typed_variants;
     use serde::{Deserialize, Serialize, Serializer};
 
-    use crate::contract::{Commodity, Crypto, Forex, Index, SecFuture, SecOption, Stock};
+    use crate::contract::{
+        Commodity, ContinuousFuture, Crypto, Forex, Index, SecFuture, SecFutureOption, SecOption,
+        Stock,
+    };
 
     // === Type definitions ===
 
@@ -188,8 +589,83 @@ pub mod historical_bar {
         Eight = 8,
     }
 
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, thiserror::Error)]
+    #[error("Invalid bar size {bar_size:?} for a duration of {duration:?}")]
+    /// An error returned when a duration and a bar size do not form one of the combinations that
+    /// the IBKR historical data service accepts. Sending them anyway would be rejected with error
+    /// 321.
+    pub struct InvalidBarSizeError {
+        /// The requested duration.
+        pub duration: Duration,
+        /// The requested bar size.
+        pub bar_size: Size,
+    }
+
     // === Type implementations ===
 
+    impl Duration {
+        #[must_use]
+        /// Return the number of seconds in the duration, counting a month as 30 days and a year
+        /// as 365 days.
+        pub const fn as_secs(self) -> u64 {
+            match self {
+                Self::Second(s) => s as u64,
+                Self::Day(d) => d as u64 * 86_400,
+                Self::Week(w) => w as u64 * 604_800,
+                Self::Month(m) => m as u64 * 2_592_000,
+                Self::Year(y) => y as u64 * 31_536_000,
+            }
+        }
+
+        /// Check that a bar size can be requested over the duration, following the
+        /// [valid duration and bar size settings](https://interactivebrokers.github.io/tws-api/historical_limitations.html).
+        /// Each bar must be no longer than the duration, and the shortest bar grows with the
+        /// duration: from 1 second for up to 1,800 seconds to 1 day for more than a month.
+        ///
+        /// # Arguments
+        /// * `bar_size` - The size of each bar to be returned.
+        ///
+        /// # Errors
+        /// Returns an [`InvalidBarSizeError`] if IBKR would reject the combination.
+        pub const fn validate(self, bar_size: Size) -> Result<(), InvalidBarSizeError> {
+            let (duration, bar) = (self.as_secs(), bar_size.as_secs());
+            let shortest = match duration {
+                0..=1_800 => 1,
+                1_801..=3_600 => 5,
+                3_601..=14_400 => 10,
+                14_401..=28_800 => 30,
+                28_801..=86_400 => 60,
+                86_401..=172_800 => 120,
+                172_801..=604_800 => 180,
+                604_801..=2_592_000 => 1_800,
+                _ => 86_400,
+            };
+            if duration == 0 || bar < shortest || bar > duration {
+                Err(InvalidBarSizeError {
+                    duration: self,
+                    bar_size,
+                })
+            } else {
+                Ok(())
+            }
+        }
+    }
+
+    impl Size {
+        #[must_use]
+        /// Return the number of seconds in each bar, counting a month as 30 days.
+        pub const fn as_secs(self) -> u64 {
+            match self {
+                Self::Seconds(s) => s as u64,
+                Self::Minutes(m) => m as u64 * 60,
+                Self::Hours(h) => h as u64 * 3_600,
+                Self::Day => 86_400,
+                Self::Week => 604_800,
+                Self::Month => 2_592_000,
+            }
+        }
+    }
+
     impl Serialize for EndDateTime {
         fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
         where
@@ -312,7 +788,7 @@ pub mod historical_bar {
 
     impl_data_type!(
         (Trades, Midpoint, Bid, Ask, BidAsk);
-        (SecOption, SecFuture, Crypto);
+        (SecOption, SecFutureOption, SecFuture, ContinuousFuture, Crypto);
         TradesMidBidAskData;
         NotTradesBidAskMidError
     );
@@ -341,7 +817,9 @@ pub mod updating_historical_bar {
     /// Re-export of [`historical_bar::Size`]
     pub use historical_bar::Size;
 
-    use crate::contract::{Commodity, Crypto, Forex, Index, SecFuture, SecOption, Stock};
+    use crate::contract::{
+        Commodity, Crypto, Forex, Index, SecFuture, SecFutureOption, SecOption, Stock,
+    };
 
     use super::historical_bar;
 
@@ -372,7 +850,7 @@ pub mod updating_historical_bar {
 
     impl_data_type!(
         (Trades, Midpoint, Bid, Ask, Data);
-        (Stock, SecOption, SecFuture, Crypto)
+        (Stock, SecOption, SecFutureOption, SecFuture, Crypto)
     );
 
     impl_data_type!(
@@ -389,12 +867,14 @@ pub mod updating_historical_bar {
 }
 
 /// Contains types and traits used by [`crate::client::Client::req_historical_ticks`] and
-/// [`crate::client::Client::req_head_timestamp`].
+/// [`crate::client::Client::head_timestamp`].
 pub mod historical_ticks {
     use ibapi_macros::typed_variants;
     use serde::{Deserialize, Serialize, Serializer};
 
-    use crate::contract::{Commodity, Contract, Crypto, Forex, Index, SecFuture, SecOption, Stock};
+    use crate::contract::{
+        Commodity, Contract, Crypto, Forex, Index, SecFuture, SecFutureOption, SecOption, Stock,
+    };
 
     // === Type definitions ===
 
@@ -412,6 +892,21 @@ pub mod historical_ticks {
     /// A simple struct to ensure that the number of ticks to return never exceeds 1,000.
     pub struct NumberOfTicks(u16);
 
+    #[derive(Debug, thiserror::Error)]
+    /// An error returned when [`crate::client::Client::head_timestamp`] fails.
+    pub enum HeadTimestampError {
+        /// Failed to send the head timestamp request to the IBKR API
+        #[error("Failed to send head timestamp request to IBKR API. Cause {0}")]
+        Io(#[from] std::io::Error),
+        /// The connection to the IBKR API was lost before a response was received
+        #[error("The connection to the IBKR API was lost before a response was received.")]
+        Disconnected,
+        /// The IBKR API did not answer the request within the client's request timeout, so the
+        /// request was cancelled
+        #[error(transparent)]
+        Timeout(#[from] crate::contract::RequestTimeout),
+    }
+
     // === Type implementations ===
 
     impl NumberOfTicks {
@@ -456,7 +951,7 @@ pub mod historical_ticks {
     #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
     #[serde(rename_all = "UPPERCASE")]
     /// The data types for a [`crate::client::Client::req_historical_ticks`] request or a
-    /// [`crate::client::Client::req_head_timestamp`] request.
+    /// [`crate::client::Client::head_timestamp`] request.
     pub enum Data {
         #[serde(rename = "TRADES")]
         /// The prices (and sizes) of actual trades for a given tick.
@@ -473,16 +968,31 @@ pub mod historical_ticks {
 
     impl_data_type!(
         (Trades, Midpoint, BidAsk, Data);
-        (Contract, Stock, Forex, SecOption, SecFuture, Crypto, Index, Commodity)
+        (Contract, Stock, Forex, SecOption, SecFutureOption, SecFuture, Crypto, Index, Commodity)
     );
 }
 
-/// Contains types and traits used by [`crate::client::Client::req_histogram_data`].
+/// Contains types and traits used by [`crate::client::Client::histogram`].
 pub mod histogram {
     use serde::{Serialize, Serializer};
 
     // === Type definitions ===
 
+    #[derive(Debug, thiserror::Error)]
+    /// An error returned when [`crate::client::Client::histogram`] fails.
+    pub enum HistogramError {
+        /// Failed to send the histogram request to the IBKR API
+        #[error("Failed to send histogram request to IBKR API. Cause {0}")]
+        Io(#[from] std::io::Error),
+        /// The connection to the IBKR API was lost before a response was received
+        #[error("The connection to the IBKR API was lost before a response was received.")]
+        Disconnected,
+        /// The IBKR API did not answer the request within the client's request timeout, so the
+        /// request was cancelled
+        #[error(transparent)]
+        Timeout(#[from] crate::contract::RequestTimeout),
+    }
+
     #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
     /// The span of dates and times over which bars will be returned.
     pub enum Duration {
@@ -530,7 +1040,9 @@ pub mod live_bar {
     /// Re-export of [`updating_historical_bar::Trades`]
     pub use updating_historical_bar::Trades;
 
-    use crate::contract::{Commodity, Contract, Crypto, Forex, Index, SecFuture, SecOption, Stock};
+    use crate::contract::{
+        Commodity, Contract, Crypto, Forex, Index, SecFuture, SecFutureOption, SecOption, Stock,
+    };
 
     use super::updating_historical_bar;
 
@@ -540,7 +1052,7 @@ pub mod live_bar {
 
     impl_data_type!(
         (Trades, Midpoint, Bid, Ask, Data);
-        (Stock, Forex, SecOption, SecFuture, Crypto, Index, Commodity, Contract)
+        (Stock, Forex, SecOption, SecFutureOption, SecFuture, Crypto, Index, Commodity, Contract)
     );
 }
 
@@ -553,7 +1065,9 @@ pub mod live_data {
     use ibapi_macros::typed_variants;
     use serde::{Deserialize, Serialize};
 
-    use crate::contract::{Commodity, Crypto, Forex, Index, SecFuture, SecOption, Stock};
+    use crate::contract::{
+        Commodity, Crypto, Forex, Index, SecFuture, SecFutureOption, SecOption, Stock,
+    };
 
     // === Type definitions ===
 
@@ -726,7 +1240,7 @@ pub mod live_data {
             IBDividends,
             Empty
         );
-        (Forex, SecOption, SecFuture, Crypto, Index, Commodity);
+        (Forex, SecOption, SecFutureOption, SecFuture, Crypto, Index, Commodity);
         GeneralData;
         NotGeneralDataError
     );
@@ -769,3 +1283,284 @@ pub mod live_ticks {
         (Stock, Forex, SecFuture, Crypto, Index, Commodity)
     );
 }
+
+#[test]
+fn test_historical_bar_validate() {
+    use historical_bar::{Duration, HourSize, MinuteSize, SecondSize, Size};
+
+    for (duration, bar_size) in [
+        (Duration::Second(60), Size::Seconds(SecondSize::One)),
+        (Duration::Second(60), Size::Minutes(MinuteSize::One)),
+        (Duration::Second(3_600), Size::Seconds(SecondSize::Five)),
+        (Duration::Second(28_800), Size::Hours(HourSize::Eight)),
+        (Duration::Day(1), Size::Minutes(MinuteSize::One)),
+        (Duration::Day(1), Size::Day),
+        (Duration::Week(1), Size::Minutes(MinuteSize::Three)),
+        (Duration::Month(1), Size::Month),
+        (Duration::Year(1), Size::Day),
+    ] {
+        assert_eq!(duration.validate(bar_size), Ok(()), "{duration:?} {bar_size:?}");
+    }
+    for (duration, bar_size) in [
+        (Duration::Second(0), Size::Seconds(SecondSize::One)),
+        (Duration::Second(60), Size::Minutes(MinuteSize::Two)),
+        (Duration::Second(3_600), Size::Seconds(SecondSize::One)),
+        (Duration::Day(1), Size::Seconds(SecondSize::Thirty)),
+        (Duration::Day(2), Size::Week),
+        (Duration::Month(1), Size::Minutes(MinuteSize::Fifteen)),
+        (Duration::Year(1), Size::Hours(HourSize::Eight)),
+    ] {
+        let error = historical_bar::InvalidBarSizeError { duration, bar_size };
+        assert_eq!(duration.validate(bar_size), Err(error));
+    }
+}
+
+#[tokio::test]
+async fn test_tick_stream() -> Result<(), Box<dyn std::error::Error>> {
+    use tokio::io::AsyncReadExt;
+
+    use crate::error_code::Error;
+    use crate::message::{Out, Subscriber, ToWrapper};
+    use crate::tick::{Accessibility, Class, Price, Size, Tick, TickAttrib};
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let stream = tokio::net::TcpStream::connect(listener.local_addr()?).await?;
+    let (mut server, _) = listener.accept().await?;
+    let writer = crate::comm::Writer::new(stream.into_split().1);
+    let (tx_wrapper, rx_wrapper) = tokio::sync::mpsc::unbounded_channel();
+    let mut pending = crate::decode::PendingQueries::new(rx_wrapper);
+    let subscribe = |req_id: i64| -> Result<TickStream, std::io::Error> {
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+        let cancel = writer.send_on_drop((Out::CancelMktData, 2_u8, req_id))?;
+        tx_wrapper
+            .send(ToWrapper::Subscribe((req_id, Subscriber::Ticks(tx))))
+            .map_err(|_| std::io::ErrorKind::BrokenPipe)?;
+        Ok(Subscription::new(req_id, rx, tx_wrapper.clone(), cancel))
+    };
+    let (mut ticks, mut rejected) = (subscribe(3)?, subscribe(5)?);
+
+    for fields in [
+        // A bid that can be executed automatically and is past the day's low
+        ["1", "6", "3", "1", "189.5", "100", "3"].as_slice(),
+        ["2", "6", "3", "8", "25000"].as_slice(),
+        ["45", "6", "3", "49", "1"].as_slice(),
+    ] {
+        let fields = fields.iter().copied().map(ToOwned::to_owned).collect();
+        assert_eq!(crate::decode::decode_subscription_msg(fields, &mut pending).await?, None);
+    }
+    // The ticks of other requests are left to the wrapper
+    let fields = vec!["1", "6", "4", "1", "1.5", "1", "0"];
+    let fields = fields.into_iter().map(ToOwned::to_owned).collect::<Vec<_>>();
+    assert_eq!(
+        crate::decode::decode_subscription_msg(fields.clone(), &mut pending).await?,
+        Some(fields)
+    );
+
+    let attrib = TickAttrib {
+        can_auto_execute: true,
+        past_limit: true,
+        pre_open: false,
+    };
+    assert_eq!(
+        ticks.next().await,
+        Some(Tick::Price {
+            price: Class::Live(Price::Bid(189.5)),
+            attrib
+        })
+    );
+    assert_eq!(ticks.next().await, Some(Tick::Size(Class::Live(Size::Bid(100.0)))));
+    assert_eq!(ticks.next().await, Some(Tick::Volume(Class::Live(25_000.0))));
+    assert_eq!(ticks.next().await, Some(Tick::Accessibility(Accessibility::Halted(1.0))));
+
+    // A rejected subscription ends with its error and is not cancelled again
+    let (mut tx, _rx) = tokio::sync::mpsc::channel(1);
+    let notifications = tokio::sync::broadcast::channel(1).0;
+    let fields = ["4", "2", "5", "354", "Requested market data is not subscribed", ""];
+    let mut fields = fields.map(ToOwned::to_owned).to_vec().into_iter();
+    crate::decode::decode_err_msg_no_wrapper(&mut fields, &mut tx, &mut pending, &notifications)
+        .await?;
+    assert_eq!(rejected.next().await, None);
+    assert!(matches!(rejected.error(), Some(Error::MarketDataNotSubscribed(_))));
+    drop(rejected);
+    tokio::task::yield_now().await;
+
+    // Dropping a stream cancels its subscription, whose ticks then go to the wrapper
+    drop(ticks);
+    let len = server.read_u32().await?;
+    let mut body = vec![0; len as usize];
+    server.read_exact(&mut body).await?;
+    // The cancel message, version 2, and the request ID
+    assert_eq!(String::from_utf8(body)?.split('\0').collect::<Vec<_>>(), ["2", "2", "3", ""]);
+    let fields = vec!["45", "6", "3", "49", "0"];
+    let fields = fields.into_iter().map(ToOwned::to_owned).collect::<Vec<_>>();
+    assert_eq!(
+        crate::decode::decode_subscription_msg(fields.clone(), &mut pending).await?,
+        Some(fields)
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_depth_stream() -> Result<(), Box<dyn std::error::Error>> {
+    use tokio::io::AsyncReadExt;
+
+    use crate::message::{Out, Subscriber, ToWrapper};
+    use crate::payload::market_depth::{CompleteEntry, Entry, Operation, Row};
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let stream = tokio::net::TcpStream::connect(listener.local_addr()?).await?;
+    let (mut server, _) = listener.accept().await?;
+    let writer = crate::comm::Writer::new(stream.into_split().1);
+    let (tx_wrapper, rx_wrapper) = tokio::sync::mpsc::unbounded_channel();
+    let mut pending = crate::decode::PendingQueries::new(rx_wrapper);
+    let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+    let cancel = writer.send_on_drop((Out::CancelMktDepth, 1_u8, 3_i64))?;
+    tx_wrapper
+        .send(ToWrapper::Subscribe((3, Subscriber::Depth(tx))))
+        .map_err(|_| std::io::Error::from(std::io::ErrorKind::BrokenPipe))?;
+    let mut depth: DepthStream = Subscription::new(3, rx, tx_wrapper.clone(), cancel);
+
+    // A bid inserted at the top of the book
+    let fields = ["12", "1", "3", "0", "0", "1", "189.5", "100"];
+    let fields = fields.map(ToOwned::to_owned).to_vec();
+    assert_eq!(crate::decode::decode_subscription_msg(fields, &mut pending).await?, None);
+    // The ticks of the same request ID are left to the wrapper
+    let fields = ["1", "6", "3", "1", "189.5", "100", "0"].map(ToOwned::to_owned).to_vec();
+    assert_eq!(
+        crate::decode::decode_subscription_msg(fields.clone(), &mut pending).await?,
+        Some(fields)
+    );
+    let row = Row {
+        position: 0,
+        price: 189.5,
+        size: 100.0,
+    };
+    assert_eq!(
+        depth.next().await,
+        Some(Operation::Insert(CompleteEntry::Ordinary(Entry::Bid(row))))
+    );
+
+    // Dropping the stream cancels its subscription
+    drop(depth);
+    let len = server.read_u32().await?;
+    let mut body = vec![0; len as usize];
+    server.read_exact(&mut body).await?;
+    // The cancel message, version 1, and the request ID
+    assert_eq!(String::from_utf8(body)?.split('\0').collect::<Vec<_>>(), ["11", "1", "3", ""]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_aggregated_bar_stream() -> Result<(), Box<dyn std::error::Error>> {
+    use tokio::io::AsyncReadExt;
+
+    use crate::message::{Out, Subscriber, ToWrapper};
+    use crate::payload::{Bar, BarCore};
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let stream = tokio::net::TcpStream::connect(listener.local_addr()?).await?;
+    let (mut server, _) = listener.accept().await?;
+    let writer = crate::comm::Writer::new(stream.into_split().1);
+    let (tx_wrapper, rx_wrapper) = tokio::sync::mpsc::unbounded_channel();
+    let mut pending = crate::decode::PendingQueries::new(rx_wrapper);
+    let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+    let cancel = writer.send_on_drop((Out::CancelRealTimeBars, 1_u8, 3_i64))?;
+    tx_wrapper
+        .send(ToWrapper::Subscribe((3, Subscriber::RealTimeBars(tx))))
+        .map_err(|_| std::io::Error::from(std::io::ErrorKind::BrokenPipe))?;
+    let bars: BarStream = Subscription::new(3, rx, tx_wrapper.clone(), cancel);
+    let mut bars = bars.aggregate(chrono::Duration::seconds(10));
+
+    // Two bars of the first 10 seconds, then one of the next
+    for fields in [
+        ["50", "3", "3", "1700000000", "10", "11", "9.5", "10.5", "0", "0", "0"],
+        ["50", "3", "3", "1700000005", "10.5", "12", "10", "11", "0", "0", "0"],
+        ["50", "3", "3", "1700000010", "11", "11.5", "10.5", "11", "0", "0", "0"],
+    ] {
+        let fields = fields.map(ToOwned::to_owned).to_vec();
+        assert_eq!(crate::decode::decode_subscription_msg(fields, &mut pending).await?, None);
+    }
+    let datetime = chrono::DateTime::from_timestamp(1_700_000_000, 0).ok_or("timestamp")?;
+    assert_eq!(
+        bars.next().await,
+        Some(Bar::Ordinary(BarCore {
+            datetime,
+            open: 10.0,
+            high: 12.0,
+            low: 9.5,
+            close: 11.0,
+        }))
+    );
+
+    // Dropping the stream cancels its subscription
+    drop(bars);
+    let len = server.read_u32().await?;
+    let mut body = vec![0; len as usize];
+    server.read_exact(&mut body).await?;
+    // The cancel message, version 1, and the request ID
+    assert_eq!(String::from_utf8(body)?.split('\0').collect::<Vec<_>>(), ["51", "1", "3", ""]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_bar_update_stream() -> Result<(), Box<dyn std::error::Error>> {
+    use tokio::io::AsyncReadExt;
+
+    use crate::message::{Out, Subscriber, ToWrapper};
+    use crate::payload::{Bar, BarCore, BarUpdate};
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let stream = tokio::net::TcpStream::connect(listener.local_addr()?).await?;
+    let (mut server, _) = listener.accept().await?;
+    let writer = crate::comm::Writer::new(stream.into_split().1);
+    let (tx_wrapper, rx_wrapper) = tokio::sync::mpsc::unbounded_channel();
+    let mut pending = crate::decode::PendingQueries::new(rx_wrapper);
+    let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+    let cancel = writer.send_on_drop((Out::CancelHistoricalData, 1_u8, 3_i64))?;
+    tx_wrapper
+        .send(ToWrapper::Subscribe((3, Subscriber::UpdatingBars(tx))))
+        .map_err(|_| std::io::Error::from(std::io::ErrorKind::BrokenPipe))?;
+    let updates = Subscription::new(3, rx, tx_wrapper.clone(), cancel);
+
+    // The backlog of one bar, which is amended, then followed by a new bar
+    for fields in [
+        vec![
+            "17", "3", "1700000000", "1700000060", "1", "1700000000", "10", "11", "9.5", "10.5",
+            "0", "0", "0",
+        ],
+        vec!["90", "3", "0", "1700000000", "10", "11.5", "9.5", "11", "0", "0"],
+        vec!["90", "3", "0", "1700000060", "11", "11", "10.5", "10.5", "0", "0"],
+    ] {
+        let fields = fields.into_iter().map(ToOwned::to_owned).collect();
+        assert_eq!(crate::decode::decode_subscription_msg(fields, &mut pending).await?, None);
+    }
+    let bar = |timestamp, open, high, low, close| -> Result<Bar, &str> {
+        Ok(Bar::Ordinary(BarCore {
+            datetime: chrono::DateTime::from_timestamp(timestamp, 0).ok_or("timestamp")?,
+            open,
+            high,
+            low,
+            close,
+        }))
+    };
+    let (bars, mut updates) = backlog(updates).await?;
+    assert_eq!(bars, [bar(1_700_000_000, 10.0, 11.0, 9.5, 10.5)?]);
+    assert_eq!(
+        updates.next().await,
+        Some(BarUpdate::Replace(bar(1_700_000_000, 10.0, 11.5, 9.5, 11.0)?))
+    );
+    assert_eq!(
+        updates.next().await,
+        Some(BarUpdate::New(bar(1_700_000_060, 11.0, 11.0, 10.5, 10.5)?))
+    );
+
+    // Dropping the stream cancels its subscription
+    drop(updates);
+    let len = server.read_u32().await?;
+    let mut body = vec![0; len as usize];
+    server.read_exact(&mut body).await?;
+    // The cancel message, version 1, and the request ID
+    assert_eq!(String::from_utf8(body)?.split('\0').collect::<Vec<_>>(), ["25", "1", "3", ""]);
+    Ok(())
+}
+
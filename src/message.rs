@@ -1,6 +1,9 @@
 use std::str::FromStr;
 
-use crate::contract::{Contract, Query};
+use crate::contract::{Contract, MarketRule, OptionChain, Query, SymbolMatch};
+use crate::error_code::{Error, TwsError};
+use crate::payload::{market_depth::Operation, Bar, HistogramEntry};
+use crate::tick::Tick;
 
 #[derive(Debug, Default, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
 pub struct InvalidInMsg(pub String);
@@ -369,13 +372,48 @@ pub enum Out {
     ReqUserInfo,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug)]
 pub enum ToWrapper {
     ContractQuery((Query, i64)),
+    ForgetContractQuery(i64),
+    CurrentTime,
+    CancelCurrentTime,
+    Subscribe((i64, Subscriber)),
+    Unsubscribe(i64),
+    /// A request of a deprecated method whose response still goes to the wrapper
+    WrapperRequest(i64),
+}
+
+#[allow(clippy::redundant_pub_crate)]
+#[derive(Debug)]
+/// The channel to which the decoder sends the data of a subscription whose stream the client holds,
+/// in place of the wrapper. An error that ends the subscription is sent last.
+pub(crate) enum Subscriber {
+    Ticks(tokio::sync::mpsc::UnboundedSender<Result<Tick, Error>>),
+    Depth(tokio::sync::mpsc::UnboundedSender<Result<Operation, Error>>),
+    RealTimeBars(tokio::sync::mpsc::UnboundedSender<Result<Bar, Error>>),
+    UpdatingBars(tokio::sync::mpsc::UnboundedSender<Result<UpdatingBar, Error>>),
 }
 
 #[allow(clippy::redundant_pub_crate)]
+#[derive(Debug)]
+/// The data of an updating historical bar subscription: its backlog, then each updated bar.
+pub(crate) enum UpdatingBar {
+    Backlog(Vec<Bar>),
+    Update(Bar),
+}
+
+#[allow(clippy::redundant_pub_crate, clippy::large_enum_variant)]
 #[derive(Debug, Clone, PartialEq)]
 pub(crate) enum ToClient {
-    NewContract(Contract),
+    NewContract(i64, Contract),
+    ContractQueryEnd(i64),
+    OptionChain(OptionChain),
+    OptionChainEnd(i64),
+    SymbolMatches(Vec<SymbolMatch>),
+    MarketRule(MarketRule),
+    CurrentTime(chrono::DateTime<chrono::Utc>),
+    HeadTimestamp(i64, chrono::DateTime<chrono::Utc>),
+    Histogram(i64, Vec<HistogramEntry>),
+    RequestError(TwsError),
 }
@@ -0,0 +1,508 @@
+use std::collections::{HashMap, VecDeque};
+
+use tokio::sync::mpsc;
+use tracing::warn;
+
+use crate::client::ClientPort;
+use crate::constants;
+use crate::contract::{Contract, Query, QueryOptions, SearchSymbolsError, SymbolMatch};
+use crate::decode;
+use crate::error_code::{Notification, TwsError};
+use crate::message::{In, ToClient, ToWrapper};
+use crate::reader::split_fields;
+
+/// Recorded sessions that can be replayed with [`MockClient::from_session`].
+pub mod fixtures {
+    /// The contract details of the AAPL stock, in answer to the first request of a session.
+    pub const STOCK_CONTRACT_DETAILS: &[u8] =
+        include_bytes!("../fixtures/stock_contract_details.bin");
+    /// The contract details of the AAPL January 17, 2025 190 call, in answer to the first request
+    /// of a session.
+    pub const OPTION_CONTRACT_DETAILS: &[u8] =
+        include_bytes!("../fixtures/option_contract_details.bin");
+    /// Error 200, "No security definition has been found for the request", in answer to the first
+    /// request of a session.
+    pub const NO_SECURITY_DEFINITION: &[u8] =
+        include_bytes!("../fixtures/no_security_definition.bin");
+}
+
+#[derive(Debug)]
+/// A [`ClientPort`] that answers requests with canned messages instead of sending them to the
+/// IBKR trading systems, so that code which resolves contracts with functions like
+/// [`crate::contract::new`] can be tested without a connection.
+///
+/// The messages are replayed in order, whatever the requests that they answer, and are decoded
+/// exactly like the messages received by an [`crate::client::ActiveClient`]. Like an active
+/// client, the mock numbers its requests from 0 in the order in which they are sent, so the
+/// messages must carry the same request IDs as a fresh connection would. A message that answers a
+/// request which has not been sent yet is held back until it is, just like no real response can
+/// precede its request, so that concurrent callers can be tested with responses in any order.
+/// An error message that
+/// rejects a pending query ends it with the error, as returned by
+/// [`ClientPort::take_request_error`], and once the messages run out, every
+/// further response is [`None`], as if the connection had closed, unless the mock is
+/// [`MockClient::silent_when_exhausted`].
+pub struct MockClient {
+    messages: VecDeque<Vec<String>>,
+    req_id: core::ops::RangeFrom<i64>,
+    queries: Vec<(i64, Query)>,
+    silent: bool,
+    request_timeout: Option<std::time::Duration>,
+    tx_wrapper: mpsc::UnboundedSender<ToWrapper>,
+    rx_wrapper: decode::PendingQueries,
+    tx: mpsc::Sender<ToClient>,
+    rx: mpsc::Receiver<ToClient>,
+    notifications: tokio::sync::broadcast::Sender<Notification>,
+    request_errors: HashMap<i64, TwsError>,
+}
+
+impl MockClient {
+    #[must_use]
+    /// Create a new [`MockClient`] that replays `messages`.
+    ///
+    /// # Arguments
+    /// * `messages` - The bodies of the messages to replay, each of which is a sequence of
+    ///   null-terminated fields without the length prefix with which it is sent over the wire.
+    pub fn new(messages: Vec<Vec<u8>>) -> Self {
+        let (tx_wrapper, rx_wrapper) = mpsc::unbounded_channel();
+        let (tx, rx) = mpsc::channel(constants::TO_CLIENT_CHANNEL_SIZE);
+        Self {
+            messages: messages.iter().map(|body| split_fields(body)).collect(),
+            req_id: 0..,
+            queries: Vec::new(),
+            silent: false,
+            request_timeout: None,
+            tx_wrapper,
+            rx_wrapper: decode::PendingQueries::new(rx_wrapper),
+            tx,
+            rx,
+            notifications: tokio::sync::broadcast::channel(1).0,
+            request_errors: HashMap::new(),
+        }
+    }
+
+    /// Create a new [`MockClient`] that replays a recorded session, like one of the [`fixtures`].
+    ///
+    /// # Arguments
+    /// * `session` - The messages to replay, each of which is prefixed with its length as a
+    ///   big-endian `u32`, just like they are received from the IBKR trading systems.
+    ///
+    /// # Errors
+    /// Returns an error if a message is shorter than its length prefix.
+    pub fn from_session(mut session: &[u8]) -> Result<Self, std::io::Error> {
+        let mut messages = Vec::new();
+        while let Some((prefix, rest)) = session.split_first_chunk::<4>() {
+            let len = usize::try_from(u32::from_be_bytes(*prefix))
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+            if rest.len() < len {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::UnexpectedEof,
+                    "Message is shorter than its length prefix",
+                ));
+            }
+            let (body, rest) = rest.split_at(len);
+            messages.push(body.to_vec());
+            session = rest;
+        }
+        if !session.is_empty() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::UnexpectedEof,
+                "Incomplete length prefix",
+            ));
+        }
+        Ok(Self::new(messages))
+    }
+
+    /// Create a new [`MockClient`] that replays a recorded session from a file. See
+    /// [`MockClient::from_session`] for the format of the file.
+    ///
+    /// # Errors
+    /// Returns any error encountered while reading the file, or an error if a message in the file
+    /// is shorter than its length prefix.
+    pub fn from_session_file(path: impl AsRef<std::path::Path>) -> Result<Self, std::io::Error> {
+        Self::from_session(&std::fs::read(path)?)
+    }
+
+    #[must_use]
+    #[inline]
+    /// Never answer once the messages run out, like a TWS that stays silent, rather than behaving
+    /// like a closed connection.
+    pub const fn silent_when_exhausted(mut self) -> Self {
+        self.silent = true;
+        self
+    }
+
+    #[must_use]
+    #[inline]
+    /// Set the [`ClientPort::request_timeout`] of the mock, which has none by default.
+    pub const fn with_request_timeout(mut self, timeout: std::time::Duration) -> Self {
+        self.request_timeout = Some(timeout);
+        self
+    }
+
+    #[must_use]
+    #[inline]
+    /// Return the contract queries that have been sent, with their request IDs, in the order in
+    /// which they were sent.
+    pub fn queries(&self) -> &[(i64, Query)] {
+        &self.queries
+    }
+
+    #[must_use]
+    #[inline]
+    /// Return the number of messages that have yet to be replayed.
+    pub fn remaining(&self) -> usize {
+        self.messages.len()
+    }
+
+    #[inline]
+    fn get_next_req_id(&mut self) -> i64 {
+        self.req_id.next().unwrap()
+    }
+
+    // Whether a message answers a request with an ID that has not been handed out yet
+    fn answers_unsent_request(&self, fields: &[String]) -> bool {
+        let index = match fields.first().and_then(|t| t.parse().ok()) {
+            Some(In::ContractData | In::BondContractData | In::SymbolSamples) => 1,
+            Some(In::ContractDataEnd | In::ErrMsg) => 2,
+            _ => return false,
+        };
+        fields
+            .get(index)
+            .and_then(|req_id| req_id.parse::<i64>().ok())
+            .is_some_and(|req_id| req_id >= self.req_id.start)
+    }
+
+    /// Decode the next message, if any, with the same decoders that an active client uses.
+    async fn decode_next(&mut self) -> Option<()> {
+        if self.messages.is_empty() && self.silent
+            || self.messages.front().is_some_and(|m| self.answers_unsent_request(m))
+        {
+            return std::future::pending().await;
+        }
+        // The message is only taken once it is decoded, so that a receive that is cancelled while
+        // decoding, like by a `tokio::select!`, decodes it again rather than losing it
+        let fields = self.messages.front()?.clone();
+        let res = match fields.first().and_then(|t| t.parse().ok()) {
+            Some(In::ContractData) => decode::decode_contract_no_wrapper(&mut fields.into_iter(), &mut self.tx, &mut self.rx_wrapper).await.map_err(|e| e.with_context("contract data msg")),
+            Some(In::BondContractData) => decode::decode_bond_contract_no_wrapper(&mut fields.into_iter(), &mut self.tx, &mut self.rx_wrapper).await.map_err(|e| e.with_context("bond contract data msg")),
+            Some(In::ContractDataEnd) => decode::decode_contract_data_end_no_wrapper(&mut fields.into_iter(), &mut self.tx, &mut self.rx_wrapper).await.map_err(|e| e.with_context("contract data end msg")),
+            Some(In::SymbolSamples) => decode::decode_symbol_samples_no_wrapper(&mut fields.into_iter(), &mut self.tx).await.map_err(|e| e.with_context("symbol samples msg")),
+            Some(In::ErrMsg) => decode::decode_err_msg_no_wrapper(&mut fields.into_iter(), &mut self.tx, &mut self.rx_wrapper, &self.notifications).await.map_err(|e| e.with_context("err msg msg")),
+            _ => {
+                warn!("Unexpected message replayed by the mock client. Fields: {:?}", &fields);
+                Ok(())
+            }
+        };
+        self.messages.pop_front();
+        if let Err(e) = res {
+            warn!("Failed to decode a message replayed by the mock client: {e}");
+        }
+        Some(())
+    }
+}
+
+impl ClientPort for MockClient {
+    async fn send_contract_query(
+        &mut self,
+        query: Query,
+        _options: QueryOptions,
+    ) -> Result<i64, std::io::Error> {
+        let req_id = self.get_next_req_id();
+        self.tx_wrapper
+            .send(ToWrapper::ContractQuery((query.clone(), req_id)))
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::BrokenPipe, e.to_string()))?;
+        self.queries.push((req_id, query));
+        Ok(req_id)
+    }
+
+    async fn recv_contract_queries(
+        &mut self,
+        pending: &mut HashMap<i64, Vec<Contract>>,
+    ) -> Option<(i64, Vec<Contract>)> {
+        loop {
+            self.decode_next().await?;
+            while let Ok(msg) = self.rx.try_recv() {
+                match msg {
+                    ToClient::NewContract(req_id, c) => match pending.get_mut(&req_id) {
+                        Some(contracts) => contracts.push(c),
+                        None => warn!("Unexpected contract received for request {req_id}"),
+                    },
+                    ToClient::ContractQueryEnd(req_id) => {
+                        if let Some(contracts) = pending.remove(&req_id) {
+                            return Some((req_id, contracts));
+                        }
+                        warn!("Unexpected contract query end received for request {req_id}");
+                    }
+                    ToClient::RequestError(e) => {
+                        if let Some(contracts) = pending.remove(&e.req_id) {
+                            let req_id = e.req_id;
+                            self.request_errors.insert(req_id, e);
+                            return Some((req_id, contracts));
+                        }
+                        warn!("Unexpected error received for request {}: {e}", e.req_id);
+                    }
+                    _ => warn!("Unexpected message received during a contract query"),
+                }
+            }
+        }
+    }
+
+    async fn send_matching_symbols(&mut self, _pattern: &str) -> Result<i64, SearchSymbolsError> {
+        Ok(self.get_next_req_id())
+    }
+
+    async fn recv_matching_symbols(&mut self) -> Option<Vec<SymbolMatch>> {
+        loop {
+            self.decode_next().await?;
+            while let Ok(msg) = self.rx.try_recv() {
+                match msg {
+                    ToClient::SymbolMatches(matches) => return Some(matches),
+                    _ => warn!("Unexpected contract data received during a symbol search"),
+                }
+            }
+        }
+    }
+
+    #[inline]
+    fn request_timeout(&self) -> Option<std::time::Duration> {
+        self.request_timeout
+    }
+
+    #[inline]
+    fn forget_contract_query(&mut self, req_id: i64) {
+        let _ = self.tx_wrapper.send(ToWrapper::ForgetContractQuery(req_id));
+    }
+
+    #[inline]
+    fn take_request_error(&mut self, req_id: i64) -> Option<TwsError> {
+        self.request_errors.remove(&req_id)
+    }
+}
+
+#[tokio::test]
+async fn test_mock_stock() -> Result<(), crate::contract::NewSecurityError> {
+    use crate::contract::{ContractId, Stock};
+    use crate::exchange::Routing;
+
+    let mut client = MockClient::from_session(fixtures::STOCK_CONTRACT_DETAILS)?;
+    let query = Query::IbContractId(ContractId(265_598), Routing::Smart);
+    let stock: Stock = crate::contract::new(&mut client, query.clone()).await?;
+    assert_eq!(crate::contract::Security::symbol(&stock), "AAPL");
+    assert_eq!(crate::contract::Security::long_name(&stock), "APPLE INC");
+    assert_eq!(client.queries(), [(0, query)]);
+    assert_eq!(client.remaining(), 0);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_mock_contract_details() -> Result<(), crate::contract::NewSecurityError> {
+    use crate::contract::{ContractId, Security};
+    use crate::exchange::Routing;
+
+    let mut client = MockClient::from_session(fixtures::STOCK_CONTRACT_DETAILS)?;
+    let contracts = client
+        .contract_details(Query::IbContractId(ContractId(265_598), Routing::Smart))
+        .await?;
+    let [contract] = contracts.as_slice() else {
+        panic!("The session has a single contract");
+    };
+    let details = contract.details();
+    assert_eq!(details.industry(), "Technology");
+    assert_eq!(details.category(), "Computers");
+    assert_eq!(details.subcategory(), "Computers");
+    assert_eq!(details.market_name(), "NMS");
+    assert_eq!(details.time_zone_id(), "US/Eastern");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_mock_option() -> Result<(), crate::contract::NewSecurityError> {
+    use crate::contract::{ContractId, SecOption};
+    use crate::exchange::Routing;
+
+    let mut client = MockClient::from_session(fixtures::OPTION_CONTRACT_DETAILS)?;
+    let option: SecOption = crate::contract::new(
+        &mut client,
+        Query::IbContractId(ContractId(718_006_936), Routing::Smart),
+    )
+    .await?;
+    assert!(option.is_call());
+    assert!((option.strike() - 190.0).abs() < f64::EPSILON);
+    assert_eq!(option.underlying_contract_id(), ContractId(265_598));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_mock_no_security_definition() -> Result<(), std::io::Error> {
+    use crate::contract::{ContractId, NewSecurityError, Stock};
+    use crate::exchange::Routing;
+
+    let mut client = MockClient::from_session(fixtures::NO_SECURITY_DEFINITION)?;
+    let query = Query::IbContractId(ContractId(1), Routing::Smart);
+    let Err(NewSecurityError::Rejected(e)) =
+        crate::contract::new::<Stock>(&mut client, query.clone()).await
+    else {
+        panic!("The query is rejected with error 200");
+    };
+    assert_eq!(e.req_id, 0);
+    assert_eq!(e.code, crate::error_code::ErrorCode::NoSecurityDefinition);
+    assert_eq!(
+        e.message,
+        "No security definition has been found for the request"
+    );
+    // Once the messages run out, the mock behaves like a closed connection
+    assert!(matches!(
+        crate::contract::new::<Stock>(&mut client, query).await,
+        Err(NewSecurityError::Disconnected)
+    ));
+    assert!(MockClient::from_session(&fixtures::STOCK_CONTRACT_DETAILS[..10]).is_err());
+
+    // A search that matches no contract has no matches rather than failing
+    let mut client = MockClient::from_session(fixtures::NO_SECURITY_DEFINITION)?;
+    let spec = crate::contract::ContractSpec::new(
+        "NOPE",
+        crate::contract::ContractType::Stock,
+        Routing::Smart,
+        crate::currency::Currency::UsDollar,
+    );
+    assert!(matches!(crate::contract::search(&mut client, spec).await, Ok(v) if v.is_empty()));
+
+    // The rejection is routed to the handle of a contract client that sent the query
+    let port = MockClient::from_session(fixtures::NO_SECURITY_DEFINITION)?;
+    let (mut client, task) = crate::client::ContractClient::spawn(port);
+    let query = Query::IbContractId(ContractId(1), Routing::Smart);
+    assert!(matches!(
+        crate::contract::new::<Stock>(&mut client.clone(), query).await,
+        Err(NewSecurityError::Rejected(e)) if e.req_id == 0
+    ));
+    assert!(client.take_request_error(0).is_none());
+    drop(client);
+    task.await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_mock_contract_cache_duplicates() -> Result<(), std::io::Error> {
+    use crate::contract::{ContractCache, ContractId, NewSecurityError};
+    use crate::exchange::Routing;
+
+    let mut client = MockClient::from_session(fixtures::NO_SECURITY_DEFINITION)?;
+    let query = Query::IbContractId(ContractId(1), Routing::Smart);
+    let mut cache = ContractCache::new();
+    let results = cache
+        .get_or_fetch_many(&mut client, vec![query.clone(), query.clone()])
+        .await;
+    // Both appearances of the query get the error with which IBKR rejected it
+    for result in results {
+        let Err(NewSecurityError::Rejected(e)) = result else {
+            panic!("The query is rejected with error 200");
+        };
+        assert_eq!(e.code, crate::error_code::ErrorCode::NoSecurityDefinition);
+    }
+    assert_eq!(client.queries(), [(0, query)]);
+    assert!(cache.is_empty());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_mock_many_pending_queries() -> Result<(), std::io::Error> {
+    use crate::contract::ContractId;
+    use crate::exchange::Routing;
+
+    let no_definition = "No security definition has been found for the request";
+    let body = format!("4\x002\x0014\x00200\x00{no_definition}\x00\x00");
+    let mut client = MockClient::new(vec![body.into_bytes()]);
+    // More queries are sent before the first response than a bounded channel would hold, and
+    // every one of them is still registered, so the rejection of the last one is routed to it
+    for _ in 0..15 {
+        let query = Query::IbContractId(ContractId(265_598), Routing::Smart);
+        client.send_contract_query(query, QueryOptions::default()).await?;
+    }
+    assert_eq!(client.recv_contract_query(14).await, Some(Vec::new()));
+    let e = client.take_request_error(14);
+    assert!(e.is_some_and(|e| e.code == crate::error_code::ErrorCode::NoSecurityDefinition));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_mock_request_timeout() -> Result<(), std::io::Error> {
+    use crate::contract::{ContractId, NewSecurityError, RequestKind, RequestTimeout, Stock};
+    use crate::exchange::Routing;
+
+    let mut client = MockClient::new(Vec::new())
+        .silent_when_exhausted()
+        .with_request_timeout(std::time::Duration::from_millis(5));
+    for req_id in 0..3 {
+        let query = Query::IbContractId(ContractId(265_598), Routing::Smart);
+        assert!(matches!(
+            crate::contract::new::<Stock>(&mut client, query).await,
+            Err(NewSecurityError::Timeout(RequestTimeout {
+                req_id: id,
+                kind: RequestKind::ContractDetails,
+            })) if id == req_id
+        ));
+    }
+    // Every query that timed out is forgotten rather than left pending
+    assert!(client.rx_wrapper.is_empty());
+    let queries = (0..3)
+        .map(|i| Query::IbContractId(ContractId(i), Routing::Smart))
+        .collect();
+    let results = crate::contract::new_many::<Stock>(&mut client, queries).await;
+    assert!(results
+        .iter()
+        .all(|r| matches!(r, Err(NewSecurityError::Timeout(_)))));
+    assert!(client.rx_wrapper.is_empty());
+    assert!(matches!(
+        crate::contract::search_symbols(&mut client, "apple").await,
+        Err(SearchSymbolsError::Timeout(RequestTimeout {
+            kind: RequestKind::MatchingSymbols,
+            ..
+        }))
+    ));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_mock_dropped_query() -> Result<(), std::io::Error> {
+    use crate::contract::{ContractId, Stock};
+    use crate::exchange::Routing;
+
+    let mut client = MockClient::new(Vec::new()).silent_when_exhausted();
+    let query = Query::IbContractId(ContractId(265_598), Routing::Smart);
+    // The mock has no request timeout, so the query is only given up on when its future is dropped
+    let wait = std::time::Duration::from_millis(5);
+    let new = crate::contract::new::<Stock>(&mut client, query.clone());
+    assert!(tokio::time::timeout(wait, new).await.is_err());
+    assert_eq!(client.queries(), [(0, query.clone())]);
+    assert!(client.rx_wrapper.is_empty());
+
+    let queries = vec![query.clone(), query.clone()];
+    let new_many = crate::contract::new_many::<Stock>(&mut client, queries);
+    assert!(tokio::time::timeout(wait, new_many).await.is_err());
+    assert_eq!(client.queries().len(), 3);
+    assert!(client.rx_wrapper.is_empty());
+
+    // A contract client forgets the query of a dropped future on the port that sent it
+    let (mut handle, task) = crate::client::ContractClient::spawn(client);
+    let new = crate::contract::new::<Stock>(&mut handle, query);
+    assert!(tokio::time::timeout(wait, new).await.is_err());
+    drop(handle);
+    let mut client = task.await?;
+    assert_eq!(client.queries().len(), 4);
+    assert!(client.rx_wrapper.is_empty());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_mock_search_symbols() -> Result<(), SearchSymbolsError> {
+    let body = "79\x000\x001\x00265598\x00AAPL\x00STK\x00NASDAQ\x00USD\x000\x00APPLE INC\x00\x00";
+    let mut client = MockClient::new(vec![body.as_bytes().to_vec()]);
+    let matches = client.search_symbols("apple").await?;
+    let [aapl] = matches.as_slice() else {
+        panic!("The session has a single match");
+    };
+    assert_eq!(aapl.symbol(), "AAPL");
+    assert_eq!(aapl.contract_id(), crate::contract::ContractId(265_598));
+    Ok(())
+}
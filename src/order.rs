@@ -2,10 +2,13 @@ use std::collections::HashMap;
 use std::fmt::Formatter;
 use std::str::FromStr;
 
-use serde::{Serialize, Serializer};
+use serde::{Deserialize, Serialize, Serializer};
 use serde::ser::SerializeTuple;
 
-use crate::contract::{Commodity, Crypto, Forex, Index, SecFuture, SecOption, Security, Stock};
+use crate::contract::{
+    Bond, Cfd, Combo, Commodity, Crypto, Forex, Index, SecFuture, SecFutureOption, SecOption,
+    Security, Stock, StructuredProduct, Warrant,
+};
 
 // ==============================================
 // === Core Order Types (Market, Limit, etc.) ===
@@ -13,6 +16,17 @@ use crate::contract::{Commodity, Crypto, Forex, Index, SecFuture, SecOption, Sec
 
 // === Type definitions ===
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+/// The side of a trade.
+pub enum Action {
+    #[serde(rename = "BUY")]
+    /// Buy the security.
+    Buy,
+    #[serde(rename = "SELL")]
+    /// Sell the security.
+    Sell,
+}
+
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
 /// The time periods for which an order is active and can be executed against.
 pub enum TimeInForce {
@@ -72,6 +86,156 @@ impl FromStr for TimeInForce {
     }
 }
 
+macro_rules! order_type_codes {
+    ($($(#[doc = $doc: expr])* $variant: ident => $code: literal $(| $alias: literal)*),* $(,)?) => {
+        #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+        #[serde(from = "String", into = "String")]
+        /// An order type, or an order attribute like an algo or a time in force, that IBKR reports
+        /// as supported by a contract, like "LMT" or "STP LMT".
+        ///
+        /// IBKR spells some codes differently in contract details than when placing an order, like
+        /// "STPLMT" for "STP LMT", so both spellings parse into the same code, which is displayed
+        /// with the spelling used to place orders.
+        pub enum OrderTypeCode {
+            $(
+                $(#[doc = $doc])*
+                $variant,
+            )*
+            /// Any other code, exactly as IBKR reported it
+            Other(String),
+        }
+
+        impl OrderTypeCode {
+            #[must_use]
+            /// Get the TWS code of the order type, like "STP LMT".
+            pub fn as_str(&self) -> &str {
+                match self {
+                    $(Self::$variant => $code,)*
+                    Self::Other(code) => code,
+                }
+            }
+        }
+
+        impl FromStr for OrderTypeCode {
+            type Err = std::convert::Infallible;
+
+            /// Parse an order type from its TWS code, ignoring case and surrounding whitespace.
+            /// Unknown codes become [`OrderTypeCode::Other`], so this never fails.
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                Ok(match s.trim().to_ascii_uppercase().as_str() {
+                    $($code $(| $alias)* => Self::$variant,)*
+                    _ => Self::Other(s.to_owned()),
+                })
+            }
+        }
+    };
+}
+
+order_type_codes!(
+    /// A market order
+    Market => "MKT",
+    /// A limit order
+    Limit => "LMT",
+    /// A stop order
+    Stop => "STP",
+    /// A stop limit order
+    StopLimit => "STP LMT" | "STPLMT",
+    /// A trailing stop order
+    Trail => "TRAIL",
+    /// A trailing stop limit order
+    TrailLimit => "TRAIL LIMIT" | "TRAILLMT",
+    /// A market if touched order
+    MarketIfTouched => "MIT",
+    /// A limit if touched order
+    LimitIfTouched => "LIT",
+    /// A trailing market if touched order
+    TrailMarketIfTouched => "TRAIL MIT" | "TRAILMIT",
+    /// A trailing limit if touched order
+    TrailLimitIfTouched => "TRAIL LIT" | "TRAILLIT",
+    /// A market on close order
+    MarketOnClose => "MOC",
+    /// A limit on close order
+    LimitOnClose => "LOC",
+    /// A market to limit order
+    MarketToLimit => "MTL",
+    /// A market with protection order
+    MarketWithProtection => "MKT PRT" | "MKTPRT",
+    /// A relative, or pegged to primary, order
+    Relative => "REL",
+    /// A pegged to market order
+    PeggedToMarket => "PEG MKT" | "PEGMKT",
+    /// A pegged to midpoint order
+    PeggedToMidpoint => "PEG MID" | "PEGMID",
+    /// A pegged to benchmark order
+    PeggedToBenchmark => "PEG BENCH" | "PEGBENCH",
+    /// A pegged to stock order, for options
+    PeggedToStock => "PEG STK" | "PEGSTK",
+    /// A snap to market order
+    SnapToMarket => "SNAP MKT" | "SNAPMKT",
+    /// A snap to midpoint order
+    SnapToMidpoint => "SNAP MID" | "SNAPMID",
+    /// A snap to primary order
+    SnapToPrimary => "SNAP PRIM" | "SNAPPRIM",
+    /// A midprice order
+    Midprice => "MIDPRICE" | "MIDPX",
+    /// A volatility order, for options
+    Volatility => "VOL",
+    /// A box top order
+    BoxTop => "BOX TOP" | "BOXTOP",
+    /// An order that is only submitted for the current trading day
+    Day => "DAY",
+    /// A good until canceled order
+    GoodUntilCanceled => "GTC",
+    /// A good until date order
+    GoodUntilDate => "GTD",
+    /// An immediate or cancel order
+    ImmediateOrCancel => "IOC",
+    /// A fill or kill order
+    FillOrKill => "FOK",
+    /// An all or none order
+    AllOrNone => "AON",
+    /// A one cancels all group of orders
+    OneCancelsAll => "OCA",
+    /// An order for the opening auction
+    Opening => "OPG",
+    /// An IBKR algorithmic order
+    Algo => "ALGO",
+    /// An order whose quantity is given in cash rather than in units of the contract
+    CashQuantity => "CASHQTY",
+    /// An order that can be restricted to regular trading hours
+    RegularTradingHours => "RTH",
+    /// A hidden order
+    Hidden => "HID",
+    /// An iceberg, or reserve, order
+    Iceberg => "ICE",
+    /// A preview order, which reports its margin impact without being placed
+    WhatIf => "WHATIF",
+);
+
+impl From<String> for OrderTypeCode {
+    fn from(value: String) -> Self {
+        match value.parse() {
+            Ok(code) => code,
+            Err(never) => match never {},
+        }
+    }
+}
+
+impl From<OrderTypeCode> for String {
+    fn from(value: OrderTypeCode) -> Self {
+        match value {
+            OrderTypeCode::Other(code) => code,
+            code => code.as_str().to_owned(),
+        }
+    }
+}
+
+impl std::fmt::Display for OrderTypeCode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
 #[derive(Debug, Clone, Eq, PartialEq)]
 /// A generic order to buy or sell a security `S`: `Security` according to the parameters specified by the generic
 /// parameter `E`: `Executable`.
@@ -107,7 +271,7 @@ where
             Self::Sell { execute_method, .. } => ("SELL", execute_method),
         };
         ser.serialize_element(action)?;
-        serialize_executable(*exec, &mut ser)?;
+        serialize_executable(*exec, self.get_security(), &mut ser)?;
         ser.end()
     }
 }
@@ -154,8 +318,10 @@ pub struct Limit {
 // === Order Trait Definition and Implementations ===
 // ==================================================
 
-/// Represents the data that will be serialized for BAG contracts (which are not currently supported).
-pub type BagRequestContent<'a> = (u64, &'a str, u64, &'a str, u64, HashMap<&'a str, &'a str>);
+/// Represents the order-specific data that will be serialized for BAG contracts: the number of
+/// per-leg prices, the price of each leg, the number of smart combo routing parameters, and the
+/// parameters themselves.
+pub type BagRequestContent<'a> = (usize, &'a [Option<f64>], usize, HashMap<&'a str, &'a str>);
 /// Represents the data that will be serialized for delta neutral orders (which are not currently implemented).
 pub type DeltaNeutralOrderContent<'a> =
     (i64, &'a str, &'a str, &'a str, &'a str, bool, i64, &'a str);
@@ -276,8 +442,11 @@ pub trait Executable<S: Security>: Send + Sync {
     }
 
     #[inline]
-    /// Return the BAG request and combo leg content, if it exists.
-    fn get_bag_request_content(&self) -> ConditionalField<(), BagRequestContent> {
+    /// Return the per-leg prices and smart combo routing parameters, if they exist.
+    ///
+    /// Only serialized for orders on a [`Combo`](crate::contract::Combo), in which case the
+    /// missing value is a count of zero for both.
+    fn get_bag_request_content(&self) -> ConditionalField<(usize, usize), BagRequestContent<'_>> {
         ConditionalField::default()
     }
 
@@ -358,7 +527,6 @@ pub trait Executable<S: Security>: Send + Sync {
     /// Return the auction's starting price.
     ///
     /// For BOX orders only.
-
     fn get_box_starting_price(&self) -> Option<f64> {
         None
     }
@@ -442,7 +610,7 @@ pub trait Executable<S: Security>: Send + Sync {
 
     #[inline]
     /// Return the delta neutral order content if it exists.
-    fn get_delta_neutral_order_content(&self) -> ConditionalField<(), DeltaNeutralOrderContent> {
+    fn get_delta_neutral_order_content(&self) -> ConditionalField<(), DeltaNeutralOrderContent<'_>> {
         ConditionalField::default()
     }
 
@@ -655,7 +823,7 @@ pub trait Executable<S: Security>: Send + Sync {
 
     #[inline]
     /// Return order conditions content.
-    fn get_order_conditions_content(&self) -> ConditionalField<usize, OrderConditionsContent> {
+    fn get_order_conditions_content(&self) -> ConditionalField<usize, OrderConditionsContent<'_>> {
         ConditionalField::Missing(0)
     }
 
@@ -842,7 +1010,11 @@ pub trait Executable<S: Security>: Send + Sync {
 
 #[inline]
 #[allow(clippy::too_many_lines)]
-fn serialize_executable<E, Sec, Ser>(exec: &E, ser: &mut Ser) -> Result<(), Ser::Error>
+fn serialize_executable<E, Sec, Ser>(
+    exec: &E,
+    security: &Sec,
+    ser: &mut Ser,
+) -> Result<(), Ser::Error>
 where
     E: Executable<Sec>,
     Sec: crate::contract::Security,
@@ -866,7 +1038,15 @@ where
     ser.serialize_element(&exec.get_trigger_method())?;
     ser.serialize_element(&exec.get_can_fill_outside_regular_trading_hours())?;
     ser.serialize_element(&exec.get_is_hidden_on_nasdaq_market_depth())?;
-    ser.serialize_element(&exec.get_bag_request_content())?;
+    if let Some(legs) = security.combo_legs() {
+        // Each leg is followed by its open / close, short sale slot, designated location, and
+        // exempt code, which are left as the defaults
+        let legs = legs
+            .iter()
+            .map(|leg| (leg.contract_id, leg.ratio, leg.action, leg.exchange, 0, 0, "", -1))
+            .collect::<Vec<_>>();
+        ser.serialize_element(&(legs.len(), legs, exec.get_bag_request_content()))?;
+    }
     ser.serialize_element(&None::<()>)?;
     ser.serialize_element(&exec.get_discretionary_amount())?;
     ser.serialize_element(&exec.get_good_after_time())?;
@@ -914,7 +1094,15 @@ where
     ser.serialize_element(&exec.get_clearing_account())?;
     ser.serialize_element(&exec.get_clearing_intent())?;
     ser.serialize_element(&exec.get_is_not_held())?;
-    ser.serialize_element(&exec.get_delta_neutral_contract_content())?;
+    match security.delta_neutral_contract() {
+        Some(dnc) => ser.serialize_element(&ConditionalField::<bool, _>::Present((
+            true,
+            dnc.contract_id().0,
+            dnc.delta(),
+            dnc.price(),
+        )))?,
+        None => ser.serialize_element(&exec.get_delta_neutral_contract_content())?,
+    }
     ser.serialize_element(&exec.get_algo_strategy())?;
     ser.serialize_element(&exec.get_algo_strategy_content())?;
     ser.serialize_element(&exec.get_algo_id())?;
@@ -1175,7 +1363,8 @@ macro_rules! impl_executable {
     };
 }
 
-impl_executable!(Market; Forex, Crypto, Stock, Index, SecFuture, SecOption, Commodity; {
+impl_executable!(Market; Forex, Crypto, Stock, Index, SecFuture, SecOption, Commodity, Bond, Cfd,
+    SecFutureOption, Warrant, StructuredProduct, Combo; {
     fn get_quantity(&self) -> f64 {
         self.quantity
     }
@@ -1188,7 +1377,8 @@ impl_executable!(Market; Forex, Crypto, Stock, Index, SecFuture, SecOption, Comm
         self.time_in_force
     }
 });
-impl_executable!(Limit; Forex, Crypto, Stock, Index, SecFuture, SecOption, Commodity; {
+impl_executable!(Limit; Forex, Crypto, Stock, Index, SecFuture, SecOption, Commodity, Bond, Cfd,
+    SecFutureOption, Warrant, StructuredProduct, Combo; {
     fn get_quantity(&self) -> f64 {
         self.quantity
     }
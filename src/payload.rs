@@ -169,6 +169,153 @@ pub mod market_depth {
             .try_into()
             .map_err(|_| Error::invalid_value(serde::de::Unexpected::Str(&s), &"Valid UTF-8 Mpid"))
     }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    /// The participant that posted a row of an order book, as reported in a [`CompleteEntry`].
+    pub enum Source {
+        /// The exchange from which a SMART depth row is sourced.
+        Exchange(Primary),
+        /// The market maker that posted the row.
+        MarketMaker(Mpid),
+        /// No participant is reported, like for the rows of a single exchange's book.
+        Unknown,
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    /// A single price level of a [`Book`].
+    pub struct Level {
+        /// The participant that posted the level.
+        pub source: Source,
+        /// The level's price.
+        pub price: f64,
+        /// The level's size.
+        pub size: f64,
+    }
+
+    #[derive(Debug, Clone, Default, PartialEq)]
+    /// A limit order book that is maintained from the [`Operation`]s of a
+    /// [`crate::client::Client::req_market_depth`] subscription.
+    ///
+    /// IBKR positions the rows of each participant independently, so a SMART depth book, which
+    /// interleaves the rows of many exchanges, keeps a separate ladder for each [`Source`] and
+    /// merges them by price.
+    pub struct Book {
+        bids: std::collections::BTreeMap<Source, Vec<(f64, f64)>>,
+        asks: std::collections::BTreeMap<Source, Vec<(f64, f64)>>,
+    }
+
+    impl Book {
+        #[must_use]
+        #[inline]
+        /// Create an empty order book.
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        /// Apply a single change to the book.
+        ///
+        /// # Arguments
+        /// * `operation` - The change, as passed to
+        ///   [`crate::wrapper::LocalWrapper::update_market_depth`].
+        pub fn apply(&mut self, operation: Operation) {
+            let (Operation::Insert(entry) | Operation::Update(entry) | Operation::Delete(entry)) =
+                operation;
+            let (source, entry) = match entry {
+                CompleteEntry::SmartDepth { exchange, entry } => {
+                    (Source::Exchange(exchange), entry)
+                }
+                CompleteEntry::MarketMaker { market_maker, entry } => {
+                    (Source::MarketMaker(market_maker), entry)
+                }
+                CompleteEntry::Ordinary(entry) => (Source::Unknown, entry),
+            };
+            let (side, row) = match entry {
+                Entry::Bid(row) => (&mut self.bids, row),
+                Entry::Ask(row) => (&mut self.asks, row),
+            };
+            let ladder = side.entry(source).or_default();
+            let position = usize::try_from(row.position).unwrap_or(usize::MAX);
+            match operation {
+                Operation::Insert(_) => {
+                    ladder.insert(position.min(ladder.len()), (row.price, row.size));
+                }
+                Operation::Update(_) => match ladder.get_mut(position) {
+                    Some(level) => *level = (row.price, row.size),
+                    None => ladder.push((row.price, row.size)),
+                },
+                Operation::Delete(_) => {
+                    if position < ladder.len() {
+                        ladder.remove(position);
+                    }
+                }
+            }
+            if ladder.is_empty() {
+                side.remove(&source);
+            }
+        }
+
+        /// Remove every level from the book, like when IBKR resets a subscription.
+        pub fn clear(&mut self) {
+            self.bids.clear();
+            self.asks.clear();
+        }
+
+        #[must_use]
+        /// Get the bid levels, from the highest price to the lowest.
+        pub fn bids(&self) -> Vec<Level> {
+            let mut levels = ladder_levels(&self.bids);
+            levels.sort_by(|a, b| b.price.total_cmp(&a.price));
+            levels
+        }
+
+        #[must_use]
+        /// Get the ask levels, from the lowest price to the highest.
+        pub fn asks(&self) -> Vec<Level> {
+            let mut levels = ladder_levels(&self.asks);
+            levels.sort_by(|a, b| a.price.total_cmp(&b.price));
+            levels
+        }
+
+        #[must_use]
+        /// Get the bid with the highest price, if there is any.
+        pub fn best_bid(&self) -> Option<Level> {
+            self.bids().first().copied()
+        }
+
+        #[must_use]
+        /// Get the ask with the lowest price, if there is any.
+        pub fn best_ask(&self) -> Option<Level> {
+            self.asks().first().copied()
+        }
+
+        #[must_use]
+        /// Get the midpoint between the best bid and the best ask.
+        ///
+        /// # Returns
+        /// The midpoint, or `None` if either side of the book is empty.
+        pub fn mid(&self) -> Option<f64> {
+            Some((self.best_bid()?.price + self.best_ask()?.price) / 2.0)
+        }
+
+        #[must_use]
+        /// Get the `n`th best bid and ask, where level 0 holds the best bid and the best ask.
+        ///
+        /// # Returns
+        /// The bid and the ask at level `n`, either of which is `None` if its side of the book
+        /// has no more than `n` levels.
+        pub fn level(&self, n: usize) -> (Option<Level>, Option<Level>) {
+            (self.bids().get(n).copied(), self.asks().get(n).copied())
+        }
+    }
+
+    /// Flatten the ladders of every source on one side of a [`Book`] into unsorted levels.
+    fn ladder_levels(side: &std::collections::BTreeMap<Source, Vec<(f64, f64)>>) -> Vec<Level> {
+        side.iter()
+            .flat_map(|(&source, ladder)| {
+                ladder.iter().map(move |&(price, size)| Level { source, price, size })
+            })
+            .collect()
+    }
 }
 
 #[derive(Debug, Default, Clone, Copy, PartialEq, PartialOrd, Serialize, Deserialize)]
@@ -176,7 +323,8 @@ pub mod market_depth {
 pub struct HistogramEntry {
     /// The price (x-value).
     pub price: f64,
-    /// The frequency of the price (size / y-value).
+    /// The frequency of the price (size / y-value). Like the other sizes of the crate, such as
+    /// those of [`crate::tick::Size`], it is an [`f64`] rather than a decimal type.
     pub size: f64,
 }
 
@@ -274,6 +422,197 @@ impl From<Trade> for TradeSerDeHelper {
     }
 }
 
+impl Bar {
+    #[must_use]
+    #[inline]
+    /// Get the datetime, open, high, low, and close of the bar.
+    pub const fn core(&self) -> &BarCore {
+        match self {
+            Self::Ordinary(bar) | Self::Trades(Trade { bar, .. }) => bar,
+        }
+    }
+
+    #[inline]
+    fn core_mut(&mut self) -> &mut BarCore {
+        match self {
+            Self::Ordinary(bar) | Self::Trades(Trade { bar, .. }) => bar,
+        }
+    }
+
+    #[must_use]
+    /// Merge the bar with `next`, which immediately follows it, into a single bar that spans both.
+    ///
+    /// The merged bar keeps the datetime and the open of the bar, takes the close of `next`, and
+    /// has the highest high and the lowest low of the two. If either bar is a [`Bar::Trades`] bar,
+    /// the merged bar is one too: its volume and trade count are the sums of those of the two
+    /// bars, and its WAP is their WAP weighted by volume. An [`Bar::Ordinary`] bar counts as a bar
+    /// without any trades.
+    ///
+    /// # Arguments
+    /// * `next` - The bar that follows this one.
+    ///
+    /// # Returns
+    /// The merged bar.
+    pub fn merge(self, next: Self) -> Self {
+        let (first, second) = (self.core(), next.core());
+        let bar = BarCore {
+            datetime: first.datetime,
+            open: first.open,
+            high: first.high.max(second.high),
+            low: first.low.min(second.low),
+            close: second.close,
+        };
+        let trades = |bar: &Self| match bar {
+            Self::Trades(trade) => (trade.volume, trade.wap, trade.trade_count),
+            Self::Ordinary(_) => (0.0, 0.0, 0),
+        };
+        match (self, next) {
+            (Self::Ordinary(_), Self::Ordinary(_)) => Self::Ordinary(bar),
+            _ => {
+                let ((v1, w1, n1), (v2, w2, n2)) = (trades(&self), trades(&next));
+                let volume = v1 + v2;
+                let wap = if volume > 0.0 {
+                    (w1 * v1 + w2 * v2) / volume
+                } else {
+                    w2
+                };
+                Self::Trades(Trade {
+                    bar,
+                    volume,
+                    wap,
+                    trade_count: n1 + n2,
+                })
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// Rolls consecutive bars, like the 5-second bars of
+/// [`crate::client::Client::req_real_time_bars`], into bars of a longer period, like 1 or 5
+/// minutes, with [`Bar::merge`].
+///
+/// Periods are aligned to the Unix epoch, so 1-minute bars start on the minute, and each rolled
+/// bar is dated at the start of its period.
+pub struct BarAggregator {
+    period: i64,
+    current: Option<Bar>,
+}
+
+impl BarAggregator {
+    #[must_use]
+    #[inline]
+    /// Create an aggregator that rolls bars into bars of `period`.
+    ///
+    /// # Arguments
+    /// * `period` - The period of the rolled bars, which is rounded down to whole seconds. A
+    ///   period shorter than a second is treated as one second.
+    pub fn new(period: chrono::Duration) -> Self {
+        Self {
+            period: period.num_seconds().max(1),
+            current: None,
+        }
+    }
+
+    /// Add the next bar.
+    ///
+    /// # Arguments
+    /// * `bar` - The next bar, which must not be dated before the previous one.
+    ///
+    /// # Returns
+    /// The rolled bar of the previous period, once `bar` starts a new one.
+    pub fn push(&mut self, mut bar: Bar) -> Option<Bar> {
+        let start = bar.core().datetime.timestamp();
+        let period_start = start - start.rem_euclid(self.period);
+        match self.current.take() {
+            Some(current) if current.core().datetime.timestamp() == period_start => {
+                self.current = Some(current.merge(bar));
+                None
+            }
+            previous => {
+                if let Some(datetime) = DateTime::from_timestamp(period_start, 0) {
+                    bar.core_mut().datetime = datetime;
+                }
+                self.current = Some(bar);
+                previous
+            }
+        }
+    }
+
+    /// Take the rolled bar of the current period, even if the period is not over yet, like when
+    /// the subscription ends.
+    ///
+    /// # Returns
+    /// The rolled bar, or `None` if no bar has been added since the last rolled bar was returned.
+    pub fn flush(&mut self) -> Option<Bar> {
+        self.current.take()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Serialize, Deserialize)]
+/// How a bar from [`crate::wrapper::LocalWrapper::updating_historical_bar`] changes the bars of a
+/// [`crate::client::Client::req_updating_historical_bar`] subscription.
+pub enum BarUpdate {
+    /// The bar amends the last bar, which is still forming.
+    Replace(Bar),
+    /// The bar starts a new bar, so the previous last bar is complete.
+    New(Bar),
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+/// The bars of a [`crate::client::Client::req_updating_historical_bar`] subscription: the backlog
+/// of its first [`crate::wrapper::LocalWrapper::historical_bars`] callback, kept up to date by its
+/// [`crate::wrapper::LocalWrapper::updating_historical_bar`] callbacks.
+pub struct UpdatingBars {
+    bars: Vec<Bar>,
+}
+
+impl UpdatingBars {
+    #[must_use]
+    #[inline]
+    /// Create the bars of a subscription from its backlog.
+    ///
+    /// # Arguments
+    /// * `backlog` - The bars of the first callback, from the earliest to the latest.
+    pub const fn new(backlog: Vec<Bar>) -> Self {
+        Self { bars: backlog }
+    }
+
+    /// Apply an updated bar, which replaces the last bar if both have the same datetime.
+    ///
+    /// # Arguments
+    /// * `bar` - The updated bar.
+    ///
+    /// # Returns
+    /// Whether the bar replaced the last bar or was added as a new one.
+    pub fn update(&mut self, bar: Bar) -> BarUpdate {
+        match self.bars.last_mut() {
+            Some(last) if last.core().datetime == bar.core().datetime => {
+                *last = bar;
+                BarUpdate::Replace(bar)
+            }
+            _ => {
+                self.bars.push(bar);
+                BarUpdate::New(bar)
+            }
+        }
+    }
+
+    #[must_use]
+    #[inline]
+    /// Return the bars, from the earliest to the latest, which is still forming.
+    pub fn bars(&self) -> &[Bar] {
+        &self.bars
+    }
+
+    #[must_use]
+    #[inline]
+    /// Return the bars, from the earliest to the latest, which is still forming.
+    pub fn into_bars(self) -> Vec<Bar> {
+        self.bars
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 #[serde(tag = "tick")]
 /// A historical or live tick.
@@ -475,3 +814,51 @@ impl FromStr for Locate {
         }
     }
 }
+
+#[test]
+fn test_bar_aggregator() {
+    let trade = |seconds: i64, [open, high, low, close]: [f64; 4], volume, wap, trade_count| {
+        Bar::Trades(Trade {
+            bar: BarCore {
+                datetime: DateTime::from_timestamp(1_735_741_800 + seconds, 0).unwrap(),
+                open,
+                high,
+                low,
+                close,
+            },
+            volume,
+            wap,
+            trade_count,
+        })
+    };
+
+    // Twelve 5-second bars from 14:30:00 to 14:30:55 UTC, then the first bar of 14:31
+    let mut minute = BarAggregator::new(chrono::Duration::minutes(1));
+    let mut rolled = Vec::new();
+    for i in 0..13 {
+        #[allow(clippy::cast_precision_loss)]
+        let price = 100.0 + i as f64;
+        let bar = trade(5 * i, [price, price + 2.0, price - 1.0, price + 1.0], 10.0, price, 2);
+        rolled.extend(minute.push(bar));
+    }
+    assert_eq!(rolled, [trade(0, [100.0, 113.0, 99.0, 112.0], 120.0, 105.5, 24)]);
+    assert_eq!(minute.flush(), Some(trade(60, [112.0, 114.0, 111.0, 113.0], 10.0, 112.0, 2)));
+    assert_eq!(minute.flush(), None);
+
+    // A bar without trades adds nothing to the volume or the WAP
+    let mut five_minutes = BarAggregator::new(chrono::Duration::minutes(5));
+    assert_eq!(five_minutes.push(trade(130, [10.0, 11.0, 9.0, 10.5], 30.0, 10.0, 3)), None);
+    let quiet = Bar::Ordinary(*trade(135, [10.5, 10.5, 8.5, 10.0], 0.0, 0.0, 0).core());
+    assert_eq!(five_minutes.push(quiet), None);
+    assert_eq!(five_minutes.push(trade(140, [10.0, 12.0, 10.0, 12.0], 10.0, 12.0, 1)), None);
+    let Some(Bar::Trades(merged)) = five_minutes.flush() else {
+        panic!("Expected a trade bar");
+    };
+    assert_eq!(merged.bar, *trade(0, [10.0, 12.0, 8.5, 12.0], 0.0, 0.0, 0).core());
+    assert!((merged.volume - 40.0).abs() < f64::EPSILON);
+    assert!((merged.wap - 10.5).abs() < 1e-9);
+    assert_eq!(merged.trade_count, 4);
+
+    let midpoint = |seconds| Bar::Ordinary(*trade(seconds, [1.0, 1.5, 0.5, 1.0], 0.0, 0.0, 0).core());
+    assert_eq!(midpoint(0).merge(midpoint(5)), midpoint(0));
+}
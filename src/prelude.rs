@@ -1,21 +1,36 @@
-pub use crate::account::{Attribute, Tag, TagValue};
-pub use crate::client::{ActiveClient, Builder, Client, Host, Mode};
+pub use crate::account::{AccountCode, Attribute, Tag, TagValue};
+pub use crate::client::{
+    ActiveClient, Builder, Client, ClientBuilder, ClientPort, ContractClient, Host, Mode,
+    ResilientClient,
+};
 pub use crate::contract::{
-    self, Commodity, Contract, ContractId, ContractType, Crypto, ExchangeProxy, Forex, Index,
-    NoExchangeProxy, Query, SecFuture, SecOption, SecOptionClass, SecOptionInner, Security, Stock,
+    self, Bond, Cfd, Combo, ComboLeg, Commodity, ContinuousFuture, Contract, ContractCache,
+    ContractDetails, ContractId, ContractKey, ContractSpec, ContractType, Crypto,
+    DeltaNeutralContract, EmptyIndexSymbolError, ExchangeProxy, Expiring, Forex, ForexBuilder,
+    HasUnderlying, Index, InvalidOsi, InvalidSecFutureError, MarketRule, MarketRuleId,
+    MixedCurrency, Moneyness, MutualFund, NoExchangeProxy, OptionChain, OptionChainError,
+    OptionRight, OsiParts, ParseSecOptionClassError, PriceIncrement, Query, QueryOptions,
+    RequestKind, RequestTimeout, SameCurrencyPairError, Schedule, SecFuture, SecFutureOption,
+    SecFutureOptionInner, SecOption, SecOptionBuilder, SecOptionClass, SecOptionInner, Security,
+    Session, Stock, StockBuilder, StructuredProduct, SymbolMatch, TradingClass, Warrant,
+    WarrantInner,
 };
 pub use crate::currency::Currency;
+pub use crate::error_code::{ErrorCode, Notification, TwsError};
 pub use crate::exchange;
 pub use crate::execution::{Exec, Execution, Filter, OrderSide};
 pub use crate::figi::Figi;
 pub use crate::market_data::{
     histogram, historical_bar, historical_ticks, live_bar, live_data, live_ticks,
-    updating_historical_bar,
+    updating_historical_bar, AggregatedBarStream, BarStream, BarUpdateStream, DepthStream,
+    Subscription, TickStream,
 };
-pub use crate::order::{Limit, Market, Order, TimeInForce};
+pub use crate::mock::MockClient;
+pub use crate::order::{Action, Limit, Market, Order, OrderTypeCode, TimeInForce};
 pub use crate::payload::{
-    Bar, BarCore, BidAsk, ExchangeId, Fill, HistogramEntry, Last, Midpoint, OrderStatus,
-    OrderStatusCore, Pnl, PnlSingle, Position, PositionSummary, TickData, Trade,
+    Bar, BarAggregator, BarCore, BarUpdate, BidAsk, ExchangeId, Fill, HistogramEntry, Last,
+    Midpoint, OrderStatus, OrderStatusCore, Pnl, PnlSingle, Position, PositionSummary, TickData,
+    Trade, UpdatingBars,
 };
 pub use crate::payload::market_depth::{CompleteEntry, Entry, Mpid, Operation, Row};
 pub use crate::tick;
@@ -1,12 +1,22 @@
-use bytes::{Buf, BytesMut};
 use tokio::{io::AsyncReadExt, net::tcp::OwnedReadHalf};
 use tracing::{error, info, warn};
 
+use crate::record::{Direction, Recorder};
+
+#[inline]
+/// Split the body of a message into its null-terminated fields.
+pub(crate) fn split_fields(body: &[u8]) -> Vec<String> {
+    body.split(|b| *b == 0)
+        .map(|s| core::str::from_utf8(s).unwrap_or("").to_owned())
+        .collect()
+}
+
 #[derive(Debug)]
 pub struct Reader {
     inner: OwnedReadHalf,
-    tx: tokio::sync::mpsc::Sender<Vec<String>>,
+    tx: Option<tokio::sync::mpsc::Sender<Vec<String>>>,
     disconnect: tokio_util::sync::CancellationToken,
+    recorder: Recorder,
 }
 
 impl Reader {
@@ -14,11 +24,13 @@ impl Reader {
         r_reader: OwnedReadHalf,
         tx: tokio::sync::mpsc::Sender<Vec<String>>,
         r_disconnect: tokio_util::sync::CancellationToken,
+        recorder: Recorder,
     ) -> Self {
         Self {
             inner: r_reader,
-            tx,
+            tx: Some(tx),
             disconnect: r_disconnect,
+            recorder,
         }
     }
 
@@ -27,29 +39,58 @@ impl Reader {
         loop {
             tokio::select! {
                 biased;
-                () = async {
-                    if let Ok(Ok(len)) = self.inner.read_u32().await.map(usize::try_from) {
-                        let mut buf = BytesMut::with_capacity(len);
-                        let mut total_read = 0;
-                        while total_read < len {
-                            match self.inner.read_buf(&mut buf).await {
-                                Ok(0) => { warn!("TCP Reader read 0 bytes (this should never happen and is likely an error in message parsing)") },
-                                Ok(n) => { total_read += n; },
-                                Err(e) => error!(error=%e, "IO Error when receiving message.")
-                            }
-                        }
-                        let msg = buf.chunk()
-                        .split(|b| *b == 0)
-                        .map(|s| core::str::from_utf8(s).unwrap_or("").to_owned())
-                        .collect::<Vec<String>>();
-                        match self.tx.send(msg).await {
-                            Ok(()) => (),
-                            Err(e) => error!(%e, "IO Error when sending message. Client receiver may have dropped."),
+                body = read_message(&mut self.inner) => match body {
+                    Ok(body) => {
+                        self.recorder.record(Direction::Inbound, &body);
+                        let Some(tx) = &self.tx else { break self };
+                        if let Err(e) = tx.send(split_fields(&body)).await {
+                            error!(%e, "IO Error when sending message. Client receiver may have dropped.");
                         }
                     }
-                } => (),
+                    Err(e) => {
+                        warn!(error=%e, "Reader thread: connection lost");
+                        // Closing the channel tells the client loop that the connection is gone
+                        self.tx = None;
+                        break self;
+                    }
+                },
                 () = self.disconnect.cancelled() => { info!("Reader thread: disconnecting"); break self} ,
             }
         }
     }
 }
+
+// Read the body of the next length-prefixed message
+async fn read_message(inner: &mut OwnedReadHalf) -> Result<Vec<u8>, std::io::Error> {
+    let len = usize::try_from(inner.read_u32().await?)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+    let mut body = vec![0; len];
+    inner.read_exact(&mut body).await?;
+    Ok(body)
+}
+
+#[tokio::test]
+async fn test_reader_connection_lost() -> Result<(), std::io::Error> {
+    use tokio::io::AsyncWriteExt;
+
+    let listener = tokio::net::TcpListener::bind((std::net::Ipv4Addr::LOCALHOST, 0)).await?;
+    let stream = tokio::net::TcpStream::connect(listener.local_addr()?).await?;
+    let (mut server, _) = listener.accept().await?;
+    server.write_all(b"\0\0\0\x049\x008\x00").await?;
+    drop(server);
+
+    let (tx, mut rx) = tokio::sync::mpsc::channel(1);
+    let (r_reader, _w_reader) = stream.into_split();
+    let reader = Reader::new(
+        r_reader,
+        tx,
+        tokio_util::sync::CancellationToken::new(),
+        Recorder::default(),
+    );
+    let handle = tokio::spawn(reader.run());
+    assert_eq!(rx.recv().await, Some(vec!["9".to_owned(), "8".to_owned(), String::new()]));
+    // The channel closes once the connection is lost, without any cancellation
+    assert_eq!(rx.recv().await, None);
+    handle.await.map_err(std::io::Error::other)?;
+    Ok(())
+}
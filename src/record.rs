@@ -0,0 +1,344 @@
+use std::collections::VecDeque;
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+
+use chrono::{DateTime, Utc};
+use tokio::sync::mpsc;
+use tracing::warn;
+
+use crate::constants;
+use crate::contract::Contract;
+use crate::message::ToClient;
+use crate::reader::split_fields;
+use crate::wrapper::LocalWrapper;
+
+/// The bytes with which every recording starts, followed by [`VERSION`].
+pub const MAGIC: &[u8; 7] = b"IBKRREC";
+
+/// The version of the recording format that is written by [`crate::client::Client::record_to`].
+pub const VERSION: u8 = 1;
+
+// The direction byte, the timestamp, and the length of the body
+const FRAME_HEADER_LEN: usize = 1 + 8 + 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+/// The direction in which a recorded [`Frame`] was sent.
+pub enum Direction {
+    /// A message received from the IBKR trading systems.
+    Inbound,
+    /// A message sent to the IBKR trading systems.
+    Outbound,
+}
+
+impl Direction {
+    #[inline]
+    const fn as_byte(self) -> u8 {
+        match self {
+            Self::Inbound => b'I',
+            Self::Outbound => b'O',
+        }
+    }
+}
+
+impl std::fmt::Display for Direction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Self::Inbound => "<-",
+            Self::Outbound => "->",
+        };
+        write!(f, "{s}")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// A single message in a recording.
+pub struct Frame {
+    /// Whether the message was received or sent.
+    pub direction: Direction,
+    /// The time at which the message was received or sent, to the microsecond.
+    pub timestamp: DateTime<Utc>,
+    /// The body of the message, which is a sequence of null-terminated fields without the length
+    /// prefix with which it is sent over the wire.
+    pub body: Vec<u8>,
+}
+
+impl Frame {
+    #[must_use]
+    #[inline]
+    /// Return the fields of the message, the first of which identifies its type.
+    pub fn fields(&self) -> Vec<String> {
+        split_fields(&self.body)
+    }
+}
+
+/// Read the frames of a recording.
+///
+/// A recording starts with [`MAGIC`] and a [`VERSION`] byte. Each frame that follows has a
+/// 13-byte header: a direction byte (`I` for inbound and `O` for outbound), the timestamp as a
+/// big-endian `i64` of microseconds since the Unix epoch, and the length of the body as a
+/// big-endian `u32`, just like the length prefix of a message on the wire. The header is followed
+/// by the body itself.
+///
+/// # Errors
+/// Returns an error if the recording does not start with [`MAGIC`], if its version is not
+/// supported, or if it is truncated or otherwise malformed.
+pub fn read(recording: &[u8]) -> Result<Vec<Frame>, std::io::Error> {
+    let invalid = |msg| std::io::Error::new(std::io::ErrorKind::InvalidData, msg);
+    let truncated = || {
+        std::io::Error::new(
+            std::io::ErrorKind::UnexpectedEof,
+            "The recording is truncated",
+        )
+    };
+
+    let mut rest = recording
+        .strip_prefix(MAGIC.as_slice())
+        .ok_or_else(|| invalid("The recording does not start with the expected magic bytes"))?;
+    let (&version, after) = rest.split_first().ok_or_else(truncated)?;
+    if version != VERSION {
+        return Err(invalid("Unsupported recording version"));
+    }
+    rest = after;
+
+    let mut frames = Vec::new();
+    while !rest.is_empty() {
+        let (header, after) = rest
+            .split_first_chunk::<FRAME_HEADER_LEN>()
+            .ok_or_else(truncated)?;
+        let direction = match header[0] {
+            b'I' => Direction::Inbound,
+            b'O' => Direction::Outbound,
+            _ => return Err(invalid("Invalid frame direction")),
+        };
+        let micros = i64::from_be_bytes(header[1..9].try_into().unwrap_or_default());
+        let timestamp =
+            DateTime::from_timestamp_micros(micros).ok_or_else(|| invalid("Invalid timestamp"))?;
+        let len = usize::try_from(u32::from_be_bytes(
+            header[9..].try_into().unwrap_or_default(),
+        ))
+        .map_err(|_| invalid("Invalid frame length"))?;
+        if after.len() < len {
+            return Err(truncated());
+        }
+        let (body, after) = after.split_at(len);
+        frames.push(Frame {
+            direction,
+            timestamp,
+            body: body.to_vec(),
+        });
+        rest = after;
+    }
+    Ok(frames)
+}
+
+/// Read the frames of a recording from a file. See [`read`] for the format of the file.
+///
+/// # Errors
+/// Returns any error encountered while reading the file, or the same errors as [`read`].
+pub fn read_file(path: impl AsRef<std::path::Path>) -> Result<Vec<Frame>, std::io::Error> {
+    read(&std::fs::read(path)?)
+}
+
+#[derive(Debug, Clone, Default)]
+/// The file to which a client and its reader thread record their frames, if any. Clones share the
+/// same file.
+pub(crate) struct Recorder(Arc<Mutex<Option<std::io::BufWriter<std::fs::File>>>>);
+
+impl Recorder {
+    /// Start recording to a new file at `path`, replacing any recording in progress.
+    pub(crate) fn start(&self, path: &std::path::Path) -> Result<(), std::io::Error> {
+        let mut file = std::io::BufWriter::new(std::fs::File::create(path)?);
+        file.write_all(MAGIC)?;
+        file.write_all(&[VERSION])?;
+        file.flush()?;
+        if let Some(mut previous) = self.lock().replace(file) {
+            previous.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Stop the recording in progress, if any.
+    pub(crate) fn stop(&self) -> Result<(), std::io::Error> {
+        match self.lock().take() {
+            Some(mut file) => file.flush(),
+            None => Ok(()),
+        }
+    }
+
+    /// Record a message body, if a recording is in progress. A failure to record is logged
+    /// rather than returned, so that it never interrupts the connection.
+    pub(crate) fn record(&self, direction: Direction, body: &[u8]) {
+        let mut guard = self.lock();
+        let Some(file) = guard.as_mut() else {
+            return;
+        };
+        let Ok(len) = u32::try_from(body.len()) else {
+            warn!("Message is too long to record");
+            return;
+        };
+        let res = file
+            .write_all(&[direction.as_byte()])
+            .and_then(|()| file.write_all(&Utc::now().timestamp_micros().to_be_bytes()))
+            .and_then(|()| file.write_all(&len.to_be_bytes()))
+            .and_then(|()| file.write_all(body))
+            .and_then(|()| file.flush());
+        if let Err(e) = res {
+            warn!("Failed to record message: {e}");
+        }
+    }
+
+    /// Record every length-prefixed message in `buf`.
+    pub(crate) fn record_frames(&self, direction: Direction, mut buf: &[u8]) {
+        while let Some((prefix, rest)) = buf.split_first_chunk::<4>() {
+            let len = usize::try_from(u32::from_be_bytes(*prefix))
+                .unwrap_or(usize::MAX)
+                .min(rest.len());
+            let (body, rest) = rest.split_at(len);
+            self.record(direction, body);
+            buf = rest;
+        }
+    }
+
+    #[inline]
+    fn lock(&self) -> std::sync::MutexGuard<'_, Option<std::io::BufWriter<std::fs::File>>> {
+        // A panic while recording can't leave the file in a worse state than a truncated frame
+        self.0
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+}
+
+#[derive(Debug)]
+/// Replays the inbound frames of a recording through the same decoders that an active client uses,
+/// calling the methods of a [`LocalWrapper`] without a connection to the IBKR trading systems.
+///
+/// Outbound frames are skipped, and contracts, which an active client returns to the function that
+/// requested them rather than to its wrapper, are collected in [`ReplayClient::contracts`].
+pub struct ReplayClient {
+    frames: VecDeque<Frame>,
+    contracts: Vec<Contract>,
+    tx: mpsc::Sender<ToClient>,
+    rx: mpsc::Receiver<ToClient>,
+    rx_wrapper: crate::decode::PendingQueries,
+    notifications: tokio::sync::broadcast::Sender<crate::error_code::Notification>,
+}
+
+impl ReplayClient {
+    #[must_use]
+    /// Create a new [`ReplayClient`] that replays `frames` in order.
+    pub fn new(frames: Vec<Frame>) -> Self {
+        let (tx, rx) = mpsc::channel(constants::TO_CLIENT_CHANNEL_SIZE);
+        // No contract queries are pending during a replay
+        let rx_wrapper = crate::decode::PendingQueries::new(mpsc::unbounded_channel().1);
+        Self {
+            frames: frames.into(),
+            contracts: Vec::new(),
+            tx,
+            rx,
+            rx_wrapper,
+            notifications: tokio::sync::broadcast::channel(1).0,
+        }
+    }
+
+    /// Create a new [`ReplayClient`] that replays a recording from a file. See [`read`] for the
+    /// format of the file.
+    ///
+    /// # Errors
+    /// Returns the same errors as [`read_file`].
+    pub fn from_file(path: impl AsRef<std::path::Path>) -> Result<Self, std::io::Error> {
+        Ok(Self::new(read_file(path)?))
+    }
+
+    #[must_use]
+    #[inline]
+    /// Return the next frame to be replayed, if any.
+    pub fn next_frame(&self) -> Option<&Frame> {
+        self.frames.front()
+    }
+
+    #[must_use]
+    #[inline]
+    /// Return the contracts that have been decoded so far.
+    pub fn contracts(&self) -> &[Contract] {
+        &self.contracts
+    }
+
+    /// Replay the next frame, if it is inbound, with `wrapper`, and return it.
+    ///
+    /// # Returns
+    /// The replayed frame, or [`None`] if every frame has been replayed.
+    pub async fn step<W: LocalWrapper>(&mut self, wrapper: &mut W) -> Option<Frame> {
+        let frame = self.frames.pop_front()?;
+        if frame.direction == Direction::Inbound {
+            crate::client::decode_msg_local(
+                frame.fields(),
+                wrapper,
+                &mut self.tx,
+                &mut self.rx_wrapper,
+                &self.notifications,
+            )
+            .await;
+            while let Ok(msg) = self.rx.try_recv() {
+                if let ToClient::NewContract(_, contract) = msg {
+                    self.contracts.push(contract);
+                }
+            }
+        }
+        Some(frame)
+    }
+
+    /// Replay every remaining frame with `wrapper`.
+    pub async fn run<W: LocalWrapper>(&mut self, wrapper: &mut W) {
+        while self.step(wrapper).await.is_some() {}
+    }
+}
+
+#[tokio::test]
+async fn test_record_and_replay() -> Result<(), std::io::Error> {
+    struct Errors(Vec<i64>);
+
+    impl LocalWrapper for Errors {
+        fn error(
+            &mut self,
+            _req_id: i64,
+            error_code: i64,
+            _error_string: String,
+            _advanced_order_reject_json: String,
+        ) -> impl std::future::Future {
+            async move { self.0.push(error_code) }
+        }
+    }
+
+    let path = std::env::temp_dir().join(format!("ibapi-recording-{}.bin", std::process::id()));
+    let recorder = Recorder::default();
+    // Nothing is recorded before a recording starts
+    recorder.record(Direction::Inbound, b"4\x002\x000\x00200\x00\x00\x00");
+    recorder.start(&path)?;
+    recorder.record_frames(Direction::Outbound, b"\0\0\0\x049\x008\x00");
+    recorder.record_frames(Direction::Inbound, crate::mock::fixtures::STOCK_CONTRACT_DETAILS);
+    recorder.record_frames(Direction::Inbound, crate::mock::fixtures::NO_SECURITY_DEFINITION);
+    recorder.stop()?;
+    recorder.record(Direction::Inbound, b"4\x002\x000\x00200\x00\x00\x00");
+
+    let frames = read_file(&path)?;
+    std::fs::remove_file(&path)?;
+    assert_eq!(frames.len(), 4);
+    assert_eq!(frames[0].direction, Direction::Outbound);
+    assert_eq!(frames[0].fields(), ["9", "8", ""]);
+    assert!(frames.windows(2).all(|w| w[0].timestamp <= w[1].timestamp));
+
+    let mut replay = ReplayClient::new(frames);
+    let mut errors = Errors(Vec::new());
+    replay.run(&mut errors).await;
+    assert!(replay.next_frame().is_none());
+    assert_eq!(errors.0, [200]);
+    let [contract] = replay.contracts() else {
+        panic!("The recording has a single contract");
+    };
+    assert_eq!(crate::contract::Security::symbol(contract), "AAPL");
+
+    assert!(read(b"IBKRREC\x02").is_err());
+    assert!(read(b"not a recording").is_err());
+    assert!(read(b"IBKRREC\x01I\0\0\0").is_err());
+    Ok(())
+}
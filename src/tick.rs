@@ -383,6 +383,100 @@ pub(crate) mod indicators {
     impl Valid for f64 {}
 }
 
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+/// The attributes of a bid, ask, or last price tick.
+pub struct TickAttrib {
+    /// Whether an order at the bid or ask price would be executed automatically.
+    pub can_auto_execute: bool,
+    /// Whether the bid is lower than the day's lowest value or the ask is higher than the day's
+    /// highest value.
+    pub past_limit: bool,
+    /// Whether the price is from the pre-open session.
+    pub pre_open: bool,
+}
+
+impl TickAttrib {
+    #[must_use]
+    #[inline]
+    /// Parse the attributes from the bit mask of a price tick message.
+    pub(crate) const fn from_mask(mask: u8) -> Self {
+        Self {
+            can_auto_execute: mask & 1 != 0,
+            past_limit: mask & 2 != 0,
+            pre_open: mask & 4 != 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// A single tick of a [`crate::market_data::TickStream`]. Each variant carries the value of the
+/// [`crate::wrapper::LocalWrapper`] callback of the same name, so a stream receives the same
+/// ticks as a wrapper does.
+pub enum Tick {
+    /// Price data, like the bid (tick type 1) or the last price (tick type 4).
+    Price {
+        /// The price.
+        price: Class<Price>,
+        /// The attributes of the price.
+        attrib: TickAttrib,
+    },
+    /// Size data, like the bid size (tick type 0) or the last size (tick type 5).
+    Size(Class<Size>),
+    /// Price data in yield terms.
+    Yield(Yield),
+    /// The high or low price over a period.
+    Extreme(ExtremeValue),
+    /// The results of option computations.
+    SecOptionComputation(Class<SecOptionCalculationSource>),
+    /// The exchanges that quote the best bid, best ask, or last price.
+    QuotingExchanges(QuotingExchanges),
+    /// The open interest of a derivative.
+    OpenInterest(OpenInterest),
+    /// Volatility data.
+    Volatility(Volatility),
+    /// Timestamp data.
+    TimeStamp(Class<TimeStamp>),
+    /// Auction data.
+    Auction(AuctionData),
+    /// The mark price.
+    MarkPrice(MarkPrice),
+    /// Factors related to prices.
+    PriceFactor(PriceFactor),
+    /// The ability to short or trade the security, like whether it is halted.
+    Accessibility(Accessibility),
+    /// Dividends.
+    Dividends(Dividends),
+    /// News.
+    News(News),
+    /// IPO prices.
+    Ipo(Ipo),
+    /// Summary volume over a period.
+    SummaryVolume(SummaryVolume),
+    /// Option volume.
+    SecOptionVolume(SecOptionVolume),
+    /// The number of trades in the day.
+    TradeCount(TradeCount),
+    /// The rate of trades or volume.
+    Rate(Rate),
+    /// The volume for the day (tick type 8).
+    Volume(Volume),
+    /// Real-time volume.
+    RealTimeVolume(RealTimeVolume),
+    /// The Net Asset Value of an ETF.
+    EtfNav(EtfNav),
+    /// The parameters of the subscription.
+    Params {
+        /// The minimum tick of the security.
+        min_tick: f64,
+        /// The identifier of the exchanges included in the SMART aggregate exchange.
+        exchange_id: crate::payload::ExchangeId,
+        /// The snapshot permissions of the subscription.
+        snapshot_permissions: u32,
+    },
+    /// The class of the data that is returned.
+    MarketDataClass(crate::payload::MarketDataClass),
+}
+
 #[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(untagged)]
 /// The result of an option calculation.
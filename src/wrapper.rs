@@ -1,3 +1,6 @@
+// The blanket implementation of `LocalWrapper` for every `Wrapper` calls its deprecated methods
+#![allow(deprecated)]
+
 use std::future::Future;
 
 use chrono::{DateTime, NaiveTime, Utc};
@@ -5,11 +8,11 @@ use ibapi_macros::debug_trait;
 
 use crate::account::{Attribute, TagValue};
 use crate::client::ActiveClient;
-use crate::contract::{Contract, ExchangeProxy};
+use crate::contract::{Contract, DeltaNeutralContract, ExchangeProxy};
 use crate::execution::{CommissionReport, Execution};
 use crate::payload::{
-    self, Bar, ExchangeId, HistogramEntry, OrderStatus, Pnl, PnlSingle, Position, PositionSummary,
-    TickData,
+    self, Bar, ExchangeId, HistogramEntry, OrderStatus, Pnl, PnlSingle, Position,
+    PositionSummary, TickData,
 };
 use crate::tick::{
     self, Accessibility, AuctionData, Class, Dividends, ExtremeValue, Ipo, MarkPrice, News,
@@ -118,6 +121,7 @@ pub trait LocalWrapper {
     ) -> impl Future {
     }
     /// The callback message containing a complete histogram from [`crate::client::Client::req_histogram_data`].
+    #[deprecated(since = "0.2.0", note = "use `Client::histogram`, which returns the entries in order")]
     fn histogram(
         &mut self,
         req_id: i64,
@@ -136,6 +140,7 @@ pub trait LocalWrapper {
     /// The callback message containing an updated historical bar from [`crate::client::Client::req_updating_historical_bar`].
     fn updating_historical_bar(&mut self, req_id: i64, bar: Bar) -> impl Future {}
     /// The callback message containing a timestamp for the beginning of data for a contract and specified data type from [`crate::client::Client::req_head_timestamp`].
+    #[deprecated(since = "0.2.0", note = "use `Client::head_timestamp`, which returns the timestamp")]
     fn head_timestamp(&mut self, req_id: i64, timestamp: DateTime<Utc>) -> impl Future {}
     /// The callback message containing a vector of historical ticks from [`crate::client::Client::req_historical_ticks`] for [`crate::client::Client::req_tick_by_tick_data`].
     fn historical_ticks(&mut self, req_id: i64, ticks: Vec<TickData>) -> impl Future {}
@@ -185,6 +190,13 @@ pub trait LocalWrapper {
         permanent_id: i64,
     ) -> impl Future {
     }
+    /// The callback message that contains the hedge attached to an open order, if it has one. It follows the corresponding [`LocalWrapper::open_order`].
+    fn open_order_delta_neutral_contract(
+        &mut self,
+        order_id: i64,
+        delta_neutral_contract: DeltaNeutralContract,
+    ) -> impl Future {
+    }
     /// The callback message that contains information about an execution.
     fn execution(&mut self, req_id: i64, execution: Execution) -> impl Future {}
     ///  The callback message indicating the end of an execution details request
@@ -1,6 +1,7 @@
+#![allow(clippy::manual_async_fn)]
+
 use std::future::Future;
 
-use tokio;
 
 use ibapi::account::Tag;
 use ibapi::client::{ActiveClient, Builder, Host, Mode};
@@ -12,7 +13,7 @@ impl Wrapper for AccountDataWrapper {}
 
 impl Recurring for AccountDataWrapper {
     fn cycle(&mut self) -> impl Future<Output = ()> + Send {
-        async { () }
+        async {}
     }
 }
 
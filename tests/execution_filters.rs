@@ -1,7 +1,9 @@
+#![allow(clippy::manual_async_fn)]
+
 use std::future::Future;
 use ibapi::prelude::*;
 
-
+#[allow(clippy::large_enum_variant)]
 enum ExecutionMessage {
     Response(i64, Execution),
     Finished(i64),
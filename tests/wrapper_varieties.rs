@@ -1,3 +1,5 @@
+#![allow(clippy::manual_async_fn)]
+
 use std::future::Future;
 
 use ibapi::client::{ActiveClient, Builder, Host, Mode};
@@ -12,7 +14,7 @@ impl Wrapper for SendWrapper {}
 
 impl Recurring for SendWrapper {
     fn cycle(&mut self) -> impl Future<Output = ()> + Send {
-        async { () }
+        async {}
     }
 }
 